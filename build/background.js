@@ -0,0 +1,88 @@
+'use strict';
+
+import {
+  default as init, onAlarm, onCommand, onExtensionsChanged, onMenuClicked,
+  onMessage, onMessageExternal, onNotificationClicked, onOmniboxInputChanged,
+  onOmniboxInputEntered, onStorageChanged, onSuspend, onTabCreated,
+  onTabRemoved, onTabUpdated, onWindowRemoved,
+} from './cubicle.js';
+
+const listenerMap = new Map();
+const wasmLoaded = init();
+
+/**
+ * Adds a function as a listener of a runtime event.
+ * @param {string} event - Name of the runtime event to listen for.
+ * @param {Function} handler - Handler to be added as a listener of the event.
+ */
+export function addRuntimeListener(event, handler) {
+  listenerMap.set(event, handler);
+}
+
+/**
+ * Main entrypoint for initializing the background page,
+ * uses promises to wait for WASM and attaches runtime listeners.
+ * This is an IIFE as this is the first function to be executed.
+ */
+(function main() {
+  for (const runtimeProperty in browser.runtime) {
+    if (!runtimeProperty.startsWith('on')) continue;
+    browser.runtime[runtimeProperty].addListener((...handlerArgs) => {
+      return wasmLoaded.then(async () => {
+        if (listenerMap.has(runtimeProperty)) {
+          return listenerMap.get(runtimeProperty)(...handlerArgs);
+        }
+      });
+    });
+  }
+
+  addRuntimeListener('onMessage', onMessage);
+  addRuntimeListener('onMessageExternal', onMessageExternal);
+  addRuntimeListener('onSuspend', onSuspend);
+
+  browser.storage.onChanged.addListener((changes, areaName) => {
+    wasmLoaded.then(async () => onStorageChanged(changes, areaName));
+  });
+  browser.tabs.onCreated.addListener((tab) => {
+    wasmLoaded.then(async () => onTabCreated(tab));
+  });
+  browser.tabs.onRemoved.addListener((tabId) => {
+    wasmLoaded.then(async () => onTabRemoved(tabId));
+  });
+  browser.tabs.onUpdated.addListener((tabId, changeInfo, tab) => {
+    wasmLoaded.then(async () =>
+      onTabUpdated(tabId, tab, changeInfo.url !== undefined));
+  }, {properties: ['url']});
+  browser.windows.onRemoved.addListener((windowId) => {
+    wasmLoaded.then(async () => onWindowRemoved(windowId));
+  });
+  browser.alarms.onAlarm.addListener((alarm) => {
+    wasmLoaded.then(async () => onAlarm(alarm.name));
+  });
+  browser.notifications.onClicked.addListener((notificationId) => {
+    wasmLoaded.then(async () => onNotificationClicked(notificationId));
+  });
+  for (const event of ['onEnabled', 'onDisabled', 'onInstalled',
+    'onUninstalled']) {
+    browser.management[event].addListener(() => {
+      wasmLoaded.then(async () => onExtensionsChanged());
+    });
+  }
+  browser.menus.create({
+    id: 'open-homepage',
+    title: browser.i18n.getMessage('menu_open_homepage'),
+    contexts: ['tab'],
+  });
+  browser.menus.onClicked.addListener((info, tab) => {
+    wasmLoaded.then(async () => onMenuClicked(info.menuItemId, tab));
+  });
+  browser.commands.onCommand.addListener((command) => {
+    wasmLoaded.then(async () => onCommand(command));
+  });
+  browser.omnibox.onInputChanged.addListener((text, suggest) => {
+    wasmLoaded.then(async () => onOmniboxInputChanged(text)).then(suggest);
+  });
+  browser.omnibox.onInputEntered.addListener((text, disposition) => {
+    wasmLoaded.then(async () => onOmniboxInputEntered(text, disposition));
+  });
+})();
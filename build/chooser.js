@@ -0,0 +1,13 @@
+'use strict';
+
+import redirect from './components/context.js';
+
+/**
+ * Main entrypoint for the chooser of where a tab should be opened,
+ * the tab is given by the `tab_id` query parameter.
+ * This is an IIFE as this is a standalone page.
+ */
+(function main() {
+  const tabId = new URLSearchParams(window.location.search).get('tab_id');
+  redirect({view: 'relocation_chooser', tab_id: Number(tabId)});
+})();
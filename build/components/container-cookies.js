@@ -0,0 +1,46 @@
+'use strict';
+
+import {
+  logStatus,
+  messageContainerSelection,
+  stateUpdateRedirect,
+} from './context.js';
+
+/**
+ * Messages the background that cookies in the selected container
+ * should be cleared, then updates the popup.
+ * @param {?Array<string>} domains - Domains of the cookies to be cleared,
+ *     `null` for clearing all cookies.
+ */
+function messageClearCookies(domains) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'clear_cookies',
+      cookie_store_id: selectContainer.value,
+      domains: domains,
+    },
+  }).then(logStatus('Cookies were cleared'));
+}
+
+/**
+ * Entry for the container cookies view.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  document.getElementById('btn-clear-selected-cookies')
+      ?.addEventListener('click', () => {
+        const domains = [];
+        for (const element of document
+            .getElementsByClassName('check-cookie-domain')) {
+          if (element.checked) domains.push(element.value);
+        }
+        messageClearCookies(domains);
+      });
+  document.getElementById('btn-clear-all-cookies')
+      ?.addEventListener('click', () => messageClearCookies(null));
+  document.getElementById('btn-back').addEventListener('click', () => {
+    const selectContainer = document.getElementById('select-container');
+    messageContainerSelection(selectContainer.value);
+  });
+}
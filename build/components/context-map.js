@@ -0,0 +1,66 @@
+'use strict';
+
+// This file will be templated at build time
+// to import all view listener attaching functions.
+
+
+    import context1 from './delete-prompt.js';
+
+    import context2 from './import.js';
+
+    import context3 from './rule-tester.js';
+
+    import context4 from './temporary-report.js';
+
+    import context5 from './quick-switcher.js';
+
+    import context6 from './context.js';
+
+    import context7 from './new-container.js';
+
+    import context8 from './container-cookies.js';
+
+    import context9 from './welcome.js';
+
+    import context10 from './options-body.js';
+
+    import context11 from './relocation-chooser.js';
+
+    import context12 from './devtools-panel.js';
+
+    import context13 from './container-detail.js';
+
+    import context14 from './sessions.js';
+
+
+export const CONTEXT_MAP = new Map([
+    
+        ['delete-prompt', context1],
+    
+        ['import', context2],
+    
+        ['rule-tester', context3],
+    
+        ['temporary-report', context4],
+    
+        ['quick-switcher', context5],
+    
+        ['context', context6],
+    
+        ['new-container', context7],
+    
+        ['container-cookies', context8],
+    
+        ['welcome', context9],
+    
+        ['options-body', context10],
+    
+        ['relocation-chooser', context11],
+    
+        ['devtools-panel', context12],
+    
+        ['container-detail', context13],
+    
+        ['sessions', context14],
+    
+]);
@@ -0,0 +1,145 @@
+'use strict';
+
+import {CONTEXT_MAP} from './context-map.js';
+
+// update screen is the same as the new container screen and can be reused
+CONTEXT_MAP.set('update-container', CONTEXT_MAP.get('new-container'));
+
+/**
+ * In-band marker prefix to denote that the following data is a valid
+ * cookie store ID, otherwise it may be interpreted as a control sequence.
+ */
+export const COOKIE_STORE_ID_MARKER_PREFIX = 'b64_';
+
+/**
+ * Creates a function that displays a message in the status bar when called.
+ * Useful for logging in a thenable handler.
+ * @param {string} message - Message to be displayed.
+ * @return {Function} Function that displays the message when called.
+ */
+export function logStatus(message) {
+  return () => {
+    document.getElementById('lbl-status').innerText = message;
+  };
+}
+
+/**
+ * Messages the background about a container selection, then updates the popup.
+ * @param {string} value - The ID of the selected container if it starts with
+ *     [COOKIE_STORE_ID_MARKER_PREFIX], `new` if a new container is requested,
+ *     `more` if the next page of containers is requested,
+ *     and `none` if "no container" (default cookie store) is selected.
+ * @return {Promise} Promise that fulfils once the update is fully complete.
+ */
+export function messageContainerSelection(value) {
+  if (value === 'more') return loadMoreContainers();
+  document.getElementById('select-container').dataset.lastValue = value;
+  const iconBtn = document.getElementById('btn-icon');
+  const iconImg = document.getElementById('img-icon');
+
+  const resetIconStyle = () => {
+    iconBtn.style.visibility = 'hidden';
+  };
+
+  const btnDelete = document.getElementById('btn-delete');
+  if (value.startsWith(COOKIE_STORE_ID_MARKER_PREFIX)) {
+    btnDelete.style.visibility = 'visible';
+  } else btnDelete.style.visibility = 'hidden';
+
+  if (value === 'new') {
+    return redirect({view: 'new_container'}).then(resetIconStyle);
+  } else if (value === 'none') {
+    return redirect({view: 'welcome'}).then(resetIconStyle);
+  } else {
+    return redirect({
+      view: 'container_detail', cookie_store_id: value,
+    }).then(() => {
+      iconBtn.style.visibility = 'visible';
+      iconBtn.style.backgroundColor = document
+          .getElementById('data-icon-color').getAttribute('data-icon-color');
+      iconImg.src = document.getElementById('data-icon-link')
+          .getAttribute('data-icon-link');
+    });
+  }
+}
+
+/**
+ * Updates the popup with the specified composed view.
+ * This may be merged with [stateUpdateRedirect] in the future.
+ * @param {object} viewEnum - View specification.
+ * @return {Promise} Promise that fulfils once the view is rendered.
+ */
+export default async function redirect(viewEnum) {
+  const mainElement = document.getElementsByTagName('main')[0];
+  mainElement.replaceChildren();
+  return browser.runtime.sendMessage({
+    message_type: 'request_page', view: viewEnum,
+  }).then((html) => {
+    mainElement.innerHTML = html;
+    CONTEXT_MAP.get(viewEnum.view.replaceAll('_', '-'))(viewEnum);
+  });
+}
+
+/**
+ * Sends a message to the background, and updates elements in the popup.
+ * @param {string} messageType - Action type for determining
+ *     elements to update.
+ * @param {object} messageEnum - The actual message.
+ * @return {Promise} Promise that fulfils once the update is fully complete.
+ */
+export async function stateUpdateRedirect(messageType, messageEnum) {
+  const mainElement = document.getElementsByTagName('main')[0];
+  mainElement.style.display = 'none';
+  const selectContainer = document.getElementById('select-container');
+  selectContainer.disabled = true;
+  const message = {message_type: messageType, ...messageEnum};
+  return browser.runtime.sendMessage(message).then((html) => {
+    selectContainer.innerHTML = html;
+    messageContainerSelection(selectContainer.value);
+  }).finally(() => {
+    selectContainer.disabled = false;
+    mainElement.style.display = 'flex';
+  });
+}
+
+/**
+ * Lists the next page of containers in place of the `more` entry,
+ * then selects the previously selected entry again.
+ * @return {Promise} Promise that fulfils once the page is listed.
+ */
+async function loadMoreContainers() {
+  const selectElement = document.getElementById('select-container');
+  const moreOption = selectElement.querySelector('option[value="more"]');
+  selectElement.value = selectElement.dataset.lastValue ?? 'none';
+  const filter = moreOption.dataset.filter;
+  return browser.runtime.sendMessage({
+    message_type: 'request_page', view: {
+      view: 'fetch_all_containers',
+      selected: moreOption.dataset.selected,
+      filter: filter === '' ? null : filter,
+      offset: Number(moreOption.dataset.nextOffset),
+    },
+  }).then((html) => {
+    moreOption.insertAdjacentHTML('beforebegin', html);
+    moreOption.remove();
+  });
+}
+
+/**
+ * Updates the container listing in the `select-container` element.
+ * @param {?string} selected - The ID of the container to be selected,
+ *     `null` for the container of the current tab.
+ * @param {?string} filter - Text that the listed containers' names or
+ *     suffixes should contain, `null` to list all containers.
+ * @return {Promise} Promise that fulfils once the listing is updated.
+ */
+export async function updateContainerListing(selected = null, filter = null) {
+  return browser.runtime.sendMessage({
+    message_type: 'request_page', view: {
+      view: 'fetch_all_containers', selected: selected, filter: filter,
+    },
+  }).then((html) => {
+    const selectElement = document.getElementById('select-container');
+    selectElement.innerHTML = html;
+  });
+}
@@ -0,0 +1,34 @@
+'use strict';
+
+/**
+ * Messages the background where the tab should be opened,
+ * then closes the chooser.
+ * @param {Event} event - Generated click event,
+ *     for finding the choice of the button.
+ */
+function messageChooseRelocation(event) {
+  const tabId = document.getElementById('list-relocation-choices')
+      .dataset.tabId;
+  const choice = {choice: event.target.dataset.choice};
+  if (choice.choice === 'container') {
+    choice.cookie_store_id = event.target.dataset.cookieStoreId;
+  }
+  browser.runtime.sendMessage({
+    message_type: 'choose_relocation',
+    tab_id: Number(tabId),
+    choice: choice,
+    should_remember:
+        document.getElementById('checkbox-remember-choice').checked,
+  }).finally(() => window.close());
+}
+
+/**
+ * Entry for the chooser of where a tab should be opened.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  for (const button of document
+      .getElementsByClassName('btn-relocation-choice')) {
+    button.addEventListener('click', messageChooseRelocation);
+  }
+}
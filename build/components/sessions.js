@@ -0,0 +1,58 @@
+'use strict';
+
+import redirect, {logStatus} from './context.js';
+
+/**
+ * Messages the background that the tabs in containers should be saved
+ * under a name, then lists the sessions again.
+ * @param {string} name - Name of the session, replacing any with that name.
+ * @return {Promise} Promise that fulfils once the session list is updated.
+ */
+function messageSaveSession(name) {
+  return browser.runtime.sendMessage({
+    message_type: 'save_session', name: name,
+  }).then((tabCount) => redirect({view: 'sessions'})
+      .then(logStatus(`Saved ${tabCount} tabs`)));
+}
+
+/**
+ * Messages the background that a saved session should be reopened.
+ * @param {string} name - Name of the session.
+ * @return {Promise} Promise that fulfils once the tabs are reopened.
+ */
+function messageRestoreSession(name) {
+  return browser.runtime.sendMessage({
+    message_type: 'restore_session', name: name,
+  }).then((tabCount) => logStatus(`Restored ${tabCount} tabs`)());
+}
+
+/**
+ * Messages the background that the containers purged last should be
+ * recreated.
+ * @return {Promise} Promise that fulfils once the containers are recreated.
+ */
+function messageRestoreLastSession() {
+  return browser.runtime.sendMessage({
+    message_type: 'restore_last_session',
+  }).then((tabCount) => logStatus(`Restored ${tabCount} tabs`)());
+}
+
+/**
+ * Entry for the session list.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  document.getElementById('form-save-session')
+      .addEventListener('submit', (event) => {
+        event.preventDefault();
+        const name = document.getElementById('input-session-name').value;
+        messageSaveSession(name.trim());
+      });
+  document.getElementById('btn-restore-last-session')
+      ?.addEventListener('click', messageRestoreLastSession);
+  for (const button of document.getElementsByClassName('btn-restore-session')) {
+    button.addEventListener('click', () => {
+      messageRestoreSession(button.getAttribute('data-name'));
+    });
+  }
+}
@@ -0,0 +1,35 @@
+'use strict';
+
+import redirect, {logStatus, stateUpdateRedirect} from './context.js';
+
+/**
+ * Messages the background that a permanent container should be created
+ * for a suffix, then shows the container listing.
+ * @param {string} suffix - Encoded suffix that the container should match.
+ */
+function messageCreateForSuffix(suffix) {
+  stateUpdateRedirect('container_action', {
+    action: {action: 'create_for_suffix', suffix: suffix},
+  }).then(logStatus(`Container was created for '${suffix}'`))
+      .catch(logStatus(`Container was not created for '${suffix}'`));
+}
+
+/**
+ * Entry for the temporary container report.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  document.getElementById('form-temporary-report')
+      .addEventListener('submit', (event) => {
+        event.preventDefault();
+        const days = Number(
+            document.getElementById('input-temporary-report-days').value);
+        redirect({view: 'temporary_report', days: days});
+      });
+  for (const button of
+    document.getElementsByClassName('btn-create-for-suffix')) {
+    button.addEventListener('click', () => {
+      messageCreateForSuffix(button.getAttribute('data-suffix'));
+    });
+  }
+}
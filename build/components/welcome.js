@@ -0,0 +1,129 @@
+'use strict';
+
+import {
+  default as redirect,
+  logStatus,
+  stateUpdateRedirect,
+  updateContainerListing,
+} from './context.js';
+
+/**
+ * Shows a step of the welcome wizard.
+ * @param {string} step - Step to show, `overview` to leave the wizard.
+ * @return {Promise} Promise that fulfils once the step is shown.
+ */
+function showStep(step) {
+  return redirect({view: 'welcome', step: step});
+}
+
+/**
+ * Messages the background that the browser's containers should be imported,
+ * then updates the listing and proceeds to the next step.
+ * @param {Event} event - Generated click event,
+ *     for disabling the button on click.
+ */
+function messageWizardImport(event) {
+  event.target.disabled = true;
+  browser.runtime.sendMessage({
+    message_type: 'migrate_container',
+    migrate_type: {migrate_type: 'native'},
+    detect_temp: document.getElementById('check-wizard-detect-temp').checked,
+  }).then(() => updateContainerListing())
+      .then(logStatus('Import was successful'))
+      .then(() => showStep('psl'))
+      .catch(logStatus('Import failed'))
+      .finally(() => event.target.disabled = false);
+}
+
+/**
+ * Messages the background that a focus container should be started,
+ * which is closed with its tabs after some minutes.
+ * The pop-up is closed once the container is opened.
+ * @param {number} minutes - Minutes until the focus container is closed.
+ */
+function messageStartFocus(minutes) {
+  browser.runtime.sendMessage({
+    message_type: 'start_focus',
+    duration: {secs: minutes * 60, nanos: 0},
+  }).then(() => window.close())
+      .catch(logStatus('Focus container was not started'));
+}
+
+/**
+ * Messages the background that the public suffix list should be downloaded
+ * from the URL of the current list, or the default URL like the options page,
+ * then proceeds to the next step.
+ * @param {Event} event - Generated click event,
+ *     for disabling the button on click.
+ */
+function messageWizardPslUpdate(event) {
+  event.target.disabled = true;
+  const progressPsl = document.getElementById('progress-wizard-psl');
+  progressPsl.hidden = false;
+  browser.runtime.sendMessage({
+    message_type: 'psl_update',
+    url: event.target.dataset.pslUrl,
+  })
+      .then(logStatus('Public suffix list was updated'))
+      .then(() => showStep('recording'))
+      .catch(logStatus('Public suffix list was not updated'))
+      .finally(() => {
+        event.target.disabled = false;
+        progressPsl.hidden = true;
+      });
+}
+
+/**
+ * Messages the background that the first recording container should be
+ * created, then shows the new container.
+ * @param {Event} event - Generated submit event, for extracting the name.
+ */
+function messageWizardRecording(event) {
+  event.preventDefault();
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'submit_identity_details',
+      cookie_store_id: null,
+      details: {
+        color: 'cycle',
+        icon: 'fingerprint',
+        name: event.target.elements['name'].value.trim(),
+      },
+      should_record: true,
+    },
+  }).then(logStatus('Container was created'))
+      .catch(logStatus('Container was not created'));
+}
+
+/**
+ * Entrypoint for the welcome page displayed when no container is selected,
+ * which is a wizard on the first run.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  document.getElementById('btn-import')
+      ?.addEventListener('click', () => redirect({view: 'import'}));
+  document.getElementById('btn-sessions')
+      ?.addEventListener('click', () => redirect({view: 'sessions'}));
+  document.getElementById('btn-rule-tester')
+      ?.addEventListener('click', () => redirect({view: 'rule_tester'}));
+  document.getElementById('btn-temporary-report')
+      ?.addEventListener('click', () => redirect({view: 'temporary_report'}));
+  document.getElementById('btn-wizard')
+      ?.addEventListener('click', () => showStep('identities'));
+  document.getElementById('form-focus')
+      ?.addEventListener('submit', (event) => {
+        event.preventDefault();
+        messageStartFocus(Math.floor(
+            Number(document.getElementById('input-focus-minutes').value)));
+      });
+  for (const button of document.getElementsByClassName('btn-wizard-next')) {
+    button.addEventListener('click', () => showStep(button.dataset.nextStep));
+  }
+  document.getElementById('btn-wizard-import-native')
+      ?.addEventListener('click', messageWizardImport);
+  document.getElementById('btn-wizard-psl-update')
+      ?.addEventListener('click', messageWizardPslUpdate);
+  document.getElementById('form-wizard-recording')
+      ?.addEventListener('submit', messageWizardRecording);
+}
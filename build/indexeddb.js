@@ -0,0 +1,102 @@
+'use strict';
+
+/** Name of the database holding entries that are too large for storage. */
+const DATABASE_NAME = 'cubicle';
+/** Name of the object store, values are keyed by the keys of the entries. */
+const STORE_NAME = 'entries';
+
+/**
+ * Opens the database, creating the object store on first use.
+ * @return {Promise<IDBDatabase>} Promise that fulfils with the database.
+ */
+function openDatabase() {
+  return new Promise((resolve, reject) => {
+    const request = indexedDB.open(DATABASE_NAME, 1);
+    request.onupgradeneeded = () => {
+      request.result.createObjectStore(STORE_NAME);
+    };
+    request.onsuccess = () => resolve(request.result);
+    request.onerror = () => reject(request.error);
+  });
+}
+
+/**
+ * Runs operations on the object store in a single transaction.
+ * @param {IDBTransactionMode} mode - Whether the transaction writes.
+ * @param {Function} operate - Function that takes the object store,
+ *     its return value is only complete once the transaction is.
+ * @return {Promise} Promise that fulfils with the return value of operate.
+ */
+function transact(mode, operate) {
+  return openDatabase().then((database) => new Promise((resolve, reject) => {
+    const transaction = database.transaction(STORE_NAME, mode);
+    const result = operate(transaction.objectStore(STORE_NAME));
+    transaction.oncomplete = () => {
+      database.close();
+      resolve(result);
+    };
+    transaction.onerror = transaction.onabort = () => {
+      database.close();
+      reject(transaction.error);
+    };
+  }));
+}
+
+/**
+ * Gets entries in the same way as `storage.local.get`.
+ * @param {?(string|string[]|Object)} keys - Key or keys to get,
+ *     an object maps keys to their default values, null gets all entries.
+ * @return {Promise<Object>} Promise that fulfils with the entries.
+ */
+export function getEntries(keys) {
+  return transact('readonly', (store) => {
+    const entries = {};
+    if (keys === null || keys === undefined) {
+      const request = store.openCursor();
+      request.onsuccess = () => {
+        const cursor = request.result;
+        if (!cursor) return;
+        entries[cursor.key] = cursor.value;
+        cursor.continue();
+      };
+      return entries;
+    }
+    const defaults = typeof keys === 'string' || Array.isArray(keys) ?
+        Object.fromEntries([keys].flat().map((key) => [key, undefined])) :
+        keys;
+    for (const [key, defaultValue] of Object.entries(defaults)) {
+      const request = store.get(key);
+      request.onsuccess = () => {
+        const value = request.result ?? defaultValue;
+        if (value !== undefined) entries[key] = value;
+      };
+    }
+    return entries;
+  });
+}
+
+/**
+ * Sets entries in the same way as `storage.local.set`.
+ * @param {Object} entries - Object mapping keys to their new values.
+ * @return {Promise} Promise that fulfils once the entries are written.
+ */
+export function setEntries(entries) {
+  return transact('readwrite', (store) => {
+    for (const [key, value] of Object.entries(entries)) {
+      store.put(value, key);
+    }
+  });
+}
+
+/**
+ * Removes entries in the same way as `storage.local.remove`.
+ * @param {(string|string[])} keys - Key or keys to remove.
+ * @return {Promise} Promise that fulfils once the entries are removed.
+ */
+export function removeEntries(keys) {
+  return transact('readwrite', (store) => {
+    for (const key of [keys].flat()) {
+      store.delete(key);
+    }
+  });
+}
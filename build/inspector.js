@@ -0,0 +1,15 @@
+'use strict';
+
+import redirect from './components/context.js';
+
+/**
+ * Main entrypoint for the devtools panel of the inspected tab,
+ * which is shown again whenever the inspected tab navigates.
+ * This is an IIFE as this is a standalone page.
+ */
+(function main() {
+  const tabId = browser.devtools.inspectedWindow.tabId;
+  const showPanel = () => redirect({view: 'devtools_panel', tab_id: tabId});
+  browser.devtools.network.onNavigated.addListener(showPanel);
+  showPanel();
+})();
@@ -0,0 +1,100 @@
+'use strict';
+
+import {
+  default as redirect,
+  COOKIE_STORE_ID_MARKER_PREFIX,
+  messageContainerSelection,
+  updateContainerListing,
+} from './components/context.js';
+
+/**
+ * Messages the background that an identity details update is requested,
+ * then updates the popup.
+ */
+function messageContainerUpdate() {
+  const selectContainer = document.getElementById('select-container');
+  redirect({
+    view: 'update_container',
+    cookie_store_id: selectContainer.value,
+  });
+}
+
+/**
+ * Filters the container listing while keeping the current selection if it is
+ * still listed, the popup is updated if the selection changes.
+ * @param {string} filter - Text that the listed containers' names or
+ *     suffixes should contain.
+ */
+function filterContainerListing(filter) {
+  const selectContainer = document.getElementById('select-container');
+  const previousValue = selectContainer.value;
+  const selected = previousValue.startsWith(COOKIE_STORE_ID_MARKER_PREFIX)?
+      previousValue : null;
+  updateContainerListing(selected, filter === ''? null : filter).then(() => {
+    if (selectContainer.value !== previousValue) {
+      messageContainerSelection(selectContainer.value);
+    }
+  });
+}
+
+/**
+ * Requests the health of the background, then shows a banner if the context
+ * failed to load, as the container listing would otherwise be empty.
+ * @return {Promise} Promise that fulfils once the banner is updated.
+ */
+function messageHealthCheck() {
+  return browser.runtime.sendMessage({message_type: 'health_check'})
+      .then((status) => {
+        const isDegraded = JSON.parse(status).is_degraded;
+        document.getElementById('lbl-degraded').hidden = !isDegraded;
+      });
+}
+
+/**
+ * Requests the background to load the containers again,
+ * then updates the banner and the container listing.
+ * @param {Event} event - Click event of the retry button.
+ */
+function messageRetryLoad(event) {
+  event.target.disabled = true;
+  browser.runtime.sendMessage({message_type: 'retry_load'})
+      .finally(() => messageHealthCheck())
+      .then(() => updateContainerListing())
+      .finally(() => {
+        event.target.disabled = false;
+      });
+}
+
+/**
+ * Main entrypoint for popup creation, mainly for attaching listeners.
+ * This is an IIFE as this is the first function to be executed.
+ */
+(function main() {
+  const selectContainer = document.getElementById('select-container');
+  selectContainer.addEventListener('change', (event) => {
+    messageContainerSelection(event.target.value);
+  });
+  document.getElementById('input-filter')
+      .addEventListener('input', (event) => {
+        filterContainerListing(event.target.value);
+      });
+  document.getElementById('btn-icon')
+      .addEventListener('click', messageContainerUpdate);
+  document.getElementById('btn-delete')
+      .addEventListener('click', () => redirect({
+        view: 'delete_prompt',
+        cookie_store_id: selectContainer.value,
+      }));
+  document.getElementById('btn-retry-load')
+      .addEventListener('click', messageRetryLoad);
+  document.getElementById('btn-options')
+      .addEventListener('click', () => {
+        window.open(browser.runtime.getURL('options.html'));
+        window.close();
+      });
+
+  messageHealthCheck();
+  updateContainerListing().then(() => {
+    messageContainerSelection(selectContainer.value);
+  });
+})();
@@ -0,0 +1,13 @@
+'use strict';
+
+import redirect from './components/context.js';
+
+/**
+ * Main entrypoint for the switcher of the container of a tab,
+ * the tab is given by the `tab_id` query parameter.
+ * This is an IIFE as this is a standalone page.
+ */
+(function main() {
+  const tabId = new URLSearchParams(window.location.search).get('tab_id');
+  redirect({view: 'quick_switcher', tab_id: Number(tabId)});
+})();
@@ -0,0 +1,78 @@
+//! Realistic data shared by the benchmarks,
+//! shaped like what is read back from the storage.
+
+use std::mem;
+
+use async_std::task;
+use base64::prelude::*;
+use cubicle::context::GlobalContext;
+use cubicle::domain::psl::Psl;
+use cubicle::migrate::BUILTIN_PSL_VERSION;
+use serde_json::{json, Map, Value};
+
+/// Number of containers, more than most users would ever create.
+pub const CONTAINER_COUNT: usize = 500;
+
+/// Builtin public suffix list, which has about 9k entries.
+const PSL_BYTES: &[u8] = include_bytes!("../../res/public_suffix_list.dat");
+
+/// Encodes a [CookieStoreId](cubicle::interop::contextual_identities::CookieStoreId)
+/// as it is serialized to the storage.
+fn encoded_cookie_store_id(cookie_store_id: &str) -> String {
+    format!("b64_{}", BASE64_URL_SAFE_NO_PAD.encode(cookie_store_id))
+}
+
+/// Parses the builtin public suffix list the same way as
+/// [GlobalContext::hydrate_psl], which is apart from the context.
+pub fn psl() -> Psl {
+    let mut stream = PSL_BYTES;
+    task::block_on(Psl::from_stream(&mut stream, *BUILTIN_PSL_VERSION))
+        .expect("builtin PSL is valid")
+}
+
+/// Serialized context with [CONTAINER_COUNT] containers,
+/// each with every type of suffix.
+pub fn stored_context() -> String {
+    let mut stored = Map::new();
+    for index in 0..CONTAINER_COUNT {
+        let cookie_store_id = format!("firefox-container-{index}");
+        let encoded = encoded_cookie_store_id(&cookie_store_id);
+        let container = json!({
+            "handle": encoded,
+            "identity": {
+                "cookieStoreId": cookie_store_id,
+                "color": "blue",
+                "colorCode": "#37adff",
+                "icon": "fingerprint",
+                "iconUrl": "resource://usercontext-content/fingerprint.svg",
+                "name": format!("Container {index}"),
+            },
+            "variant": "Permanent",
+            "suffixes": [
+                format!("site{index}.com"),
+                format!("!ads.site{index}.com"),
+                format!("*.site{index}.net"),
+                format!("site{index}.*"),
+            ],
+        });
+        stored.insert(format!("container/{encoded}"), container);
+    }
+    Value::Object(stored).to_string()
+}
+
+/// Deserializes and indexes a context the same way as
+/// [GlobalContext::from_storage].
+pub fn load_context(stored: &str) -> GlobalContext {
+    let mut context: GlobalContext =
+        serde_json::from_str(stored).expect("generated context is valid");
+    let uncached_containers = mem::take(&mut context.containers);
+    context.containers.merge(uncached_containers);
+    context
+}
+
+/// Finishes the handles of all containers so that the context can be dropped.
+pub fn finish(context: GlobalContext) {
+    for container in context.containers.iter() {
+        container.handle().finish();
+    }
+}
@@ -0,0 +1,22 @@
+//! Benchmark of restoring the context from the storage,
+//! and parsing the public suffix list right after,
+//! which happens every time the extension starts.
+
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_context_load(c: &mut Criterion) {
+    let stored = common::stored_context();
+    c.bench_function("context_load", |b| {
+        b.iter(|| {
+            let mut context = common::load_context(black_box(&stored));
+            assert_eq!(context.containers.iter().count(), common::CONTAINER_COUNT);
+            context.psl = common::psl();
+            common::finish(context);
+        })
+    });
+}
+
+criterion_group!(benches, bench_context_load);
+criterion_main!(benches);
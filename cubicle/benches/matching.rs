@@ -0,0 +1,61 @@
+//! Benchmarks of matching domains against the public suffix list
+//! and the suffixes of containers.
+
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cubicle::domain::EncodedDomain;
+use cubicle::util::clock::SystemClock;
+
+/// Domains that hit different parts of the lists,
+/// including one that matches nothing.
+const DOMAINS: [&str; 5] = [
+    "www.site250.com",
+    "ads.site499.com",
+    "deep.sub.site0.net",
+    "site42.co.uk",
+    "unrelated.example.org",
+];
+
+fn domains() -> Vec<EncodedDomain> {
+    DOMAINS
+        .into_iter()
+        .map(|domain| EncodedDomain::try_from(domain).expect("controlled benchmark"))
+        .collect()
+}
+
+fn bench_psl_match_suffix(c: &mut Criterion) {
+    let mut context = common::load_context(&common::stored_context());
+    context.psl = common::psl();
+    let domains = domains();
+    c.bench_function("psl_match_suffix", |b| {
+        b.iter(|| {
+            for domain in &domains {
+                black_box(context.psl.match_suffix(black_box(domain.clone())));
+            }
+        })
+    });
+    common::finish(context);
+}
+
+fn bench_match_container(c: &mut Criterion) {
+    let mut context = common::load_context(&common::stored_context());
+    context.psl = common::psl();
+    let domains = domains();
+    c.bench_function("match_container", |b| {
+        b.iter(|| {
+            for domain in &domains {
+                let container_match = context.containers.match_container(
+                    black_box(domain.clone()),
+                    &context.psl,
+                    &SystemClock,
+                );
+                black_box(container_match.is_some());
+            }
+        })
+    });
+    common::finish(context);
+}
+
+criterion_group!(benches, bench_psl_match_suffix, bench_match_container);
+criterion_main!(benches);
@@ -1,7 +1,10 @@
 'use strict';
 
 import {
-  default as init, onMessage, onTabRemoved, onTabUpdated,
+  default as init, onAlarm, onCommand, onExtensionsChanged, onMenuClicked,
+  onMessage, onMessageExternal, onNotificationClicked, onOmniboxInputChanged,
+  onOmniboxInputEntered, onStorageChanged, onSuspend, onTabCreated,
+  onTabRemoved, onTabUpdated, onWindowRemoved,
 } from './cubicle.js';
 
 const listenerMap = new Map();
@@ -34,11 +37,52 @@ export function addRuntimeListener(event, handler) {
   }
 
   addRuntimeListener('onMessage', onMessage);
+  addRuntimeListener('onMessageExternal', onMessageExternal);
+  addRuntimeListener('onSuspend', onSuspend);
 
+  browser.storage.onChanged.addListener((changes, areaName) => {
+    wasmLoaded.then(async () => onStorageChanged(changes, areaName));
+  });
+  browser.tabs.onCreated.addListener((tab) => {
+    wasmLoaded.then(async () => onTabCreated(tab));
+  });
   browser.tabs.onRemoved.addListener((tabId) => {
     wasmLoaded.then(async () => onTabRemoved(tabId));
   });
-  browser.tabs.onUpdated.addListener((tabId, _changeInfo, tab) => {
-    wasmLoaded.then(async () => onTabUpdated(tabId, tab));
+  browser.tabs.onUpdated.addListener((tabId, changeInfo, tab) => {
+    wasmLoaded.then(async () =>
+      onTabUpdated(tabId, tab, changeInfo.url !== undefined));
   }, {properties: ['url']});
+  browser.windows.onRemoved.addListener((windowId) => {
+    wasmLoaded.then(async () => onWindowRemoved(windowId));
+  });
+  browser.alarms.onAlarm.addListener((alarm) => {
+    wasmLoaded.then(async () => onAlarm(alarm.name));
+  });
+  browser.notifications.onClicked.addListener((notificationId) => {
+    wasmLoaded.then(async () => onNotificationClicked(notificationId));
+  });
+  for (const event of ['onEnabled', 'onDisabled', 'onInstalled',
+    'onUninstalled']) {
+    browser.management[event].addListener(() => {
+      wasmLoaded.then(async () => onExtensionsChanged());
+    });
+  }
+  browser.menus.create({
+    id: 'open-homepage',
+    title: browser.i18n.getMessage('menu_open_homepage'),
+    contexts: ['tab'],
+  });
+  browser.menus.onClicked.addListener((info, tab) => {
+    wasmLoaded.then(async () => onMenuClicked(info.menuItemId, tab));
+  });
+  browser.commands.onCommand.addListener((command) => {
+    wasmLoaded.then(async () => onCommand(command));
+  });
+  browser.omnibox.onInputChanged.addListener((text, suggest) => {
+    wasmLoaded.then(async () => onOmniboxInputChanged(text)).then(suggest);
+  });
+  browser.omnibox.onInputEntered.addListener((text, disposition) => {
+    wasmLoaded.then(async () => onOmniboxInputEntered(text, disposition));
+  });
 })();
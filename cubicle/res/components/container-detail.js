@@ -1,6 +1,7 @@
 'use strict';
 
 import {
+  default as redirect,
   logStatus,
   messageContainerSelection,
   stateUpdateRedirect,
@@ -12,6 +13,10 @@ import {
  *     suffix ID and raw suffix value.
  */
 function colorizeSuffixInput(element) {
+  if (element.value.endsWith('.*')) {
+    element.style.color = 'orange';
+    return;
+  }
   switch (element.value.charAt(0)) {
     case '*': element.style.color = 'orange'; break;
     case '!': element.style.color = 'crimson'; break;
@@ -19,6 +24,46 @@ function colorizeSuffixInput(element) {
   }
 }
 
+/**
+ * Messages the background to validate the suffix in a text input element,
+ * then marks the element as invalid and describes the problems in its title.
+ * The original title is restored if the suffix is acceptable.
+ * @param {HTMLInputElement} element - Text input element with an encoded
+ *     suffix ID and raw suffix value.
+ */
+function messageValidateSuffix(element) {
+  const originalTitle = element.getAttribute('data-original-title') ??
+      element.title;
+  element.setAttribute('data-original-title', originalTitle);
+  if (element.value.trim() === '') {
+    element.setCustomValidity('');
+    element.title = originalTitle;
+    return;
+  }
+  browser.runtime.sendMessage({
+    message_type: 'validate_suffix',
+    value: element.value,
+    cookie_store_id: document.getElementById('select-container').value,
+  }).then((validation) => {
+    validation = JSON.parse(validation);
+    const problems = [];
+    if (validation.validity === 'invalid') {
+      problems.push(validation.reason);
+    } else {
+      if (validation.conflicts.length > 0) {
+        problems.push('Already used by ' + validation.conflicts.join(', '));
+      }
+      if (validation.is_public_suffix) {
+        problems.push(`'${validation.encoded}' is a public suffix, ` +
+            'the container may span unrelated sites');
+      }
+    }
+    element.setCustomValidity(problems.join('\n'));
+    element.title = problems.length > 0 ?
+        problems.join('\n') : originalTitle;
+  });
+}
+
 /**
  * Messages the background that the recorded suffixes are acceptable,
  * and should be persisted as a permanent container.
@@ -54,7 +99,277 @@ function messageUpdateSuffix(encodedOldSuffix, newSuffix) {
     if (newSuffix === '') {
       logStatus(`Suffix '${encodedOldSuffix}' was removed`)();
     } else logStatus(`Suffix '${newSuffix}' was added`)();
-  }).catch(logStatus(`Suffix '${newSuffix}' is invalid`));
+  }).catch((error) => {
+    logStatus(error?.message ?? `Suffix '${newSuffix}' is invalid`)();
+  });
+}
+
+/**
+ * Messages the background that all suffixes should be replaced,
+ * nothing is changed if any of the suffixes is invalid.
+ * Then updates the popup.
+ * @param {string} suffixText - Suffixes separated by new lines,
+ *     blank lines are ignored.
+ */
+function messageReplaceSuffixes(suffixText) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'replace_suffixes',
+      cookie_store_id: selectContainer.value,
+      suffixes: suffixText.split('\n'),
+    },
+  }).then(logStatus('Suffixes were replaced'))
+      .catch(logStatus('Some suffixes are invalid, nothing was changed'));
+}
+
+/**
+ * Requests the optional permission to read the browser history,
+ * then messages the background for suffixes visited alongside the selected
+ * container according to the history,
+ * then appends those not yet listed to the bulk edit text area.
+ * Must be called from a user action for the permission to be requested.
+ */
+function messageSuggestSuffixes() {
+  const selectContainer = document.getElementById('select-container');
+  const textarea = document.getElementById('textarea-suffixes');
+  browser.permissions.request({permissions: ['history']}).then((granted) => {
+    if (!granted) {
+      throw new Error('history permission was not granted');
+    }
+    return browser.runtime.sendMessage({
+      message_type: 'suggest_suffixes',
+      cookie_store_id: selectContainer.value,
+    });
+  }).then((response) => {
+    const listed = textarea.value.split('\n').map((line) => line.trim());
+    const suggestions = JSON.parse(response)
+        .filter((suffix) => !listed.includes(suffix));
+    if (suggestions.length === 0) {
+      logStatus('No suffixes were suggested from history')();
+      return;
+    }
+    textarea.value = [...listed.filter((line) => line !== ''),
+      ...suggestions].join('\n');
+  }).catch(logStatus('Suffixes were not suggested from history'));
+}
+
+/**
+ * Messages the background that all request headers should be replaced,
+ * nothing is changed if any of the headers is invalid.
+ * Then updates the popup.
+ * @param {string} headerText - Headers in the format of `Name: value`
+ *     separated by new lines, blank lines are ignored.
+ */
+function messageReplaceRequestHeaders(headerText) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'replace_request_headers',
+      cookie_store_id: selectContainer.value,
+      headers: headerText.split('\n'),
+    },
+  }).then(logStatus('Request headers were replaced'))
+      .catch(logStatus('Some request headers are invalid, nothing was changed'));
+}
+
+/**
+ * Messages the background that all rewrite rules should be replaced,
+ * nothing is changed if any of the rules is invalid.
+ * Then updates the popup.
+ * @param {string} ruleText - Rules in the format of `host from to` or
+ *     `strip name` separated by new lines, blank lines are ignored.
+ */
+function messageReplaceRewriteRules(ruleText) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'replace_rewrite_rules',
+      cookie_store_id: selectContainer.value,
+      rules: ruleText.split('\n'),
+    },
+  }).then(logStatus('Rewrite rules were replaced'))
+      .catch(logStatus('Some rewrite rules are invalid, nothing was changed'));
+}
+
+/**
+ * Messages the background that a copy of the selected container is requested,
+ * then updates the popup to select the copy.
+ * @param {boolean} excludeSuffixes - Whether the copy should start
+ *     with no suffixes.
+ * @param {boolean} asFamilyMember - Whether the copy should share the
+ *     suffixes of the selected container as another account.
+ */
+function messageCloneContainer(excludeSuffixes, asFamilyMember) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'clone_container',
+      cookie_store_id: selectContainer.value,
+      exclude_suffixes: excludeSuffixes,
+      as_family_member: asFamilyMember,
+    },
+  }).then(logStatus('Container was cloned'));
+}
+
+/**
+ * Messages the background that the selected container should no longer
+ * share the suffixes of its family, then updates the popup.
+ */
+function messageLeaveFamily() {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'leave_family',
+      cookie_store_id: selectContainer.value,
+    },
+  }).then(logStatus('Container left its family'));
+}
+
+/**
+ * Messages the background that the selected container should be, or should
+ * no longer be, the default container for new tabs in the current window.
+ * @param {boolean} isDefault - Whether the container should be the default.
+ */
+function messageSetWindowDefault(isDefault) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'set_window_default',
+      cookie_store_id: selectContainer.value,
+      is_default: isDefault,
+    },
+  }).then(logStatus(isDefault?
+      'Container is now the window default' :
+      'Container is no longer the window default'));
+}
+
+/**
+ * Messages the background that the suffixes and details of the selected
+ * container should be, or should no longer be, protected from modifications.
+ * @param {boolean} isLocked - Whether the container should be locked.
+ */
+function messageSetLocked(isLocked) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'set_locked',
+      cookie_store_id: selectContainer.value,
+      is_locked: isLocked,
+    },
+  }).then(logStatus(isLocked?
+      'Container is now locked' : 'Container is no longer locked'));
+}
+
+/**
+ * Messages the background that suffixes of the selected container should
+ * only match during the given hours, then updates the popup.
+ * @param {?object} activeHours - Active hours with `weekdays` as an array of
+ *     abbreviated weekday names, `start_hour` and exclusive `end_hour`,
+ *     `null` for matching at all times.
+ */
+function messageSetActiveHours(activeHours) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'set_active_hours',
+      cookie_store_id: selectContainer.value,
+      active_hours: activeHours,
+    },
+  }).then(logStatus(activeHours === null?
+      'Active hours were cleared' : 'Active hours were set'))
+      .catch(logStatus('Active hours are invalid, nothing was changed'));
+}
+
+/**
+ * Messages the background that the priority of the selected container
+ * should be changed, then updates the popup.
+ * @param {number} priority - Priority over other containers with suffixes
+ *     matching the same domain, higher is preferred.
+ */
+function messageSetPriority(priority) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'set_priority',
+      cookie_store_id: selectContainer.value,
+      priority: priority,
+    },
+  }).then(logStatus(`Priority was set to ${priority}`));
+}
+
+/**
+ * Messages the background that the homepage of the selected container
+ * should be replaced, then updates the popup.
+ * @param {string} homepage - Web address of the homepage,
+ *     empty string for removing the homepage instead.
+ */
+function messageSetHomepage(homepage) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'set_homepage',
+      cookie_store_id: selectContainer.value,
+      homepage: homepage,
+    },
+  }).then(logStatus('Homepage was set'))
+      .catch(logStatus(`Homepage '${homepage}' is invalid`));
+}
+
+/**
+ * Messages the background that a new tab should be opened to the homepage
+ * of the selected container, then closes the popup.
+ */
+function messageOpenHomepage() {
+  browser.runtime.sendMessage({
+    message_type: 'open_homepage',
+    cookie_store_id: document.getElementById('select-container').value,
+  }).then(() => window.close())
+      .catch(logStatus('Homepage was not opened'));
+}
+
+/**
+ * Messages the background that all tabs in the selected container should be
+ * unloaded from memory, then updates the popup.
+ */
+function messageDiscardContainerTabs() {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'discard_container_tabs',
+      cookie_store_id: selectContainer.value,
+    },
+  }).then(logStatus('Tabs were discarded'))
+      .catch(logStatus('Tabs were not discarded'));
+}
+
+/**
+ * Messages the background that all tabs in the selected container should be
+ * moved into a new window, then updates the popup.
+ * @param {boolean} shouldNameWindow - Whether the window title should be
+ *     prefixed with the name of the container.
+ */
+function messageGatherTabs(shouldNameWindow) {
+  const selectContainer = document.getElementById('select-container');
+  stateUpdateRedirect('container_action', {
+    action: {
+      action: 'gather_tabs',
+      cookie_store_id: selectContainer.value,
+      should_name_window: shouldNameWindow,
+    },
+  }).then(logStatus('Tabs were gathered'))
+      .catch(logStatus('Tabs were not gathered'));
+}
+
+/**
+ * Messages the background that a tab should be brought to the front.
+ * @param {number} tabId - ID of the tab to be activated.
+ */
+function messageFocusTab(tabId) {
+  browser.runtime.sendMessage({
+    message_type: 'focus_tab',
+    tab_id: tabId,
+  }).then(() => window.close());
 }
 
 /**
@@ -72,8 +387,10 @@ export default function main() {
   for (const element of document.getElementsByClassName('input-suffix')) {
     const encodedOldSuffix = element.id.slice('suffix-'.length);
 
+    colorizeSuffixInput(element);
     element.addEventListener('input', (event) => {
       colorizeSuffixInput(event.target);
+      messageValidateSuffix(event.target);
     });
     element.addEventListener('keydown', (event) => {
       if (event.key === 'Enter') {
@@ -85,5 +402,88 @@ export default function main() {
         .addEventListener('click', () => {
           messageUpdateSuffix(encodedOldSuffix, '');
         });
+    const btnExclusion =
+        document.getElementById('btn-exclusion-' + encodedOldSuffix);
+    btnExclusion?.addEventListener('click', () => {
+      messageUpdateSuffix(encodedOldSuffix,
+          btnExclusion.getAttribute('data-toggled-suffix'));
+    });
+  }
+
+  document.getElementById('btn-clone').addEventListener('click', () => {
+    messageCloneContainer(
+        !document.getElementById('check-clone-suffixes').checked,
+        document.getElementById('check-clone-family').checked);
+  });
+  document.getElementById('btn-leave-family')
+      ?.addEventListener('click', messageLeaveFamily);
+  document.getElementById('btn-cookies').addEventListener('click', () => {
+    redirect({
+      view: 'container_cookies',
+      cookie_store_id: document.getElementById('select-container').value,
+    });
+  });
+  document.getElementById('btn-open-homepage')
+      .addEventListener('click', messageOpenHomepage);
+  document.getElementById('input-homepage')
+      .addEventListener('change', (event) => {
+        messageSetHomepage(event.target.value.trim());
+      });
+  document.getElementById('check-window-default')
+      .addEventListener('change', (event) => {
+        messageSetWindowDefault(event.target.checked);
+      });
+  document.getElementById('input-priority')
+      .addEventListener('change', (event) => {
+        if (Number.isInteger(event.target.valueAsNumber)) {
+          messageSetPriority(event.target.valueAsNumber);
+        }
+      });
+  document.getElementById('btn-set-active-hours')
+      .addEventListener('click', () => {
+        const weekdays = [];
+        for (const element of document
+            .getElementsByClassName('check-weekday')) {
+          if (element.checked) weekdays.push(element.value);
+        }
+        messageSetActiveHours({
+          weekdays: weekdays,
+          start_hour: Number(document.getElementById('input-start-hour').value),
+          end_hour: Number(document.getElementById('input-end-hour').value),
+        });
+      });
+  document.getElementById('btn-clear-active-hours')
+      ?.addEventListener('click', () => messageSetActiveHours(null));
+  document.getElementById('check-locked')
+      ?.addEventListener('change', (event) => {
+        messageSetLocked(event.target.checked);
+      });
+  document.getElementById('btn-replace-suffixes')
+      .addEventListener('click', () => {
+        messageReplaceSuffixes(
+            document.getElementById('textarea-suffixes').value);
+      });
+  document.getElementById('btn-suggest-suffixes')
+      .addEventListener('click', messageSuggestSuffixes);
+  document.getElementById('btn-replace-headers')
+      .addEventListener('click', () => {
+        messageReplaceRequestHeaders(
+            document.getElementById('textarea-headers').value);
+      });
+  document.getElementById('btn-replace-rewrite-rules')
+      .addEventListener('click', () => {
+        messageReplaceRewriteRules(
+            document.getElementById('textarea-rewrite-rules').value);
+      });
+
+  document.getElementById('btn-discard-tabs')
+      ?.addEventListener('click', messageDiscardContainerTabs);
+  document.getElementById('btn-gather-tabs')?.addEventListener('click', () => {
+    messageGatherTabs(document.getElementById('check-name-window').checked);
+  });
+  for (const element of document.getElementsByClassName('btn-focus-tab')) {
+    element.addEventListener('click', () => {
+      messageFocusTab(Number(element.getAttribute('data-tab-id')));
+    });
   }
 }
@@ -27,10 +27,13 @@ export function logStatus(message) {
  * Messages the background about a container selection, then updates the popup.
  * @param {string} value - The ID of the selected container if it starts with
  *     [COOKIE_STORE_ID_MARKER_PREFIX], `new` if a new container is requested,
+ *     `more` if the next page of containers is requested,
  *     and `none` if "no container" (default cookie store) is selected.
  * @return {Promise} Promise that fulfils once the update is fully complete.
  */
 export function messageContainerSelection(value) {
+  if (value === 'more') return loadMoreContainers();
+  document.getElementById('select-container').dataset.lastValue = value;
   const iconBtn = document.getElementById('btn-icon');
   const iconImg = document.getElementById('img-icon');
 
@@ -99,14 +102,41 @@ export async function stateUpdateRedirect(messageType, messageEnum) {
   });
 }
 
+/**
+ * Lists the next page of containers in place of the `more` entry,
+ * then selects the previously selected entry again.
+ * @return {Promise} Promise that fulfils once the page is listed.
+ */
+async function loadMoreContainers() {
+  const selectElement = document.getElementById('select-container');
+  const moreOption = selectElement.querySelector('option[value="more"]');
+  selectElement.value = selectElement.dataset.lastValue ?? 'none';
+  const filter = moreOption.dataset.filter;
+  return browser.runtime.sendMessage({
+    message_type: 'request_page', view: {
+      view: 'fetch_all_containers',
+      selected: moreOption.dataset.selected,
+      filter: filter === '' ? null : filter,
+      offset: Number(moreOption.dataset.nextOffset),
+    },
+  }).then((html) => {
+    moreOption.insertAdjacentHTML('beforebegin', html);
+    moreOption.remove();
+  });
+}
+
 /**
  * Updates the container listing in the `select-container` element.
+ * @param {?string} selected - The ID of the container to be selected,
+ *     `null` for the container of the current tab.
+ * @param {?string} filter - Text that the listed containers' names or
+ *     suffixes should contain, `null` to list all containers.
  * @return {Promise} Promise that fulfils once the listing is updated.
  */
-export async function updateContainerListing() {
+export async function updateContainerListing(selected = null, filter = null) {
   return browser.runtime.sendMessage({
     message_type: 'request_page', view: {
-      view: 'fetch_all_containers', selected: null,
+      view: 'fetch_all_containers', selected: selected, filter: filter,
     },
   }).then((html) => {
     const selectElement = document.getElementById('select-container');
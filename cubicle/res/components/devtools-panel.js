@@ -0,0 +1,16 @@
+'use strict';
+
+import redirect from './context.js';
+
+/**
+ * Entrypoint for the devtools panel describing the inspected tab.
+ * Mainly for attaching listeners.
+ * @param {object} viewEnum - View that was rendered, for refreshing.
+ */
+export default function main(viewEnum) {
+  document.getElementById('btn-devtools-refresh')
+      .addEventListener('click', () => redirect(viewEnum));
+  document.getElementById('btn-devtools-copy')
+      .addEventListener('click', () => navigator.clipboard.writeText(
+          document.getElementById('pre-devtools-json').innerText));
+}
@@ -29,7 +29,44 @@ function messageSubmitIdentityDetails(event) {
       cookie_store_id: cookieStoreId, details: identityDetails,
       should_record: shouldRecord,
     },
-  }).then(logStatus('Container was ' + verb));
+  }).then(logStatus('Container was ' + verb))
+      .catch(logStatus('Container was not ' + verb));
+}
+
+/**
+ * Shows a warning if the name is used by another container,
+ * names are compared case-insensitively.
+ * Duplicate names are allowed but may be confusing in the listing.
+ */
+function warnTakenName() {
+  const name = document.getElementById('form-new-container')
+      .elements['name'].value.trim().toLowerCase();
+  const isTaken = Array.from(
+      document.getElementById('datalist-taken-names').options)
+      .some((option) => option.value === name);
+  document.getElementById('lbl-name-taken').style.display =
+      isTaken? 'block' : 'none';
+}
+
+/**
+ * Messages the background for details suggested from the current tab,
+ * then fills the form with the suggestion.
+ * The name is kept if no name is suggested.
+ */
+function messageSuggestIdentityDetails() {
+  browser.runtime.sendMessage({
+    message_type: 'suggest_identity_details',
+  }).then((detailsJson) => {
+    const details = JSON.parse(detailsJson);
+    const form = document.getElementById('form-new-container');
+    if (details.name !== '') form.elements['name'].value = details.name;
+    warnTakenName();
+    for (const key of ['color', 'icon']) {
+      const radio = document.getElementById(`radio-${details[key]}`);
+      if (radio !== null) radio.checked = true;
+    }
+  }).then(logStatus('Details were suggested from the current tab'))
+      .catch(logStatus('No details can be suggested from the current tab'));
 }
 
 /**
@@ -40,4 +77,9 @@ function messageSubmitIdentityDetails(event) {
 export default function main() {
   document.getElementById('form-new-container')
       .addEventListener('submit', messageSubmitIdentityDetails);
+  document.getElementById('form-new-container').elements['name']
+      .addEventListener('input', warnTakenName);
+  warnTakenName();
+  document.getElementById('btn-suggest')
+      ?.addEventListener('click', messageSuggestIdentityDetails);
 }
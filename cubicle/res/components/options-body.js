@@ -1,8 +1,28 @@
 'use strict';
 
+import redirect from './context.js';
+
+/**
+ * Updates the PSL download progress bar,
+ * which is indeterminate if the total size is unknown.
+ * @param {object} event - Event emitted by the background.
+ */
+function onPslUpdateProgress(event) {
+  if (event.event_type !== 'psl_update_progress') return;
+  const progressPsl = document.getElementById('progress-psl');
+  if (progressPsl === null) return;
+  if (event.total === null) {
+    progressPsl.removeAttribute('value');
+  } else {
+    progressPsl.max = event.total;
+    progressPsl.value = event.loaded;
+  }
+}
+
 /**
  * Messages the background that a PSL update is requested,
  * then updates the "last updated" date.
+ * Progress is shown while the list is downloaded.
  * @param {Event} event - Generated click event,
  *     for disabling the button on click.
  */
@@ -11,10 +31,145 @@ function messagePslUpdate(event) {
   const pslUrl = document.getElementById('url-psl-url').value;
   pslUrl.disabled = true;
   const lblPslDate = document.getElementById('lbl-psl-date');
+  const progressPsl = document.getElementById('progress-psl');
+  progressPsl.removeAttribute('value');
+  progressPsl.hidden = false;
   browser.runtime.sendMessage({
     message_type: 'psl_update',
     url: pslUrl === ''? null : pslUrl,
-  }).then((newDate) => lblPslDate.innerText = newDate);
+  }).then((newDate) => lblPslDate.innerText = newDate)
+      .finally(() => progressPsl.hidden = true);
+}
+
+/**
+ * Converts the value of a container target selection to a target.
+ * @param {string} value - `no_container`, `temporary`,
+ *     or the ID of a designated container.
+ * @return {object} The target for the background.
+ */
+function toContainerTarget(value) {
+  return ['no_container', 'temporary'].includes(value) ?
+      {target: value} : {target: 'designated', cookie_store_id: value};
+}
+
+/**
+ * Collects the external link rules from the rules table,
+ * rows with empty suffixes are skipped.
+ * @return {object} Targets keyed by suffixes.
+ */
+function collectExternalLinkRules() {
+  const rules = {};
+  const rows = document.getElementById('table-external-link-rules').rows;
+  for (const row of rows) {
+    const suffix = row.querySelector('.input-rule-suffix').value.trim();
+    if (suffix === '') continue;
+    rules[suffix] =
+        toContainerTarget(row.querySelector('.select-rule-target').value);
+  }
+  return rules;
+}
+
+/**
+ * Collects the remembered choices of sites from the overrides table.
+ * @return {object} Lists of overrides keyed by suffixes.
+ */
+function collectDomainOverrides() {
+  const domainOverrides = {};
+  const rows = document.getElementById('table-domain-overrides').rows;
+  for (const row of rows) {
+    const suffix = row.dataset.suffix;
+    domainOverrides[suffix] ??= [];
+    domainOverrides[suffix].push({
+      from_container: row.dataset.fromContainer,
+      target: toContainerTarget(row.dataset.target),
+    });
+  }
+  return domainOverrides;
+}
+
+/**
+ * Removes the row of an external link rule from the rules table.
+ * @param {Event} event - Generated click event,
+ *     for finding the row of the button.
+ */
+function removeExternalLinkRule(event) {
+  event.target.closest('tr').remove();
+}
+
+/**
+ * Appends an empty external link rule to the rules table.
+ */
+function addExternalLinkRule() {
+  const row = document.getElementById('template-external-link-rule')
+      .content.cloneNode(true);
+  row.querySelector('.btn-remove-rule')
+      .addEventListener('click', removeExternalLinkRule);
+  document.getElementById('table-external-link-rules').append(row);
+}
+
+/**
+ * Moves an assigning step before the previous step in the list.
+ * @param {Event} event - Generated click event,
+ *     for finding the list item of the button.
+ */
+function moveAssignStepUp(event) {
+  const item = event.target.closest('li');
+  item.previousElementSibling?.before(item);
+  messagePreviewStrategies();
+}
+
+/**
+ * Collects the checked steps of the assign chain in their listed order.
+ * @return {string[]} Steps of the chain.
+ */
+function collectAssignChain() {
+  return Array.from(document
+      .getElementsByClassName('checkbox-assign-step'))
+      .filter((checkbox) => checkbox.checked)
+      .map((checkbox) => checkbox.dataset.step);
+}
+
+/**
+ * Describes where a URL would be opened for the preview.
+ * @param {object} trace - Trace of where the URL would be opened.
+ * @return {string} The localized description.
+ */
+function describePreviewTrace(trace) {
+  const decision = trace.decision;
+  if (decision.decision === 'move') {
+    return trace.target_name;
+  }
+  if (decision.decision === 'new_temporary' && decision.domain !== null) {
+    return browser.i18n.getMessage(
+        'options_preview_suffixed_temporary', decision.domain);
+  }
+  return browser.i18n.getMessage(`rule_tester_decision_${decision.decision}`);
+}
+
+/**
+ * Messages the background to evaluate the sample URLs against the
+ * strategies that are selected but not yet saved, then shows the results.
+ * @return {Promise} Promise that fulfils once the results are shown.
+ */
+function messagePreviewStrategies() {
+  const rows = Array.from(document
+      .getElementById('table-strategy-preview').tBodies[0].rows);
+  const container =
+      document.getElementById('select-preview-container').value;
+  const ejectStrategy = document
+      .querySelector('input[name="eject_strategy"]:checked')?.value;
+  return browser.runtime.sendMessage({
+    message_type: 'preview_strategies',
+    urls: rows.map((row) => row.dataset.url),
+    current_container: container === 'none' ? null : container,
+    assign_chain: collectAssignChain(),
+    eject_strategy: ejectStrategy ?? 'isolated_temporary',
+  }).then((traces) => {
+    JSON.parse(traces).forEach((trace, index) => {
+      rows[index].getElementsByClassName('data-strategy-preview')[0]
+          .innerText = describePreviewTrace(trace);
+    });
+  });
 }
 
 /**
@@ -30,6 +185,46 @@ function messageApplyPreferences(event) {
   const shouldRevertOldTabCheckbox = document
       .getElementById('checkbox-should-revert-old-tab');
   preferences['should_revert_old_tab'] = shouldRevertOldTabCheckbox.checked;
+  const shouldNotifyRelocationCheckbox = document
+      .getElementById('checkbox-should-notify-relocation');
+  preferences['should_notify_relocation'] =
+      shouldNotifyRelocationCheckbox.checked;
+  const shouldSimulateRelocationCheckbox = document
+      .getElementById('checkbox-should-simulate-relocation');
+  preferences['should_simulate_relocation'] =
+      shouldSimulateRelocationCheckbox.checked;
+  const shouldLearnSuffixesCheckbox = document
+      .getElementById('checkbox-should-learn-suffixes');
+  preferences['should_learn_suffixes'] = shouldLearnSuffixesCheckbox.checked;
+  const shouldReduceSuffixesCheckbox = document
+      .getElementById('checkbox-should-reduce-suffixes');
+  preferences['should_reduce_suffixes'] = shouldReduceSuffixesCheckbox.checked;
+  const shouldInheritOpenerContainerCheckbox = document
+      .getElementById('checkbox-should-inherit-opener-container');
+  preferences['should_inherit_opener_container'] =
+      shouldInheritOpenerContainerCheckbox.checked;
+  preferences['assign_chain'] = collectAssignChain();
+  preferences['new_tab_container'] = toContainerTarget(
+      document.getElementById('select-new-tab-container').value);
+  preferences['external_link_container'] = toContainerTarget(
+      document.getElementById('select-external-link-container').value);
+  preferences['external_link_rules'] = collectExternalLinkRules();
+  preferences['domain_overrides'] = collectDomainOverrides();
+  preferences['opener_chain'] = {
+    depth: Number(document.getElementById('number-opener-chain-depth').value),
+    expiry_secs:
+        Number(document.getElementById('number-opener-chain-expiry').value),
+  };
+  preferences['trusted_extension_ids'] = document
+      .getElementById('textarea-trusted-extension-ids').value
+      .split(/[\s,]+/).filter((extensionId) => extensionId !== '');
+  preferences['intercepted_schemes'] = Array.from(document
+      .getElementsByClassName('checkbox-intercepted-scheme'))
+      .filter((checkbox) => checkbox.checked)
+      .map((checkbox) => checkbox.value);
+  preferences['additional_schemes'] = document
+      .getElementById('input-additional-schemes').value.toLowerCase()
+      .split(/[\s,]+/).filter((scheme) => scheme !== '');
 
   browser.runtime.sendMessage({
     message_type: 'apply_preferences',
@@ -37,6 +232,186 @@ function messageApplyPreferences(event) {
   });
 }
 
+/**
+ * Messages the background that a bookmark folder should be bound to
+ * a container, then refreshes the preferences page.
+ * @param {Event} event - Generated submit event, for extracting form data.
+ */
+function messageBindBookmarkFolder(event) {
+  const formData = new FormData(event.target);
+  browser.runtime.sendMessage({
+    message_type: 'bind_bookmark_folder',
+    folder_id: formData.get('folder_id'),
+    cookie_store_id: formData.get('cookie_store_id'),
+  }).then(() => redirect({view: 'options_body'}));
+}
+
+/**
+ * Messages the background that a bookmark folder should be unbound,
+ * then refreshes the preferences page.
+ * @param {Event} event - Generated click event, for finding the folder ID.
+ */
+function messageUnbindBookmarkFolder(event) {
+  browser.runtime.sendMessage({
+    message_type: 'unbind_bookmark_folder',
+    folder_id: event.target.dataset.folderId,
+  }).then(() => redirect({view: 'options_body'}));
+}
+
+/**
+ * Messages the background that the alias groups should be replaced,
+ * then refreshes the preferences page.
+ * Nothing is changed if any of the domains is invalid.
+ */
+function messageReplaceAliasGroups() {
+  const aliasGroupText =
+      document.getElementById('textarea-alias-groups').value;
+  browser.runtime.sendMessage({
+    message_type: 'replace_alias_groups',
+    groups: aliasGroupText.split('\n'),
+  }).then(() => redirect({view: 'options_body'}));
+}
+
+/**
+ * Messages the background that orphaned entries should be removed
+ * from the storage, then refreshes the preferences page.
+ * @param {Event} event - Generated click event,
+ *     for disabling the button on click.
+ */
+function messageCompactStorage(event) {
+  event.target.disabled = true;
+  browser.runtime.sendMessage({message_type: 'compact_storage'})
+      .then(() => redirect({view: 'options_body'}));
+}
+
+/**
+ * Messages the background that the guest mode should be entered or left,
+ * with the PIN if entered, then refreshes the preferences page.
+ * @param {Event} event - Generated click event, for finding the message type.
+ */
+function messageGuestMode(event) {
+  const pin = document.getElementById('input-guest-pin')?.value ?? '';
+  browser.runtime.sendMessage({
+    message_type: event.target.dataset.messageType,
+    pin: pin === '' ? null : pin,
+  }).then(() => redirect({view: 'options_body'}))
+      .catch((error) => document.getElementById('lbl-guest-mode').innerText =
+          error.message);
+}
+
+/**
+ * Messages the background to reconcile its containers with the identities
+ * in the browser, then lists the differences that remain,
+ * each with a button for adopting or cleaning it.
+ * Unknown identities are adopted with the suffixes in their text fields,
+ * which are pre-populated with suggestions from their open tabs.
+ * @param {object} actions - Identities to adopt or clean before listing.
+ * @param {object[]} [actions.adopt] - IDs and suffixes of identities to adopt.
+ * @param {string[]} [actions.clean] - IDs of entries to clean.
+ */
+function messageReconcile({adopt = [], clean = []} = {}) {
+  browser.runtime.sendMessage({
+    message_type: 'reconcile',
+    adopt: adopt,
+    clean: clean,
+  }).then((reconciliation) => {
+    const {unknown_identities: unknown, missing_identities: missing} =
+        JSON.parse(reconciliation);
+    const createItem = (entry, labelKey, buttonKey) => {
+      const item = document.createElement('li');
+      item.innerText = browser.i18n.getMessage(labelKey, entry.name) + ' ';
+      const button = document.createElement('button');
+      button.innerText = browser.i18n.getMessage(buttonKey);
+      item.appendChild(button);
+      return [item, button];
+    };
+    document.getElementById('list-reconcile-unknown').replaceChildren(
+        ...unknown.map((entry) => {
+          const [item, button] = createItem(entry,
+              'options_reconcile_unknown', 'options_reconcile_adopt');
+          const inputSuffixes = document.createElement('input');
+          inputSuffixes.type = 'text';
+          inputSuffixes.value = entry.suggested_suffixes.join(' ');
+          inputSuffixes.placeholder =
+              browser.i18n.getMessage('options_reconcile_suffixes');
+          inputSuffixes.setAttribute('aria-label', inputSuffixes.placeholder);
+          item.insertBefore(inputSuffixes, button);
+          button.addEventListener('click', () => messageReconcile({adopt: [{
+            cookie_store_id: entry.cookie_store_id,
+            suffixes: inputSuffixes.value.split(/\s+/),
+          }]}));
+          return item;
+        }));
+    document.getElementById('list-reconcile-missing').replaceChildren(
+        ...missing.map((entry) => {
+          const [item, button] = createItem(entry,
+              'options_reconcile_missing', 'options_reconcile_clean');
+          button.addEventListener('click',
+              () => messageReconcile({clean: [entry.cookie_store_id]}));
+          return item;
+        }));
+  });
+}
+
+/**
+ * Messages the background that temporary containers should be deleted,
+ * only those idle for the given number of days if specified,
+ * then shows the number of deleted containers.
+ * @param {Event} event - Generated click event,
+ *     for disabling the button on click.
+ */
+function messagePurgeTemporary(event) {
+  event.target.disabled = true;
+  const days = document.getElementById('input-purge-temporary-days').value;
+  const lblPurgeTemporary = document.getElementById('lbl-purge-temporary');
+  browser.runtime.sendMessage({
+    message_type: 'container_action',
+    action: {
+      action: 'purge_temporary',
+      older_than: days === '' ? null : {secs: Number(days) * 86400, nanos: 0},
+    },
+  }).then((count) => lblPurgeTemporary.innerText =
+      browser.i18n.getMessage('options_purge_temporary_done', count))
+      .finally(() => event.target.disabled = false);
+}
+
+/**
+ * Requests the number of outstanding handles of each container
+ * from the background, then lists them in the preferences page.
+ */
+function messageHandleDiagnostics() {
+  browser.runtime.sendMessage({message_type: 'handle_diagnostics'})
+      .then((diagnostics) => {
+        const items = JSON.parse(diagnostics).map((diagnostic) => {
+          const item = document.createElement('li');
+          const variant = diagnostic.is_temporary ? ' (temporary)' : '';
+          item.innerText = `${diagnostic.name}${variant}: ` +
+              diagnostic.outstanding_handles;
+          return item;
+        });
+        document.getElementById('list-handle-diagnostics')
+            .replaceChildren(...items);
+      });
+}
+
+/**
+ * Copies the report of the last crash to the clipboard.
+ */
+function copyCrashReport() {
+  const report = document.getElementById('pre-crash-report').innerText;
+  navigator.clipboard.writeText(report);
+}
+
+/**
+ * Requests the optional permission to read privacy settings,
+ * then refreshes the preferences page to show how the browser isolates sites.
+ * Must be called from a user action for the permission to be requested.
+ */
+function requestIsolationDetection() {
+  browser.permissions.request({permissions: ['privacy']})
+      .then(() => redirect({view: 'options_body'}));
+}
+
 /**
  * Entrypoint for the extension preferences page.
  * Mainly for attaching listeners.
@@ -44,6 +419,50 @@ function messageApplyPreferences(event) {
 export default function main() {
   document.getElementById('btn-psl-update')
       .addEventListener('click', messagePslUpdate);
+  browser.runtime.onMessage.addListener(onPslUpdateProgress);
   document.getElementById('form-preferences')
       .addEventListener('submit', messageApplyPreferences);
+  const sectionCompactConfirm =
+      document.getElementById('section-compact-confirm');
+  document.getElementById('btn-compact-storage')
+      .addEventListener('click', () => sectionCompactConfirm.hidden = false);
+  document.getElementById('btn-compact-yes')
+      .addEventListener('click', messageCompactStorage);
+  document.getElementById('btn-compact-no')
+      .addEventListener('click', () => sectionCompactConfirm.hidden = true);
+  document.getElementById('btn-guest-mode')
+      .addEventListener('click', messageGuestMode);
+  document.getElementById('btn-detect-isolation')
+      ?.addEventListener('click', requestIsolationDetection);
+  document.getElementById('btn-reconcile')
+      .addEventListener('click', () => messageReconcile());
+  document.getElementById('btn-purge-temporary')
+      .addEventListener('click', messagePurgeTemporary);
+  document.getElementById('btn-add-external-link-rule')
+      .addEventListener('click', addExternalLinkRule);
+  for (const button of document.getElementsByClassName('btn-remove-rule')) {
+    button.addEventListener('click', removeExternalLinkRule);
+  }
+  for (const button of document
+      .getElementsByClassName('btn-move-assign-step-up')) {
+    button.addEventListener('click', moveAssignStepUp);
+  }
+  for (const input of document.querySelectorAll(
+      '.checkbox-assign-step, input[name="eject_strategy"], ' +
+      '#select-preview-container')) {
+    input.addEventListener('change', messagePreviewStrategies);
+  }
+  messagePreviewStrategies();
+  document.getElementById('btn-handle-diagnostics')
+      .addEventListener('click', messageHandleDiagnostics);
+  document.getElementById('btn-copy-crash-report')
+      ?.addEventListener('click', copyCrashReport);
+  document.getElementById('form-bookmark-binding')
+      .addEventListener('submit', messageBindBookmarkFolder);
+  for (const button of document
+      .getElementsByClassName('btn-unbind-bookmark-folder')) {
+    button.addEventListener('click', messageUnbindBookmarkFolder);
+  }
+  document.getElementById('btn-replace-alias-groups')
+      .addEventListener('click', messageReplaceAliasGroups);
 }
@@ -0,0 +1,98 @@
+'use strict';
+
+/**
+ * Messages the background that the tab should be reopened in the container,
+ * then closes the switcher.
+ * @param {string} cookieStoreId - ID of the chosen container.
+ */
+function messageReopenTab(cookieStoreId) {
+  const tabId = document.getElementById('list-quick-switcher').dataset.tabId;
+  browser.runtime.sendMessage({
+    message_type: 'reopen_tab',
+    tab_id: Number(tabId),
+    cookie_store_id: cookieStoreId,
+  }).finally(() => window.close());
+}
+
+/**
+ * Messages the background for containers matching the query,
+ * then lists only the matched containers with the best matches first.
+ * @param {string} query - Text typed in the search box.
+ * @return {Promise} Promise that fulfils once the listing is updated.
+ */
+function messageSearchContainers(query) {
+  return browser.runtime.sendMessage({
+    message_type: 'search_containers',
+    query: query,
+  }).then((matches) => {
+    const list = document.getElementById('list-quick-switcher');
+    const items = new Map(Array.from(list.children)
+        .map((item) => [item.dataset.cookieStoreId, item]));
+    for (const item of items.values()) item.hidden = true;
+    for (const cookieStoreId of JSON.parse(matches)) {
+      const item = items.get(cookieStoreId);
+      if (item === undefined) continue;
+      item.hidden = false;
+      list.append(item);
+    }
+    document.getElementById('lbl-quick-switcher-empty').hidden =
+        Array.from(items.values()).some((item) => !item.hidden);
+  });
+}
+
+/**
+ * Visible buttons of the listed containers, in their listed order.
+ * @return {HTMLElement[]} Buttons for choosing the containers.
+ */
+function visibleButtons() {
+  return Array.from(document.getElementsByClassName('btn-quick-switch'))
+      .filter((button) => !button.closest('li').hidden);
+}
+
+/**
+ * Handles keys for choosing a container without the mouse,
+ * the arrow keys move between the containers,
+ * Enter in the search box chooses the best match,
+ * and Escape closes the switcher.
+ * @param {KeyboardEvent} event - Generated keydown event.
+ */
+function onSwitcherKeyDown(event) {
+  const buttons = visibleButtons();
+  const index = buttons.indexOf(document.activeElement);
+  switch (event.key) {
+    case 'ArrowDown':
+      buttons[Math.min(index + 1, buttons.length - 1)]?.focus();
+      break;
+    case 'ArrowUp':
+      if (index <= 0) {
+        document.getElementById('input-quick-switcher').focus();
+      } else buttons[index - 1].focus();
+      break;
+    case 'Enter':
+      if (index !== -1) return;
+      buttons[0]?.click();
+      break;
+    case 'Escape':
+      window.close();
+      break;
+    default:
+      return;
+  }
+  event.preventDefault();
+}
+
+/**
+ * Entry for the switcher of the container of a tab.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  for (const button of document.getElementsByClassName('btn-quick-switch')) {
+    button.addEventListener('click',
+        () => messageReopenTab(button.dataset.cookieStoreId));
+  }
+  const input = document.getElementById('input-quick-switcher');
+  input.addEventListener('input',
+      () => messageSearchContainers(input.value));
+  document.addEventListener('keydown', onSwitcherKeyDown);
+  input.focus();
+}
@@ -0,0 +1,68 @@
+'use strict';
+
+/**
+ * Gets a message of the extension's locale for showing the trace,
+ * falling back to the given text if there is no such message.
+ * @param {string} key - Key of the message.
+ * @param {string} fallback - Text to show if there is no such message.
+ * @return {string} The localized message.
+ */
+function localize(key, fallback) {
+  return browser.i18n.getMessage(key) || fallback;
+}
+
+/**
+ * Shows a decision trace in the result list.
+ * @param {object} trace - Trace of where the URL would be opened.
+ */
+function showTrace(trace) {
+  const none = localize('rule_tester_none', 'None');
+  const set = (id, text) => {
+    document.getElementById(id).innerText = text ?? none;
+  };
+  set('data-rule-tester-domain', trace.domain);
+  set('data-rule-tester-registrable-domain', trace.registrable_domain);
+  set('data-rule-tester-matched-suffix', trace.matched_suffix);
+  const branch = trace.designation === null ?
+      localize(`rule_tester_branch_${trace.branch}`, trace.branch) :
+      localize(`rule_tester_designation_${trace.designation}`,
+          trace.designation);
+  set('data-rule-tester-branch', branch);
+  const decision = trace.decision.decision;
+  set('data-rule-tester-target', decision === 'move' ?
+      trace.target_name :
+      localize(`rule_tester_decision_${decision}`, decision));
+  document.getElementById('list-rule-tester-result').style.display = 'block';
+}
+
+/**
+ * Messages the background to simulate opening the URL,
+ * then shows where it would be opened.
+ * @return {Promise} Promise that fulfils once the result is shown.
+ */
+function messageSimulateRelocation() {
+  const url = document.getElementById('input-rule-tester-url').value;
+  const container =
+      document.getElementById('select-rule-tester-container').value;
+  return browser.runtime.sendMessage({
+    message_type: 'simulate_relocation',
+    url: url,
+    current_container: container === 'none' ? null : container,
+  }).then((trace) => showTrace(JSON.parse(trace)));
+}
+
+/**
+ * Entry for the rule tester.
+ * Mainly for attaching listeners.
+ */
+export default function main() {
+  const form = document.getElementById('form-rule-tester');
+  form.addEventListener('submit', (event) => {
+    event.preventDefault();
+    messageSimulateRelocation();
+  });
+  document.getElementById('input-rule-tester-url')
+      .addEventListener('input', messageSimulateRelocation);
+  document.getElementById('select-rule-tester-container')
+      .addEventListener('change', messageSimulateRelocation);
+}
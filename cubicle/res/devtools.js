@@ -0,0 +1,12 @@
+'use strict';
+
+/**
+ * Main entrypoint for the devtools page, which creates the Cubicle panel.
+ * The panel is given the ID of the inspected tab in its own script,
+ * as the `devtools` API is not available to the background.
+ * This is an IIFE as this is a standalone page.
+ */
+(function main() {
+  browser.devtools.panels.create(
+      'Cubicle', '', '/inspector.html');
+})();
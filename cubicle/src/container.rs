@@ -1,15 +1,20 @@
 //! Additional functionalities for the builtin [ContextualIdentity].
 
 use std::cell::Cell;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
-use std::thread;
+use std::sync::{Arc, Weak};
+use std::{iter, thread};
 
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Timelike, Utc, Weekday};
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::domain::suffix::{self, MatchMode, Suffix, SuffixType};
-use crate::domain::EncodedDomain;
+use crate::domain::psl::Psl;
+use crate::domain::suffix::{Suffix, SuffixType};
+use crate::domain::{EncodedDomain, IdnaPolicy};
 #[mockall_double::double]
 use crate::interop::contextual_identities::ContextualIdentity;
 use crate::interop::contextual_identities::{
@@ -17,19 +22,34 @@ use crate::interop::contextual_identities::{
 };
 use crate::interop::storage;
 use crate::interop::tabs::TabId;
+use crate::interop::web_request::HttpHeader;
+use crate::rewrite::RewriteRule;
 use crate::tab::RelocationDetail;
+use crate::transaction::Staged;
+use crate::util::clock::Clock;
 use crate::util::errors::CustomError;
 
 /// A glorified lookup table for [Container],
 /// either from a [CookieStoreId] or an [EncodedDomain].
-#[derive(Default, Deserialize, Serialize)]
+/// Names are also indexed in lowercase for searching.
+/// Suffixes are indexed to all containers with them,
+/// so removing a container does not unmap the same suffix of another.
+/// Serialized as a map from [ContainerKey] to containers.
+#[derive(Default)]
 pub struct ContainerOwner {
-    #[serde(skip)]
-    suffix_id_map: BTreeMap<Suffix, CookieStoreId>,
-    #[serde(flatten)]
+    suffix_id_map: BTreeMap<Suffix, Vec<CookieStoreId>>,
+    name_id_map: BTreeMap<String, HashSet<CookieStoreId>>,
+    tombstones: HashMap<CookieStoreId, Tombstone>,
     id_container_map: HashMap<CookieStoreId, Container>,
 }
 
+/// Name of a buried container,
+/// kept while any handle to the container is outstanding.
+struct Tombstone {
+    name: String,
+    handle: Weak<CookieStoreId>,
+}
+
 impl ContainerOwner {
     /// Fetches all [ContextualIdentity] and treat them as [Container],
     /// detects temporary containers by name if needed.
@@ -54,13 +74,17 @@ impl ContainerOwner {
         Ok(owner)
     }
 
-    /// Inserts a container, this will also add suffix mappings for lookup.
+    /// Inserts a container, this will also add suffix and name mappings for lookup.
     pub fn insert(&mut self, container: Container) {
+        self.name_id_map
+            .entry(container.identity_details().name.to_lowercase())
+            .or_default()
+            .insert(container.handle().cookie_store_id().clone());
         if container.variant.allows_suffix_match() {
-            for suffix in container.suffixes.iter() {
-                self.suffix_id_map
-                    .insert(suffix.clone(), container.handle().cookie_store_id().clone());
-            }
+            self.index_suffixes(
+                container.handle().cookie_store_id(),
+                container.suffixes.iter().cloned(),
+            );
         }
         self.id_container_map
             .insert(container.handle().cookie_store_id().clone(), container);
@@ -96,39 +120,460 @@ impl ContainerOwner {
         }
     }
 
+    /// Updates the owned container with the same ID in place,
+    /// so that existing handles remain valid.
+    /// Inserts the container if there is no such container.
+    pub fn upsert(&mut self, container: Container) {
+        let cookie_store_id = container.handle().cookie_store_id().clone();
+        if self.get(&cookie_store_id).is_none() {
+            self.insert(container);
+        } else if let Some(mut existing_container) = self.get_mut(cookie_store_id) {
+            existing_container.assign(container);
+        }
+    }
+
     /// Remove an owned container.
     /// Returns the popped container, or [None] if not found.
     pub fn remove(&mut self, cookie_store_id: &CookieStoreId) -> Option<Container> {
         let container = self.id_container_map.remove(cookie_store_id);
         if container.is_some() {
-            self.suffix_id_map
-                .retain(|_suffix, id| *id != *cookie_store_id);
+            self.unindex_suffixes(cookie_store_id);
+            self.unindex_name(cookie_store_id);
         }
         container
     }
 
+    /// Replaces an owned container with another container in one step,
+    /// so that no lookup observes both or neither of them,
+    /// and members of the family named by the replaced container
+    /// are moved to the new container.
+    /// Returns the replaced container, or [None] if not found,
+    /// in which case the new container is still inserted.
+    pub fn replace(
+        &mut self,
+        cookie_store_id: &CookieStoreId,
+        container: Container,
+    ) -> Option<Container> {
+        let replaced = self.remove(cookie_store_id);
+        let new_cookie_store_id = container.handle().cookie_store_id().clone();
+        for member in self.id_container_map.values_mut() {
+            if member.family.as_ref() == Some(cookie_store_id) {
+                member.family = Some(new_cookie_store_id.clone());
+            }
+        }
+        self.insert(container);
+        replaced
+    }
+
+    /// Removes a container that was deleted from the browser,
+    /// and remembers it so that handles acquired before the deletion
+    /// are known to be stale, until none of them is outstanding.
+    /// If the container named a family, the family is kept with another
+    /// canonical container, see [ContainerOwner::promote_family_member],
+    /// and the changed members are queued to be stored.
+    pub fn bury(&mut self, cookie_store_id: &CookieStoreId) -> Option<Container> {
+        let container = self.remove(cookie_store_id)?;
+        self.tombstones
+            .retain(|_, tombstone| tombstone.handle.strong_count() > 0);
+        self.tombstones.insert(
+            cookie_store_id.clone(),
+            Tombstone {
+                name: container.identity_details().name,
+                handle: Arc::downgrade(&container.handle().inner),
+            },
+        );
+        for member_id in self.promote_family_member(&container) {
+            storage::queue_single_entry(&ContainerKey::from(&member_id), &self.get(&member_id));
+        }
+        Some(container)
+    }
+
+    /// Promotes the oldest member of the family named by a removed container
+    /// to be the new canonical container, which takes over the suffixes
+    /// of the removed container, and the other members join its family.
+    /// Returns the IDs of the changed members.
+    fn promote_family_member(&mut self, removed: &Container) -> Vec<CookieStoreId> {
+        let removed_id = removed.handle().cookie_store_id();
+        let members = self.family_members(removed_id);
+        let Some((promoted_id, other_ids)) = members.split_first() else {
+            return members;
+        };
+        if let Some(mut promoted) = self.get_mut(promoted_id.clone()) {
+            promoted.family = None;
+            promoted.suffixes.extend(removed.suffixes.iter().cloned());
+        }
+        for member_id in other_ids {
+            if let Some(mut member) = self.get_mut(member_id.clone()) {
+                member.family = Some(promoted_id.clone());
+            }
+        }
+        members
+    }
+
+    /// Checks if a container is still usable for creating tabs.
+    /// Containers that were never owned are assumed to be usable.
+    /// Fails with [DeletedContainer](CustomError::DeletedContainer)
+    /// if the container was buried, the operation may be retried
+    /// with a newly matched container.
+    pub fn ensure_alive(&self, cookie_store_id: &CookieStoreId) -> Result<(), CustomError> {
+        match self.tombstones.get(cookie_store_id) {
+            Some(tombstone) => Err(CustomError::DeletedContainer {
+                name: tombstone.name.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Searches for containers with names or suffixes that contain the filter,
+    /// case-insensitively.
+    /// Returns an iterator over the matched containers.
+    pub fn search<'a>(&'a self, filter: &str) -> impl Iterator<Item = &'a Container> + 'a {
+        let filter = filter.to_lowercase();
+        let name_matches = self
+            .name_id_map
+            .iter()
+            .filter(|(name, _ids)| name.contains(&filter))
+            .flat_map(|(_name, ids)| ids.iter())
+            .collect::<HashSet<&CookieStoreId>>();
+        self.id_container_map
+            .iter()
+            .filter(move |(cookie_store_id, container)| {
+                name_matches.contains(cookie_store_id)
+                    || container.suffixes.iter().any(|suffix| {
+                        suffix.raw().to_lowercase().contains(&filter)
+                            || suffix.encoded().contains(&filter)
+                    })
+            })
+            .map(|(_cookie_store_id, container)| container)
+    }
+
+    /// Searches for containers with names containing the characters
+    /// of the query in order, case-insensitively,
+    /// excluding temporary containers.
+    /// Returns the matched containers with the best matches first,
+    /// which are those with consecutive characters and word starts matched.
+    pub fn fuzzy_search(&self, query: &str) -> Vec<&Container> {
+        let query = query.to_lowercase();
+        let mut matches = self
+            .name_id_map
+            .iter()
+            .filter_map(|(name, ids)| Some((fuzzy_score(name, &query)?, ids)))
+            .flat_map(|(score, ids)| {
+                self.non_temporary(ids)
+                    .filter_map(move |cookie_store_id| Some((score, self.get(cookie_store_id)?)))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, _container)| Reverse(*score));
+        matches
+            .into_iter()
+            .map(|(_score, container)| container)
+            .collect()
+    }
+
+    /// Finds a container by its name case-insensitively,
+    /// excluding temporary containers.
+    /// Any of them may be returned if multiple containers share the name.
+    pub fn find_by_name(&self, name: &str) -> Option<&Container> {
+        let ids = self.name_id_map.get(&name.to_lowercase())?;
+        self.non_temporary(ids)
+            .find_map(|cookie_store_id| self.id_container_map.get(cookie_store_id))
+    }
+
+    /// Lowercase names of containers, excluding temporary containers.
+    /// The name of the given container is excluded,
+    /// unless it is shared with other containers.
+    pub fn names_except<'a>(
+        &'a self,
+        cookie_store_id: Option<&'a CookieStoreId>,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.name_id_map
+            .iter()
+            .filter(move |(_name, ids)| {
+                self.non_temporary(ids)
+                    .any(|id| Some(id) != cookie_store_id)
+            })
+            .map(|(name, _ids)| name.as_str())
+    }
+
+    /// Lowercase names that are shared by multiple containers,
+    /// excluding temporary containers as they are named by the suffixes.
+    pub fn duplicate_names(&self) -> impl Iterator<Item = &str> {
+        self.name_id_map
+            .iter()
+            .filter(|(_name, ids)| self.non_temporary(ids).count() > 1)
+            .map(|(name, _ids)| name.as_str())
+    }
+
+    /// Filters out IDs of temporary and removed containers.
+    fn non_temporary<'a>(
+        &'a self,
+        ids: &'a HashSet<CookieStoreId>,
+    ) -> impl Iterator<Item = &'a CookieStoreId> + 'a {
+        ids.iter().filter(|id| {
+            self.id_container_map
+                .get(id)
+                .is_some_and(|container| container.variant != ContainerVariant::Temporary)
+        })
+    }
+
+    /// Adds suffix mappings of a container.
+    /// Exclusion suffixes are not mapped as they are checked per container.
+    fn index_suffixes(
+        &mut self,
+        cookie_store_id: &CookieStoreId,
+        suffixes: impl Iterator<Item = Suffix>,
+    ) {
+        for suffix in suffixes.filter(|suffix| !suffix.is_exclusion()) {
+            let ids = self.suffix_id_map.entry(suffix).or_default();
+            if !ids.contains(cookie_store_id) {
+                ids.push(cookie_store_id.clone());
+            }
+        }
+    }
+
+    /// Finds another container that is mapped to the suffix with the same
+    /// priority as the given container, in which case only the container
+    /// inserted first matches and the other is silently shadowed.
+    /// Exclusion suffixes and containers that do not match by suffixes
+    /// never conflict.
+    /// Returns the conflicting container, [None] if there is no conflict.
+    pub fn suffix_conflict(
+        &self,
+        suffix: &Suffix,
+        cookie_store_id: &CookieStoreId,
+    ) -> Option<&Container> {
+        let container = self.get(cookie_store_id)?;
+        if !container.variant.allows_suffix_match() {
+            return None;
+        }
+        self.conflict_with(suffix, Some(cookie_store_id), container.priority)
+    }
+
+    /// Checks that the suffixes can be added to a container with the priority
+    /// without conflicting with another container,
+    /// see [ContainerOwner::suffix_conflict].
+    /// The ID is [None] for a container that is not owned yet.
+    /// Every writer of suffixes should check with this first.
+    /// Fails with [SuffixConflict](CustomError::SuffixConflict)
+    /// for the first conflicting suffix.
+    pub fn ensure_no_conflict<'a>(
+        &self,
+        cookie_store_id: Option<&CookieStoreId>,
+        priority: i32,
+        suffixes: impl IntoIterator<Item = &'a Suffix>,
+    ) -> Result<(), CustomError> {
+        for suffix in suffixes {
+            if let Some(conflict) = self.conflict_with(suffix, cookie_store_id, priority) {
+                return Err(CustomError::SuffixConflict {
+                    suffix: suffix.raw(),
+                    name: conflict.identity_details().name,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds a container other than the given one that is mapped to
+    /// the suffix with the priority, exclusion suffixes never conflict.
+    fn conflict_with(
+        &self,
+        suffix: &Suffix,
+        cookie_store_id: Option<&CookieStoreId>,
+        priority: i32,
+    ) -> Option<&Container> {
+        if suffix.is_exclusion() {
+            return None;
+        }
+        self.suffix_id_map
+            .get(suffix)?
+            .iter()
+            .filter(|id| Some(*id) != cookie_store_id)
+            .filter_map(|id| self.get(id))
+            .find(|other| other.priority == priority)
+    }
+
+    /// Removes the suffix mappings of a container, empty entries are discarded.
+    fn unindex_suffixes(&mut self, cookie_store_id: &CookieStoreId) {
+        self.suffix_id_map.retain(|_suffix, ids| {
+            ids.retain(|id| id != cookie_store_id);
+            !ids.is_empty()
+        });
+    }
+
+    /// Removes the name mapping of a container, empty entries are discarded.
+    fn unindex_name(&mut self, cookie_store_id: &CookieStoreId) {
+        self.name_id_map.retain(|_name, ids| {
+            ids.remove(cookie_store_id);
+            !ids.is_empty()
+        });
+    }
+
     /// Matches a container to the given domain by the stored suffixes,
-    /// skipping over the removed containers.
+    /// expanding the TLD globs with the public suffix list,
+    /// skipping over the removed containers, those outside of
+    /// their active hours according to the clock,
+    /// and those rejecting the domain with an exclusion suffix.
+    /// The container with the highest priority wins if multiple suffixes
+    /// match, ties are broken by choosing the most specific suffix.
     /// Returns a [ContainerMatch], [None] if there is no match.
-    /// Glob suffix may not match if the container with the corresponding
-    /// normal suffix is removed, this may be fixed in the future.
-    pub fn match_container(&mut self, domain: EncodedDomain) -> Option<ContainerMatch> {
-        let matches = suffix::match_suffix(&self.suffix_id_map, domain, MatchMode::Full);
-        for (matched_domain, suffix) in matches {
-            let cookie_store_id = self.suffix_id_map.get(&suffix).expect("suffix matched");
-            if let Some(container) = self.id_container_map.remove(cookie_store_id) {
-                let container = self
-                    .id_container_map
-                    .entry(cookie_store_id.clone())
-                    .or_insert(container);
-                return Some(ContainerMatch {
-                    container,
-                    matched_domain,
-                    suffix,
-                });
+    pub fn match_container(
+        &mut self,
+        domain: EncodedDomain,
+        psl: &Psl,
+        clock: &impl Clock,
+    ) -> Option<ContainerMatch> {
+        let (matched_domain, suffix, cookie_store_id) = self.find_match(domain, psl, clock)?;
+        let container = self
+            .id_container_map
+            .get_mut(&cookie_store_id)
+            .expect("container matched");
+        Some(ContainerMatch {
+            container,
+            matched_domain,
+            suffix,
+        })
+    }
+
+    /// Matches a container like [ContainerOwner::match_container],
+    /// but only returns its [CookieStoreId] so that the owner is not borrowed.
+    pub fn match_cookie_store_id(
+        &self,
+        domain: EncodedDomain,
+        psl: &Psl,
+        clock: &impl Clock,
+    ) -> Option<CookieStoreId> {
+        self.find_match(domain, psl, clock)
+            .map(|(_matched_domain, _suffix, cookie_store_id)| cookie_store_id)
+    }
+
+    /// Finds the winning suffix match among the accepting containers,
+    /// see [ContainerOwner::match_container] for the rules.
+    /// Returns the matched domain, the suffix and the container's ID.
+    pub fn find_match(
+        &self,
+        domain: EncodedDomain,
+        psl: &Psl,
+        clock: &impl Clock,
+    ) -> Option<(EncodedDomain, Suffix, CookieStoreId)> {
+        let now = clock.now();
+        let id_container_map = &self.id_container_map;
+        self.match_suffixes(domain.clone(), psl)
+            .into_iter()
+            .filter_map(|(matched_domain, suffix, cookie_store_id)| {
+                let container = id_container_map.get(cookie_store_id)?;
+                (container.is_active_at(now) && !container.excludes(&domain, &matched_domain))
+                    .then_some((matched_domain, suffix, cookie_store_id))
+            })
+            .min_by_key(|(_matched_domain, _suffix, cookie_store_id)| {
+                Reverse(id_container_map[*cookie_store_id].priority)
+            })
+            .map(|(matched_domain, suffix, cookie_store_id)| {
+                (matched_domain, suffix, cookie_store_id.clone())
+            })
+    }
+
+    /// Finds all accepting containers, see [ContainerOwner::match_container]
+    /// for the rules, each listed once from the highest priority.
+    /// Containers of the same priority are listed by their most specific
+    /// suffixes, from the most specific to the least specific.
+    /// Active members of a family are listed after the canonical container.
+    pub fn find_matches(
+        &self,
+        domain: EncodedDomain,
+        psl: &Psl,
+        clock: &impl Clock,
+    ) -> Vec<CookieStoreId> {
+        let now = clock.now();
+        let mut cookie_store_ids = Vec::<CookieStoreId>::new();
+        for (matched_domain, _suffix, cookie_store_id) in self.match_suffixes(domain.clone(), psl) {
+            let Some(container) = self.id_container_map.get(cookie_store_id) else {
+                continue;
+            };
+            if container.is_active_at(now)
+                && !container.excludes(&domain, &matched_domain)
+                && !cookie_store_ids.contains(cookie_store_id)
+            {
+                cookie_store_ids.push(cookie_store_id.clone());
             }
         }
-        None
+        cookie_store_ids.sort_by_key(|cookie_store_id| {
+            Reverse(self.id_container_map[cookie_store_id].priority)
+        });
+        let mut family_ids = Vec::<CookieStoreId>::new();
+        for cookie_store_id in cookie_store_ids {
+            let members = self
+                .family_members(&cookie_store_id)
+                .into_iter()
+                .filter(|member_id| self.id_container_map[member_id].is_active_at(now));
+            for family_id in iter::once(cookie_store_id).chain(members) {
+                if !family_ids.contains(&family_id) {
+                    family_ids.push(family_id);
+                }
+            }
+        }
+        family_ids
+    }
+
+    /// Members of the family named by the canonical container,
+    /// excluding the canonical container, from the oldest member.
+    pub fn family_members(&self, cookie_store_id: &CookieStoreId) -> Vec<CookieStoreId> {
+        let mut members = self
+            .id_container_map
+            .values()
+            .filter(|container| container.family.as_ref() == Some(cookie_store_id))
+            .collect::<Vec<&Container>>();
+        members.sort_by_key(|container| container.created);
+        members
+            .into_iter()
+            .map(|container| container.handle().cookie_store_id().clone())
+            .collect()
+    }
+
+    /// The canonical container of the family that the container belongs to,
+    /// which is the container itself if it is not a member of any family
+    /// or if the canonical container no longer exists.
+    pub fn canonical_id(&self, cookie_store_id: &CookieStoreId) -> CookieStoreId {
+        self.get(cookie_store_id)
+            .and_then(|container| container.family.clone())
+            .filter(|canonical_id| self.get(canonical_id).is_some())
+            .unwrap_or_else(|| cookie_store_id.clone())
+    }
+
+    /// Looks up the mapped suffixes that match the domain or its ancestors,
+    /// from the most specific to the least specific.
+    /// Normal, glob and TLD glob suffixes are looked up independently,
+    /// and all containers with a matched suffix are listed in insertion order.
+    /// TLD globs are looked up with the public suffix of the domain removed.
+    fn match_suffixes(
+        &self,
+        domain: EncodedDomain,
+        psl: &Psl,
+    ) -> Vec<(EncodedDomain, Suffix, &CookieStoreId)> {
+        let public_suffix = psl
+            .public_suffix(&domain)
+            .map(|public_suffix| format!(".{}", public_suffix.encoded()));
+        let mut matches = Vec::new();
+        for matched_domain in iter::successors(Some(domain), EncodedDomain::parent) {
+            let normal = Suffix::new(SuffixType::Normal, matched_domain.clone());
+            let glob = matched_domain
+                .parent()
+                .map(|parent| Suffix::new(SuffixType::Glob, parent));
+            let tld_glob = public_suffix
+                .as_deref()
+                .and_then(|public_suffix| matched_domain.encoded().strip_suffix(public_suffix))
+                .and_then(|stripped| EncodedDomain::parse(stripped, &IdnaPolicy::Lenient).ok())
+                .map(|stripped| Suffix::new(SuffixType::TldGlob, stripped));
+            for suffix in iter::once(normal).chain(glob).chain(tld_glob) {
+                let Some((suffix, ids)) = self.suffix_id_map.get_key_value(&suffix) else {
+                    continue;
+                };
+                matches.extend(
+                    ids.iter()
+                        .map(|id| (matched_domain.clone(), suffix.clone(), id)),
+                );
+            }
+        }
+        matches
     }
 
     /// Iterator over owned containers.
@@ -137,9 +582,122 @@ impl ContainerOwner {
     }
 }
 
+impl Serialize for ContainerOwner {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            self.id_container_map
+                .iter()
+                .map(|(cookie_store_id, container)| {
+                    (ContainerKey::from(cookie_store_id), container)
+                }),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for ContainerOwner {
+    /// Entries that are not containers are ignored,
+    /// as the owner is flattened with other parts of the context.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ContainerOwnerVisitor)
+    }
+}
+
+/// Visitor of stored containers, handles of containers that are read
+/// before a corrupt entry are finished so that they can be dropped.
+struct ContainerOwnerVisitor;
+
+impl<'de> Visitor<'de> for ContainerOwnerVisitor {
+    type Value = ContainerOwner;
+
+    fn expecting(&self, formatter: &mut Formatter) -> FmtResult {
+        write!(formatter, "a map of containers")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut owner = ContainerOwner::default();
+        let mut insert_all = || {
+            while let Some(key) = map.next_key::<String>()? {
+                if ContainerKey::parse(&key).is_none() {
+                    map.next_value::<IgnoredAny>()?;
+                } else if let Some(container) = map.next_value::<Option<Container>>()? {
+                    owner.insert(container);
+                }
+            }
+            Ok(())
+        };
+        if let Err(error) = insert_all() {
+            for container in owner.iter() {
+                container.handle().finish();
+            }
+            return Err(error);
+        }
+        Ok(owner)
+    }
+}
+
+/// Storage key of a container, namespaced as `container/` followed by
+/// the encoded [CookieStoreId], so that it never collides with other entries.
+#[derive(Clone)]
+pub struct ContainerKey {
+    cookie_store_id: CookieStoreId,
+}
+
+impl ContainerKey {
+    /// Namespace of all container keys.
+    const PREFIX: &'static str = "container/";
+
+    /// Parses a key, [None] if it is not a container key.
+    pub fn parse(key: &str) -> Option<Self> {
+        let cookie_store_id = CookieStoreId::from_encoded(key.strip_prefix(Self::PREFIX)?)?;
+        Some(Self { cookie_store_id })
+    }
+
+    /// The [CookieStoreId] of the container stored under this key.
+    pub fn cookie_store_id(&self) -> &CookieStoreId {
+        &self.cookie_store_id
+    }
+}
+
+impl From<&CookieStoreId> for ContainerKey {
+    fn from(cookie_store_id: &CookieStoreId) -> Self {
+        Self {
+            cookie_store_id: cookie_store_id.clone(),
+        }
+    }
+}
+
+impl Display for ContainerKey {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        write!(
+            formatter,
+            "{}{}",
+            Self::PREFIX,
+            self.cookie_store_id.encoded()
+        )
+    }
+}
+
+impl Serialize for ContainerKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 /// Handle of a [Container] that is owned by a [ContainerOwner].
 /// Dereferences into a container.
-/// When dropped, the owner's suffix lookup table is updated.
+/// When dropped, the owner's suffix and name lookup tables are updated.
 pub struct OwnerHandle<'a> {
     owner: &'a mut ContainerOwner,
     cookie_store_id: CookieStoreId,
@@ -167,16 +725,20 @@ impl DerefMut for OwnerHandle<'_> {
 
 impl Drop for OwnerHandle<'_> {
     fn drop(&mut self) {
+        let name = self.identity_details().name.to_lowercase();
+        self.owner.unindex_name(&self.cookie_store_id);
+        self.owner
+            .name_id_map
+            .entry(name)
+            .or_default()
+            .insert(self.cookie_store_id.clone());
+
         if !self.variant.allows_suffix_match() {
             return;
         }
-        self.owner
-            .suffix_id_map
-            .retain(|_suffix, cookie_store_id| *cookie_store_id != self.cookie_store_id);
+        self.owner.unindex_suffixes(&self.cookie_store_id);
         let suffixes = self.suffixes.clone().into_iter();
-        self.owner
-            .suffix_id_map
-            .extend(suffixes.map(|suffix| (suffix, self.cookie_store_id.clone())));
+        self.owner.index_suffixes(&self.cookie_store_id, suffixes);
     }
 }
 
@@ -208,6 +770,11 @@ impl ContainerHandle {
     pub fn cookie_store_id(&self) -> &CookieStoreId {
         &self.inner
     }
+
+    /// Number of handles to the same container, including this handle.
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
 }
 
 impl Clone for ContainerHandle {
@@ -237,13 +804,140 @@ impl Drop for ContainerHandle {
     }
 }
 
+/// Minimum time between recorded uses of a container by navigation,
+/// see [Container::was_used_recently].
+const USE_PRECISION: Duration = Duration::minutes(1);
+
 /// Wrapper around [ContextualIdentity] with handle.
-#[derive(Deserialize, Serialize)]
+/// Timestamps default to the Unix epoch and [None] for older entries.
+/// Request headers are set for all requests made in this container.
+/// Rewrite rules apply to the URLs of tabs moved into this container.
+/// The homepage is opened by new tabs explicitly opened in this container.
+/// Members of a family share the suffixes of the canonical container
+/// that the family is named by, such as for multiple accounts of a site.
+/// Suffixes only match during the active hours if there are any.
+/// Priority decides the container to use when suffixes of multiple containers
+/// match the same domain, higher is preferred.
+/// Containers managed by a policy are stored with their own state,
+/// see [Container::manage].
+#[derive(Deserialize)]
+#[serde(from = "StoredContainer")]
 pub struct Container {
     handle: ContainerHandle,
     identity: ContextualIdentity,
     pub variant: ContainerVariant,
     pub suffixes: BTreeSet<Suffix>,
+    pub request_headers: Vec<HttpHeader>,
+    pub rewrite_rules: Vec<RewriteRule>,
+    pub homepage: Option<String>,
+    pub family: Option<CookieStoreId>,
+    pub active_hours: Option<ActiveHours>,
+    pub priority: i32,
+    created: DateTime<Utc>,
+    last_used: Option<DateTime<Utc>>,
+    unmanaged: Option<Box<UnmanagedState>>,
+}
+
+/// State of a [Container] before a policy managed it,
+/// which is what is stored in place of the managed state.
+#[derive(Clone)]
+struct UnmanagedState {
+    variant: ContainerVariant,
+    suffixes: BTreeSet<Suffix>,
+    priority: i32,
+}
+
+/// Serialized form of a [Container], read back as a [StoredContainer].
+#[derive(Serialize)]
+struct SerializedContainer<'a> {
+    handle: &'a ContainerHandle,
+    identity: &'a ContextualIdentity,
+    variant: &'a ContainerVariant,
+    suffixes: &'a BTreeSet<Suffix>,
+    request_headers: &'a Vec<HttpHeader>,
+    rewrite_rules: &'a Vec<RewriteRule>,
+    homepage: &'a Option<String>,
+    family: &'a Option<CookieStoreId>,
+    active_hours: &'a Option<ActiveHours>,
+    priority: i32,
+    created: DateTime<Utc>,
+    last_used: Option<DateTime<Utc>>,
+}
+
+impl Serialize for Container {
+    /// Serializes the state before a policy managed this container,
+    /// so that the managed state is never persisted.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (variant, suffixes, priority) = match &self.unmanaged {
+            Some(unmanaged) => (&unmanaged.variant, &unmanaged.suffixes, unmanaged.priority),
+            None => (&self.variant, &self.suffixes, self.priority),
+        };
+        SerializedContainer {
+            handle: &self.handle,
+            identity: &self.identity,
+            variant,
+            suffixes,
+            request_headers: &self.request_headers,
+            rewrite_rules: &self.rewrite_rules,
+            homepage: &self.homepage,
+            family: &self.family,
+            active_hours: &self.active_hours,
+            priority,
+            created: self.created,
+            last_used: self.last_used,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Stored form of a [Container], the handle is only created
+/// once all fields are read, so that reading a corrupt entry
+/// does not drop an unfinished handle.
+#[derive(Deserialize)]
+struct StoredContainer {
+    handle: CookieStoreId,
+    identity: ContextualIdentity,
+    variant: ContainerVariant,
+    suffixes: BTreeSet<Suffix>,
+    #[serde(default)]
+    request_headers: Vec<HttpHeader>,
+    #[serde(default)]
+    rewrite_rules: Vec<RewriteRule>,
+    #[serde(default)]
+    homepage: Option<String>,
+    #[serde(default)]
+    family: Option<CookieStoreId>,
+    #[serde(default)]
+    active_hours: Option<ActiveHours>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    created: DateTime<Utc>,
+    #[serde(default)]
+    last_used: Option<DateTime<Utc>>,
+}
+
+impl From<StoredContainer> for Container {
+    fn from(stored: StoredContainer) -> Self {
+        Self {
+            handle: ContainerHandle::from(stored.handle),
+            identity: stored.identity,
+            variant: stored.variant,
+            suffixes: stored.suffixes,
+            request_headers: stored.request_headers,
+            rewrite_rules: stored.rewrite_rules,
+            homepage: stored.homepage,
+            family: stored.family,
+            active_hours: stored.active_hours,
+            priority: stored.priority,
+            created: stored.created,
+            last_used: stored.last_used,
+            unmanaged: None,
+        }
+    }
 }
 
 impl Container {
@@ -260,6 +954,15 @@ impl Container {
             identity,
             variant,
             suffixes,
+            request_headers: Vec::default(),
+            rewrite_rules: Vec::default(),
+            homepage: None,
+            family: None,
+            active_hours: None,
+            priority: 0,
+            created: Utc::now(),
+            last_used: None,
+            unmanaged: None,
         })
     }
 
@@ -271,7 +974,7 @@ impl Container {
 
     /// Deletes this container, fails if the browser indicates so.
     pub async fn delete(&self) -> Result<(), CustomError> {
-        self.identity.cookie_store_id().delete_identity().await?;
+        self.identity.delete().await?;
         self.handle.finish();
         Ok(())
     }
@@ -287,12 +990,156 @@ impl Container {
         }
     }
 
+    /// Replaces everything except the handle with those of another container,
+    /// the handle of the other container is finished.
+    /// If this container is managed and the other is not,
+    /// the state of the other replaces the state before being managed.
+    pub fn assign(&mut self, other: Container) {
+        let Container {
+            handle,
+            identity,
+            variant,
+            suffixes,
+            request_headers,
+            rewrite_rules,
+            homepage,
+            family,
+            active_hours,
+            priority,
+            created,
+            last_used,
+            unmanaged,
+        } = other;
+        handle.finish();
+        self.identity = identity;
+        match (&mut self.unmanaged, unmanaged) {
+            (Some(own_unmanaged), None) => {
+                **own_unmanaged = UnmanagedState {
+                    variant,
+                    suffixes,
+                    priority,
+                };
+            }
+            (_, unmanaged) => {
+                self.variant = variant;
+                self.suffixes = suffixes;
+                self.priority = priority;
+                self.unmanaged = unmanaged;
+            }
+        }
+        self.request_headers = request_headers;
+        self.rewrite_rules = rewrite_rules;
+        self.homepage = homepage;
+        self.family = family;
+        self.active_hours = active_hours;
+        self.created = created;
+        self.last_used = last_used;
+    }
+
     /// Handle to this container, the holder must clean up the container
     /// appropriately after releasing the handle.
     /// For example, by using [Container::delete_if_empty].
     pub fn handle(&self) -> &ContainerHandle {
         &self.handle
     }
+
+    /// Number of handles held outside of this container,
+    /// a temporary container is only deleted once there are none.
+    pub fn outstanding_handles(&self) -> usize {
+        self.handle.strong_count() - 1
+    }
+
+    /// Time when this container was created or first discovered.
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    /// Time when a tab was last assigned to or navigated in this container,
+    /// [None] if it has never been used.
+    /// Listings may be sorted by it, and idle temporary containers are purged
+    /// by it, see [Container::is_idle_for].
+    /// Temporary containers are never reused by it,
+    /// as each of them is meant to start without any stored state.
+    pub fn last_used(&self) -> Option<DateTime<Utc>> {
+        self.last_used
+    }
+
+    /// Checks if no tab in this container was used for the duration,
+    /// counting from the creation if it was never used.
+    /// Durations too long to be represented are never reached.
+    pub fn is_idle_for(&self, duration: std::time::Duration) -> bool {
+        let Ok(duration) = Duration::from_std(duration) else {
+            return false;
+        };
+        Utc::now() - self.last_used.unwrap_or(self.created) >= duration
+    }
+
+    /// Records that a tab has just been assigned to or navigated in
+    /// this container.
+    pub fn mark_used(&mut self) {
+        self.last_used = Some(Utc::now());
+    }
+
+    /// Checks if a use was recorded within the last minute,
+    /// in which case navigation need not be recorded again,
+    /// so that frequent navigation is not persisted every time.
+    pub fn was_used_recently(&self) -> bool {
+        self.last_used
+            .is_some_and(|last_used| Utc::now() - last_used < USE_PRECISION)
+    }
+
+    /// Checks if an exclusion suffix of this container rejects the domain.
+    /// Only exclusions of the domain or its ancestors up to the matched domain
+    /// are considered, so a more specific suffix overrides an exclusion.
+    pub fn excludes(&self, domain: &EncodedDomain, matched_domain: &EncodedDomain) -> bool {
+        for ancestor in iter::successors(Some(domain.clone()), EncodedDomain::parent) {
+            let exclusion = Suffix::new(SuffixType::Exclusion, ancestor.clone());
+            if self.suffixes.contains(&exclusion) {
+                return true;
+            }
+            if ancestor == *matched_domain {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Checks if this container accepts tabs at the given local time.
+    pub fn is_active_at(&self, time: NaiveDateTime) -> bool {
+        match &self.active_hours {
+            Some(active_hours) => active_hours.contains(time),
+            None => true,
+        }
+    }
+
+    /// Applies the state required by a policy in memory only,
+    /// the container is locked with the highest priority and the suffixes.
+    /// The state before being managed is kept for storing,
+    /// so that removing the policy restores it on the next start.
+    pub fn manage(&mut self, suffixes: BTreeSet<Suffix>) {
+        if self.unmanaged.is_none() {
+            self.unmanaged = Some(Box::new(UnmanagedState {
+                variant: self.variant.clone(),
+                suffixes: self.suffixes.clone(),
+                priority: self.priority,
+            }));
+        }
+        self.variant = ContainerVariant::Locked;
+        self.priority = i32::MAX;
+        self.suffixes = suffixes;
+    }
+
+    /// Checks if the suffixes and details of this container can be modified.
+    /// Fails with [LockedContainer](CustomError::LockedContainer) otherwise.
+    pub fn ensure_modifiable(&self) -> Result<(), CustomError> {
+        if self.variant.allows_modification() {
+            Ok(())
+        } else {
+            Err(CustomError::LockedContainer {
+                name: self.identity_details().name,
+            })
+        }
+    }
 }
 
 impl IdentityDetailsProvider for Container {
@@ -301,6 +1148,34 @@ impl IdentityDetailsProvider for Container {
     }
 }
 
+impl Staged for Container {
+    /// Copies everything, the handle of the copy is finished
+    /// as it is not held by anything.
+    fn stage(&self) -> Self {
+        let handle = self.handle.clone();
+        handle.finish();
+        Self {
+            handle,
+            identity: self.identity.clone(),
+            variant: self.variant.clone(),
+            suffixes: self.suffixes.clone(),
+            request_headers: self.request_headers.clone(),
+            rewrite_rules: self.rewrite_rules.clone(),
+            homepage: self.homepage.clone(),
+            family: self.family.clone(),
+            active_hours: self.active_hours.clone(),
+            priority: self.priority,
+            created: self.created,
+            last_used: self.last_used,
+            unmanaged: self.unmanaged.clone(),
+        }
+    }
+
+    fn commit(&mut self, staged: Self) {
+        self.assign(staged);
+    }
+}
+
 impl From<ContextualIdentity> for Container {
     /// Builds a container from a [ContextualIdentity].
     /// By default, it will be a [ContainerVariant::Permanent] container
@@ -311,10 +1186,58 @@ impl From<ContextualIdentity> for Container {
             identity,
             variant: ContainerVariant::Permanent,
             suffixes: BTreeSet::default(),
+            request_headers: Vec::default(),
+            rewrite_rules: Vec::default(),
+            homepage: None,
+            family: None,
+            active_hours: None,
+            priority: 0,
+            created: Utc::now(),
+            last_used: None,
+            unmanaged: None,
         }
     }
 }
 
+/// Weekdays and hours in the local time zone when a container accepts tabs,
+/// from the start of the start hour to the start of the end hour.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ActiveHours {
+    pub weekdays: Vec<Weekday>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl Default for ActiveHours {
+    /// Working hours from 9 to 17 on weekdays.
+    fn default() -> Self {
+        Self {
+            weekdays: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            start_hour: 9,
+            end_hour: 17,
+        }
+    }
+}
+
+impl ActiveHours {
+    /// Checks if the hours are within a day and the end is after the start.
+    pub fn is_valid(&self) -> bool {
+        self.start_hour < self.end_hour && self.end_hour <= 24
+    }
+
+    /// Checks if the time is within the active hours.
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.weekdays.contains(&time.weekday())
+            && (self.start_hour..self.end_hour).contains(&time.hour())
+    }
+}
+
 /// Variants of containers.
 /// - [Permanent](ContainerVariant::Permanent) means that the container is
 ///   created by the user and all container operations are managed by the user.
@@ -322,11 +1245,15 @@ impl From<ContextualIdentity> for Container {
 ///   be recreated with the new name after tabs movements are captured.
 /// - [Temporary](ContainerVariant::Temporary) means that the container is
 ///   generated, and should be deleted once all tabs within it have closed.
-#[derive(Deserialize, Eq, PartialEq, Serialize)]
+/// - [Locked](ContainerVariant::Locked) means that the container is the same
+///   as a permanent container, except that the suffixes and details
+///   cannot be modified until it is unlocked.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
 pub enum ContainerVariant {
     Permanent,
     Recording { active: bool },
     Temporary,
+    Locked,
 }
 
 impl ContainerVariant {
@@ -351,11 +1278,16 @@ impl ContainerVariant {
                     SuffixType::Normal,
                     relocation_detail.new_domain,
                 ));
+                storage::queue_single_entry(
+                    &ContainerKey::from(&relocation_detail.current_cookie_store_id),
+                    &*container,
+                );
                 tab_id.reload_tab().await.and(Ok(None))
             }
-            Self::Permanent | Self::Recording { active: false } | Self::Temporary => {
-                Ok(Some(relocation_detail))
-            }
+            Self::Permanent
+            | Self::Recording { active: false }
+            | Self::Temporary
+            | Self::Locked => Ok(Some(relocation_detail)),
         }
     }
 
@@ -374,45 +1306,87 @@ impl ContainerVariant {
                 let deleted = container.delete_if_empty().await.unwrap_or(false);
                 drop(container);
                 if deleted {
-                    containers.remove(&cookie_store_id);
-                    storage::remove_entries(&[cookie_store_id]).await
+                    containers.bury(&cookie_store_id);
+                    storage::remove_entries(&[ContainerKey::from(&cookie_store_id)]).await
                 } else {
                     Ok(())
                 }
             }
-            Self::Permanent | Self::Recording { .. } => Ok(()),
+            Self::Permanent | Self::Recording { .. } | Self::Locked => Ok(()),
         }
     }
 
     /// Checks if suffixes from a specific container should be matched.
     pub fn allows_suffix_match(&self) -> bool {
         match *self {
-            Self::Permanent | Self::Temporary => true,
+            Self::Permanent | Self::Temporary | Self::Locked => true,
             Self::Recording { .. } => false,
         }
     }
+
+    /// Checks if suffixes and details from a specific container
+    /// can be modified.
+    pub fn allows_modification(&self) -> bool {
+        *self != Self::Locked
+    }
+}
+
+/// Scores how well the name matches the query for [ContainerOwner::fuzzy_search],
+/// [None] if the name does not contain the characters of the query in order.
+/// Both are expected to be in lowercase.
+fn fuzzy_score(name: &str, query: &str) -> Option<u32> {
+    let name = name.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next_index = None;
+    for query_char in query.chars() {
+        let start = next_index.unwrap_or_default();
+        let index = start + name[start..].iter().position(|char| *char == query_char)?;
+        score += 1;
+        if next_index == Some(index) {
+            score += 2;
+        }
+        if index == 0 || !name[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        next_index = Some(index + 1);
+    }
+    Some(score)
 }
 
 #[cfg(test)]
 pub mod test {
+    use async_std::io::Cursor;
     use async_std::sync::Mutex;
+    use chrono::NaiveDate;
     use once_cell::sync::Lazy;
+    use serde_assert::{Deserializer as AssertDeserializer, Token, Tokens};
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test;
 
     use super::*;
     use crate::interop::contextual_identities::{CookieStoreId, MockContextualIdentity};
+    use crate::util::clock::{FixedClock, SystemClock};
 
-    static CONTEXTUAL_IDENTITY_MUTEX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    pub static CONTEXTUAL_IDENTITY_MUTEX: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
     async fn test_container(
         details: IdentityDetails,
         suffixes: BTreeSet<Suffix>,
         mock_identity_setup: impl FnOnce(&mut MockContextualIdentity),
+    ) -> Container {
+        test_container_with_id("mock_id", details, suffixes, mock_identity_setup).await
+    }
+
+    pub async fn test_container_with_id(
+        cookie_store_id: &str,
+        details: IdentityDetails,
+        suffixes: BTreeSet<Suffix>,
+        mock_identity_setup: impl FnOnce(&mut MockContextualIdentity),
     ) -> Container {
         let mut mock_identity = MockContextualIdentity::new();
         mock_identity
             .expect_cookie_store_id()
-            .return_const(CookieStoreId::new(String::from("mock_id")));
+            .return_const(CookieStoreId::new(String::from(cookie_store_id)));
         mock_identity_setup(&mut mock_identity);
         let ctx_mock_identity = MockContextualIdentity::create_context();
         ctx_mock_identity.expect().return_once(|details| {
@@ -425,18 +1399,794 @@ pub mod test {
             .expect("mocked contextual identity")
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
     async fn test_container_create_and_handle() -> Result<(), CustomError> {
         let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
         let container =
             test_container(IdentityDetails::default(), BTreeSet::default(), |_| ()).await;
 
-        assert_eq!(1usize, Arc::strong_count(&container.handle().inner));
+        assert_eq!(1usize, container.handle().strong_count());
+        assert_eq!(0usize, container.outstanding_handles());
         assert_eq!(
             CookieStoreId::new(String::from("mock_id")),
             *container.handle().cookie_store_id()
         );
+        let handle = container.handle().clone();
+        assert_eq!(2usize, handle.strong_count());
+        assert_eq!(1usize, container.outstanding_handles());
+        handle.finish();
+        drop(handle);
+        assert_eq!(0usize, container.outstanding_handles());
+        container.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_mark_used() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut container =
+            test_container(IdentityDetails::default(), BTreeSet::default(), |_| ()).await;
+
+        assert_eq!(container.last_used(), None);
+        assert!(!container.was_used_recently());
+        container.mark_used();
+        assert!(container.last_used().is_some());
+        assert!(container.was_used_recently());
+        container.last_used = Some(Utc::now() - USE_PRECISION);
+        assert!(!container.was_used_recently());
+        container.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_is_idle_for() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut container =
+            test_container(IdentityDetails::default(), BTreeSet::default(), |_| ()).await;
+
+        assert!(container.is_idle_for(std::time::Duration::ZERO));
+        assert!(!container.is_idle_for(std::time::Duration::from_secs(3600)));
+        assert!(!container.is_idle_for(std::time::Duration::MAX));
+        container.created -= Duration::days(2);
+        assert!(container.is_idle_for(std::time::Duration::from_secs(86400)));
+        container.mark_used();
+        assert!(!container.is_idle_for(std::time::Duration::from_secs(86400)));
+        container.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_ensure_modifiable() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut container = test_container(
+            IdentityDetails::default(),
+            BTreeSet::default(),
+            |mock_identity| {
+                mock_identity
+                    .expect_identity_details()
+                    .returning(IdentityDetails::default);
+            },
+        )
+        .await;
+
+        container.ensure_modifiable()?;
+        container.variant = ContainerVariant::Locked;
+        assert!(matches!(
+            container.ensure_modifiable(),
+            Err(CustomError::LockedContainer { .. })
+        ));
+        container.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_stage_and_commit() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut container = test_container(
+            IdentityDetails::default(),
+            BTreeSet::default(),
+            |mock_identity| {
+                mock_identity
+                    .expect_clone()
+                    .returning(MockContextualIdentity::new);
+            },
+        )
+        .await;
+
+        let mut staged = container.stage();
+        staged.priority = 1;
+        assert_eq!(0, container.priority);
+        assert_eq!(1usize, container.outstanding_handles());
+        container.commit(staged);
+        assert_eq!(1, container.priority);
+        assert_eq!(0usize, container.outstanding_handles());
         container.handle().finish();
         Ok(())
     }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_manage() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let own_suffixes = BTreeSet::from([Suffix::try_from("example.com")?]);
+        let managed_suffixes = BTreeSet::from([Suffix::try_from("example.net")?]);
+        let mut container = test_container(IdentityDetails::default(), own_suffixes, |_| ()).await;
+        container.variant = ContainerVariant::Permanent;
+
+        container.manage(managed_suffixes.clone());
+        assert!(matches!(container.variant, ContainerVariant::Locked));
+        assert_eq!(i32::MAX, container.priority);
+        assert!(managed_suffixes == container.suffixes);
+
+        let mut updated = test_container(IdentityDetails::default(), BTreeSet::new(), |_| ()).await;
+        updated.variant = ContainerVariant::Permanent;
+        container.assign(updated);
+        assert!(matches!(container.variant, ContainerVariant::Locked));
+        assert!(managed_suffixes == container.suffixes);
+        let unmanaged = container.unmanaged.as_ref().expect("managed container");
+        assert!(unmanaged.suffixes.is_empty());
+        assert_eq!(0, unmanaged.priority);
+        container.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_container_key() {
+        let cookie_store_id = CookieStoreId::new(String::from("mock_id"));
+        let key = ContainerKey::from(&cookie_store_id).to_string();
+        assert_eq!("container/b64_bW9ja19pZA", key);
+        assert_eq!(
+            Some(&cookie_store_id),
+            ContainerKey::parse(&key)
+                .as_ref()
+                .map(ContainerKey::cookie_store_id)
+        );
+        assert!(ContainerKey::parse("b64_bW9ja19pZA").is_none());
+        assert!(ContainerKey::parse("container/psl").is_none());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_container_deserialize_corrupt() {
+        let mut deserializer = AssertDeserializer::builder()
+            .tokens(Tokens(vec![
+                Token::Map { len: Some(2) },
+                Token::Str(String::from("handle")),
+                Token::Str(String::from("b64_bW9ja19pZA")),
+                Token::Str(String::from("variant")),
+                Token::Bool(false),
+                Token::MapEnd,
+            ]))
+            .build();
+        assert!(Container::deserialize(&mut deserializer).is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_active_hours() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let suffixes = BTreeSet::from([Suffix::try_from("example.com")?]);
+        let mut container = test_container(IdentityDetails::default(), suffixes, |mock_identity| {
+            mock_identity
+                .expect_identity_details()
+                .returning(IdentityDetails::default);
+        })
+        .await;
+        container.active_hours = Some(ActiveHours {
+            weekdays: vec![Weekday::Mon],
+            start_hour: 9,
+            end_hour: 17,
+        });
+        let cookie_store_id = container.handle().cookie_store_id().clone();
+        let mut owner = ContainerOwner::default();
+        owner.insert(container);
+
+        let monday = NaiveDate::from_ymd_opt(2024, 1, 1).expect("valid date");
+        let tuesday = monday.succ_opt().expect("valid date");
+        for (date, hour, should_match) in [
+            (monday, 9, true),
+            (monday, 16, true),
+            (monday, 17, false),
+            (tuesday, 10, false),
+        ] {
+            let clock = FixedClock(date.and_hms_opt(hour, 0, 0).expect("valid time"));
+            let container_match = owner.match_container(
+                EncodedDomain::try_from("example.com")?,
+                &Psl::default(),
+                &clock,
+            );
+            assert_eq!(should_match, container_match.is_some());
+        }
+
+        owner
+            .remove(&cookie_store_id)
+            .expect("inserted container")
+            .handle()
+            .finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_priority() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = ContainerOwner::default();
+        for (cookie_store_id, suffix) in [
+            ("mock_glob", "*.example.com"),
+            ("mock_normal", "example.com"),
+        ] {
+            let suffixes = BTreeSet::from([Suffix::try_from(suffix)?]);
+            let container = test_container_with_id(
+                cookie_store_id,
+                IdentityDetails::default(),
+                suffixes,
+                |mock_identity| {
+                    mock_identity
+                        .expect_identity_details()
+                        .returning(IdentityDetails::default);
+                },
+            )
+            .await;
+            owner.insert(container);
+        }
+        let glob_id = CookieStoreId::new(String::from("mock_glob"));
+        let normal_id = CookieStoreId::new(String::from("mock_normal"));
+        let match_id = |owner: &mut ContainerOwner| -> Result<_, CustomError> {
+            let domain = EncodedDomain::try_from("mail.example.com")?;
+            Ok(owner
+                .match_container(domain, &Psl::default(), &SystemClock)
+                .map(|container_match| {
+                    container_match.container.handle().cookie_store_id().clone()
+                }))
+        };
+
+        assert_eq!(Some(glob_id.clone()), match_id(&mut owner)?);
+        owner
+            .get_mut(normal_id.clone())
+            .expect("inserted container")
+            .priority = 1;
+        assert_eq!(Some(normal_id.clone()), match_id(&mut owner)?);
+
+        for cookie_store_id in [glob_id, normal_id] {
+            owner
+                .remove(&cookie_store_id)
+                .expect("inserted container")
+                .handle()
+                .finish();
+        }
+        Ok(())
+    }
+
+    pub async fn test_owner(containers: &[(&str, &[&str])]) -> Result<ContainerOwner, CustomError> {
+        let mut owner = ContainerOwner::default();
+        for (cookie_store_id, suffixes) in containers {
+            let suffixes = suffixes
+                .iter()
+                .map(|suffix| Suffix::try_from(*suffix))
+                .collect::<Result<BTreeSet<Suffix>, CustomError>>()?;
+            let container = test_container_with_id(
+                cookie_store_id,
+                IdentityDetails::default(),
+                suffixes,
+                |mock_identity| {
+                    mock_identity
+                        .expect_identity_details()
+                        .returning(IdentityDetails::default);
+                },
+            )
+            .await;
+            owner.insert(container);
+        }
+        Ok(owner)
+    }
+
+    fn match_id(
+        owner: &mut ContainerOwner,
+        domain: &str,
+    ) -> Result<Option<CookieStoreId>, CustomError> {
+        let domain = EncodedDomain::try_from(domain)?;
+        Ok(owner
+            .match_container(domain, &Psl::default(), &SystemClock)
+            .map(|container_match| container_match.container.handle().cookie_store_id().clone()))
+    }
+
+    fn mock_id(cookie_store_id: &str) -> Option<CookieStoreId> {
+        Some(CookieStoreId::new(String::from(cookie_store_id)))
+    }
+
+    pub fn remove_container(owner: &mut ContainerOwner, cookie_store_id: &str) {
+        owner
+            .remove(&CookieStoreId::new(String::from(cookie_store_id)))
+            .expect("inserted container")
+            .handle()
+            .finish();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_bury() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_temp", &["example.com"]),
+            ("mock_other", &["example.net"]),
+        ])
+        .await?;
+        let cookie_store_id = CookieStoreId::new(String::from("mock_temp"));
+        assert!(owner.ensure_alive(&cookie_store_id).is_ok());
+
+        owner
+            .bury(&cookie_store_id)
+            .expect("inserted container")
+            .handle()
+            .finish();
+        assert!(owner.get(&cookie_store_id).is_none());
+        assert_eq!(None, match_id(&mut owner, "example.com")?);
+        assert!(matches!(
+            owner.ensure_alive(&cookie_store_id),
+            Err(CustomError::DeletedContainer { .. })
+        ));
+        assert!(owner
+            .ensure_alive(&CookieStoreId::new(String::from("mock_unowned")))
+            .is_ok());
+
+        let stale_handle = owner
+            .get(&CookieStoreId::new(String::from("mock_other")))
+            .expect("inserted container")
+            .handle()
+            .clone();
+        owner
+            .bury(stale_handle.cookie_store_id())
+            .expect("inserted container")
+            .handle()
+            .finish();
+        assert!(owner.ensure_alive(&cookie_store_id).is_ok());
+        assert!(matches!(
+            owner.ensure_alive(stale_handle.cookie_store_id()),
+            Err(CustomError::DeletedContainer { .. })
+        ));
+        stale_handle.finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_exclusion() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            (
+                "mock_mail",
+                &[
+                    "*.example.com",
+                    "!mail.example.com",
+                    "inbox.mail.example.com",
+                ],
+            ),
+            ("mock_fallback", &["*.com"]),
+        ])
+        .await?;
+        for (domain, expected_id) in [
+            ("www.example.com", "mock_mail"),
+            ("mail.example.com", "mock_fallback"),
+            ("login.mail.example.com", "mock_fallback"),
+            ("inbox.mail.example.com", "mock_mail"),
+        ] {
+            assert_eq!(mock_id(expected_id), match_id(&mut owner, domain)?);
+        }
+
+        remove_container(&mut owner, "mock_mail");
+        remove_container(&mut owner, "mock_fallback");
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_tld_glob() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_google", &["google.*", "!mail.google.com"]),
+            ("mock_mail", &["mail.google.co.uk"]),
+        ])
+        .await?;
+        let mut bytes = Cursor::new("uk\nco.uk\n".as_bytes());
+        let psl = Psl::from_stream(&mut bytes, Utc::now().date_naive()).await?;
+        for (domain, expected_id) in [
+            ("google.com", mock_id("mock_google")),
+            ("www.google.co.uk", mock_id("mock_google")),
+            ("mail.google.com", None),
+            ("mail.google.co.uk", mock_id("mock_mail")),
+            ("google.example.com", None),
+            ("co.uk", None),
+        ] {
+            let got = owner
+                .match_container(EncodedDomain::try_from(domain)?, &psl, &SystemClock)
+                .map(|container_match| {
+                    container_match.container.handle().cookie_store_id().clone()
+                });
+            assert_eq!(expected_id, got, "{domain}");
+        }
+
+        remove_container(&mut owner, "mock_google");
+        remove_container(&mut owner, "mock_mail");
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_shared_suffix() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        for removal_order in [["mock_first", "mock_second"], ["mock_second", "mock_first"]] {
+            let mut owner = test_owner(&[
+                ("mock_first", &["example.com"]),
+                ("mock_second", &["example.com"]),
+            ])
+            .await?;
+            assert_eq!(mock_id("mock_first"), match_id(&mut owner, "example.com")?);
+            remove_container(&mut owner, removal_order[0]);
+            assert_eq!(
+                mock_id(removal_order[1]),
+                match_id(&mut owner, "example.com")?
+            );
+            remove_container(&mut owner, removal_order[1]);
+            assert_eq!(None, match_id(&mut owner, "example.com")?);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_replace() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[("mock_recording", &[]), ("mock_member", &[])]).await?;
+        let recording_id = CookieStoreId::new(String::from("mock_recording"));
+        let confirmed_id = CookieStoreId::new(String::from("mock_confirmed"));
+        owner
+            .get_mut(recording_id.clone())
+            .expect("inserted container")
+            .variant = ContainerVariant::Recording { active: true };
+        owner
+            .get_mut(CookieStoreId::new(String::from("mock_member")))
+            .expect("inserted container")
+            .family = Some(recording_id.clone());
+        let confirmed = test_container_with_id(
+            "mock_confirmed",
+            IdentityDetails::default(),
+            BTreeSet::from([Suffix::try_from("example.com")?]),
+            |mock_identity| {
+                mock_identity
+                    .expect_identity_details()
+                    .returning(IdentityDetails::default);
+            },
+        )
+        .await;
+
+        assert_eq!(None, match_id(&mut owner, "example.com")?);
+        owner
+            .replace(&recording_id, confirmed)
+            .expect("inserted container")
+            .handle()
+            .finish();
+        assert!(owner.get(&recording_id).is_none());
+        assert_eq!(
+            mock_id("mock_confirmed"),
+            match_id(&mut owner, "example.com")?
+        );
+        assert_eq!(
+            owner.family_members(&confirmed_id),
+            vec![CookieStoreId::new(String::from("mock_member"))]
+        );
+
+        for cookie_store_id in ["mock_confirmed", "mock_member"] {
+            remove_container(&mut owner, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_suffix_conflict() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_first", &["example.com"]),
+            ("mock_second", &["example.net"]),
+        ])
+        .await?;
+        let second_id = CookieStoreId::new(String::from("mock_second"));
+        let conflict_id = |owner: &ContainerOwner, suffix| -> Result<_, CustomError> {
+            Ok(owner
+                .suffix_conflict(&Suffix::try_from(suffix)?, &second_id)
+                .map(|container| container.handle().cookie_store_id().clone()))
+        };
+
+        assert_eq!(mock_id("mock_first"), conflict_id(&owner, "example.com")?);
+        assert_eq!(None, conflict_id(&owner, "example.net")?);
+        assert_eq!(None, conflict_id(&owner, "!example.com")?);
+        assert_eq!(None, conflict_id(&owner, "example.org")?);
+        owner
+            .get_mut(second_id.clone())
+            .expect("inserted container")
+            .priority = 1;
+        assert_eq!(None, conflict_id(&owner, "example.com")?);
+
+        let suffixes = [
+            Suffix::try_from("example.org")?,
+            Suffix::try_from("example.com")?,
+        ];
+        assert!(owner
+            .ensure_no_conflict(Some(&second_id), 1, &suffixes)
+            .is_ok());
+        assert!(matches!(
+            owner.ensure_no_conflict(None, 0, &suffixes),
+            Err(CustomError::SuffixConflict { .. })
+        ));
+
+        for cookie_store_id in ["mock_first", "mock_second"] {
+            remove_container(&mut owner, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_find_matches() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_glob", &["*.example.com"]),
+            ("mock_normal", &["mail.example.com", "example.com"]),
+            ("mock_other", &["example.net"]),
+        ])
+        .await?;
+        let find_matches = |owner: &ContainerOwner| -> Result<_, CustomError> {
+            let domain = EncodedDomain::try_from("mail.example.com")?;
+            Ok(owner.find_matches(domain, &Psl::default(), &SystemClock))
+        };
+        let glob_id = CookieStoreId::new(String::from("mock_glob"));
+        let normal_id = CookieStoreId::new(String::from("mock_normal"));
+
+        assert_eq!(
+            vec![normal_id.clone(), glob_id.clone()],
+            find_matches(&owner)?
+        );
+        owner
+            .get_mut(glob_id.clone())
+            .expect("inserted container")
+            .priority = 1;
+        assert_eq!(vec![glob_id, normal_id], find_matches(&owner)?);
+
+        for cookie_store_id in ["mock_glob", "mock_normal", "mock_other"] {
+            remove_container(&mut owner, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_family() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_account_a", &["example.com"]),
+            ("mock_account_b", &[]),
+            ("mock_other", &["example.net"]),
+        ])
+        .await?;
+        let account_a_id = CookieStoreId::new(String::from("mock_account_a"));
+        let account_b_id = CookieStoreId::new(String::from("mock_account_b"));
+        let find_matches = |owner: &ContainerOwner| -> Result<_, CustomError> {
+            let domain = EncodedDomain::try_from("example.com")?;
+            Ok(owner.find_matches(domain, &Psl::default(), &SystemClock))
+        };
+
+        assert_eq!(vec![account_a_id.clone()], find_matches(&owner)?);
+        assert_eq!(owner.canonical_id(&account_b_id), account_b_id);
+        owner
+            .get_mut(account_b_id.clone())
+            .expect("inserted container")
+            .family = Some(account_a_id.clone());
+        assert_eq!(
+            vec![account_a_id.clone(), account_b_id.clone()],
+            find_matches(&owner)?
+        );
+        assert_eq!(
+            owner.family_members(&account_a_id),
+            vec![account_b_id.clone()]
+        );
+        assert_eq!(owner.canonical_id(&account_b_id), account_a_id);
+        assert_eq!(
+            mock_id("mock_account_a"),
+            match_id(&mut owner, "example.com")?
+        );
+
+        remove_container(&mut owner, "mock_account_a");
+        assert_eq!(owner.canonical_id(&account_b_id), account_b_id);
+        for cookie_store_id in ["mock_account_b", "mock_other"] {
+            remove_container(&mut owner, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_promote_family_member() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = test_owner(&[
+            ("mock_canonical", &["example.com"]),
+            ("mock_older", &["example.net"]),
+            ("mock_newer", &[]),
+        ])
+        .await?;
+        let canonical_id = CookieStoreId::new(String::from("mock_canonical"));
+        let older_id = CookieStoreId::new(String::from("mock_older"));
+        let newer_id = CookieStoreId::new(String::from("mock_newer"));
+        for (cookie_store_id, days) in [(&older_id, 2), (&newer_id, 1)] {
+            let mut member = owner
+                .get_mut(cookie_store_id.clone())
+                .expect("inserted container");
+            member.family = Some(canonical_id.clone());
+            member.created -= Duration::days(days);
+        }
+
+        let removed = owner.remove(&canonical_id).expect("inserted container");
+        assert_eq!(
+            owner.promote_family_member(&removed),
+            vec![older_id.clone(), newer_id.clone()]
+        );
+        removed.handle().finish();
+        let older = owner.get(&older_id).expect("inserted container");
+        assert!(older.family.is_none());
+        assert_eq!(2, older.suffixes.len());
+        assert_eq!(owner.canonical_id(&newer_id), older_id);
+        assert_eq!(mock_id("mock_older"), match_id(&mut owner, "example.com")?);
+        for cookie_store_id in ["mock_older", "mock_newer"] {
+            remove_container(&mut owner, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_match_glob_without_normal() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        for (first, second) in [
+            (
+                ("mock_normal", "mail.example.com"),
+                ("mock_glob", "*.example.com"),
+            ),
+            (
+                ("mock_glob", "*.example.com"),
+                ("mock_normal", "mail.example.com"),
+            ),
+        ] {
+            let mut owner = test_owner(&[(first.0, &[first.1]), (second.0, &[second.1])]).await?;
+            assert_eq!(
+                mock_id("mock_normal"),
+                match_id(&mut owner, "mail.example.com")?
+            );
+            owner
+                .get_mut(CookieStoreId::new(String::from("mock_normal")))
+                .expect("inserted container")
+                .active_hours = Some(ActiveHours {
+                weekdays: Vec::new(),
+                ..Default::default()
+            });
+            assert_eq!(
+                mock_id("mock_glob"),
+                match_id(&mut owner, "mail.example.com")?
+            );
+            remove_container(&mut owner, "mock_normal");
+            assert_eq!(
+                mock_id("mock_glob"),
+                match_id(&mut owner, "mail.example.com")?
+            );
+            remove_container(&mut owner, "mock_glob");
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_search() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let suffixes = BTreeSet::from([Suffix::try_from("mail.example.com")?]);
+        let container = test_container(IdentityDetails::default(), suffixes, |mock_identity| {
+            mock_identity
+                .expect_identity_details()
+                .returning(|| IdentityDetails {
+                    name: String::from("Work Mail"),
+                    ..Default::default()
+                });
+        })
+        .await;
+        let cookie_store_id = container.handle().cookie_store_id().clone();
+        let mut owner = ContainerOwner::default();
+        owner.insert(container);
+
+        for filter in ["work", "MAIL", "example"] {
+            assert_eq!(1, owner.search(filter).count());
+        }
+        assert_eq!(0, owner.search("personal").count());
+
+        owner
+            .remove(&cookie_store_id)
+            .expect("inserted container")
+            .handle()
+            .finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_container_owner_duplicate_names() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut owner = ContainerOwner::default();
+        for (cookie_store_id, name, variant) in [
+            ("mock_work", "Work", ContainerVariant::Permanent),
+            ("mock_work_copy", "work", ContainerVariant::Permanent),
+            ("mock_temporary", "Temporary", ContainerVariant::Temporary),
+            (
+                "mock_temporary_copy",
+                "Temporary",
+                ContainerVariant::Temporary,
+            ),
+        ] {
+            let mut container = test_container_with_id(
+                cookie_store_id,
+                IdentityDetails::default(),
+                BTreeSet::default(),
+                |mock_identity| {
+                    mock_identity
+                        .expect_identity_details()
+                        .returning(move || IdentityDetails {
+                            name: String::from(name),
+                            ..Default::default()
+                        });
+                },
+            )
+            .await;
+            container.variant = variant;
+            owner.insert(container);
+        }
+        let work_id = CookieStoreId::new(String::from("mock_work"));
+        let work_copy_id = CookieStoreId::new(String::from("mock_work_copy"));
+
+        assert_eq!(vec!["work"], owner.duplicate_names().collect::<Vec<_>>());
+        assert_eq!(
+            vec!["work"],
+            owner.names_except(Some(&work_id)).collect::<Vec<_>>()
+        );
+        owner
+            .remove(&work_copy_id)
+            .expect("inserted container")
+            .handle()
+            .finish();
+        assert_eq!(0, owner.duplicate_names().count());
+        assert_eq!(0, owner.names_except(Some(&work_id)).count());
+        assert_eq!(vec!["work"], owner.names_except(None).collect::<Vec<_>>());
+
+        for cookie_store_id in ["mock_work", "mock_temporary", "mock_temporary_copy"] {
+            owner
+                .remove(&CookieStoreId::new(String::from(cookie_store_id)))
+                .expect("inserted container")
+                .handle()
+                .finish();
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("work", ""), Some(0));
+        assert!(fuzzy_score("work", "wk").is_some());
+        assert_eq!(fuzzy_score("work", "kw"), None);
+        assert_eq!(fuzzy_score("work", "works"), None);
+        let projects = fuzzy_score("side projects", "pr");
+        let super_score = fuzzy_score("super", "pr");
+        assert!(projects > super_score);
+        assert!(fuzzy_score("work", "wo") > fuzzy_score("wxo", "wo"));
+    }
 }
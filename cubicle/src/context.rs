@@ -1,82 +1,957 @@
 //! Data that are persisted to the storage with version control.
 
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::mem;
 
-use js_sys::{JsString, Reflect};
+use async_std::io::BufReader;
+use chrono::NaiveDate;
+use js_sys::{JsString, Object, Reflect, JSON};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::JsValue;
 
-use crate::container::{ContainerOwner, ContainerVariant};
-use crate::domain::psl::Psl;
-use crate::interop::contextual_identities::CookieStoreId;
-use crate::interop::{self, storage};
-use crate::message::Message;
+use crate::container::{
+    Container, ContainerHandle, ContainerKey, ContainerOwner, ContainerVariant,
+};
+use crate::domain::alias::AliasGroups;
+use crate::domain::psl::{Psl, PslDiff};
+use crate::domain::suffix::{Suffix, SuffixBuilder, SuffixType};
+use crate::guest::{GuestMode, GUEST_MODE_KEY};
+use crate::interop::bookmarks::{self, BookmarkFolderId};
+use crate::interop::cache;
+#[mockall_double::double]
+use crate::interop::contextual_identities::ContextualIdentity;
+use crate::interop::contextual_identities::{
+    CookieStoreId, IdentityDetails, IdentityDetailsProvider,
+};
+use crate::interop::fetch::Fetch;
+use crate::interop::storage::StorageBackend;
+use crate::interop::tabs::TabProperties;
+use crate::interop::windows::WindowId;
+use crate::interop::{self, storage, tabs};
+use crate::interop::{management, web_request};
+use crate::learning::{SuffixLearning, SUFFIX_LEARNING_KEY};
 use crate::migrate::{self, Version};
-use crate::preferences::Preferences;
+use crate::policy::ManagedPolicy;
+use crate::preferences::{ContainerTarget, Preferences};
+use crate::rules::{DecisionTrace, Designation, RulesEngine};
+use crate::session::{self, Session, Sessions};
+use crate::statistics::{TemporaryStatistics, TEMPORARY_STATISTICS_KEY};
+use crate::tab::{ManagedTabs, TabDeterminant};
+use crate::util::clock::SystemClock;
 use crate::util::errors::CustomError;
+use crate::util::log;
 
 /// Persisting data for determining which container to switch to.
+/// Window default containers are not persisted,
+/// as window IDs are not stable across sessions.
+/// Bookmark bindings map folders to the container for their bookmarks.
+/// Alias groups are copied to [ManagedTabs] for checking relocations,
+/// which are paused while conflicting extensions are enabled.
+/// Containers and preferences managed by policy are not persisted as managed,
+/// as the policy is read again at startup.
+/// A context that is not loaded is the default placeholder,
+/// which is degraded if loading from the storage failed,
+/// so that it is not written over the stored context.
+/// The public suffix list is parsed from its [PslSource] after the rest
+/// is loaded, so that tabs can be handled while it is pending.
 #[derive(Default, Deserialize, Serialize)]
 pub struct GlobalContext {
     #[serde(flatten)]
     pub containers: ContainerOwner,
-    #[serde(default)]
+    #[serde(skip)]
     pub psl: Psl,
     #[serde(default)]
     pub preferences: Preferences,
+    #[serde(default)]
+    pub bookmark_bindings: BTreeMap<BookmarkFolderId, CookieStoreId>,
+    #[serde(default)]
+    pub alias_groups: AliasGroups,
+    #[serde(default)]
+    pub guest_mode: Option<GuestMode>,
+    #[serde(default)]
+    pub suffix_learning: SuffixLearning,
+    #[serde(default)]
+    pub temporary_statistics: TemporaryStatistics,
+    #[serde(skip)]
+    pub window_containers: HashMap<WindowId, CookieStoreId>,
+    #[serde(skip)]
+    pub managed_containers: HashSet<CookieStoreId>,
+    #[serde(skip)]
+    pub are_preferences_managed: bool,
+    #[serde(skip)]
+    pub conflicting_extensions: Vec<String>,
+    #[serde(skip)]
+    pub is_loaded: bool,
+    #[serde(skip)]
+    pub is_degraded: bool,
+    #[serde(skip)]
+    pub is_psl_pending: bool,
+}
+
+/// Key of the public suffix list from before it was stored by its source.
+const LEGACY_PSL_KEY: &str = "psl";
+
+/// Path of the bundled public suffix list relative to the extension.
+const PSL_BUNDLED_PATH: &str = "public_suffix_list.dat";
+
+/// URL of the public suffix list if no other URL has been used.
+pub const PSL_DEFAULT_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+/// Differences between the identities in the browser and the containers
+/// known to Cubicle, which would otherwise accumulate silently.
+/// - `unknown_identities` are identities that Cubicle has no entries for.
+/// - `missing_identities` are entries whose identities no longer exist.
+#[derive(Default, Serialize)]
+pub struct Reconciliation {
+    unknown_identities: Vec<DriftedEntry>,
+    missing_identities: Vec<DriftedEntry>,
+}
+
+/// Identity or entry that is out of sync, see [Reconciliation].
+/// Unknown identities come with suffixes suggested from their open tabs,
+/// so that they can be adopted as managed containers quickly.
+#[derive(Serialize)]
+struct DriftedEntry {
+    cookie_store_id: CookieStoreId,
+    name: String,
+    suggested_suffixes: Vec<String>,
+}
+
+/// Request to adopt an unknown identity with the given suffixes,
+/// see [GlobalContext::reconcile].
+#[derive(Deserialize)]
+pub struct Adoption {
+    cookie_store_id: CookieStoreId,
+    #[serde(default)]
+    suffixes: Vec<String>,
+}
+
+/// Where the public suffix list was obtained, stored instead of the list
+/// so that the list can be parsed again from the raw file when needed.
+/// Downloaded files are kept in the [cache] under their URLs,
+/// the bundled file is used if there is no URL.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PslSource {
+    pub url: Option<String>,
+    pub last_updated: NaiveDate,
+}
+
+impl Default for PslSource {
+    fn default() -> Self {
+        Self {
+            url: None,
+            last_updated: *migrate::BUILTIN_PSL_VERSION,
+        }
+    }
+}
+
+impl PslSource {
+    /// Opens the file for reading, along with the date of the list.
+    /// Falls back to the bundled file if the downloaded file is absent.
+    /// Fails if the file cannot be opened or the browser indicates so.
+    pub async fn open(&self) -> Result<(Fetch, NaiveDate), CustomError> {
+        if let Some(url) = &self.url {
+            match cache::get(url).await? {
+                Some(response) => return Ok((Fetch::from_response(&response)?, self.last_updated)),
+                None => log::warn!("downloaded public suffix list is absent, using bundled list"),
+            }
+        }
+        let bundled = Self::default();
+        let path = interop::prepend_extension_base_url(PSL_BUNDLED_PATH);
+        Ok((Fetch::get_stream(&path, None).await?, bundled.last_updated))
+    }
+
+    /// Reads the rules of the list from the file without parsing them,
+    /// see [PslSource::open] and [Psl::read_rules].
+    pub async fn read_rules(&self) -> Result<HashSet<String>, CustomError> {
+        let (fetch, _last_updated) = self.open().await?;
+        Psl::read_rules(&mut BufReader::new(fetch)).await
+    }
+
+    /// Parses the list from the file, see [PslSource::open].
+    /// The bundled list is parsed instead if the downloaded file is corrupt.
+    /// Fails if the bundled list cannot be read or the browser indicates so.
+    pub async fn parse(&self) -> Result<Psl, CustomError> {
+        let (fetch, last_updated) = self.open().await?;
+        let parsed = Psl::from_stream(&mut BufReader::new(fetch), last_updated).await;
+        match parsed {
+            Err(error) if self.url.is_some() => {
+                log::warn!("failed to parse downloaded public suffix list, {error}");
+                let (fetch, last_updated) = Self::default().open().await?;
+                Psl::from_stream(&mut BufReader::new(fetch), last_updated).await
+            }
+            parsed => parsed,
+        }
+    }
+}
+
+/// Public suffix list parsed from a source.
+pub enum ParsedPsl {
+    /// The whole list.
+    Full(Psl),
+    /// The changes from the list parsed from the base source.
+    Diff { base: PslSource, diff: PslDiff },
+}
+
+/// Storage entry of the source of the public suffix list.
+#[derive(Default, Deserialize, Serialize)]
+struct StoredPslSource {
+    psl_source: Option<PslSource>,
 }
 
 impl GlobalContext {
     /// Populates a context after checking the version for compatibility.
+    /// Existing tabs, including those from session restore,
+    /// are registered to the given [ManagedTabs].
+    /// The public suffix list is left pending, see [GlobalContext::hydrate_psl].
     /// Fails with [CustomError::UnsupportedVersion]
     /// or if the browser indicates so.
-    pub async fn from_storage() -> Result<Self, CustomError> {
+    pub async fn from_storage(managed_tabs: &mut ManagedTabs) -> Result<Self, CustomError> {
+        migrate::namespace_storage_keys().await?;
         let mut stored_version = Version::default();
         storage::get_with_keys(&mut stored_version).await?;
+        let existing_tabs = tabs::query_all().await?;
         let mut context = GlobalContext::default();
         if stored_version == Version::default() {
             storage::set_with_serde_keys(&context).await?;
             storage::set_with_serde_keys(&migrate::CURRENT_VERSION).await?;
-            Message::PslUpdate { url: None }
-                .act(&mut &mut context)
-                .await?;
-            Ok(context)
         } else if stored_version != migrate::CURRENT_VERSION {
-            Err(CustomError::UnsupportedVersion)
+            return Err(CustomError::UnsupportedVersion);
         } else {
             let all_stored = storage::get_all().await?;
-            Reflect::delete_property(&all_stored, &JsString::from("version"))
+            let stored_preferences = Reflect::get(&all_stored, &JsString::from("preferences"))
                 .expect("constructed object from get all function");
-            context = interop::cast_or_standard_mismatch(JsValue::from(all_stored))?;
-
-            if context.psl.is_empty() {
-                Message::PslUpdate { url: None }
-                    .act(&mut &mut context)
-                    .await?;
+            let backend = interop::cast_or_standard_mismatch::<Preferences>(stored_preferences)
+                .map(|preferences| preferences.large_data_backend)
+                .unwrap_or_default();
+            if backend != StorageBackend::Local {
+                Object::assign(&all_stored, &backend.get_all().await?);
             }
+            storage::set_large_data_backend(backend);
+            let stored_event_log = Reflect::get(&all_stored, &JsString::from(log::EVENT_LOG_KEY))
+                .expect("constructed object from get all function");
+            if let Ok(event_log) = interop::cast_or_standard_mismatch(stored_event_log) {
+                log::restore(event_log);
+            }
+            context = Self::from_entries(&all_stored);
+            context.repair_references();
 
-            context.purge_temporary_containers().await?;
+            context.purge_temporary_containers(&existing_tabs).await?;
             let uncached_containers = mem::take(&mut context.containers);
             context.containers.merge(uncached_containers);
-            Ok(context)
         }
+        if let Ok(policy) = ManagedPolicy::from_managed_storage().await {
+            context.merge_policy(policy).await;
+        }
+        if let Ok(policy) = ManagedPolicy::fetch().await {
+            context.merge_policy(policy).await;
+        }
+        context.adopt_tabs(&existing_tabs, managed_tabs);
+        context.configure_managed_tabs(managed_tabs);
+        log::set_level(context.preferences.log_level);
+        context.detect_conflicts(managed_tabs).await;
+        context.sync_header_rules();
+        context.is_loaded = true;
+        context.is_psl_pending = true;
+        Ok(context)
+    }
+
+    /// Source of the public suffix list that was left pending,
+    /// [None] if the list is not pending.
+    /// The bundled list is the source if none is stored,
+    /// in which case a list stored in the legacy parsed form is removed.
+    /// Fails if the browser indicates so.
+    pub async fn pending_psl_source(&self) -> Result<Option<PslSource>, CustomError> {
+        if !self.is_psl_pending {
+            return Ok(None);
+        }
+        let backend = self.preferences.large_data_backend;
+        let mut stored = StoredPslSource::default();
+        if let Err(error) = backend.get_with_keys(&mut stored).await {
+            log::warn!("discarded corrupt entry `psl_source` from storage, {error}");
+        }
+        if stored.psl_source.is_none() {
+            backend.remove_entries(&[LEGACY_PSL_KEY]).await?;
+            if backend != StorageBackend::Local {
+                storage::remove_entries(&[LEGACY_PSL_KEY]).await?;
+            }
+        }
+        Ok(Some(stored.psl_source.unwrap_or_default()))
+    }
+
+    /// Loads the list parsed from the [pending source](Self::pending_psl_source),
+    /// the list is discarded if it has been replaced meanwhile.
+    pub fn hydrate_psl(&mut self, psl: Psl) {
+        if mem::take(&mut self.is_psl_pending) {
+            self.psl = psl;
+        }
+    }
+
+    /// Source of the current public suffix list, [None] if it is pending.
+    /// Fails if the browser indicates so.
+    pub async fn current_psl_source(&self) -> Result<Option<PslSource>, CustomError> {
+        if self.is_psl_pending {
+            return Ok(None);
+        }
+        let mut stored = StoredPslSource::default();
+        self.preferences
+            .large_data_backend
+            .get_with_keys(&mut stored)
+            .await?;
+        Ok(Some(stored.psl_source.unwrap_or_default()))
+    }
+
+    /// Updates the public suffix list with one parsed from the source.
+    /// Changes are only applied if the current list is parsed from their base,
+    /// otherwise nothing is changed and `false` is returned,
+    /// in which case the list should be parsed in full.
+    /// Fails if the browser indicates so.
+    pub async fn update_psl(
+        &mut self,
+        parsed: ParsedPsl,
+        source: &PslSource,
+    ) -> Result<bool, CustomError> {
+        match parsed {
+            ParsedPsl::Full(psl) => {
+                self.store_psl_source(source).await?;
+                self.psl = psl;
+                self.is_psl_pending = false;
+            }
+            ParsedPsl::Diff { base, diff } => {
+                if self.current_psl_source().await?.as_ref() != Some(&base) {
+                    return Ok(false);
+                }
+                self.store_psl_source(source).await?;
+                log::info!("applied {} changes to the public suffix list", diff.len());
+                self.psl.apply(diff);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stores the source of the public suffix list in place of the previous.
+    /// The previously downloaded file is removed from the cache
+    /// if it is from another URL.
+    /// Fails if the browser indicates so.
+    async fn store_psl_source(&self, source: &PslSource) -> Result<(), CustomError> {
+        let backend = self.preferences.large_data_backend;
+        let mut previous = StoredPslSource::default();
+        let previous_url = backend
+            .get_with_keys(&mut previous)
+            .await
+            .ok()
+            .and(previous.psl_source)
+            .and_then(|previous| previous.url)
+            .filter(|previous_url| source.url.as_ref() != Some(previous_url));
+        backend.store_single_entry("psl_source", source).await?;
+        if let Some(previous_url) = previous_url {
+            if let Err(error) = cache::delete(&previous_url).await {
+                log::warn!("failed to remove previous public suffix list, {error}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Builder for suffixes entered by the user,
+    /// which are reduced to their registrable domains if preferred.
+    pub fn suffix_builder(&self) -> SuffixBuilder<'_> {
+        Suffix::normalized(&self.psl).registrable(self.preferences.should_reduce_suffixes)
+    }
+
+    /// Moves entries kept in the large data backend from the previous one
+    /// to the one in the preferences, removing them from the previous one.
+    /// Queued entries of large datasets are written to the new one afterwards.
+    /// Fails if the browser indicates so.
+    pub async fn move_large_data(&self, previous: StorageBackend) -> Result<(), CustomError> {
+        let backend = self.preferences.large_data_backend;
+        if backend == previous {
+            return Ok(());
+        }
+        previous
+            .move_entries(
+                backend,
+                &[
+                    "psl_source",
+                    session::SESSIONS_KEY,
+                    session::LAST_SESSION_KEY,
+                    log::EVENT_LOG_KEY,
+                    TEMPORARY_STATISTICS_KEY,
+                ],
+            )
+            .await?;
+        storage::set_large_data_backend(backend);
+        Ok(())
+    }
+
+    /// Casts stored entries into a context one by one,
+    /// so that a corrupt entry is discarded instead of failing the whole load.
+    /// Entries of other parts, such as the public suffix list, are skipped.
+    /// Discarded entries are reported to the event log,
+    /// and are replaced in the storage once the same part is written.
+    fn from_entries(all_stored: &Object) -> Self {
+        let mut context = Self::default();
+        for key in Object::keys(all_stored)
+            .iter()
+            .filter_map(|key| key.as_string())
+        {
+            let value = Reflect::get(all_stored, &JsString::from(key.as_str()))
+                .expect("constructed object from get all function");
+            let cast_result = match key.as_str() {
+                "preferences" => interop::cast_or_standard_mismatch(value)
+                    .map(|preferences| context.preferences = preferences),
+                "bookmark_bindings" => interop::cast_or_standard_mismatch(value)
+                    .map(|bindings| context.bookmark_bindings = bindings),
+                "alias_groups" => interop::cast_or_standard_mismatch(value)
+                    .map(|groups| context.alias_groups = groups),
+                GUEST_MODE_KEY => interop::cast_or_standard_mismatch(value)
+                    .map(|guest_mode| context.guest_mode = guest_mode),
+                SUFFIX_LEARNING_KEY => interop::cast_or_standard_mismatch(value)
+                    .map(|learning| context.suffix_learning = learning),
+                TEMPORARY_STATISTICS_KEY => interop::cast_or_standard_mismatch(value)
+                    .map(|statistics| context.temporary_statistics = statistics),
+                _ if ContainerKey::parse(&key).is_some() => interop::cast_or_standard_mismatch::<
+                    Option<Container>,
+                >(value)
+                .map(|container| {
+                    if let Some(container) = container {
+                        context.containers.insert(container);
+                    }
+                }),
+                _ => Ok(()),
+            };
+            if let Err(error) = cast_result {
+                log::warn!("discarded corrupt entry `{key}` from storage, {error}");
+            }
+        }
+        context
     }
 
-    /// Deletes and remove temporary containers from the [ContainerOwner].
+    /// Removes references to containers that do not exist,
+    /// such as those left behind by a discarded container entry.
+    /// Suffixes and names are not checked,
+    /// as they are indexed from the containers themselves.
+    /// Repaired parts are queued to be stored.
+    fn repair_references(&mut self) {
+        let containers = &self.containers;
+        let binding_count = self.bookmark_bindings.len();
+        self.bookmark_bindings
+            .retain(|_, cookie_store_id| containers.get(cookie_store_id).is_some());
+        let dangling_binding_count = binding_count - self.bookmark_bindings.len();
+        if dangling_binding_count > 0 {
+            log::warn!("removed {dangling_binding_count} bookmark bindings to missing containers");
+            storage::queue_single_entry("bookmark_bindings", &self.bookmark_bindings);
+        }
+
+        let preferences = &mut self.preferences;
+        let mut dangling_target_count = 0;
+        let targets = [
+            &mut preferences.new_tab_container,
+            &mut preferences.external_link_container,
+        ]
+        .into_iter()
+        .chain(preferences.external_link_rules.values_mut());
+        for target in targets {
+            if let ContainerTarget::Designated { cookie_store_id } = target {
+                if containers.get(cookie_store_id).is_none() {
+                    *target = ContainerTarget::default();
+                    dangling_target_count += 1;
+                }
+            }
+        }
+        preferences
+            .domain_overrides
+            .retain(|_suffix, domain_overrides| {
+                let override_count = domain_overrides.len();
+                domain_overrides.retain(|domain_override| {
+                    let is_target_alive = match &domain_override.target {
+                        ContainerTarget::Designated { cookie_store_id } => {
+                            containers.get(cookie_store_id).is_some()
+                        }
+                        ContainerTarget::NoContainer | ContainerTarget::Temporary => true,
+                    };
+                    is_target_alive && containers.get(&domain_override.from_container).is_some()
+                });
+                dangling_target_count += override_count - domain_overrides.len();
+                !domain_overrides.is_empty()
+            });
+        if dangling_target_count > 0 {
+            log::warn!("reset {dangling_target_count} preferences targeting missing containers");
+            storage::queue_single_entry("preferences", &self.preferences);
+        }
+    }
+
+    /// Copies the alias groups, the intercepted schemes and
+    /// how openers are followed to the [ManagedTabs],
+    /// as relocations are checked without locking the context.
+    pub fn configure_managed_tabs(&self, managed_tabs: &mut ManagedTabs) {
+        managed_tabs.replace_alias_groups(self.alias_groups.clone());
+        managed_tabs.replace_schemes(
+            self.preferences.intercepted_schemes.clone(),
+            self.preferences.additional_schemes.clone(),
+        );
+        managed_tabs.inherit_opener(self.preferences.should_inherit_opener_container);
+        managed_tabs.replace_opener_chain(self.preferences.opener_chain.clone());
+    }
+
+    /// Detects enabled extensions that also relocate tabs,
+    /// relocation is paused if there are any to avoid fighting over tabs.
+    /// Best effort as conflicts are assumed to be absent if undetectable.
+    pub async fn detect_conflicts(&mut self, managed_tabs: &mut ManagedTabs) {
+        self.conflicting_extensions = management::conflicting_extensions()
+            .await
+            .unwrap_or_default();
+        managed_tabs.pause_relocation(!self.conflicting_extensions.is_empty());
+    }
+
+    /// Merges a managed policy, containers are found by their names
+    /// and created if they do not exist.
+    /// Managed containers are locked with the highest priority,
+    /// and their suffixes are replaced by those from the policy,
+    /// both in memory only, see [Container::manage].
+    /// Managed preferences replace the stored preferences in memory only.
+    /// Containers that cannot be created, or whose suffixes conflict with
+    /// another managed container, are skipped.
+    pub async fn merge_policy(&mut self, policy: ManagedPolicy) {
+        if let Some(preferences) = policy.preferences {
+            self.preferences = preferences;
+            self.are_preferences_managed = true;
+        }
+        for managed_container in policy.containers {
+            let existing_id = self
+                .containers
+                .find_by_name(&managed_container.name)
+                .map(|container| container.handle().cookie_store_id().clone());
+            if let Err(error) = self.containers.ensure_no_conflict(
+                existing_id.as_ref(),
+                i32::MAX,
+                &managed_container.suffixes,
+            ) {
+                log::warn!(
+                    "managed container `{}` was skipped, {error}",
+                    managed_container.name
+                );
+                continue;
+            }
+            let cookie_store_id = match existing_id {
+                Some(cookie_store_id) => cookie_store_id,
+                None => {
+                    let details = IdentityDetails {
+                        name: managed_container.name.clone(),
+                        ..Default::default()
+                    };
+                    let container = match Container::create(
+                        details,
+                        ContainerVariant::Permanent,
+                        BTreeSet::new(),
+                    )
+                    .await
+                    {
+                        Ok(container) => container,
+                        Err(error) => {
+                            log::warn!(
+                                "managed container `{}` was not created, {error}",
+                                managed_container.name
+                            );
+                            continue;
+                        }
+                    };
+                    let cookie_store_id = container.handle().cookie_store_id().clone();
+                    storage::queue_single_entry(&ContainerKey::from(&cookie_store_id), &container);
+                    self.containers.insert(container);
+                    cookie_store_id
+                }
+            };
+            self.containers
+                .get_mut(cookie_store_id.clone())
+                .expect("container found or inserted")
+                .manage(managed_container.suffixes);
+            self.managed_containers.insert(cookie_store_id);
+        }
+    }
+
+    /// Replaces the request header rules with the headers
+    /// configured on the owned containers.
+    pub fn sync_header_rules(&self) {
+        web_request::replace_header_rules(
+            self.containers
+                .iter()
+                .filter(|container| !container.request_headers.is_empty())
+                .map(|container| {
+                    (
+                        container.handle().cookie_store_id().clone(),
+                        container.request_headers.clone(),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    /// Gets a handle to the default container of the window,
+    /// [None] if the window has no default container or it was removed.
+    #[must_use = "clean up must be done before releasing the handle"]
+    pub fn window_container(&self, window_id: &WindowId) -> Option<ContainerHandle> {
+        let cookie_store_id = self.window_containers.get(window_id)?;
+        let container = self.containers.get(cookie_store_id)?;
+        Some(container.handle().clone())
+    }
+
+    /// Gets a handle to the container bound to a folder with a bookmark
+    /// of the URL, best effort and [None] if there is no such container.
+    #[must_use = "clean up must be done before releasing the handle"]
+    pub async fn bookmark_container(&self, url: &str) -> Option<ContainerHandle> {
+        if self.bookmark_bindings.is_empty() {
+            return None;
+        }
+        let folder_ids = bookmarks::parent_folder_ids(url).await.ok()?;
+        folder_ids.iter().find_map(|folder_id| {
+            let cookie_store_id = self.bookmark_bindings.get(folder_id)?;
+            let container = self.containers.get(cookie_store_id)?;
+            Some(container.handle().clone())
+        })
+    }
+
+    /// Decides where a navigation to the URL would be opened without
+    /// touching any tab, given the container the tab is currently in,
+    /// [None] if the tab is not contained.
+    /// Bookmark bindings and the container of the opener are considered,
+    /// but not window defaults as they only apply to new tabs.
+    pub async fn simulate_relocation(
+        &self,
+        url: &str,
+        current_container: Option<&CookieStoreId>,
+        opener_container: Option<CookieStoreId>,
+    ) -> DecisionTrace {
+        let designated = self.bookmark_container(url).await.map(|handle| {
+            handle.finish();
+            handle.cookie_store_id().clone()
+        });
+        self.rules_engine()
+            .designate(designated, Designation::BookmarkBinding)
+            .opened_from(opener_container)
+            .trace(url, current_container)
+    }
+
+    /// Rules engine that reads from this context,
+    /// active hours of containers are checked against the system clock.
+    pub fn rules_engine(&self) -> RulesEngine<'_, SystemClock> {
+        RulesEngine::new(&self.containers, &self.psl, &self.preferences, &SystemClock)
+    }
+
+    /// Gets a handle to the container with the given cookie store ID,
+    /// or a finished handle if the container is not owned.
+    #[must_use = "clean up must be done before releasing the handle"]
+    pub fn handle_of(&self, cookie_store_id: &CookieStoreId) -> ContainerHandle {
+        match self.containers.get(cookie_store_id) {
+            Some(container) => container.handle().clone(),
+            None => {
+                let handle = ContainerHandle::from(cookie_store_id.clone());
+                handle.finish();
+                handle
+            }
+        }
+    }
+
+    /// Registers existing tabs to the [ManagedTabs] with their domains,
+    /// so that they are recognized as openers after a restart.
+    /// Tabs in owned containers share the container's handle,
+    /// so that temporary containers are deleted once those tabs are closed.
+    fn adopt_tabs(&self, existing_tabs: &[TabProperties], managed_tabs: &mut ManagedTabs) {
+        for tab in existing_tabs {
+            let tab_det = TabDeterminant {
+                container_handle: self.handle_of(&tab.cookie_store_id),
+                domain: tab.domain().ok().flatten(),
+            };
+            if let Some(old_det) = managed_tabs.register(tab.id(), tab_det) {
+                old_det.container_handle.finish();
+            }
+        }
+    }
+
+    /// Applies changes to the storage made from other contexts.
+    /// Changes that are already reflected in this context are skipped,
+    /// which includes those made by this context.
+    /// Fails if some changed values are not compatible.
+    pub fn apply_storage_changes(
+        &mut self,
+        new_values: Vec<(String, JsValue)>,
+    ) -> Result<(), CustomError> {
+        let is_reflected = |new_value: &JsValue, current_value: JsValue| {
+            JSON::stringify(new_value).ok().map(String::from)
+                == JSON::stringify(&current_value).ok().map(String::from)
+        };
+        for (key, new_value) in new_values {
+            match key.as_str() {
+                "preferences"
+                    if !self.are_preferences_managed
+                        && !is_reflected(&new_value, interop::to_jsvalue(&self.preferences)) =>
+                {
+                    self.preferences = interop::cast_or_standard_mismatch(new_value)?;
+                }
+                "bookmark_bindings"
+                    if !is_reflected(&new_value, interop::to_jsvalue(&self.bookmark_bindings)) =>
+                {
+                    self.bookmark_bindings = interop::cast_or_standard_mismatch(new_value)?;
+                }
+                "alias_groups"
+                    if !is_reflected(&new_value, interop::to_jsvalue(&self.alias_groups)) =>
+                {
+                    self.alias_groups = interop::cast_or_standard_mismatch(new_value)?;
+                }
+                GUEST_MODE_KEY
+                    if !is_reflected(&new_value, interop::to_jsvalue(&self.guest_mode)) =>
+                {
+                    self.guest_mode = interop::cast_or_standard_mismatch(new_value)?;
+                }
+                _ => {
+                    let Some(container_key) = ContainerKey::parse(&key) else {
+                        continue;
+                    };
+                    let cookie_store_id = container_key.cookie_store_id().clone();
+                    let current_value = interop::to_jsvalue(&self.containers.get(&cookie_store_id));
+                    if new_value.is_null() || new_value.is_undefined() {
+                        if let Some(container) = self.containers.remove(&cookie_store_id) {
+                            container.handle().finish();
+                        }
+                    } else if !is_reflected(&new_value, current_value) {
+                        let container = interop::cast_or_standard_mismatch::<Container>(new_value)?;
+                        self.containers.upsert(container);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes stored container entries that are empty or whose identities
+    /// no longer exist in the browser, along with their containers.
+    /// Returns the number of removed entries.
     /// Fails if the browser indicates so.
-    /// May be changed in the future to accommodate session restore.
-    async fn purge_temporary_containers(&mut self) -> Result<(), CustomError> {
+    pub async fn compact_storage(&mut self) -> Result<usize, CustomError> {
+        let identity_keys = ContextualIdentity::fetch_all()
+            .await?
+            .iter()
+            .map(|identity| ContainerKey::from(identity.cookie_store_id()).to_string())
+            .collect::<HashSet<String>>();
+        let all_stored = storage::get_all().await?;
+        let orphaned_keys = Object::keys(&all_stored)
+            .iter()
+            .filter_map(|key| key.as_string())
+            .filter(|key| ContainerKey::parse(key).is_some())
+            .filter(|key| {
+                let value = Reflect::get(&all_stored, &JsString::from(key.as_str()))
+                    .expect("constructed object from get all function");
+                value.is_null() || !identity_keys.contains(key)
+            })
+            .collect::<Vec<String>>();
+        for key in orphaned_keys
+            .iter()
+            .filter_map(|key| ContainerKey::parse(key))
+        {
+            if let Some(container) = self.containers.remove(key.cookie_store_id()) {
+                container.handle().finish();
+            }
+        }
+        storage::remove_entries(&orphaned_keys).await?;
+        Ok(orphaned_keys.len())
+    }
+
+    /// Diffs the identities in the browser against the [ContainerOwner],
+    /// after adopting the given unknown identities as permanent containers
+    /// and cleaning the given entries whose identities are gone.
+    /// IDs that are not out of sync are ignored.
+    /// Returns the remaining differences.
+    /// Fails if the browser indicates so, or if a suffix to adopt with
+    /// is invalid or conflicts with another container,
+    /// in which case nothing is adopted or cleaned.
+    pub async fn reconcile(
+        &mut self,
+        adopt: &[Adoption],
+        clean: &[CookieStoreId],
+    ) -> Result<Reconciliation, CustomError> {
+        let builder = self.suffix_builder();
+        let adopt = adopt
+            .iter()
+            .map(|adoption| {
+                let suffixes = adoption
+                    .suffixes
+                    .iter()
+                    .map(|suffix| suffix.trim())
+                    .filter(|suffix| !suffix.is_empty())
+                    .map(|suffix| builder.parse(suffix, &self.preferences.idna_policy))
+                    .collect::<Result<BTreeSet<Suffix>, CustomError>>()?;
+                self.containers.ensure_no_conflict(
+                    Some(&adoption.cookie_store_id),
+                    0,
+                    &suffixes,
+                )?;
+                Ok((adoption.cookie_store_id.clone(), suffixes))
+            })
+            .collect::<Result<HashMap<CookieStoreId, BTreeSet<Suffix>>, CustomError>>()?;
+        let identities = ContextualIdentity::fetch_all().await?;
+        let identity_ids = identities
+            .iter()
+            .map(|identity| identity.cookie_store_id().clone())
+            .collect::<HashSet<CookieStoreId>>();
+
+        let mut reconciliation = Reconciliation::default();
+        for identity in identities {
+            let cookie_store_id = identity.cookie_store_id().clone();
+            if self.containers.get(&cookie_store_id).is_some() {
+                continue;
+            }
+            if let Some(suffixes) = adopt.get(&cookie_store_id) {
+                let mut container = Container::from(identity);
+                container.suffixes = suffixes.clone();
+                storage::store_single_entry(&ContainerKey::from(&cookie_store_id), &container)
+                    .await?;
+                self.containers.insert(container);
+            } else {
+                let suggested_suffixes = self.suggest_adoption_suffixes(&cookie_store_id).await?;
+                reconciliation.unknown_identities.push(DriftedEntry {
+                    cookie_store_id,
+                    name: identity.identity_details().name,
+                    suggested_suffixes,
+                });
+            }
+        }
+
+        let missing = self
+            .containers
+            .iter()
+            .filter(|container| !identity_ids.contains(container.handle().cookie_store_id()))
+            .map(|container| DriftedEntry {
+                cookie_store_id: container.handle().cookie_store_id().clone(),
+                name: container.identity_details().name,
+                suggested_suffixes: Vec::default(),
+            })
+            .collect::<Vec<DriftedEntry>>();
+        let mut cleaned_keys = Vec::new();
+        for entry in missing {
+            if !clean.contains(&entry.cookie_store_id) {
+                reconciliation.missing_identities.push(entry);
+                continue;
+            }
+            if let Some(container) = self.containers.bury(&entry.cookie_store_id) {
+                container.handle().finish();
+            }
+            cleaned_keys.push(ContainerKey::from(&entry.cookie_store_id));
+        }
+        storage::remove_entries(&cleaned_keys).await?;
+        self.sync_header_rules();
+        Ok(reconciliation)
+    }
+
+    /// Suggests suffixes for adopting an identity, which are the registrable
+    /// domains of its open tabs that are not yet claimed by any container.
+    /// Fails if the browser indicates so.
+    async fn suggest_adoption_suffixes(
+        &self,
+        cookie_store_id: &CookieStoreId,
+    ) -> Result<Vec<String>, CustomError> {
+        let suffixes = tabs::query_by_cookie_store_id(cookie_store_id)
+            .await?
+            .iter()
+            .filter_map(|tab| self.unclaimed_suffix(tab.url()))
+            .collect::<BTreeSet<Suffix>>();
+        Ok(suffixes.iter().map(Suffix::unicode).collect())
+    }
+
+    /// Ranks suffixes suggested for a container from the browser history,
+    /// which are the registrable domains most visited in the windows of
+    /// visited URLs around the times that the container's tabs were visited,
+    /// see [crate::suggest_suffixes].
+    /// Suffixes already claimed by any container are excluded,
+    /// and the most visited domain comes first.
+    pub fn rank_suggested_suffixes(&self, windows: &[Vec<String>]) -> Vec<String> {
+        const SUGGESTION_LIMIT: usize = 10;
+
+        let mut visit_counts = BTreeMap::<Suffix, usize>::new();
+        for window in windows {
+            let window_suffixes = window
+                .iter()
+                .filter_map(|url| self.unclaimed_suffix(url))
+                .collect::<BTreeSet<Suffix>>();
+            for suffix in window_suffixes {
+                *visit_counts.entry(suffix).or_default() += 1;
+            }
+        }
+        let mut ranked = visit_counts.into_iter().collect::<Vec<(Suffix, usize)>>();
+        ranked.sort_by(|(suffix, count), (other_suffix, other_count)| {
+            other_count
+                .cmp(count)
+                .then_with(|| suffix.cmp(other_suffix))
+        });
+        ranked
+            .iter()
+            .take(SUGGESTION_LIMIT)
+            .map(|(suffix, _)| suffix.unicode())
+            .collect()
+    }
+
+    /// Converts a URL to a suffix of its registrable domain,
+    /// or nothing if the URL has no domain or any container claims the suffix.
+    fn unclaimed_suffix(&self, url: &str) -> Option<Suffix> {
+        let domain = interop::url_to_domain(url).ok()?;
+        if domain.encoded().is_empty() {
+            return None;
+        }
+        let registrable_domain = self.psl.match_suffix(domain.clone()).unwrap_or(domain);
+        let suffix = Suffix::new(SuffixType::Normal, registrable_domain);
+        (!self
+            .containers
+            .iter()
+            .any(|container| container.suffixes.contains(&suffix)))
+        .then_some(suffix)
+    }
+
+    /// Deletes and remove temporary containers from the [ContainerOwner],
+    /// unless they still have tabs, for example from session restore.
+    /// Fails if the browser indicates so.
+    async fn purge_temporary_containers(
+        &mut self,
+        existing_tabs: &[TabProperties],
+    ) -> Result<(), CustomError> {
         let temp_handles = self
             .containers
             .iter()
             .filter(|container| container.variant == ContainerVariant::Temporary)
             .map(|container| container.handle().cookie_store_id().clone())
+            .filter(|cookie_store_id| {
+                !existing_tabs
+                    .iter()
+                    .any(|tab| tab.cookie_store_id == *cookie_store_id)
+            })
             .collect::<Vec<CookieStoreId>>();
-        for cookie_store_id in &temp_handles {
-            if let Some(container) = self.containers.remove(cookie_store_id) {
+        if !temp_handles.is_empty() {
+            self.save_last_session(&temp_handles).await;
+        }
+        let mut purged_handles = Vec::new();
+        for cookie_store_id in temp_handles {
+            if let Some(container) = self.containers.remove(&cookie_store_id) {
                 container.delete().await?;
             }
+            purged_handles.push(ContainerKey::from(&cookie_store_id));
+        }
+        storage::remove_entries(&purged_handles).await
+    }
+
+    /// Saves the temporary containers about to be purged as the last session,
+    /// replacing the previous one, so that they can be recovered if
+    /// they were lost unintentionally.
+    /// Failures are only logged, as they should not block loading.
+    async fn save_last_session(&self, purged_handles: &[CookieStoreId]) {
+        let backend = self.preferences.large_data_backend;
+        let session = Session::snapshot_before_purge(
+            purged_handles
+                .iter()
+                .filter_map(|cookie_store_id| self.containers.get(cookie_store_id)),
+        );
+        let result = match Sessions::load(backend).await {
+            Ok(mut sessions) => sessions.store_last_session(session, backend).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            log::warn!("last session was not saved, {error}");
         }
-        storage::remove_entries(&temp_handles).await
     }
 }
@@ -0,0 +1,123 @@
+//! Reports of panics, stored so that they outlive the background page
+//! and can be copied from the preferences page into bug reports.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::context::GlobalContext;
+use crate::interop::storage;
+use crate::util::errors::CustomError;
+
+/// Key of the last crash report in the storage.
+pub const LAST_CRASH_KEY: &str = "last_crash";
+
+/// Summary of the [GlobalContext] at the time of a panic.
+#[derive(Deserialize, Serialize)]
+pub struct StateSummary {
+    container_count: usize,
+    psl_len: usize,
+    psl_last_updated: NaiveDate,
+    are_preferences_managed: bool,
+}
+
+impl From<&GlobalContext> for StateSummary {
+    fn from(global_context: &GlobalContext) -> Self {
+        Self {
+            container_count: global_context.containers.iter().count(),
+            psl_len: global_context.psl.len(),
+            psl_last_updated: global_context.psl.last_updated(),
+            are_preferences_managed: global_context.are_preferences_managed,
+        }
+    }
+}
+
+/// Report of a panic, the state is [None] if the context was in use,
+/// for example if the panic occurred while it was locked.
+#[derive(Deserialize, Serialize)]
+pub struct CrashReport {
+    time: DateTime<Utc>,
+    message: String,
+    state: Option<StateSummary>,
+}
+
+impl CrashReport {
+    /// Creates a report from the message of a panic.
+    pub fn new(message: String, global_context: Option<&GlobalContext>) -> Self {
+        Self {
+            time: Utc::now(),
+            message,
+            state: global_context.map(StateSummary::from),
+        }
+    }
+}
+
+impl Display for CrashReport {
+    /// Plain text form for pasting into bug reports.
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        writeln!(formatter, "Crashed at {}", self.time.to_rfc3339())?;
+        writeln!(formatter, "{}", self.message)?;
+        let Some(state) = &self.state else {
+            return write!(formatter, "State unavailable as the context was in use");
+        };
+        writeln!(formatter, "Containers: {}", state.container_count)?;
+        writeln!(
+            formatter,
+            "Public suffixes: {}, updated on {}",
+            state.psl_len, state.psl_last_updated
+        )?;
+        write!(
+            formatter,
+            "Preferences managed: {}",
+            state.are_preferences_managed
+        )
+    }
+}
+
+/// Stores a report without waiting for the write,
+/// as the extension cannot continue after a panic.
+pub fn record(report: &CrashReport) {
+    storage::store_single_entry_detached(LAST_CRASH_KEY, report);
+}
+
+/// Storage entry of the last crash report.
+#[derive(Default, Deserialize, Serialize)]
+struct LastCrash {
+    last_crash: Option<CrashReport>,
+}
+
+/// Gets the last crash report, [None] if the extension never crashed.
+/// Fails if the browser indicates so.
+pub async fn last_crash() -> Result<Option<CrashReport>, CustomError> {
+    let mut last_crash = LastCrash::default();
+    storage::get_with_keys(&mut last_crash).await?;
+    Ok(last_crash.last_crash)
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_crash_report_display() {
+        let global_context = GlobalContext::default();
+        let report = CrashReport::new(
+            String::from("panicked at src/lib.rs"),
+            Some(&global_context),
+        );
+        let text = report.to_string();
+        assert!(text.contains("\npanicked at src/lib.rs\n"));
+        assert!(text.contains("\nContainers: 0\n"));
+        assert!(text.ends_with("Preferences managed: false"));
+
+        let report = CrashReport::new(String::from("panicked at src/lib.rs"), None);
+        assert!(report
+            .to_string()
+            .ends_with("State unavailable as the context was in use"));
+    }
+}
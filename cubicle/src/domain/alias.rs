@@ -0,0 +1,105 @@
+//! Groups of domains that belong to the same logical entity,
+//! so that navigating between them does not split sessions.
+
+use std::collections::BTreeSet;
+use std::iter;
+
+use serde::{Deserialize, Serialize};
+
+use super::EncodedDomain;
+use crate::util::errors::CustomError;
+
+/// Groups of registrable domains declared to be the same site,
+/// such as `google.com`, `youtube.com` and `gstatic.com`.
+/// Subdomains belong to the groups of their ancestors.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct AliasGroups {
+    groups: Vec<BTreeSet<EncodedDomain>>,
+}
+
+impl AliasGroups {
+    /// Parses groups from lines of domains separated by
+    /// whitespaces or commas, blank lines are skipped.
+    /// Fails if any of the domains is invalid.
+    pub fn parse_lines(lines: &[String]) -> Result<Self, CustomError> {
+        let mut groups = Vec::new();
+        for line in lines {
+            let group = line
+                .split(|char: char| char.is_whitespace() || char == ',')
+                .filter(|domain| !domain.is_empty())
+                .map(EncodedDomain::try_from)
+                .collect::<Result<BTreeSet<EncodedDomain>, CustomError>>()?;
+            if !group.is_empty() {
+                groups.push(group);
+            }
+        }
+        Ok(Self { groups })
+    }
+
+    /// Lines of unencoded domains in each group, for editing.
+    pub fn to_lines(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(EncodedDomain::raw)
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    /// Checks if two domains are the same, or belong to the same group.
+    pub fn are_aliases(&self, domain: &EncodedDomain, other: &EncodedDomain) -> bool {
+        domain == other
+            || self
+                .group_index(domain)
+                .is_some_and(|index| self.group_index(other) == Some(index))
+    }
+
+    /// Finds the group of the domain by its most specific ancestor
+    /// that is listed, the first group wins if listed in multiple groups.
+    /// Returns the index of the group, [None] if the domain is not grouped.
+    fn group_index(&self, domain: &EncodedDomain) -> Option<usize> {
+        iter::successors(Some(domain.clone()), EncodedDomain::parent).find_map(|ancestor| {
+            self.groups
+                .iter()
+                .position(|group| group.contains(&ancestor))
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::util::test::TestFrom;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_alias_groups_are_aliases() -> Result<(), CustomError> {
+        let alias_groups = AliasGroups::parse_lines(&[
+            String::from("google.com youtube.com, gstatic.com"),
+            String::from(""),
+            String::from("example.com"),
+        ])?;
+        let are_aliases = |domain, other| {
+            alias_groups.are_aliases(&EncodedDomain::tfrom(domain), &EncodedDomain::tfrom(other))
+        };
+        assert!(are_aliases("mail.google.com", "www.youtube.com"));
+        assert!(are_aliases("fonts.gstatic.com", "google.com"));
+        assert!(are_aliases("example.net", "example.net"));
+        assert!(!are_aliases("google.com", "example.com"));
+        assert!(!are_aliases("example.com", "example.net"));
+        assert_eq!(
+            vec!["google.com gstatic.com youtube.com", "example.com"],
+            alias_groups.to_lines()
+        );
+        assert!(AliasGroups::parse_lines(&[String::from("a..com")]).is_err());
+        Ok(())
+    }
+}
@@ -1,15 +1,36 @@
 //! Domain representation and matching,
 //! core components of initial container designation.
 
+pub mod alias;
 pub mod psl;
 pub mod suffix;
+pub mod url;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod property_test;
 
 use std::cmp::Ordering;
 
+use derivative::Derivative;
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::util::errors::CustomError;
 use crate::util::SingleStringVisitor;
 
+/// Policy of encoding domains as international domain names.
+/// - [Strict](IdnaPolicy::Strict) means that domains must follow the
+///   ASCII rules of STD3 and the length limits of DNS.
+/// - [Lenient](IdnaPolicy::Lenient) means that unusual hostnames,
+///   such as those with underscores or a trailing dot, are also accepted.
+#[derive(Clone, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IdnaPolicy {
+    #[derivative(Default)]
+    Strict,
+    Lenient,
+}
+
 /// Domain that can be encoded as an international domain name.
 #[derive(Clone, Debug, Eq, Serialize)]
 #[serde(transparent)]
@@ -30,6 +51,12 @@ impl EncodedDomain {
     pub fn raw(&self) -> &str {
         &self.raw
     }
+
+    /// Decoded version of the domain, which is the Unicode form even if
+    /// the domain was constructed from an encoded string.
+    pub fn unicode(&self) -> String {
+        idna::domain_to_unicode(&self.encoded).0
+    }
 }
 
 impl EncodedDomain {
@@ -60,14 +87,53 @@ impl EncodedDomain {
     }
 }
 
+impl EncodedDomain {
+    /// Constructs a domain from a string with the given policy,
+    /// bare TLDs are accepted as domain for allowing all suffixes.
+    /// A trailing dot is dropped if the policy is [Lenient](IdnaPolicy::Lenient).
+    /// Fails with [CustomError::InvalidDomain] if the string cannot be
+    /// encoded as an international domain name, or has empty segments.
+    pub fn parse(value: &str, policy: &IdnaPolicy) -> Result<Self, CustomError> {
+        let value = match policy {
+            IdnaPolicy::Strict => value,
+            IdnaPolicy::Lenient => value.strip_suffix('.').unwrap_or(value),
+        };
+        let invalid_domain = |details: String| CustomError::InvalidDomain {
+            domain: String::from(value),
+            details,
+        };
+        let compat_value = match policy {
+            IdnaPolicy::Strict => idna::domain_to_ascii_strict(&format!("{value}.example")),
+            IdnaPolicy::Lenient => {
+                if value.split('.').any(str::is_empty) {
+                    return Err(invalid_domain(String::from("empty segment")));
+                }
+                idna::domain_to_ascii(&format!("{value}.example"))
+            }
+        }
+        .map_err(|error| invalid_domain(error.to_string()))?;
+        let encoded = String::from(
+            compat_value
+                .strip_suffix(".example")
+                .expect("suffix preserved from encoded domain"),
+        );
+        Ok(Self {
+            encoded,
+            raw: String::from(value),
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for EncodedDomain {
+    /// Deserializes a domain leniently,
+    /// so that domains accepted under any policy can be restored.
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         use serde::de::{Error, Unexpected};
         let raw_domain = deserializer.deserialize_string(SingleStringVisitor)?;
-        Self::try_from(&*raw_domain).or(Err(Error::invalid_value(
+        Self::parse(&raw_domain, &IdnaPolicy::Lenient).or(Err(Error::invalid_value(
             Unexpected::Str(&raw_domain),
             &"an encodable domain",
         )))
@@ -75,25 +141,12 @@ impl<'de> Deserialize<'de> for EncodedDomain {
 }
 
 impl TryFrom<&str> for EncodedDomain {
-    type Error = idna::Errors;
+    type Error = CustomError;
 
-    /// Constructs a domain from a string,
-    /// bare TLDs are accepted as domain for allowing all suffixes.
-    /// Fails with [idna::Errors] if the string cannot be encoded as an
-    /// international domain name.
-    /// May be changed to [CustomError::InvalidDomain](crate::util::errors::CustomError::InvalidDomain)
-    /// later.
+    /// Constructs a domain from a string with the [Strict](IdnaPolicy::Strict)
+    /// policy, see [EncodedDomain::parse].
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let compat_value = idna::domain_to_ascii_strict(&format!("{}.example", value))?;
-        let encoded = String::from(
-            compat_value
-                .strip_suffix(".example")
-                .expect("suffix preserved from encoded domain"),
-        );
-        Ok(Self {
-            encoded,
-            raw: String::from(value),
-        })
+        Self::parse(value, &IdnaPolicy::Strict)
     }
 }
 
@@ -116,18 +169,21 @@ impl Ord for EncodedDomain {
 
 #[cfg(test)]
 pub mod test {
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test;
 
     use super::*;
     use crate::util::test::TestFrom;
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_tld() {
         assert_eq!(EncodedDomain::tfrom("example.com").tld().raw(), "com");
         assert_eq!(EncodedDomain::tfrom("com").tld().raw(), "com");
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_parent() {
         assert_eq!(
             EncodedDomain::tfrom("example.com").parent(),
@@ -136,7 +192,8 @@ pub mod test {
         assert_eq!(EncodedDomain::tfrom("com").parent(), None);
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_try_from() {
         assert!(EncodedDomain::try_from("a.com").is_ok());
         assert!(EncodedDomain::try_from("測試.net").is_ok());
@@ -145,14 +202,35 @@ pub mod test {
         assert!(EncodedDomain::try_from("com.").is_err());
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_domain_parse_lenient() {
+        let lenient = |value| EncodedDomain::parse(value, &IdnaPolicy::Lenient);
+        let encoded = |value| {
+            lenient(value)
+                .ok()
+                .map(|domain| String::from(domain.encoded()))
+        };
+        assert!(EncodedDomain::try_from("my_host.intranet").is_err());
+        assert_eq!(
+            encoded("my_host.intranet"),
+            Some(String::from("my_host.intranet"))
+        );
+        assert_eq!(encoded("intranet."), Some(String::from("intranet")));
+        assert!(lenient("a..com").is_err());
+        assert!(lenient(".com").is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_reverse() {
         assert!(EncodedDomain::tfrom("sub.example.com")
             .reverse()
             .eq(["com", "example", "sub"]));
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_eq() {
         assert_eq!(
             EncodedDomain::tfrom("example.net"),
@@ -164,7 +242,8 @@ pub mod test {
         );
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_domain_order() {
         let table = [
             "example.com",
@@ -0,0 +1,161 @@
+//! Property-based tests for suffix ordering and matching,
+//! which check the binary tree search against brute-force references.
+//! Only run natively, as random generation is unavailable in the browser.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::iter;
+
+use async_std::io::Cursor;
+use async_std::task;
+use chrono::NaiveDate;
+use proptest::prelude::*;
+
+use super::psl::Psl;
+use super::suffix::{self, MatchMode, Suffix, SuffixType};
+use super::EncodedDomain;
+use crate::util::test::TestFrom;
+
+/// Labels from a small alphabet so that generated domains often share
+/// ancestors, including a Unicode label and its encoded form.
+fn label() -> impl Strategy<Value = String> {
+    prop_oneof![
+        8 => "[a-c]{1,2}",
+        1 => Just(String::from("ü")),
+        1 => Just(String::from("xn--tda")),
+    ]
+}
+
+fn domain() -> impl Strategy<Value = String> {
+    prop::collection::vec(label(), 1..=4).prop_map(|labels| labels.join("."))
+}
+
+/// Suffixes of every type in their textual representation.
+fn suffix() -> impl Strategy<Value = String> {
+    (0..4usize, domain()).prop_map(|(suffix_type, domain)| match suffix_type {
+        0 => format!("!{domain}"),
+        1 => domain,
+        2 => format!("*.{domain}"),
+        _ => format!("{domain}.*"),
+    })
+}
+
+fn suffixes() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(suffix(), 0..16)
+}
+
+/// Brute-force reference of [suffix::match_suffix], which checks every
+/// suffix against every ancestor of the domain.
+/// For each ancestor, a normal suffix takes precedence over an exclusion,
+/// which takes precedence over a glob of the parent.
+/// TLD globs are expanded by containers and never matched here.
+fn reference_match(
+    suffixes: &[Suffix],
+    domain: EncodedDomain,
+    mode: MatchMode,
+) -> Vec<(String, String)> {
+    iter::successors(Some(domain), EncodedDomain::parent)
+        .filter_map(|domain| {
+            let target = match mode {
+                MatchMode::Full => domain.clone(),
+                MatchMode::Parent => domain.parent()?,
+            };
+            let parent = target.parent();
+            let precedence = |suffix: &Suffix| match suffix.suffix_type() {
+                SuffixType::Normal if *suffix.domain() == target => Some(2),
+                SuffixType::Exclusion if *suffix.domain() == target => Some(1),
+                SuffixType::Glob if Some(suffix.domain()) == parent.as_ref() => Some(0),
+                _ => None,
+            };
+            suffixes
+                .iter()
+                .filter_map(|suffix| precedence(suffix).map(|precedence| (precedence, suffix)))
+                .max_by_key(|(precedence, _)| *precedence)
+                .map(|(_, suffix)| (String::from(domain.encoded()), suffix.encoded()))
+        })
+        .collect()
+}
+
+fn tree_match(
+    set: &BTreeSet<Suffix>,
+    domain: EncodedDomain,
+    mode: MatchMode,
+) -> Vec<(String, String)> {
+    suffix::match_suffix(set, domain, mode)
+        .map(|(domain, suffix)| (String::from(domain.encoded()), suffix.encoded()))
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn test_suffix_cmp_total_order(a in suffix(), b in suffix(), c in suffix()) {
+        let (a, b, c) = (Suffix::tfrom(&*a), Suffix::tfrom(&*b), Suffix::tfrom(&*c));
+        prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+        prop_assert_eq!(a.cmp(&b).is_eq(), a == b);
+        prop_assert_eq!(a.cmp(&b).is_eq(), a.encoded() == b.encoded());
+        if a <= b && b <= c {
+            prop_assert!(a <= c);
+        }
+    }
+
+    #[test]
+    fn test_suffix_sort_is_consistent(raw_suffixes in suffixes()) {
+        let mut sorted: Vec<Suffix> = raw_suffixes.iter().map(|raw| Suffix::tfrom(&**raw)).collect();
+        sorted.sort();
+        for (index, suffix) in sorted.iter().enumerate() {
+            prop_assert!(sorted[..index].iter().all(|lesser| lesser.cmp(suffix) != Ordering::Greater));
+            prop_assert!(sorted[index..].iter().all(|greater| greater.cmp(suffix) != Ordering::Less));
+        }
+    }
+
+    #[test]
+    fn test_match_suffix_against_reference(raw_suffixes in suffixes(), raw_domain in domain()) {
+        let suffixes: Vec<Suffix> = raw_suffixes.iter().map(|raw| Suffix::tfrom(&**raw)).collect();
+        let set = BTreeSet::from_iter(suffixes.iter().cloned());
+        let domain = EncodedDomain::tfrom(&*raw_domain);
+        for is_full in [true, false] {
+            let mode = || if is_full { MatchMode::Full } else { MatchMode::Parent };
+            prop_assert_eq!(
+                tree_match(&set, domain.clone(), mode()),
+                reference_match(&suffixes, domain.clone(), mode())
+            );
+        }
+    }
+
+    #[test]
+    fn test_suffix_round_trip(raw in suffix()) {
+        let suffix = Suffix::tfrom(&*raw);
+        prop_assert!(Suffix::tfrom(&*suffix.raw()) == suffix);
+        prop_assert!(Suffix::tfrom(&*suffix.encoded()) == suffix);
+        prop_assert!(Suffix::tfrom(String::from(suffix.clone())) == suffix);
+    }
+
+    #[test]
+    fn test_psl_round_trip(raw_suffixes in suffixes(), raw_domain in domain()) {
+        let suffixes: Vec<Suffix> = raw_suffixes.iter().map(|raw| Suffix::tfrom(&**raw)).collect();
+        let list: String = suffixes
+            .iter()
+            .flat_map(|suffix| ["// comment\n".to_owned(), "\n".to_owned(), format!("{}\n", suffix.raw())])
+            .collect();
+        let psl = task::block_on(Psl::from_stream(
+            &mut Cursor::new(list.as_bytes()),
+            NaiveDate::default(),
+        ))
+        .expect("generated list should be valid");
+        prop_assert_eq!(psl.len(), BTreeSet::from_iter(suffixes.iter().cloned()).len());
+
+        let domain = EncodedDomain::tfrom(&*raw_domain);
+        let expected = reference_match(&suffixes, domain.clone(), MatchMode::Parent)
+            .into_iter()
+            .find(|(_, suffix)| !suffix.starts_with('!'))
+            .map(|(domain, _)| domain);
+        prop_assert_eq!(
+            psl.match_suffix(domain.clone()).map(|domain| String::from(domain.encoded())),
+            expected
+        );
+        let is_public_suffix = reference_match(&suffixes, domain.clone(), MatchMode::Full)
+            .first()
+            .is_some_and(|(matched, suffix)| matched == domain.encoded() && !suffix.starts_with('!'));
+        prop_assert_eq!(psl.is_public_suffix(&domain), is_public_suffix);
+    }
+}
@@ -1,10 +1,11 @@
 //! Public suffix list, as described at
 //! [publicsuffix.org](https://publicsuffix.org/).
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::io::ErrorKind;
 
 use async_std::io::prelude::*;
+use async_std::task;
 use chrono::naive::NaiveDate;
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +13,9 @@ use super::suffix::{self, MatchMode, Suffix, SuffixType};
 use super::EncodedDomain;
 use crate::util::errors::CustomError;
 
+/// Number of lines parsed before yielding to other tasks.
+const PARSE_CHUNK_LINES: usize = 256;
+
 /// Public suffix list, used for checking if domains are controlled by
 /// the same entity, and if containers should span across them.
 #[derive(Default, Deserialize, Serialize)]
@@ -24,6 +28,8 @@ impl Psl {
     /// Reads and constructs a public suffix list from a stream.
     /// Comments and empty lines are ignored,
     /// comments must start from column 0.
+    /// Yields to other tasks after every [PARSE_CHUNK_LINES] lines,
+    /// so that parsing a long list does not stall them.
     /// Fails with [CustomError::IoError] if the stream ends unexpectedly,
     /// or with [CustomError::InvalidSuffix].
     pub async fn from_stream<T>(
@@ -34,23 +40,40 @@ impl Psl {
         T: BufRead + Unpin,
     {
         let mut set = BTreeSet::default();
-        let mut buf = String::new();
-        while let 1.. = stream
-            .read_line(&mut buf)
-            .await
-            .map_err(|error| CustomError::IoError(error.kind()))?
-        {
-            let Some(strip) = buf.strip_suffix('\n').map(String::from) else {
-                return Err(CustomError::IoError(ErrorKind::OutOfMemory));
-            };
-            if !(strip.starts_with("//") || strip.is_empty()) {
-                set.insert(Suffix::try_from(&*strip)?);
-            }
-            buf.clear();
-        }
+        for_each_rule(stream, |rule| {
+            set.insert(Suffix::try_from(rule)?);
+            Ok(())
+        })
+        .await?;
         Ok(Self { last_updated, set })
     }
 
+    /// Reads the rules of a list from a stream as text without parsing them,
+    /// for comparing against a newer list with [PslDiff::from_stream].
+    /// Fails with [CustomError::IoError] if the stream ends unexpectedly.
+    pub async fn read_rules<T>(stream: &mut T) -> Result<HashSet<String>, CustomError>
+    where
+        T: BufRead + Unpin,
+    {
+        let mut rules = HashSet::default();
+        for_each_rule(stream, |rule| {
+            rules.insert(String::from(rule));
+            Ok(())
+        })
+        .await?;
+        Ok(rules)
+    }
+
+    /// Inserts and removes the suffixes that changed in a newer list.
+    /// The difference must be taken from the rules of this list.
+    pub fn apply(&mut self, diff: PslDiff) {
+        for suffix in &diff.removed {
+            self.set.remove(suffix);
+        }
+        self.set.extend(diff.inserted);
+        self.last_updated = diff.last_updated;
+    }
+
     /// Matches the given domain with the stored suffixes.
     /// Returns a domain which is equal to the input, or is an ancestor of it.
     /// [None] if the list does not specify the condition for the domain.
@@ -61,6 +84,31 @@ impl Psl {
         })
     }
 
+    /// Checks if the given domain is itself a public suffix,
+    /// such that cookies cannot be shared across its subdomains.
+    /// Domains rejected by an exclusion suffix are not public suffixes.
+    pub fn is_public_suffix(&self, domain: &EncodedDomain) -> bool {
+        suffix::match_suffix(&self.set, domain.clone(), MatchMode::Full)
+            .next()
+            .is_some_and(|(matched_domain, suffix)| {
+                matched_domain.encoded() == domain.encoded() && !suffix.is_exclusion()
+            })
+    }
+
+    /// The public suffix of the given domain, which is the parent of the
+    /// domain returned by [Psl::match_suffix]. The top level domain is assumed
+    /// if the list does not specify the condition for the domain.
+    /// [None] if the domain is itself a public suffix or a top level domain.
+    pub fn public_suffix(&self, domain: &EncodedDomain) -> Option<EncodedDomain> {
+        if self.is_public_suffix(domain) {
+            return None;
+        }
+        match self.match_suffix(domain.clone()) {
+            Some(matched_domain) => matched_domain.parent(),
+            None => domain.parent().map(|_parent| domain.tld()),
+        }
+    }
+
     /// Returns `true` if the list contains no suffix.
     pub fn is_empty(&self) -> bool {
         self.set.is_empty()
@@ -78,17 +126,106 @@ impl Psl {
     }
 }
 
+/// Suffixes inserted and removed in a newer list,
+/// parsed from only the rules that changed.
+pub struct PslDiff {
+    last_updated: NaiveDate,
+    inserted: Vec<Suffix>,
+    removed: Vec<Suffix>,
+}
+
+impl PslDiff {
+    /// Compares the rules of a newer list from a stream
+    /// against the rules of an older list from [Psl::read_rules].
+    /// Fails in the same way as [Psl::from_stream].
+    pub async fn from_stream<T>(
+        older_rules: &HashSet<String>,
+        stream: &mut T,
+        last_updated: NaiveDate,
+    ) -> Result<Self, CustomError>
+    where
+        T: BufRead + Unpin,
+    {
+        let mut kept_rules = HashSet::new();
+        let mut inserted = Vec::new();
+        for_each_rule(stream, |rule| {
+            match older_rules.get(rule) {
+                Some(older_rule) => {
+                    kept_rules.insert(older_rule);
+                }
+                None => inserted.push(Suffix::try_from(rule)?),
+            }
+            Ok(())
+        })
+        .await?;
+        let removed = older_rules
+            .iter()
+            .filter(|rule| !kept_rules.contains(rule))
+            .map(|rule| Suffix::try_from(rule.as_str()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            last_updated,
+            inserted,
+            removed,
+        })
+    }
+
+    /// The number of suffixes inserted and removed.
+    pub fn len(&self) -> usize {
+        self.inserted.len() + self.removed.len()
+    }
+
+    /// Returns `true` if no suffix changed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Calls the function with every rule of a list from a stream,
+/// see [Psl::from_stream] for which lines are rules.
+/// Fails if the stream ends unexpectedly or the function fails.
+async fn for_each_rule<T>(
+    stream: &mut T,
+    mut function: impl FnMut(&str) -> Result<(), CustomError>,
+) -> Result<(), CustomError>
+where
+    T: BufRead + Unpin,
+{
+    let mut buf = String::new();
+    let mut line_count = 0;
+    while let 1.. = stream
+        .read_line(&mut buf)
+        .await
+        .map_err(|error| CustomError::IoError(error.kind()))?
+    {
+        let Some(strip) = buf.strip_suffix('\n') else {
+            return Err(CustomError::IoError(ErrorKind::OutOfMemory));
+        };
+        if !(strip.starts_with("//") || strip.is_empty()) {
+            function(strip)?;
+        }
+        buf.clear();
+        line_count += 1;
+        if line_count % PARSE_CHUNK_LINES == 0 {
+            task::yield_now().await;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod test {
     use async_std::io::Cursor;
     use chrono::Utc;
     use indoc::indoc;
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test;
 
     use super::*;
     use crate::util::test::TestFrom;
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
     async fn test_psl_from_stream() {
         let mut builtin_bytes =
             Cursor::new(std::include_bytes!("../../res/public_suffix_list.dat"));
@@ -101,7 +238,8 @@ pub mod test {
         assert_eq!(last_updated, builtin_psl.last_updated());
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
     async fn test_psl_match_suffix() {
         let mut bytes = Cursor::new(
             indoc! {"
@@ -130,4 +268,102 @@ pub mod test {
             );
         }
     }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_psl_public_suffix() {
+        let mut bytes = Cursor::new(
+            indoc! {"
+            uk
+            co.uk
+        "}
+            .as_bytes(),
+        );
+        let psl = Psl::from_stream(&mut bytes, Utc::now().date_naive())
+            .await
+            .expect("controlled test");
+        let table = [
+            ("mail.google.co.uk", Some("co.uk")),
+            ("google.co.uk", Some("co.uk")),
+            ("google.uk", Some("uk")),
+            ("google.com", Some("com")),
+            ("co.uk", None),
+            ("com", None),
+        ];
+        for entry in table {
+            let got = psl.public_suffix(&EncodedDomain::tfrom(entry.0));
+            assert_eq!(
+                got.map(|got| String::from(got.raw())),
+                entry.1.map(String::from)
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_psl_is_public_suffix() {
+        let mut bytes = Cursor::new(
+            indoc! {"
+            com
+            *.com
+            !example.com
+        "}
+            .as_bytes(),
+        );
+        let psl = Psl::from_stream(&mut bytes, Utc::now().date_naive())
+            .await
+            .expect("controlled test");
+        let table = [
+            ("com", true),
+            ("testing.com", true),
+            ("example.com", false),
+            ("sub.testing.com", false),
+            ("example.org", false),
+        ];
+        for entry in table {
+            assert_eq!(
+                psl.is_public_suffix(&EncodedDomain::tfrom(entry.0)),
+                entry.1,
+                "{}",
+                entry.0
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_psl_apply_diff() {
+        let older = indoc! {"
+            // older
+            com
+            *.com
+            !example.com
+        "};
+        let newer = indoc! {"
+            // newer
+            com
+            !example.com
+            co.uk
+        "};
+        let older_date = NaiveDate::from_ymd_opt(2024, 1, 1).expect("controlled test");
+        let newer_date = NaiveDate::from_ymd_opt(2024, 1, 8).expect("controlled test");
+        let mut psl = Psl::from_stream(&mut Cursor::new(older.as_bytes()), older_date)
+            .await
+            .expect("controlled test");
+        let older_rules = Psl::read_rules(&mut Cursor::new(older.as_bytes()))
+            .await
+            .expect("controlled test");
+        let diff =
+            PslDiff::from_stream(&older_rules, &mut Cursor::new(newer.as_bytes()), newer_date)
+                .await
+                .expect("controlled test");
+        assert_eq!(diff.len(), 2);
+        psl.apply(diff);
+
+        let expected = Psl::from_stream(&mut Cursor::new(newer.as_bytes()), newer_date)
+            .await
+            .expect("controlled test");
+        assert_eq!(psl.last_updated(), newer_date);
+        assert!(psl.set == expected.set);
+    }
 }
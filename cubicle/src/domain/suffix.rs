@@ -2,13 +2,16 @@
 //! also used for allocating containers to domains.
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::{convert, iter, mem};
 
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use tera::Value;
 
-use super::EncodedDomain;
+use super::psl::Psl;
+use super::{EncodedDomain, IdnaPolicy};
 use crate::util::{errors::CustomError, KeyRangeExt};
 
 /// Modes for matching suffixes from different sources,
@@ -50,15 +53,16 @@ where
 
 /// Looks through a binary tree based data structure of suffixes
 /// to search for one that exactly matches the domain.
+/// The search starts from the glob of the parent,
+/// or from the exclusion of the domain if it is a top level domain.
 fn match_suffix_exact<'a, T>(set: &'a T, domain: &EncodedDomain) -> Option<Suffix>
 where
     T: KeyRangeExt<'a, Suffix> + 'a,
 {
     let end = Suffix::new(SuffixType::Normal, domain.clone());
-    let start = if let Some(parent) = domain.parent() {
-        Suffix::new(SuffixType::Glob, parent)
-    } else {
-        end.clone()
+    let start = match domain.parent() {
+        Some(parent) => Suffix::new(SuffixType::Glob, parent),
+        None => Suffix::new(SuffixType::Exclusion, domain.clone()),
     };
     let mut search_range = set.key_range(start..=end);
     search_range
@@ -66,6 +70,36 @@ where
         .cloned()
 }
 
+/// Template filter for displaying suffixes in either form,
+/// used as `{{ value | domain_form(form="encoded") }}`,
+/// the Unicode form is shown for any other form.
+/// Each line is converted separately, and leading dots such as those of
+/// cookie domains are kept. Lines that are not suffixes are left as is.
+/// Fails if the value or the form is not a string.
+pub fn tera_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let value = value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg("value should be a string"))?;
+    let is_encoded = match args.get("form") {
+        Some(form) => {
+            form.as_str()
+                .ok_or_else(|| tera::Error::msg("`form` should be a string"))?
+                == "encoded"
+        }
+        None => false,
+    };
+    let lines = value.lines().map(|line| {
+        let suffix = line.trim_start_matches('.');
+        let dots = &line[..line.len() - suffix.len()];
+        match Suffix::parse(suffix, &IdnaPolicy::Lenient) {
+            Ok(suffix) if is_encoded => format!("{dots}{}", suffix.encoded()),
+            Ok(suffix) => format!("{dots}{}", suffix.unicode()),
+            Err(_) => String::from(line),
+        }
+    });
+    Ok(Value::String(lines.collect::<Vec<String>>().join("\n")))
+}
+
 /// Valid suffix that consists of a [SuffixType] and an [EncodedDomain].
 /// This is okay as the bare glob `*` is handled separately.
 /// The ordering is organized similarly as the
@@ -87,6 +121,62 @@ impl Suffix {
         }
     }
 
+    /// Constructs a suffix from a string,
+    /// the domain is encoded with the given policy.
+    /// Fails with [CustomError::InvalidSuffix] if it has a malformed prefix,
+    /// or with [CustomError::InvalidDomain] if the contained domain
+    /// cannot be encoded as an international domain name.
+    pub fn parse(value: &str, policy: &IdnaPolicy) -> Result<Self, CustomError> {
+        if let Some(domain) = value.strip_suffix(SuffixType::TLD_GLOB_POSTFIX) {
+            return Self::parse_typed(SuffixType::TldGlob, domain, policy);
+        }
+        for suffix_type in SuffixType::iter()
+            .cycle()
+            .skip(SuffixType::INDEX_AFTER_NORMAL)
+            .filter(|suffix_type| *suffix_type != SuffixType::TldGlob)
+        {
+            if let Some(domain) = value.strip_prefix(suffix_type.prefix()) {
+                return Self::parse_typed(suffix_type, domain, policy);
+            }
+        }
+        unreachable!("empty prefix fallback for normal type");
+    }
+
+    /// Starts building a suffix from user input, see [SuffixBuilder].
+    pub fn normalized(psl: &Psl) -> SuffixBuilder<'_> {
+        SuffixBuilder {
+            psl,
+            is_registrable: false,
+        }
+    }
+
+    /// Constructs a suffix of the given type from the domain part of a string.
+    /// Fails with [CustomError::InvalidSuffix] if the domain has empty segments
+    /// or stray type markers, or with [CustomError::InvalidDomain].
+    fn parse_typed(
+        suffix_type: SuffixType,
+        domain: &str,
+        policy: &IdnaPolicy,
+    ) -> Result<Self, CustomError> {
+        let segments = match policy {
+            IdnaPolicy::Strict => domain,
+            IdnaPolicy::Lenient => domain.strip_suffix('.').unwrap_or(domain),
+        };
+        if domain.is_empty()
+            || domain.contains(['*', '!'])
+            || segments.split('.').any(|segment| segment.is_empty())
+        {
+            Err(CustomError::InvalidSuffix {
+                suffix: String::from(domain),
+            })
+        } else {
+            Ok(Self {
+                suffix_type,
+                domain: EncodedDomain::parse(domain, policy)?,
+            })
+        }
+    }
+
     /// Check if this suffix matches the given domain.
     /// Returns an [Ordering] as it was used for hinting search direction,
     /// may be changed to return a boolean value later.
@@ -110,12 +200,22 @@ impl Suffix {
     /// Encoded version of the suffix,
     /// safe to use for checking for suffix duplication.
     pub fn encoded(&self) -> String {
-        format!("{}{}", self.suffix_type.prefix(), self.domain.encoded())
+        self.suffix_type.format(self.domain.encoded())
     }
 
     /// Unencoded version of the suffix.
     pub fn raw(&self) -> String {
-        format!("{}{}", self.suffix_type.prefix(), self.domain.raw())
+        self.suffix_type.format(self.domain.raw())
+    }
+
+    /// Decoded version of the suffix, for display purposes.
+    pub fn unicode(&self) -> String {
+        self.suffix_type.format(&self.domain.unicode())
+    }
+
+    /// The domain of the suffix, without the type prefix.
+    pub fn domain(&self) -> &EncodedDomain {
+        &self.domain
     }
 
     /// The type of the suffix, primarily to check if it is an
@@ -124,47 +224,88 @@ impl Suffix {
     pub fn suffix_type(&self) -> &SuffixType {
         &self.suffix_type
     }
+
+    /// Checks if this suffix is an [Exclusion](SuffixType::Exclusion).
+    pub fn is_exclusion(&self) -> bool {
+        self.suffix_type == SuffixType::Exclusion
+    }
+
+    /// Switches between a normal suffix and an exclusion of the same domain.
+    /// Returns [None] for glob suffixes, as globs cannot be excluded.
+    pub fn toggle_exclusion(&self) -> Option<Self> {
+        let suffix_type = match self.suffix_type {
+            SuffixType::Normal => SuffixType::Exclusion,
+            SuffixType::Exclusion => SuffixType::Normal,
+            SuffixType::Glob | SuffixType::TldGlob => return None,
+        };
+        Some(Self::new(suffix_type, self.domain.clone()))
+    }
 }
 
 impl From<Suffix> for String {
     fn from(value: Suffix) -> Self {
-        String::from(value.suffix_type.prefix()) + value.domain.raw()
+        value.raw()
+    }
+}
+
+/// Builder for suffixes entered by the user, which may be pasted as URLs.
+/// Input is lowercased, and the scheme, user information, port, path,
+/// query and fragment are stripped before parsing.
+/// Normal suffixes can optionally be reduced to their registrable domain
+/// according to the public suffix list.
+pub struct SuffixBuilder<'a> {
+    psl: &'a Psl,
+    is_registrable: bool,
+}
+
+impl SuffixBuilder<'_> {
+    /// Sets whether normal suffixes are reduced to their registrable domain,
+    /// domains not specified by the public suffix list are kept as is.
+    pub fn registrable(mut self, is_registrable: bool) -> Self {
+        self.is_registrable = is_registrable;
+        self
+    }
+
+    /// Normalizes and constructs a suffix, see [Suffix::parse].
+    /// Fails in the same way as [Suffix::parse].
+    pub fn parse(&self, value: &str, policy: &IdnaPolicy) -> Result<Suffix, CustomError> {
+        let value = value.trim().to_lowercase();
+        let rest = value
+            .split_once("://")
+            .map_or(value.as_str(), |(_, rest)| rest);
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host)| host);
+        let host = host.split_once(':').map_or(host, |(host, _)| host);
+        let suffix = Suffix::parse(host, policy)?;
+        if !self.is_registrable || suffix.suffix_type != SuffixType::Normal {
+            return Ok(suffix);
+        }
+        Ok(match self.psl.match_suffix(suffix.domain.clone()) {
+            Some(registrable) => Suffix::new(SuffixType::Normal, registrable),
+            None => suffix,
+        })
     }
 }
 
 impl TryFrom<String> for Suffix {
     type Error = CustomError;
+
+    /// Constructs a suffix leniently, as this is used for deserialization,
+    /// so that suffixes accepted under any policy can be restored.
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        Self::try_from(&*value)
+        Self::parse(&value, &IdnaPolicy::Lenient)
     }
 }
 
 impl TryFrom<&str> for Suffix {
     type Error = CustomError;
 
-    /// Constructs a suffix from a string.
-    /// Fails with [CustomError::InvalidSuffix] if it has a malformed prefix,
-    /// or if the contained domain cannot be encoded as
-    /// an international domain name.
+    /// Constructs a suffix from a string with the
+    /// [Strict](IdnaPolicy::Strict) policy, see [Suffix::parse].
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        for suffix_type in SuffixType::iter()
-            .cycle()
-            .skip(SuffixType::INDEX_AFTER_NORMAL)
-        {
-            if let Some(domain) = value.strip_prefix(suffix_type.prefix()) {
-                return if domain.is_empty() || domain.split('.').any(|segment| segment.is_empty()) {
-                    Err(CustomError::InvalidSuffix {
-                        suffix: String::from(domain),
-                    })
-                } else {
-                    Ok(Self {
-                        suffix_type,
-                        domain: EncodedDomain::try_from(domain)?,
-                    })
-                };
-            }
-        }
-        unreachable!("empty prefix fallback for normal type");
+        Self::parse(value, &IdnaPolicy::Strict)
     }
 }
 
@@ -193,25 +334,40 @@ impl Ord for Suffix {
 /// Types for suffixes.
 /// The ordering is the result of suffix not storing glob star
 /// as a part of the domain.
+/// [TldGlob](SuffixType::TldGlob) suffixes such as `example.*` store the
+/// domain without any public suffix, which is expanded at match time.
 #[derive(Clone, Deserialize, EnumIter, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum SuffixType {
     Exclusion,
     Normal,
     Glob,
+    TldGlob,
 }
 
 impl SuffixType {
     /// Number of types to skip for better prefix matching.
     pub(self) const INDEX_AFTER_NORMAL: usize = 2;
 
-    /// Textual representation of the type.
+    /// Textual representation of [TldGlob](SuffixType::TldGlob),
+    /// which is the only type that is denoted after the domain.
+    pub(self) const TLD_GLOB_POSTFIX: &'static str = ".*";
+
+    /// Textual representation of the type before the domain.
     /// To parse a suffix from a string, use [Suffix::try_from] instead.
     /// To create a suffix internally, use [Suffix::new] instead.
     pub(self) fn prefix(&self) -> &str {
         match self {
             SuffixType::Glob => "*.",
             SuffixType::Exclusion => "!",
-            SuffixType::Normal => "",
+            SuffixType::Normal | SuffixType::TldGlob => "",
+        }
+    }
+
+    /// Textual representation of a suffix of this type with the domain.
+    pub(self) fn format(&self, domain: &str) -> String {
+        match self {
+            SuffixType::TldGlob => format!("{domain}{}", Self::TLD_GLOB_POSTFIX),
+            _ => format!("{}{domain}", self.prefix()),
         }
     }
 }
@@ -220,6 +376,7 @@ impl SuffixType {
 pub mod test {
     use std::collections::BTreeSet;
 
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test;
 
     use super::*;
@@ -241,12 +398,14 @@ pub mod test {
         .map(Suffix::tfrom)
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_encode_suffix() {
         assert_eq!("xn--g6w251d.net", Suffix::tfrom("測試.net").encoded());
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_match_suffix() {
         let suffix_set = BTreeSet::from(test_suffixes());
         let table = [
@@ -273,7 +432,8 @@ pub mod test {
         }
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_suffix_match_ordering() {
         let table = [
             (("*.com", "exmaple.com"), true),
@@ -292,7 +452,8 @@ pub mod test {
         }
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn test_suffix_try_from() {
         assert!(Suffix::try_from("*.com").is_ok());
         assert!(Suffix::try_from("*com").is_err());
@@ -304,9 +465,96 @@ pub mod test {
         assert!(Suffix::try_from("a..com").is_err());
         assert!(Suffix::try_from(".com").is_err());
         assert!(Suffix::try_from("com.").is_err());
+        assert!(Suffix::try_from("*.my_host.intranet").is_err());
+        assert!(Suffix::parse("*.my_host.intranet", &IdnaPolicy::Lenient).is_ok());
+        assert!(Suffix::parse("!intranet.", &IdnaPolicy::Lenient).is_ok());
+        assert!(Suffix::parse("!.intranet", &IdnaPolicy::Lenient).is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_suffix_tld_glob() {
+        let suffix = Suffix::tfrom("google.*");
+        assert!(*suffix.suffix_type() == SuffixType::TldGlob);
+        assert_eq!(suffix.domain().raw(), "google");
+        assert_eq!(suffix.raw(), "google.*");
+        assert_eq!(Suffix::tfrom("測試.*").encoded(), "xn--g6w251d.*");
+        assert!(suffix != Suffix::tfrom("google"));
+        assert!(Suffix::try_from(".*").is_err());
+        assert!(Suffix::try_from("*.google.*").is_err());
+        assert!(Suffix::try_from("!google.*").is_err());
+        assert_eq!(suffix.toggle_exclusion().map(|suffix| suffix.raw()), None);
+        assert!(!suffix
+            .match_ordering(&EncodedDomain::tfrom("google.com"))
+            .is_eq());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_suffix_toggle_exclusion() {
+        let toggle = |suffix| {
+            Suffix::tfrom(suffix)
+                .toggle_exclusion()
+                .map(|suffix| suffix.raw())
+        };
+        assert_eq!(Some(String::from("!example.com")), toggle("example.com"));
+        assert_eq!(Some(String::from("example.com")), toggle("!example.com"));
+        assert_eq!(None, toggle("*.example.com"));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_tera_filter() {
+        let filter = |value: &str, form: &str| {
+            let args = HashMap::from([(String::from("form"), Value::from(form))]);
+            tera_filter(&Value::from(value), &args).expect("string arguments")
+        };
+        assert_eq!(filter("*.測試.net", "encoded"), "*.xn--g6w251d.net");
+        assert_eq!(filter("!xn--g6w251d.net", "unicode"), "!測試.net");
+        assert_eq!(filter(".測試.net", "encoded"), ".xn--g6w251d.net");
+        assert_eq!(
+            filter("測試.net\nexample.com", "encoded"),
+            "xn--g6w251d.net\nexample.com"
+        );
+        assert_eq!(filter("not..a.suffix", "encoded"), "not..a.suffix");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_suffix_normalized() {
+        let mut bytes = async_std::io::Cursor::new("com\n".as_bytes());
+        let psl = Psl::from_stream(&mut bytes, chrono::Utc::now().date_naive())
+            .await
+            .expect("controlled test");
+        let normalize = |value: &str, is_registrable: bool| {
+            Suffix::normalized(&psl)
+                .registrable(is_registrable)
+                .parse(value, &IdnaPolicy::Strict)
+                .map(|suffix| suffix.raw())
+                .ok()
+        };
+        let table = [
+            ("  Mail.Example.COM ", false, Some("mail.example.com")),
+            (
+                "https://user@mail.example.com:8080/inbox?q#top",
+                false,
+                Some("mail.example.com"),
+            ),
+            ("mail.example.com/inbox", false, Some("mail.example.com")),
+            ("https://mail.example.com/inbox", true, Some("example.com")),
+            ("*.mail.example.com", true, Some("*.mail.example.com")),
+            ("!mail.example.com", false, Some("!mail.example.com")),
+            ("example.*", false, Some("example.*")),
+            ("mail.example.org", true, Some("mail.example.org")),
+            ("https:///inbox", false, None),
+        ];
+        for (value, is_registrable, expected) in table {
+            assert_eq!(normalize(value, is_registrable), expected.map(String::from));
+        }
     }
 
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
     fn suffix_sorting() {
         assert!(test_suffixes()
             .windows(2)
@@ -0,0 +1,126 @@
+//! Time-boxed focus containers for deliberately limited browsing,
+//! which are temporary containers bound to an alarm.
+//! Once the alarm fires, all tabs in the container are closed
+//! and the container is deleted.
+
+use std::collections::BTreeSet;
+use std::ops::DerefMut;
+use std::time::Duration;
+
+use crate::container::{Container, ContainerKey, ContainerVariant};
+use crate::context::GlobalContext;
+use crate::interop::contextual_identities::{
+    CookieStoreId, IdentityDetails, IdentityDetailsProvider,
+};
+use crate::interop::{alarms, i18n, notifications, storage, tabs};
+use crate::util::errors::CustomError;
+use crate::util::log;
+
+/// Prefix of the names of alarms ending focus containers.
+const ALARM_PREFIX: &str = "focus:";
+
+/// Name of the alarm ending the focus container.
+pub fn alarm_name(cookie_store_id: &CookieStoreId) -> String {
+    format!("{ALARM_PREFIX}{}", cookie_store_id.encoded())
+}
+
+/// Parses the name of an alarm ending a focus container,
+/// [None] if the alarm is for something else.
+pub fn parse_alarm_name(alarm_name: &str) -> Option<CookieStoreId> {
+    CookieStoreId::from_encoded(alarm_name.strip_prefix(ALARM_PREFIX)?)
+}
+
+/// Creates a focus container that ends after the duration,
+/// and opens the new tab page in it.
+/// Returns the ID of the container.
+/// Fails if the browser indicates so.
+pub async fn start(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    duration: Duration,
+) -> Result<CookieStoreId, CustomError> {
+    let minutes = (duration.as_secs() / 60).max(1);
+    let details = IdentityDetails {
+        name: i18n::get_message("focus_container_name", &[minutes.to_string()]),
+        color: global_context.preferences.temporary_container_color.clone(),
+        ..Default::default()
+    };
+    let container =
+        Container::create(details, ContainerVariant::Temporary, BTreeSet::default()).await?;
+    let cookie_store_id = container.handle().cookie_store_id().clone();
+    storage::store_single_entry(&ContainerKey::from(&cookie_store_id), &container).await?;
+    global_context.containers.insert(container);
+    alarms::create(&alarm_name(&cookie_store_id), duration)?;
+
+    let container = global_context
+        .containers
+        .get(&cookie_store_id)
+        .expect("inserted into the owner");
+    let mut tab_properties = tabs::current_tab().await?;
+    tab_properties.retarget_new_tab_page(true);
+    tab_properties.cookie_store_id = cookie_store_id.clone();
+    let new_tab_id = tab_properties.new_tab().await?;
+    crate::register_new_tab(new_tab_id, container, None).await;
+    Ok(cookie_store_id)
+}
+
+/// Closes all tabs of the focus container, deletes it,
+/// and notifies the user.
+/// Tabs that cannot be closed are logged and skipped,
+/// as the browser closes the remaining tabs when the container is deleted.
+/// Does nothing if the container no longer exists.
+/// Fails if the browser indicates so.
+pub async fn end(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    cookie_store_id: &CookieStoreId,
+) -> Result<(), CustomError> {
+    let Some(container) = global_context.containers.get(cookie_store_id) else {
+        return Ok(());
+    };
+    if container.variant != ContainerVariant::Temporary {
+        return Ok(());
+    }
+    let container_name = container.identity_details().name;
+    match tabs::query_by_cookie_store_id(cookie_store_id).await {
+        Ok(tabs) => {
+            for tab in tabs {
+                if let Err(error) = tab.id().close_tab().await {
+                    log::warn!(
+                        "tab {} of focus container was not closed, {error}",
+                        tab.id()
+                    );
+                }
+            }
+        }
+        Err(error) => log::warn!("tabs of focus container were not closed, {error}"),
+    }
+    container.delete().await?;
+    global_context.containers.bury(cookie_store_id);
+    storage::remove_entries(&[ContainerKey::from(cookie_store_id)]).await?;
+    if let Err(error) = notifications::create_basic(
+        &alarm_name(cookie_store_id),
+        i18n::get_message("notification_focus_title", &[]),
+        i18n::get_message("notification_focus_message", &[container_name]),
+    )
+    .await
+    {
+        log::warn!("end of focus container was not notified, {error}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_alarm_name() {
+        let cookie_store_id = CookieStoreId::new(String::from("firefox-container-1"));
+        let name = alarm_name(&cookie_store_id);
+        assert!(parse_alarm_name(&name) == Some(cookie_store_id));
+        assert!(parse_alarm_name("firefox-container-1").is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! Read-only guest mode for shared machines,
+//! in which changes to containers and preferences are rejected
+//! while tabs are still relocated as usual.
+//! Leaving the mode can be gated by a PIN, which is only stored hashed.
+
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::interop::crypto;
+use crate::util::errors::CustomError;
+
+/// Key of the guest mode in the storage.
+pub const GUEST_MODE_KEY: &str = "guest_mode";
+
+/// Length of the salt used for hashing the PIN.
+const SALT_LENGTH: u32 = 16;
+
+/// Iterations of PBKDF2 for new PIN hashes,
+/// high enough that the few possible PINs cannot be tried quickly.
+const PIN_ITERATIONS: u32 = 600_000;
+
+/// PBKDF2 hash of a PIN with the salt and the iterations used,
+/// the salt and the digest are encoded in base 64.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+struct PinHash {
+    salt: String,
+    iterations: u32,
+    digest: String,
+}
+
+impl PinHash {
+    /// Hashes the PIN with the salt, fails if the browser indicates so.
+    async fn digest(salt: &[u8], iterations: u32, pin: &str) -> Result<String, CustomError> {
+        let digest = crypto::pbkdf2_sha256(pin.as_bytes(), salt, iterations).await?;
+        Ok(BASE64_STANDARD_NO_PAD.encode(digest))
+    }
+}
+
+/// State of an active guest mode, see the [module](self) documentation.
+#[derive(Clone, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GuestMode {
+    #[serde(default)]
+    pin_hash: Option<PinHash>,
+}
+
+impl GuestMode {
+    /// Creates a guest mode that can only be left with the PIN if given,
+    /// empty PINs are treated as absent.
+    /// Fails if the browser indicates so.
+    pub async fn new(pin: Option<&str>) -> Result<Self, CustomError> {
+        let Some(pin) = pin.filter(|pin| !pin.is_empty()) else {
+            return Ok(Self::default());
+        };
+        let salt = crypto::random_bytes(SALT_LENGTH);
+        Ok(Self {
+            pin_hash: Some(PinHash {
+                salt: BASE64_STANDARD_NO_PAD.encode(&salt),
+                iterations: PIN_ITERATIONS,
+                digest: PinHash::digest(&salt, PIN_ITERATIONS, pin).await?,
+            }),
+        })
+    }
+
+    /// Checks if the guest mode can be left with the PIN.
+    /// Fails with [IncorrectPin](CustomError::IncorrectPin)
+    /// if the PIN does not match, or if the browser indicates so.
+    pub async fn verify(&self, pin: Option<&str>) -> Result<(), CustomError> {
+        let Some(pin_hash) = &self.pin_hash else {
+            return Ok(());
+        };
+        let salt = BASE64_STANDARD_NO_PAD
+            .decode(&pin_hash.salt)
+            .or(Err(CustomError::IncorrectPin))?;
+        let digest = PinHash::digest(&salt, pin_hash.iterations, pin.unwrap_or_default()).await?;
+        if digest == pin_hash.digest {
+            Ok(())
+        } else {
+            Err(CustomError::IncorrectPin)
+        }
+    }
+
+    /// Whether a PIN is required to leave the guest mode.
+    pub fn has_pin(&self) -> bool {
+        self.pin_hash.is_some()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_guest_mode_without_pin() -> Result<(), CustomError> {
+        let guest_mode = GuestMode::new(Some("")).await?;
+        assert!(!guest_mode.has_pin());
+        guest_mode.verify(None).await?;
+        guest_mode.verify(Some("1234")).await
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_guest_mode_with_pin() -> Result<(), CustomError> {
+        let guest_mode = GuestMode::new(Some("1234")).await?;
+        assert!(guest_mode.has_pin());
+        guest_mode.verify(Some("1234")).await?;
+        assert!(matches!(
+            guest_mode.verify(Some("4321")).await,
+            Err(CustomError::IncorrectPin)
+        ));
+        assert!(matches!(
+            guest_mode.verify(None).await,
+            Err(CustomError::IncorrectPin)
+        ));
+        Ok(())
+    }
+}
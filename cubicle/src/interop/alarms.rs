@@ -0,0 +1,38 @@
+//! Wrappers around the `browser.alarms` API,
+//! alarms are not persisted across browser restarts.
+//! Most fails are represented by
+//! [FailedAlarmOperation](CustomError::FailedAlarmOperation).
+
+use std::time::Duration;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace=["browser", "alarms"], js_name="create")]
+    fn alarm_create(name: &str, alarm_info: JsValue) -> Result<(), JsValue>;
+}
+
+/// Information of when an alarm fires, serializes to an `alarmInfo` instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlarmInfo {
+    delay_in_minutes: f64,
+}
+
+/// Schedules an alarm that fires once after the delay,
+/// replacing any existing alarm with the same name.
+/// The browser may delay alarms that fire in less than a minute.
+/// Fails if the browser indicates so.
+pub fn create(name: &str, delay: Duration) -> Result<(), CustomError> {
+    let alarm_info = interop::to_jsvalue(&AlarmInfo {
+        delay_in_minutes: delay.as_secs_f64() / 60.0,
+    });
+    alarm_create(name, alarm_info).or(Err(CustomError::FailedAlarmOperation {
+        verb: String::from("create"),
+    }))
+}
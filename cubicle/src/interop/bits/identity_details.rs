@@ -3,6 +3,7 @@
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use strum::EnumCount;
 use strum_macros::{Display, EnumCount as EnumCountMacro, EnumIter, EnumString, FromRepr};
@@ -38,10 +39,12 @@ pub trait IdentityDetailsProvider {
 
 /// Known supported color names, [Unknown](IdentityColor::Unknown) is for
 /// potentially new colors in the future.
-/// [Cycle](IdentityColor::Cycle) may be separated into its own enum in the
-/// future to avoid incorrect deserialization.
+/// [Cycle](IdentityColor::Cycle) and [FromDomain](IdentityColor::FromDomain)
+/// may be separated into their own enum in the future to avoid
+/// incorrect deserialization.
 #[derive(
     Clone,
+    Derivative,
     Deserialize,
     Display,
     EnumCountMacro,
@@ -53,6 +56,7 @@ pub trait IdentityDetailsProvider {
     Serialize,
 )]
 #[cfg_attr(test, derive(Debug))]
+#[derivative(Default)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 pub enum IdentityColor {
@@ -65,22 +69,91 @@ pub enum IdentityColor {
     Pink,
     Purple,
     Toolbar,
+    #[derivative(Default)]
     #[strum(disabled)]
     Cycle,
+    #[serde(rename = "from_domain")]
+    #[strum(disabled)]
+    FromDomain,
     #[strum(disabled, default)]
     Unknown(String),
 }
 
 impl IdentityColor {
+    /// Number of colors in the color cycle,
+    /// which excludes the toolbar color and the non-color variants.
+    const CYCLE_LENGTH: usize = Self::COUNT - 4;
+
     /// Gets a new color by rolling forward in the color cycle,
     /// the cycle is shared globally.
     pub fn new_rolling_color() -> Self {
         static COLOR_INDEX: AtomicUsize = AtomicUsize::new(0);
-        let new_index = COLOR_INDEX.fetch_add(1, Ordering::Relaxed) % (Self::COUNT - 3);
+        let new_index = COLOR_INDEX.fetch_add(1, Ordering::Relaxed) % Self::CYCLE_LENGTH;
         Self::from_repr(new_index).expect("controlled representation input range")
     }
+
+    /// Gets a color in the color cycle from the FNV-1a hash of the text,
+    /// so that the same text always gets the same color across sessions.
+    pub fn from_text(text: &str) -> Self {
+        let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        let index = usize::try_from(hash % Self::CYCLE_LENGTH as u64)
+            .expect("remainder less than the cycle length");
+        Self::from_repr(index).expect("controlled representation input range")
+    }
+
+    /// Gets the color in the color cycle closest to the given red, green and
+    /// blue components, or the toolbar color if the given color is grayish.
+    pub fn closest_to(rgb: [u8; 3]) -> Self {
+        let (min, max) = (rgb.iter().min(), rgb.iter().max());
+        if max
+            .zip(min)
+            .is_some_and(|(max, min)| max - min < GRAYISH_SPREAD)
+        {
+            return Self::Toolbar;
+        }
+        let distance = |color: &Self| {
+            color.rgb().map_or(u32::MAX, |reference| {
+                rgb.iter()
+                    .zip(reference)
+                    .map(|(component, reference)| component.abs_diff(reference))
+                    .map(|difference| u32::from(difference).pow(2))
+                    .sum()
+            })
+        };
+        (0..Self::CYCLE_LENGTH)
+            .filter_map(Self::from_repr)
+            .min_by_key(distance)
+            .expect("color cycle is not empty")
+    }
+
+    /// Red, green and blue components of the colors in the color cycle,
+    /// [None] for other variants.
+    fn rgb(&self) -> Option<[u8; 3]> {
+        match self {
+            Self::Blue => Some([0x37, 0xad, 0xff]),
+            Self::Turquoise => Some([0x00, 0xc7, 0x9a]),
+            Self::Green => Some([0x51, 0xcd, 0x00]),
+            Self::Yellow => Some([0xff, 0xcb, 0x00]),
+            Self::Orange => Some([0xff, 0x9f, 0x00]),
+            Self::Red => Some([0xff, 0x61, 0x3d]),
+            Self::Pink => Some([0xff, 0x4b, 0xda]),
+            Self::Purple => Some([0xaf, 0x51, 0xf5]),
+            Self::Toolbar | Self::Cycle | Self::FromDomain | Self::Unknown(_) => None,
+        }
+    }
+
+    /// Checks if this is a placeholder that should be resolved to
+    /// a color in the color cycle before creating an identity.
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self, Self::Cycle | Self::FromDomain)
+    }
 }
 
+/// Maximum difference between color components for a color to be grayish.
+const GRAYISH_SPREAD: u8 = 32;
+
 /// Template for predicting where the icon images are,
 /// necessary as the URL will only be provided once an identity is created.
 const ICON_URL_TEMPLATE: &str = "resource://usercontext-content/{{name}}.svg";
@@ -110,6 +183,46 @@ pub enum IdentityIcon {
 }
 
 impl IdentityIcon {
+    /// Gets the icon with a keyword found in the text,
+    /// [None] if no keywords are found.
+    /// The text is expected to be in lowercase, such as a domain.
+    pub fn from_keywords(text: &str) -> Option<Self> {
+        const KEYWORDS: [(&[&str], IdentityIcon); 7] = [
+            (
+                &["bank", "pay", "finance", "money", "invest", "tax"],
+                IdentityIcon::Dollar,
+            ),
+            (
+                &["shop", "store", "cart", "buy", "market", "amazon", "ebay"],
+                IdentityIcon::Cart,
+            ),
+            (
+                &["work", "office", "mail", "docs", "slack", "jira", "git"],
+                IdentityIcon::Briefcase,
+            ),
+            (
+                &["food", "recipe", "restaurant", "pizza"],
+                IdentityIcon::Food,
+            ),
+            (
+                &["travel", "trip", "hotel", "flight", "booking"],
+                IdentityIcon::Vacation,
+            ),
+            (
+                &["game", "music", "video", "movie", "stream", "tube"],
+                IdentityIcon::Chill,
+            ),
+            (
+                &["login", "account", "auth", "social"],
+                IdentityIcon::Fingerprint,
+            ),
+        ];
+        KEYWORDS
+            .into_iter()
+            .find(|(keywords, _icon)| keywords.iter().any(|keyword| text.contains(keyword)))
+            .map(|(_keywords, icon)| icon)
+    }
+
     /// Gets the predicted URL of the icon.
     pub fn url(&self) -> String {
         let mut context = Context::new();
@@ -121,6 +234,7 @@ impl IdentityIcon {
 
 #[cfg(test)]
 pub mod test {
+    use strum::IntoEnumIterator;
     use wasm_bindgen_test::wasm_bindgen_test;
 
     use super::*;
@@ -128,12 +242,46 @@ pub mod test {
     #[wasm_bindgen_test]
     fn test_rolling_color() {
         let initial_color = IdentityColor::new_rolling_color();
-        for _ in 1..(IdentityColor::COUNT - 3) {
+        for _ in 1..IdentityColor::CYCLE_LENGTH {
             assert_ne!(initial_color, IdentityColor::new_rolling_color());
         }
         assert_eq!(initial_color, IdentityColor::new_rolling_color());
     }
 
+    #[wasm_bindgen_test]
+    fn test_color_from_text() {
+        let color = IdentityColor::from_text("example.com");
+        assert_eq!(color, IdentityColor::from_text("example.com"));
+        assert!(IdentityColor::iter()
+            .take(IdentityColor::CYCLE_LENGTH)
+            .any(|cycle_color| cycle_color == color));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_color_closest_to() {
+        assert_eq!(
+            IdentityColor::Red,
+            IdentityColor::closest_to([0xf0, 0x50, 0x40])
+        );
+        assert_eq!(
+            IdentityColor::Blue,
+            IdentityColor::closest_to([0x20, 0x90, 0xf0])
+        );
+        assert_eq!(
+            IdentityColor::Toolbar,
+            IdentityColor::closest_to([0x80, 0x80, 0x90])
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_icon_from_keywords() {
+        assert_eq!(
+            Some(IdentityIcon::Cart),
+            IdentityIcon::from_keywords("shop.example.com")
+        );
+        assert_eq!(None, IdentityIcon::from_keywords("example.com"));
+    }
+
     #[wasm_bindgen_test]
     fn test_icon_url() {
         assert_eq!(
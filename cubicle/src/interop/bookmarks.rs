@@ -0,0 +1,101 @@
+//! Wrappers around the `browser.bookmarks` API.
+//! Most fails are represented by
+//! [FailedBookmarkOperation](CustomError::FailedBookmarkOperation).
+
+use std::collections::HashMap;
+
+use js_sys::Promise;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "bookmarks"], js_name="getTree")]
+    fn bookmark_get_tree() -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "bookmarks"], js_name="search")]
+    fn bookmark_search(query: JsValue) -> Promise;
+}
+
+/// Unique identifier of a bookmark folder.
+#[derive(Clone, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct BookmarkFolderId {
+    inner: String,
+}
+
+/// Bookmark folder with its title for listing.
+#[derive(Serialize)]
+pub struct BookmarkFolder {
+    pub id: BookmarkFolderId,
+    pub title: String,
+}
+
+/// Retained properties of a `BookmarkTreeNode` instance.
+/// Folders are the only nodes with children.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BookmarkTreeNode {
+    id: String,
+    parent_id: Option<String>,
+    #[serde(default)]
+    title: String,
+    children: Option<Vec<BookmarkTreeNode>>,
+}
+
+impl BookmarkTreeNode {
+    /// Collects this node and all descendants that are folders,
+    /// the root folder is skipped as it cannot be seen.
+    fn collect_folders(self, folders: &mut Vec<BookmarkFolder>) {
+        let Some(children) = self.children else {
+            return;
+        };
+        if self.parent_id.is_some() {
+            folders.push(BookmarkFolder {
+                id: BookmarkFolderId { inner: self.id },
+                title: self.title,
+            });
+        }
+        for child in children {
+            child.collect_folders(folders);
+        }
+    }
+}
+
+/// Gets all bookmark folders in depth-first order.
+/// Fails if the browser indicates so.
+pub async fn fetch_all_folders() -> Result<Vec<BookmarkFolder>, CustomError> {
+    let roots = interop::cast_or_standard_mismatch::<Vec<BookmarkTreeNode>>(
+        JsFuture::from(bookmark_get_tree()).await.or(Err(
+            CustomError::FailedBookmarkOperation {
+                verb: String::from("fetch all"),
+            },
+        ))?,
+    )?;
+    let mut folders = Vec::new();
+    for root in roots {
+        root.collect_folders(&mut folders);
+    }
+    Ok(folders)
+}
+
+/// Gets the folders directly containing a bookmark of the URL.
+/// Fails if the browser indicates so, such as when the URL is not absolute.
+pub async fn parent_folder_ids(url: &str) -> Result<Vec<BookmarkFolderId>, CustomError> {
+    let query = interop::to_jsvalue(&HashMap::from([("url", url)]));
+    let nodes = interop::cast_or_standard_mismatch::<Vec<BookmarkTreeNode>>(
+        JsFuture::from(bookmark_search(query)).await.or(Err(
+            CustomError::FailedBookmarkOperation {
+                verb: String::from("search"),
+            },
+        ))?,
+    )?;
+    Ok(nodes
+        .into_iter()
+        .filter_map(|node| node.parent_id)
+        .map(|parent_id| BookmarkFolderId { inner: parent_id })
+        .collect())
+}
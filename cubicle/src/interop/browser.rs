@@ -0,0 +1,75 @@
+//! Browser facilities that the relocation logic and logging depend on,
+//! abstracted so that the logic can also run natively under `cargo test`,
+//! where [FakeBrowser] stands in for the browser.
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
+#[cfg(target_arch = "wasm32")]
+use web_sys::console;
+
+use crate::domain::url::ParsedUrl;
+#[cfg(target_arch = "wasm32")]
+use crate::interop::storage;
+use crate::util::log::{EventLog, LogLevel};
+
+/// Facilities provided by the browser.
+pub trait Browser {
+    /// Parts of a URL, [None] if the URL is not valid or has no host.
+    /// Parsed without the browser by default, see [ParsedUrl::parse],
+    /// as the `URL` API is not available in all contexts.
+    fn parse_url(url: &str) -> Option<ParsedUrl> {
+        ParsedUrl::parse(url)
+    }
+
+    /// Hostname of a URL, [None] if the URL is not valid.
+    fn hostname(url: &str) -> Option<String> {
+        Self::parse_url(url).map(|url| url.host)
+    }
+
+    /// Writes a message to the console at the level.
+    fn log(level: LogLevel, message: &str);
+
+    /// Persists the event log shortly, coalescing frequent writes.
+    fn persist_event_log(event_log: &EventLog);
+}
+
+/// The actual browser, only available when compiled to WebAssembly.
+#[cfg(target_arch = "wasm32")]
+pub struct WebBrowser;
+
+#[cfg(target_arch = "wasm32")]
+impl Browser for WebBrowser {
+    fn log(level: LogLevel, message: &str) {
+        let message = JsValue::from_str(message);
+        match level {
+            LogLevel::Debug => console::debug_1(&message),
+            LogLevel::Info => console::info_1(&message),
+            LogLevel::Warn => console::warn_1(&message),
+        }
+    }
+
+    fn persist_event_log(event_log: &EventLog) {
+        storage::queue_large_entry(crate::util::log::EVENT_LOG_KEY, event_log);
+    }
+}
+
+/// In-memory browser for native builds.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FakeBrowser;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Browser for FakeBrowser {
+    fn log(level: LogLevel, message: &str) {
+        eprintln!("[{level}] {message}");
+    }
+
+    /// Nothing is persisted, as there is no storage.
+    fn persist_event_log(_event_log: &EventLog) {}
+}
+
+/// Browser for the current target.
+#[cfg(target_arch = "wasm32")]
+pub type CurrentBrowser = WebBrowser;
+/// Browser for the current target.
+#[cfg(not(target_arch = "wasm32"))]
+pub type CurrentBrowser = FakeBrowser;
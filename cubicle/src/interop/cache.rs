@@ -0,0 +1,70 @@
+//! Wrappers around the Cache API, for keeping downloaded files as they are.
+//! Most fails are represented by
+//! [FailedCacheOperation](CustomError::FailedCacheOperation).
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Cache, Response};
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+/// Name of the cache holding the files downloaded by the extension.
+const CACHE_NAME: &str = "cubicle";
+
+/// Stores a response under its URL, waiting until the body is downloaded.
+/// Fails if the body cannot be read, or if the browser indicates so.
+pub async fn put(url: &str, response: &Response) -> Result<(), CustomError> {
+    let cache = open("store to").await?;
+    JsFuture::from(cache.put_with_str(url, response))
+        .await
+        .map_err(|error| failed_operation("store to", &error))?;
+    Ok(())
+}
+
+/// Gets the response stored under the URL, [None] if there is none.
+/// Fails if the browser indicates so.
+pub async fn get(url: &str) -> Result<Option<Response>, CustomError> {
+    let cache = open("load from").await?;
+    let response = JsFuture::from(cache.match_with_str(url))
+        .await
+        .map_err(|error| failed_operation("load from", &error))?;
+    Ok(response.dyn_into().ok())
+}
+
+/// Removes the response stored under the URL,
+/// does nothing if there is none.
+/// Fails if the browser indicates so.
+pub async fn delete(url: &str) -> Result<(), CustomError> {
+    let cache = open("remove from").await?;
+    JsFuture::from(cache.delete_with_str(url))
+        .await
+        .map_err(|error| failed_operation("remove from", &error))?;
+    Ok(())
+}
+
+/// Opens the cache of the extension, creating it if it does not exist.
+/// Fails if there is no window or the browser indicates so.
+async fn open(verb_prep: &str) -> Result<Cache, CustomError> {
+    let caches = web_sys::window()
+        .ok_or(CustomError::StandardMismatch {
+            message: String::from("window should exist in page"),
+        })?
+        .caches()
+        .map_err(|error| failed_operation(verb_prep, &error))?;
+    JsFuture::from(caches.open(CACHE_NAME))
+        .await
+        .map_err(|error| failed_operation(verb_prep, &error))?
+        .dyn_into()
+        .or(Err(CustomError::StandardMismatch {
+            message: String::from("opened cache should be a cache"),
+        }))
+}
+
+/// Error of a failed operation on the cache.
+fn failed_operation(verb_prep: &str, error: &JsValue) -> CustomError {
+    CustomError::FailedCacheOperation {
+        verb_prep: String::from(verb_prep),
+        details: interop::error_details(error),
+    }
+}
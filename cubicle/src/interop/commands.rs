@@ -0,0 +1,13 @@
+//! Helpers for the `browser.commands` API,
+//! the commands and their default shortcuts are specified in the manifest.
+
+use strum_macros::EnumString;
+
+/// Command triggered by a keyboard shortcut, named as in the manifest.
+/// - [OpenQuickSwitcher](Command::OpenQuickSwitcher) opens a window for
+///   reopening the current tab in another container.
+#[derive(EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Command {
+    OpenQuickSwitcher,
+}
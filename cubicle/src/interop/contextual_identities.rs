@@ -11,6 +11,7 @@ use base64::prelude::*;
 use js_sys::{Object, Promise};
 #[cfg(test)]
 use mockall::mock;
+use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
@@ -32,7 +33,7 @@ extern "C" {
 
 /// Browser feature allowing the separation of sites' information
 /// into different identities.
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContextualIdentity {
     #[serde(
@@ -52,27 +53,29 @@ impl ContextualIdentity {
     /// allows for resynchronization with the browser.
     /// Fails if the browser indicates so.
     pub async fn fetch_all() -> Result<Vec<Self>, CustomError> {
-        let op_error = CustomError::FailedContainerOperation {
+        let op_error = |error| CustomError::FailedContainerOperation {
             verb: String::from("fetch all"),
+            details: interop::error_details(&error),
         };
         super::cast_or_standard_mismatch(
             JsFuture::from(identity_query(JsValue::from(Object::default())))
                 .await
-                .or(Err(op_error))?,
+                .map_err(op_error)?,
         )
     }
 
     /// Creates an identity using the given details.
     /// Fails if the browser indicates so.
     pub async fn create(mut details: IdentityDetails) -> Result<Self, CustomError> {
-        if details.color == IdentityColor::Cycle {
+        if details.color.is_placeholder() {
             details.color = IdentityColor::new_rolling_color();
         }
         let identity = JsFuture::from(identity_create(interop::to_jsvalue(&details)))
             .await
-            .or(Err(CustomError::FailedContainerOperation {
+            .map_err(|error| CustomError::FailedContainerOperation {
                 verb: String::from("create"),
-            }))?;
+                details: interop::error_details(&error),
+            })?;
         super::cast_or_standard_mismatch(identity)
     }
 
@@ -84,6 +87,12 @@ impl ContextualIdentity {
         Ok(())
     }
 
+    /// Deletes the identity, see [CookieStoreId::delete_identity].
+    /// Fails if the browser indicates so.
+    pub async fn delete(&self) -> Result<(), CustomError> {
+        self.cookie_store_id.delete_identity().await
+    }
+
     /// Gets the [CookieStoreId] of this identity.
     pub fn cookie_store_id(&self) -> &CookieStoreId {
         &self.cookie_store_id
@@ -140,16 +149,17 @@ impl CookieStoreId {
         &self,
         mut details: IdentityDetails,
     ) -> Result<ContextualIdentity, CustomError> {
-        if details.color == IdentityColor::Cycle {
+        if details.color.is_placeholder() {
             details.color = IdentityColor::new_rolling_color();
         }
-        let error = CustomError::FailedContainerOperation {
+        let error = |error| CustomError::FailedContainerOperation {
             verb: String::from("update"),
+            details: interop::error_details(&error),
         };
         let details = interop::to_jsvalue(&details);
         let identity = JsFuture::from(identity_update(&self.inner, details))
             .await
-            .or(Err(error))?;
+            .map_err(error)?;
         super::cast_or_standard_mismatch(identity)
     }
 
@@ -158,14 +168,26 @@ impl CookieStoreId {
     /// and the user is responsible for the cleanup.
     /// Fails if the browser indicates so.
     pub async fn delete_identity(&self) -> Result<(), CustomError> {
-        let removal_result = JsFuture::from(identity_remove(&self.inner)).await;
-        if removal_result.is_err() {
-            Err(CustomError::FailedContainerOperation {
+        JsFuture::from(identity_remove(&self.inner))
+            .await
+            .map_err(|error| CustomError::FailedContainerOperation {
                 verb: String::from("delete"),
-            })
-        } else {
-            Ok(())
-        }
+                details: interop::error_details(&error),
+            })?;
+        Ok(())
+    }
+
+    /// Encoded form of this ID, which is the same as the serialization.
+    pub fn encoded(&self) -> String {
+        String::from(Base64Visitor::MARKER_PREFIX) + &BASE64_URL_SAFE_NO_PAD.encode(&self.inner)
+    }
+
+    /// Decodes an ID from the encoded form, [None] if it is not encoded.
+    pub fn from_encoded(encoded: &str) -> Option<Self> {
+        let inner = Base64Visitor
+            .visit_str::<serde::de::value::Error>(encoded)
+            .ok()?;
+        Some(Self { inner })
     }
 
     /// Deserializes from a real unencoded value.
@@ -214,8 +236,7 @@ impl Serialize for CookieStoreId {
     where
         S: Serializer,
     {
-        let b64 = BASE64_URL_SAFE_NO_PAD.encode(&self.inner);
-        serializer.serialize_str(&(String::from(Base64Visitor::MARKER_PREFIX) + &b64))
+        serializer.serialize_str(&self.encoded())
     }
 }
 
@@ -225,12 +246,17 @@ mock! {
         pub async fn fetch_all() -> Result<Vec<Self>, CustomError>;
         pub async fn create(mut details: IdentityDetails) -> Result<Self, CustomError>;
         pub async fn update(&mut self, details: IdentityDetails) -> Result<(), CustomError>;
+        pub async fn delete(&self) -> Result<(), CustomError>;
         pub fn cookie_store_id(&self) -> &CookieStoreId;
 
         fn private_deserialize(deserializable: Result<ContextualIdentity, ()>) -> Self;
         fn private_serialize(&self) -> ContextualIdentity;
     }
 
+    impl Clone for ContextualIdentity {
+        fn clone(&self) -> Self;
+    }
+
     impl IdentityDetailsProvider for ContextualIdentity {
         fn identity_details(&self) -> IdentityDetails;
     }
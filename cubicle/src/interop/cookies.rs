@@ -0,0 +1,89 @@
+//! Wrappers around the `browser.cookies` API.
+//! Most fails are represented by
+//! [FailedCookieOperation](CustomError::FailedCookieOperation).
+
+use js_sys::Promise;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use super::contextual_identities::CookieStoreId;
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "cookies"], js_name="getAll")]
+    fn cookie_get_all(details: JsValue) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "cookies"], js_name="remove")]
+    fn cookie_remove(details: JsValue) -> Promise;
+}
+
+/// Retained properties of a `Cookie` instance for removal.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    name: String,
+    domain: String,
+    path: String,
+    secure: bool,
+    first_party_domain: Option<String>,
+}
+
+impl Cookie {
+    /// The domain of the cookie without the leading dot for subdomains.
+    pub fn domain(&self) -> &str {
+        self.domain.trim_start_matches('.')
+    }
+
+    /// Removes this cookie from the cookie store,
+    /// fails if the browser indicates so.
+    pub async fn remove(&self, cookie_store_id: &CookieStoreId) -> Result<(), CustomError> {
+        let scheme = if self.secure { "https" } else { "http" };
+        let details = CookieDetails {
+            url: Some(format!("{}://{}{}", scheme, self.domain(), self.path)),
+            name: Some(&self.name),
+            store_id: cookie_store_id.clone(),
+            first_party_domain: self.first_party_domain.as_deref(),
+        };
+        JsFuture::from(cookie_remove(interop::to_jsvalue(&details)))
+            .await
+            .or(Err(CustomError::FailedCookieOperation {
+                verb: String::from("remove"),
+            }))?;
+        Ok(())
+    }
+}
+
+/// Details for querying and removing cookies,
+/// serializes to a `details` instance.
+/// First-party domain is always serialized,
+/// so that cookies are found when first-party isolation is enabled.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CookieDetails<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(serialize_with = "CookieStoreId::serialize_inner")]
+    store_id: CookieStoreId,
+    first_party_domain: Option<&'a str>,
+}
+
+/// Gets all cookies in a cookie store, fails if the browser indicates so.
+pub async fn get_all(cookie_store_id: &CookieStoreId) -> Result<Vec<Cookie>, CustomError> {
+    let details = CookieDetails {
+        url: None,
+        name: None,
+        store_id: cookie_store_id.clone(),
+        first_party_domain: None,
+    };
+    interop::cast_or_standard_mismatch(
+        JsFuture::from(cookie_get_all(interop::to_jsvalue(&details)))
+            .await
+            .or(Err(CustomError::FailedCookieOperation {
+                verb: String::from("fetch all"),
+            }))?,
+    )
+}
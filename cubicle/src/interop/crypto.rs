@@ -0,0 +1,122 @@
+//! Wrappers around the Web Crypto API,
+//! for hashing secrets before they are stored.
+//! Native builds use a fake that is deterministic but not secure,
+//! so that the callers can be tested without a browser.
+//! Most fails are represented by
+//! [FailedCryptoOperation](CustomError::FailedCryptoOperation).
+
+#[cfg(target_arch = "wasm32")]
+use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::JsFuture;
+
+use crate::util::errors::CustomError;
+
+/// Length of keys derived by [pbkdf2_sha256] in bytes.
+const DERIVED_KEY_LENGTH: u32 = 32;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["crypto", "subtle"], js_name="importKey")]
+    fn subtle_import_key(
+        format: &str,
+        key_data: &Uint8Array,
+        algorithm: &str,
+        extractable: bool,
+        key_usages: &Array,
+    ) -> Promise;
+
+    #[wasm_bindgen(js_namespace=["crypto", "subtle"], js_name="deriveBits")]
+    fn subtle_derive_bits(algorithm: &Object, base_key: &JsValue, length: u32) -> Promise;
+
+    #[wasm_bindgen(js_namespace=crypto, js_name="getRandomValues")]
+    fn get_random_values(array: &Uint8Array) -> Uint8Array;
+}
+
+/// Derives a key from the password and the salt with PBKDF2 and SHA-256,
+/// which is slow on purpose with a high iteration count,
+/// so that short secrets cannot be brute-forced quickly.
+/// Fails if the browser indicates so.
+#[cfg(target_arch = "wasm32")]
+pub async fn pbkdf2_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<Vec<u8>, CustomError> {
+    let error = |verb: &str| CustomError::FailedCryptoOperation {
+        verb: String::from(verb),
+    };
+    let usages = Array::of1(&JsValue::from_str("deriveBits"));
+    let base_key = JsFuture::from(subtle_import_key(
+        "raw",
+        &Uint8Array::from(password),
+        "PBKDF2",
+        false,
+        &usages,
+    ))
+    .await
+    .or(Err(error("import key for")))?;
+    let algorithm = Object::new();
+    for (key, value) in [
+        ("name", JsValue::from_str("PBKDF2")),
+        ("hash", JsValue::from_str("SHA-256")),
+        ("salt", Uint8Array::from(salt).into()),
+        ("iterations", JsValue::from(iterations)),
+    ] {
+        Reflect::set(&algorithm, &JsValue::from_str(key), &value).expect("inline construction");
+    }
+    let bits = JsFuture::from(subtle_derive_bits(
+        &algorithm,
+        &base_key,
+        DERIVED_KEY_LENGTH * 8,
+    ))
+    .await
+    .or(Err(error("derive bits with")))?;
+    Ok(Uint8Array::new(&bits).to_vec())
+}
+
+/// Fake of [pbkdf2_sha256] for native builds,
+/// which only mixes the inputs with a non-cryptographic hash.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn pbkdf2_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<Vec<u8>, CustomError> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    Ok((0..DERIVED_KEY_LENGTH / 8)
+        .flat_map(|block| {
+            let mut hasher = DefaultHasher::new();
+            (password, salt, iterations, block).hash(&mut hasher);
+            hasher.finish().to_le_bytes()
+        })
+        .collect())
+}
+
+/// Generates cryptographically random bytes of the given length.
+#[cfg(target_arch = "wasm32")]
+pub fn random_bytes(length: u32) -> Vec<u8> {
+    get_random_values(&Uint8Array::new_with_length(length)).to_vec()
+}
+
+/// Fake of [random_bytes] for native builds,
+/// which are random but not cryptographically secure.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_bytes(length: u32) -> Vec<u8> {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let state = RandomState::new();
+    (0..length)
+        .map(|index| {
+            let mut hasher = state.build_hasher();
+            hasher.write_u32(index);
+            hasher.finish() as u8
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+//! Introspection of managed tabs for the devtools panel.
+//! The `browser.devtools` API is only available to the devtools page,
+//! which creates the panel and passes the ID of the inspected tab,
+//! so this module only describes what the panel is shown.
+
+use serde::Serialize;
+
+use crate::domain::EncodedDomain;
+use crate::interop::contextual_identities::CookieStoreId;
+use crate::interop::tabs::TabId;
+use crate::rules::DecisionTrace;
+
+/// Container and domain that a managed tab was registered with.
+#[derive(Serialize)]
+pub struct DeterminantInspection {
+    pub cookie_store_id: CookieStoreId,
+    pub container_name: Option<String>,
+    pub domain: Option<EncodedDomain>,
+}
+
+/// What Cubicle knows about a tab, serializes to the JSON given to the panel.
+/// - `determinant` is what the tab is managed with, [None] if unmanaged.
+/// - `relocated_from` is where the tab was relocated from, if it was.
+/// - `last_trace` is why the tab was last relocated.
+/// - `current_trace` is what would be decided for the current URL.
+#[derive(Serialize)]
+pub struct TabInspection {
+    pub tab_id: TabId,
+    pub url: Option<String>,
+    pub cookie_store_id: CookieStoreId,
+    pub container_name: Option<String>,
+    pub determinant: Option<DeterminantInspection>,
+    pub relocated_from: Option<DeterminantInspection>,
+    pub last_trace: Option<DecisionTrace>,
+    pub current_trace: Option<DecisionTrace>,
+}
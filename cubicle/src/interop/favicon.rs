@@ -0,0 +1,94 @@
+//! Decoding of favicons for suggesting container styles,
+//! images are drawn on an `OffscreenCanvas` for reading their pixels.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, ImageBitmap, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
+
+use super::fetch;
+use crate::util::errors::CustomError;
+
+/// Fetches an image and averages the colors of its pixels.
+/// Returns the red, green and blue components of the average,
+/// [None] if the image is fully transparent.
+/// Fails if the image cannot be fetched or decoded.
+pub async fn average_color(url: &str) -> Result<Option<[u8; 3]>, CustomError> {
+    let decode_error = |_| CustomError::FailedFetchRequest {
+        message: String::from("image cannot be decoded"),
+    };
+    let response = fetch::get(url, None).await?;
+    let blob = JsFuture::from(response.blob().map_err(decode_error)?)
+        .await
+        .map_err(decode_error)?
+        .dyn_into::<Blob>()
+        .map_err(decode_error)?;
+    let window = web_sys::window().ok_or(CustomError::StandardMismatch {
+        message: String::from("window should exist in page"),
+    })?;
+    let bitmap = JsFuture::from(
+        window
+            .create_image_bitmap_with_blob(&blob)
+            .map_err(decode_error)?,
+    )
+    .await
+    .map_err(decode_error)?
+    .dyn_into::<ImageBitmap>()
+    .map_err(decode_error)?;
+
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let canvas = OffscreenCanvas::new(width, height).map_err(decode_error)?;
+    let context = canvas
+        .get_context("2d")
+        .map_err(decode_error)?
+        .ok_or(CustomError::StandardMismatch {
+            message: String::from("2d context should be available"),
+        })?
+        .dyn_into::<OffscreenCanvasRenderingContext2d>()
+        .map_err(|_| CustomError::StandardMismatch {
+            message: String::from("expected a 2d context"),
+        })?;
+    context
+        .draw_image_with_image_bitmap(&bitmap, 0.0, 0.0)
+        .map_err(decode_error)?;
+    let image_data = context
+        .get_image_data(0.0, 0.0, f64::from(width), f64::from(height))
+        .map_err(decode_error)?;
+    Ok(average_rgba(&image_data.data()))
+}
+
+/// Averages pixels in RGBA format, weighted by their alpha values.
+/// Returns [None] if all pixels are transparent.
+fn average_rgba(pixels: &[u8]) -> Option<[u8; 3]> {
+    let mut sums = [0u64; 3];
+    let mut total_alpha = 0u64;
+    for pixel in pixels.chunks_exact(4) {
+        let alpha = u64::from(pixel[3]);
+        for (sum, component) in sums.iter_mut().zip(pixel) {
+            *sum += u64::from(*component) * alpha;
+        }
+        total_alpha += alpha;
+    }
+    (total_alpha > 0).then(|| {
+        sums.map(|sum| u8::try_from(sum / total_alpha).expect("average within component range"))
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn test_average_rgba() {
+        assert_eq!(None, average_rgba(&[255, 0, 0, 0]));
+        assert_eq!(
+            Some([255, 0, 0]),
+            average_rgba(&[255, 0, 0, 255, 0, 0, 255, 0])
+        );
+        assert_eq!(
+            Some([127, 0, 127]),
+            average_rgba(&[255, 0, 0, 128, 0, 0, 255, 128])
+        );
+    }
+}
@@ -4,21 +4,32 @@ use std::io::{self, ErrorKind};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
 use async_std::io::prelude::*;
 use async_std::sync::Mutex;
 use derivative::Derivative;
 use js_sys::{Error, Object, Uint8Array};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    ReadableStream, ReadableStreamByobReader, ReadableStreamGetReaderOptions,
-    ReadableStreamReaderMode, Request, RequestInit, RequestMode, Response,
+    AbortController, AbortSignal, ReadableStream, ReadableStreamByobReader,
+    ReadableStreamGetReaderOptions, ReadableStreamReaderMode, Request, RequestInit, RequestMode,
+    Response,
 };
 
-use super::bits;
+use super::{bits, cache};
 use crate::interop;
 use crate::util::errors::CustomError;
+use crate::util::retry::{self, Backoff};
+
+/// Backoff for retrying requests that failed due to network errors.
+pub const NETWORK_BACKOFF: Backoff = Backoff {
+    attempts: 3,
+    initial_delay: Duration::from_millis(500),
+    factor: 2,
+};
 
 /// The current state of the fetch.
 /// - [Delivered](FetchState::Delivered) means that there are data that are
@@ -34,13 +45,60 @@ enum FetchState {
     Done,
 }
 
+/// Number of bytes read from a fetch,
+/// the total is from the `Content-Length` header if it is present.
+#[derive(Clone, Copy, Serialize)]
+pub struct FetchProgress {
+    pub loaded: u64,
+    pub total: Option<u64>,
+}
+
 /// Main structure for fetching large files over network asynchronously.
-/// Fetch cancellation may be added later.
+/// Instances created by [Fetch::get_stream] can be cancelled,
+/// reads fail once the fetch is cancelled or timed out.
 pub struct Fetch {
     reader: ReadableStreamByobReader,
     resolve_read_then: Closure<dyn FnMut(JsValue)>,
     reject_read_then: Closure<dyn FnMut(JsValue)>,
     state: Arc<Mutex<SharedState>>,
+    controller: Option<AbortController>,
+    timeout: Option<AbortTimeout>,
+    progress: FetchProgress,
+    progress_callback: Option<Box<dyn FnMut(FetchProgress)>>,
+}
+
+/// Timer that aborts a fetch once expired, the timer is cleared when dropped.
+struct AbortTimeout {
+    handle: i32,
+    _abort: Closure<dyn FnMut()>,
+}
+
+impl AbortTimeout {
+    /// Starts a timer that aborts using the controller after the duration.
+    /// Fails if there is no window or the browser indicates so.
+    fn start(controller: AbortController, duration: Duration) -> Result<Self, CustomError> {
+        let abort = Closure::<dyn FnMut()>::new(move || controller.abort());
+        let handle = window()?
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                abort.as_ref().unchecked_ref(),
+                i32::try_from(duration.as_millis()).unwrap_or(i32::MAX),
+            )
+            .or(Err(CustomError::StandardMismatch {
+                message: String::from("timer should be set"),
+            }))?;
+        Ok(Self {
+            handle,
+            _abort: abort,
+        })
+    }
+}
+
+impl Drop for AbortTimeout {
+    fn drop(&mut self) {
+        if let Ok(window) = window() {
+            window.clear_timeout_with_handle(self.handle);
+        }
+    }
 }
 
 /// Variable state that changes when polled, or when more data is available.
@@ -55,17 +113,51 @@ struct SharedState {
 
 impl Fetch {
     /// Gets a response from an URL and create an instance using a reader.
+    /// The whole fetch is aborted if it does not complete within the timeout.
     /// Fails if the URL contains credentials, if a network error occurs,
-    /// or if the response does not contain a body.
-    pub async fn get_stream(url: &str) -> Result<Self, CustomError> {
-        Self::try_from(
-            get(url)
-                .await?
-                .body()
-                .ok_or(CustomError::FailedFetchRequest {
-                    message: String::from("response has no body"),
-                })?,
-        )
+    /// if the timeout expired, or if the response does not contain a body.
+    pub async fn get_stream(url: &str, timeout: Option<Duration>) -> Result<Self, CustomError> {
+        let controller = AbortController::new().or(Err(CustomError::StandardMismatch {
+            message: String::from("abort controller should be constructible"),
+        }))?;
+        let timeout = timeout
+            .map(|timeout| AbortTimeout::start(controller.clone(), timeout))
+            .transpose()?;
+        let response = get(url, Some(&controller.signal())).await?;
+        let mut fetch = Self::from_response(&response)?;
+        fetch.controller = Some(controller);
+        fetch.timeout = timeout;
+        Ok(fetch)
+    }
+
+    /// Creates an instance using a reader to the body of a response,
+    /// such as one stored in the [cache](crate::interop::cache).
+    /// Fails if the response does not contain a body.
+    pub fn from_response(response: &Response) -> Result<Self, CustomError> {
+        let mut fetch =
+            Self::try_from(response.body().ok_or(CustomError::FailedFetchRequest {
+                message: String::from("response has no body"),
+            })?)?;
+        fetch.progress.total = response
+            .headers()
+            .get("Content-Length")
+            .ok()
+            .flatten()
+            .and_then(|length| length.parse().ok());
+        Ok(fetch)
+    }
+
+    /// Sets a callback that is called with the progress after every read.
+    pub fn on_progress(&mut self, callback: impl FnMut(FetchProgress) + 'static) {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    /// Cancels the fetch, pending and later reads fail.
+    /// Does nothing if the instance is not created by [Fetch::get_stream].
+    pub fn cancel(&self) {
+        if let Some(controller) = &self.controller {
+            controller.abort();
+        }
     }
 
     /// Sets state and returns with [Poll::Ready] if there is available data.
@@ -165,6 +257,13 @@ impl Read for Fetch {
             let read_length = state.buffer.length() as usize;
             state.buffer.copy_to(&mut buf[..read_length]);
             state.success = Some(Ok(FetchState::Consumed));
+            drop(state);
+
+            self.progress.loaded += read_length as u64;
+            let progress = self.progress;
+            if let Some(callback) = &mut self.progress_callback {
+                callback(progress);
+            }
             Poll::Ready(Ok(read_length))
         } else {
             ret.map_ok(|_| unreachable!("all ok results have branched"))
@@ -192,27 +291,79 @@ impl TryFrom<ReadableStream> for Fetch {
             resolve_read_then: Self::read_thens(state.clone(), true),
             reject_read_then: Self::read_thens(state.clone(), false),
             state,
+            controller: None,
+            timeout: None,
+            progress: FetchProgress {
+                loaded: 0,
+                total: None,
+            },
+            progress_callback: None,
         })
     }
 }
 
-/// Gets a response from an URL.
-/// Fails if the URL contains credentials, or if a network error occurs.
-pub async fn get(url: &str) -> Result<Response, CustomError> {
+/// Gets a response from an URL and stores it in the
+/// [cache](crate::interop::cache) under the URL.
+/// The whole fetch is aborted if it does not complete within the timeout.
+/// Fails if the URL contains credentials, if a network error occurs,
+/// if the timeout expired, if the response is unsuccessful,
+/// or if the cache cannot be written.
+pub async fn download_to_cache(url: &str, timeout: Option<Duration>) -> Result<(), CustomError> {
+    let controller = AbortController::new().or(Err(CustomError::StandardMismatch {
+        message: String::from("abort controller should be constructible"),
+    }))?;
+    let _timeout = timeout
+        .map(|timeout| AbortTimeout::start(controller.clone(), timeout))
+        .transpose()?;
+    let response = get(url, Some(&controller.signal())).await?;
+    if !response.ok() {
+        return Err(CustomError::FailedFetchRequest {
+            message: format!("server responded with status {}", response.status()),
+        });
+    }
+    cache::put(url, &response).await
+}
+
+/// Gets a response from an URL, the request may be aborted using a signal.
+/// Network errors are retried with [NETWORK_BACKOFF] unless aborted.
+/// Fails if the URL contains credentials, if a network error persists,
+/// or if the request is aborted.
+pub async fn get(url: &str, signal: Option<&AbortSignal>) -> Result<Response, CustomError> {
     let mut connection_options = RequestInit::new();
-    connection_options.method("GET").mode(RequestMode::Cors);
+    connection_options
+        .method("GET")
+        .mode(RequestMode::Cors)
+        .signal(signal);
     let request = Request::new_with_str_and_init(url, &connection_options).or(Err(
         CustomError::FailedFetchRequest {
             message: String::from("credentials in URL not supported"),
         },
     ))?;
-    let window = web_sys::window().ok_or(CustomError::StandardMismatch {
-        message: String::from("window should exist in page"),
+    let window = window()?;
+    let is_aborted = || signal.is_some_and(AbortSignal::aborted);
+    let resp = retry::retry(
+        &NETWORK_BACKOFF,
+        || JsFuture::from(window.fetch_with_request(&request)),
+        |_| !is_aborted(),
+        interop::sleep,
+    )
+    .await
+    .map_err(|_| {
+        let message = if is_aborted() {
+            "request aborted"
+        } else {
+            "network error"
+        };
+        CustomError::FailedFetchRequest {
+            message: String::from(message),
+        }
     })?;
-    let resp = JsFuture::from(window.fetch_with_request(&request))
-        .await
-        .or(Err(CustomError::FailedFetchRequest {
-            message: String::from("network error"),
-        }))?;
     Ok(Response::from(resp))
 }
+
+/// Gets the window of the page, fails if it does not exist.
+fn window() -> Result<web_sys::Window, CustomError> {
+    web_sys::window().ok_or(CustomError::StandardMismatch {
+        message: String::from("window should exist in page"),
+    })
+}
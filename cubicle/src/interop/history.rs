@@ -0,0 +1,89 @@
+//! Wrappers around the `browser.history` API,
+//! for learning which sites are visited alongside a container.
+//! The API is only available once the optional permission is granted.
+//! Most fails are represented by
+//! [FailedHistoryOperation](CustomError::FailedHistoryOperation).
+
+use std::collections::HashMap;
+
+use js_sys::Promise;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace=["browser", "history"], js_name="search")]
+    fn history_search(query: JsValue) -> Result<Promise, JsValue>;
+    #[wasm_bindgen(catch, js_namespace=["browser", "history"], js_name="getVisits")]
+    fn history_get_visits(details: JsValue) -> Result<Promise, JsValue>;
+}
+
+/// Query for searching history within a time range,
+/// times are in milliseconds since the epoch.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryQuery {
+    text: &'static str,
+    start_time: f64,
+    end_time: f64,
+    max_results: usize,
+}
+
+/// Retained properties of a `HistoryItem` instance.
+#[derive(Deserialize)]
+pub struct HistoryItem {
+    #[serde(default)]
+    pub url: String,
+}
+
+/// Retained properties of a `VisitItem` instance.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VisitItem {
+    #[serde(default)]
+    visit_time: f64,
+}
+
+/// Gets pages visited between two times in milliseconds since the epoch,
+/// at most `max_results` of them.
+/// Fails if the browser indicates so.
+pub async fn search(
+    start_time: f64,
+    end_time: f64,
+    max_results: usize,
+) -> Result<Vec<HistoryItem>, CustomError> {
+    let query = interop::to_jsvalue(&HistoryQuery {
+        text: "",
+        start_time,
+        end_time,
+        max_results,
+    });
+    let error = |_error| CustomError::FailedHistoryOperation {
+        verb: String::from("search"),
+    };
+    interop::cast_or_standard_mismatch(
+        JsFuture::from(history_search(query).map_err(error)?)
+            .await
+            .map_err(error)?,
+    )
+}
+
+/// Gets the times in milliseconds since the epoch at which the URL was visited,
+/// with the most recent visit first.
+/// Fails if the browser indicates so, such as when the URL is not absolute.
+pub async fn visit_times(url: &str) -> Result<Vec<f64>, CustomError> {
+    let details = interop::to_jsvalue(&HashMap::from([("url", url)]));
+    let error = |_error| CustomError::FailedHistoryOperation {
+        verb: String::from("get visits of"),
+    };
+    let visits = interop::cast_or_standard_mismatch::<Vec<VisitItem>>(
+        JsFuture::from(history_get_visits(details).map_err(error)?)
+            .await
+            .map_err(error)?,
+    )?;
+    Ok(visits.into_iter().map(|visit| visit.visit_time).collect())
+}
@@ -0,0 +1,44 @@
+//! Wrappers around the `browser.i18n` API,
+//! messages are defined in `res/_locales`.
+
+use std::collections::HashMap;
+
+use tera::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::interop;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "i18n"], js_name="getMessage")]
+    fn i18n_get_message(message_name: &str, substitutions: JsValue) -> String;
+}
+
+/// Gets the localized message with the given name,
+/// placeholders are substituted in order.
+/// Returns the message name if no such message exists,
+/// so that missing messages are visible instead of being blank.
+pub fn get_message(message_name: &str, substitutions: &[String]) -> String {
+    let message = i18n_get_message(message_name, interop::to_jsvalue(substitutions));
+    if message.is_empty() {
+        String::from(message_name)
+    } else {
+        message
+    }
+}
+
+/// Template function for resolving localized messages,
+/// used as `{{ i18n(key="message_name", substitutions=["value"]) }}`.
+/// Fails if the key is missing or the arguments are not strings.
+pub fn tera_function(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let key = args
+        .get("key")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg("`key` should be a string"))?;
+    let substitutions = match args.get("substitutions") {
+        Some(substitutions) => tera::from_value::<Vec<String>>(substitutions.clone())
+            .map_err(|_| tera::Error::msg("`substitutions` should be an array of strings"))?,
+        None => Vec::default(),
+    };
+    Ok(Value::String(get_message(key, &substitutions)))
+}
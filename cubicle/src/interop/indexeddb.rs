@@ -0,0 +1,35 @@
+//! Storage area backed by IndexedDB, for datasets that are too large
+//! to be kept in `browser.storage.local` comfortably.
+//! Changes to this area do not trigger `storage.onChanged`.
+
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+
+use super::storage::StorageArea;
+
+#[wasm_bindgen(raw_module = "./indexeddb.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = "getEntries")]
+    fn get_entries(keys: &JsValue) -> Promise;
+    #[wasm_bindgen(js_name = "setEntries")]
+    fn set_entries(entries: &JsValue) -> Promise;
+    #[wasm_bindgen(js_name = "removeEntries")]
+    fn remove_entries(keys: &JsValue) -> Promise;
+}
+
+/// Entries in the object store of the extension's IndexedDB database.
+pub struct IndexedDbArea;
+
+impl StorageArea for IndexedDbArea {
+    fn get(keys: &JsValue) -> Promise {
+        get_entries(keys)
+    }
+
+    fn set(entries: &JsValue) -> Promise {
+        set_entries(entries)
+    }
+
+    fn remove(keys: &JsValue) -> Promise {
+        remove_entries(keys)
+    }
+}
@@ -0,0 +1,52 @@
+//! Wrappers around the `browser.management` API,
+//! for detecting other extensions that also manage containers.
+//! Most fails are represented by
+//! [FailedExtensionOperation](CustomError::FailedExtensionOperation).
+
+use js_sys::Promise;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "management"], js_name="getAll")]
+    fn management_get_all() -> Promise;
+}
+
+/// IDs of extensions that relocate tabs between containers,
+/// which are Multi-Account Containers and Temporary Containers.
+const CONFLICTING_EXTENSION_IDS: [&str; 2] = [
+    "@testpilot-containers",
+    "{c607c8df-14a7-4f28-894f-29e8722976af}",
+];
+
+/// Retained properties of an `ExtensionInfo`.
+#[derive(Deserialize)]
+struct ExtensionInfo {
+    id: String,
+    name: String,
+    enabled: bool,
+}
+
+/// Names of the enabled extensions that would fight over the same tabs.
+/// Fails if the browser indicates so.
+pub async fn conflicting_extensions() -> Result<Vec<String>, CustomError> {
+    let extensions = interop::cast_or_standard_mismatch::<Vec<ExtensionInfo>>(
+        JsFuture::from(management_get_all()).await.or(Err(
+            CustomError::FailedExtensionOperation {
+                verb: String::from("query"),
+            },
+        ))?,
+    )?;
+    Ok(extensions
+        .into_iter()
+        .filter(|extension| {
+            extension.enabled && CONFLICTING_EXTENSION_IDS.contains(&extension.id.as_str())
+        })
+        .map(|extension| extension.name)
+        .collect())
+}
@@ -0,0 +1,13 @@
+//! Helpers for the `browser.menus` API,
+//! the menu items are created by the background page.
+
+use strum_macros::EnumString;
+
+/// Menu item that was clicked, named as in the background page.
+/// - [OpenHomepage](MenuItem::OpenHomepage) opens a new tab
+///   in the container of the clicked tab.
+#[derive(EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum MenuItem {
+    OpenHomepage,
+}
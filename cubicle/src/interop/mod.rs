@@ -3,23 +3,47 @@
 //! Operations can fail with [StandardMismatch](CustomError::StandardMismatch)
 //! if it uses an external API and the API returned an unexpected value.
 
+pub mod alarms;
 mod bits;
+pub mod bookmarks;
+pub mod browser;
+pub mod cache;
+pub mod commands;
 pub mod contextual_identities;
+pub mod cookies;
+pub mod crypto;
+pub mod devtools;
+pub mod favicon;
 pub mod fetch;
+pub mod history;
+pub mod i18n;
+pub mod indexeddb;
+pub mod management;
+pub mod menus;
+pub mod native_messaging;
+pub mod notifications;
+pub mod omnibox;
+pub mod privacy;
+pub mod runtime;
 pub mod storage;
 pub mod tabs;
+pub mod theme;
+pub mod web_request;
+pub mod windows;
 
 use std::any;
+use std::io::ErrorKind;
+use std::time::Duration;
 
-use js_sys::{JsString, Object, Promise, Reflect};
+use self::browser::{Browser, CurrentBrowser};
+use crate::domain::{EncodedDomain, IdnaPolicy};
+use crate::util::errors::CustomError;
+use crate::util::retry;
+use js_sys::{Function, JsString, Object, Promise, Reflect};
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::Serializer;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::Url;
-
-use crate::domain::EncodedDomain;
-use crate::util::errors::CustomError;
 
 #[wasm_bindgen(raw_module = "./background.js")]
 extern "C" {
@@ -34,34 +58,68 @@ extern "C" {
     /// Prepends a relative path with extension's domain.
     #[wasm_bindgen(js_namespace=["browser", "runtime"], js_name="getURL")]
     pub fn prepend_extension_base_url(path: &str) -> String;
+    #[wasm_bindgen(js_namespace=["browser", "runtime"], js_name="sendMessage")]
+    fn runtime_send_message(message: JsValue) -> Promise;
+    #[wasm_bindgen(js_name = "setTimeout")]
+    fn set_timeout(handler: &Function, timeout: i32) -> i32;
+}
+
+/// Waits for the duration without blocking using `setTimeout`.
+pub async fn sleep(duration: Duration) {
+    let timeout = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+    let promise = Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, timeout);
+    });
+    drop(JsFuture::from(promise).await);
+}
+
+/// Sends a message to all other extension pages without waiting for replies.
+/// Best effort as the message is discarded if no page is listening.
+pub fn broadcast_runtime_message<T>(message: &T)
+where
+    T: Serialize + ?Sized,
+{
+    let promise = runtime_send_message(to_jsvalue(message));
+    wasm_bindgen_futures::spawn_local(async move {
+        drop(JsFuture::from(promise).await);
+    });
 }
 
 /// Fetches a file owned by the extension as a UTF-8 encoded string.
+/// The file is fetched again if the body is interrupted.
 /// Large file should be fetched using [Fetch](crate::interop::fetch::Fetch)
 /// instead.
 pub async fn fetch_extension_file(path: &str) -> String {
-    JsFuture::from(
-        fetch::get(&prepend_extension_base_url(path))
-            .await
-            .expect("valid and stable connection")
-            .text()
-            .expect("standard does not define synchronous errors"),
+    let url = prepend_extension_base_url(path);
+    let fetch_text = || async {
+        let body = JsFuture::from(
+            fetch::get(&url, None)
+                .await?
+                .text()
+                .expect("standard does not define synchronous errors"),
+        )
+        .await
+        .or(Err(CustomError::IoError(ErrorKind::Interrupted)))?;
+        Ok(body.as_string().expect("body must be a valid string"))
+    };
+    retry::retry(
+        &fetch::NETWORK_BACKOFF,
+        fetch_text,
+        |error| matches!(error, CustomError::IoError(_)),
+        sleep,
     )
     .await
-    .expect("assume consume body successful")
-    .as_string()
-    .expect("body must be a valid string")
+    .expect("valid and stable connection")
 }
 
-/// Converts a URL to [EncodedDomain] using Javascript's [Url] API.
+/// Converts a URL to [EncodedDomain] using the [Browser].
+/// The hostname is parsed leniently as it was already accepted by the browser.
 /// Fails if the URL is not valid.
 pub fn url_to_domain(url: &str) -> Result<EncodedDomain, CustomError> {
-    let hostname = Url::new(url)
-        .or(Err(CustomError::StandardMismatch {
-            message: String::from("url should be validated"),
-        }))?
-        .hostname();
-    EncodedDomain::try_from(&*hostname).or(Err(CustomError::StandardMismatch {
+    let hostname = CurrentBrowser::hostname(url).ok_or(CustomError::StandardMismatch {
+        message: String::from("url should be validated"),
+    })?;
+    EncodedDomain::parse(&hostname, &IdnaPolicy::Lenient).or(Err(CustomError::StandardMismatch {
         message: String::from("domain should be validated"),
     }))
 }
@@ -77,6 +135,18 @@ where
         .expect("serialization fail unlikely")
 }
 
+/// Describes a value rejected by the browser, which is usually an `Error`
+/// described by its name and message.
+/// Other values are described by their string form if there is one.
+pub fn error_details(error: &JsValue) -> String {
+    match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.to_string()),
+        None => error
+            .as_string()
+            .unwrap_or_else(|| String::from("unknown error")),
+    }
+}
+
 /// Gets a value within a [JsValue] using a string key via reflection.
 /// Fails if no such key was found.
 pub fn get_or_standard_mismatch(target: &Object, key: &str) -> Result<JsValue, CustomError> {
@@ -145,6 +215,17 @@ pub mod test {
         assert!(map_jsvalue_values.expect("checked ok").is_function());
     }
 
+    #[wasm_bindgen_test]
+    fn test_error_details() {
+        let error = js_sys::TypeError::new("tab not found");
+        assert_eq!(
+            error_details(&JsValue::from(error)),
+            "TypeError: tab not found"
+        );
+        assert_eq!(error_details(&JsValue::from("rejected")), "rejected");
+        assert_eq!(error_details(&JsValue::NULL), "unknown error");
+    }
+
     #[wasm_bindgen_test]
     fn test_get_or_standard_mismatch() {
         let known_object = Object::from(
@@ -0,0 +1,37 @@
+//! Wrappers around native messaging of the `browser.runtime` API,
+//! for communicating with applications installed alongside the browser.
+//! Most fails are represented by
+//! [FailedNativeMessaging](CustomError::FailedNativeMessaging).
+
+use js_sys::Promise;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "runtime"], js_name="sendNativeMessage")]
+    fn runtime_send_native_message(application: &str, message: JsValue) -> Promise;
+}
+
+/// Sends a message to a native application and waits for its response.
+/// Fails if the application is not installed or if it responds with an error,
+/// or if the response is not in the expected format.
+pub async fn send<T, R>(application: &str, message: &T) -> Result<R, CustomError>
+where
+    T: Serialize,
+    R: for<'de> Deserialize<'de>,
+{
+    let response = JsFuture::from(runtime_send_native_message(
+        application,
+        interop::to_jsvalue(message),
+    ))
+    .await
+    .or(Err(CustomError::FailedNativeMessaging {
+        application: String::from(application),
+    }))?;
+    interop::cast_or_standard_mismatch(response)
+}
@@ -0,0 +1,51 @@
+//! Wrappers around the `browser.notifications` API.
+//! Most fails are represented by
+//! [FailedNotificationOperation](CustomError::FailedNotificationOperation).
+
+use js_sys::Promise;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "notifications"], js_name="create")]
+    fn notification_create(notification_id: &str, options: JsValue) -> Promise;
+}
+
+/// Options for a basic notification,
+/// serializes to a `NotificationOptions` instance.
+#[derive(Serialize)]
+struct NotificationOptions {
+    #[serde(rename = "type")]
+    template_type: &'static str,
+    title: String,
+    message: String,
+}
+
+/// Shows a basic notification with a title and a message,
+/// replacing any existing notification with the same ID.
+/// Fails if the browser indicates so.
+pub async fn create_basic(
+    notification_id: &str,
+    title: String,
+    message: String,
+) -> Result<(), CustomError> {
+    let options = NotificationOptions {
+        template_type: "basic",
+        title,
+        message,
+    };
+    JsFuture::from(notification_create(
+        notification_id,
+        interop::to_jsvalue(&options),
+    ))
+    .await
+    .or(Err(CustomError::FailedNotificationOperation {
+        verb: String::from("create"),
+    }))?;
+    Ok(())
+}
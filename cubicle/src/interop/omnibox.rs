@@ -0,0 +1,64 @@
+//! Helpers for the `browser.omnibox` API,
+//! the keyword is specified in the manifest.
+//! The input is expected to be a container name followed by a URL,
+//! such as `work github.com`.
+
+use serde::{Deserialize, Serialize};
+
+/// Suggestion shown in the address bar dropdown,
+/// serializes to a `SuggestResult` instance.
+#[derive(Serialize)]
+pub struct SuggestResult {
+    pub content: String,
+    pub description: String,
+}
+
+/// Where the user chose to open the URL.
+#[derive(Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OnInputEnteredDisposition {
+    CurrentTab,
+    NewForegroundTab,
+    NewBackgroundTab,
+}
+
+/// Splits the input into the container name and the URL,
+/// the URL is the last word as it cannot contain spaces.
+/// Returns [None] as the URL if there is only one word.
+/// A scheme is prepended to the URL if it does not have one.
+pub fn parse_input(text: &str) -> (&str, Option<String>) {
+    match text.trim().rsplit_once(char::is_whitespace) {
+        Some((name, url)) => {
+            let url = if url.contains("://") {
+                String::from(url)
+            } else {
+                String::from("https://") + url
+            };
+            (name.trim(), Some(url))
+        }
+        None => (text.trim(), None),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn test_parse_input() {
+        let table = [
+            ("work github.com", ("work", Some("https://github.com"))),
+            (
+                " side project  http://example.com ",
+                ("side project", Some("http://example.com")),
+            ),
+            ("work", ("work", None)),
+            ("", ("", None)),
+        ];
+        for (input, (name, url)) in table {
+            assert_eq!((name, url.map(String::from)), parse_input(input));
+        }
+    }
+}
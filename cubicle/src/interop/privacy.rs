@@ -0,0 +1,119 @@
+//! Wrappers around the `browser.privacy` API,
+//! for detecting whether the browser already isolates sites from each other,
+//! in which case fewer temporary containers are needed.
+//! The API is only available once the optional permission is granted.
+//! Most fails are represented by
+//! [FailedPrivacyOperation](CustomError::FailedPrivacyOperation).
+
+use std::collections::HashMap;
+
+use js_sys::{Object, Promise};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(
+        catch,
+        js_namespace=["browser", "privacy", "websites", "firstPartyIsolate"],
+        js_name="get"
+    )]
+    fn first_party_isolate_get(details: &JsValue) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(
+        catch,
+        js_namespace=["browser", "privacy", "websites", "cookieConfig"],
+        js_name="get"
+    )]
+    fn cookie_config_get(details: &JsValue) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(js_namespace=["browser", "permissions"], js_name="contains")]
+    fn permissions_contains(permissions: &JsValue) -> Promise;
+}
+
+/// Permission needed for reading the settings.
+const PRIVACY_PERMISSION: &str = "privacy";
+
+/// Cookie behaviour of total cookie protection,
+/// which partitions cookies of third parties by the first party.
+const PARTITIONED_COOKIE_BEHAVIOR: &str = "reject_trackers_and_partition_foreign";
+
+/// Value of a browser setting, deserializes from the result of `get`.
+#[derive(Deserialize)]
+struct BrowserSetting<T> {
+    value: T,
+}
+
+/// Cookie configuration, deserializes from the value of `cookieConfig`.
+#[derive(Deserialize)]
+struct CookieConfig {
+    behavior: String,
+}
+
+/// How the browser isolates sites from each other.
+/// - `is_first_party_isolated` means that `privacy.firstparty.isolate`
+///   is on, which isolates all storage by the first party.
+/// - `is_cookie_partitioned` means that total cookie protection
+///   (dynamic first-party isolation) is on.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct IsolationState {
+    pub is_first_party_isolated: bool,
+    pub is_cookie_partitioned: bool,
+}
+
+impl IsolationState {
+    /// Whether third-party tracking across sites is already prevented,
+    /// so temporary containers are mostly needed for separating logins.
+    pub fn isolates_sites(&self) -> bool {
+        self.is_first_party_isolated || self.is_cookie_partitioned
+    }
+}
+
+/// Gets the value of a browser setting, fails if the browser indicates so.
+async fn get_setting<T>(
+    getter: fn(&JsValue) -> Result<Promise, JsValue>,
+    name: &str,
+) -> Result<T, CustomError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let error = |_| CustomError::FailedPrivacyOperation {
+        verb: format!("get {name}"),
+    };
+    let promise = getter(&Object::new().into()).map_err(error)?;
+    let setting = JsFuture::from(promise).await.map_err(error)?;
+    let setting: BrowserSetting<T> = interop::cast_or_standard_mismatch(setting)?;
+    Ok(setting.value)
+}
+
+/// Checks if the optional permission for reading the settings is granted,
+/// fails if the browser indicates so.
+async fn is_granted() -> Result<bool, CustomError> {
+    let permissions = interop::to_jsvalue(&HashMap::from([("permissions", [PRIVACY_PERMISSION])]));
+    let is_granted = JsFuture::from(permissions_contains(&permissions))
+        .await
+        .or(Err(CustomError::FailedPrivacyOperation {
+            verb: String::from("check permission for"),
+        }))?;
+    Ok(is_granted.as_bool().unwrap_or_default())
+}
+
+/// Detects how the browser isolates sites,
+/// [None] if the optional permission is not granted.
+/// Fails if the browser indicates so.
+pub async fn isolation_state() -> Result<Option<IsolationState>, CustomError> {
+    if !is_granted().await? {
+        return Ok(None);
+    }
+    let is_first_party_isolated =
+        get_setting::<bool>(first_party_isolate_get, "first party isolation").await?;
+    let cookie_config = get_setting::<CookieConfig>(cookie_config_get, "cookie config").await?;
+    Ok(Some(IsolationState {
+        is_first_party_isolated,
+        is_cookie_partitioned: cookie_config.behavior == PARTITIONED_COOKIE_BEHAVIOR,
+    }))
+}
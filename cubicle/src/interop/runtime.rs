@@ -0,0 +1,19 @@
+//! Wrappers around the `browser.runtime` API,
+//! listeners are attached in the background script.
+
+use serde::Deserialize;
+
+/// Retained properties of a `MessageSender`,
+/// the extension ID is [None] for senders that are not extensions.
+#[derive(Deserialize)]
+pub struct MessageSender {
+    #[serde(default)]
+    id: Option<String>,
+}
+
+impl MessageSender {
+    /// ID of the sending extension, [None] if it is unknown.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+}
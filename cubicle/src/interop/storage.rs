@@ -1,14 +1,32 @@
-//! Wrappers around the `browser.storage.local` API.
+//! Wrappers around the `browser.storage.local` API,
+//! and reading from the `browser.storage.managed` API.
 //! Most fails are represented by
 //! [FailedStorageOperation](CustomError::FailedStorageOperation).
+//! Frequent writes may be queued, queued entries are flushed before
+//! any other operation so that they are never out of order.
+//! Failed writes of queued entries are retried with backoff,
+//! and are dropped once all attempts failed.
+//! Large single entries are stored compressed,
+//! and are decompressed transparently when they are read.
+//! Large datasets may be kept in another [StorageArea]
+//! selected by a [StorageBackend].
 
-use js_sys::{Object, Promise, Reflect};
+use std::cell::{Cell, RefCell};
+use std::thread::LocalKey;
+use std::time::Duration;
+
+use js_sys::{Object, Promise, Reflect, JSON};
 use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 
 use crate::interop;
+use crate::interop::indexeddb::IndexedDbArea;
+use crate::util::compress;
 use crate::util::errors::CustomError;
+use crate::util::log;
+use crate::util::retry::Backoff;
 
 #[wasm_bindgen]
 extern "C" {
@@ -18,19 +36,241 @@ extern "C" {
     fn storage_set(keys: &JsValue) -> Promise;
     #[wasm_bindgen(js_namespace=["browser", "storage", "local"], js_name="remove")]
     fn storage_remove(keys: &JsValue) -> Promise;
+    #[wasm_bindgen(catch, js_namespace=["browser", "storage", "local"], js_name="getBytesInUse")]
+    fn storage_get_bytes_in_use(keys: &JsValue) -> Result<Promise, JsValue>;
+    #[wasm_bindgen(catch, js_namespace=["browser", "storage", "managed"], js_name="get")]
+    fn storage_managed_get(keys: &JsValue) -> Result<Promise, JsValue>;
+}
+
+/// Soft limit of the storage usage in bytes,
+/// which is the quota in browsers that enforce one.
+pub const QUOTA_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Delay before queued entries are written, which coalesces frequent writes.
+const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// Backoff for writing queued entries again after a failed write,
+/// the entries are dropped after the last attempt.
+const FLUSH_BACKOFF: Backoff = Backoff {
+    attempts: 5,
+    initial_delay: FLUSH_DELAY,
+    factor: 2,
+};
+
+/// Length of the JSON form of a single entry above which it is compressed,
+/// which the event log may exceed once it is full.
+const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+/// Area of entries with the semantics of `storage.local`,
+/// where each method returns a promise of the corresponding operation.
+pub trait StorageArea {
+    /// Gets entries with a key, collection of keys, or object of defaults,
+    /// or all entries if the keys are null.
+    fn get(keys: &JsValue) -> Promise;
+
+    /// Sets entries of an object.
+    fn set(entries: &JsValue) -> Promise;
+
+    /// Removes entries with a key or collection of keys.
+    fn remove(keys: &JsValue) -> Promise;
+}
+
+/// The `browser.storage.local` area.
+pub struct LocalArea;
+
+impl StorageArea for LocalArea {
+    fn get(keys: &JsValue) -> Promise {
+        storage_get(keys)
+    }
+
+    fn set(entries: &JsValue) -> Promise {
+        storage_set(entries)
+    }
+
+    fn remove(keys: &JsValue) -> Promise {
+        storage_remove(keys)
+    }
+}
+
+/// Selectable area for large datasets,
+/// such as the event log and the statistics.
+#[derive(Clone, Copy, Default, Deserialize, Display, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    IndexedDb,
+}
+
+impl StorageBackend {
+    /// Gets all entries in the area as an object, see [get_all].
+    pub async fn get_all(self) -> Result<Object, CustomError> {
+        match self {
+            Self::Local => get_all_in::<LocalArea>().await,
+            Self::IndexedDb => get_all_in::<IndexedDbArea>().await,
+        }
+    }
+
+    /// Populates a structure with values from the area,
+    /// see [get_with_keys].
+    pub async fn get_with_keys<T>(self, keys: &mut T) -> Result<(), CustomError>
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+    {
+        match self {
+            Self::Local => get_with_keys_in::<LocalArea, T>(keys).await,
+            Self::IndexedDb => get_with_keys_in::<IndexedDbArea, T>(keys).await,
+        }
+    }
+
+    /// Sets a single value with a key in the area,
+    /// see [store_single_entry].
+    pub async fn store_single_entry<K, V>(self, key: &K, value: &V) -> Result<(), CustomError>
+    where
+        K: Serialize + ?Sized,
+        V: Serialize,
+    {
+        match self {
+            Self::Local => store_single_entry_in::<LocalArea, K, V>(key, value).await,
+            Self::IndexedDb => store_single_entry_in::<IndexedDbArea, K, V>(key, value).await,
+        }
+    }
+
+    /// Removes all entries with the given collection of keys from the area,
+    /// see [remove_entries].
+    pub async fn remove_entries<S, K>(self, keys: &S) -> Result<(), CustomError>
+    where
+        S: IntoIterator<Item = K> + Serialize,
+        K: Serialize,
+    {
+        match self {
+            Self::Local => remove_entries_in::<LocalArea, S, K>(keys).await,
+            Self::IndexedDb => remove_entries_in::<IndexedDbArea, S, K>(keys).await,
+        }
+    }
+
+    /// Moves entries with the given keys from this area to another area,
+    /// the entries are removed from this area only after they are moved.
+    /// Fails if the browser indicates so.
+    pub async fn move_entries(self, target: Self, keys: &[&str]) -> Result<(), CustomError> {
+        match (self, target) {
+            (Self::Local, Self::IndexedDb) => {
+                move_entries_between::<LocalArea, IndexedDbArea>(keys).await
+            }
+            (Self::IndexedDb, Self::Local) => {
+                move_entries_between::<IndexedDbArea, LocalArea>(keys).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Moves entries with the given keys from an area to another area,
+/// see [StorageBackend::move_entries].
+async fn move_entries_between<A, B>(keys: &[&str]) -> Result<(), CustomError>
+where
+    A: StorageArea,
+    B: StorageArea,
+{
+    flush().await?;
+    let keys = interop::to_jsvalue(keys);
+    let entries = JsFuture::from(A::get(&keys)).await.map_err(|error| {
+        CustomError::FailedStorageOperation {
+            verb_prep: String::from("load from"),
+            details: interop::error_details(&error),
+        }
+    })?;
+    set_without_flush::<B>(&entries).await?;
+    JsFuture::from(A::remove(&keys)).await.map_err(|error| {
+        CustomError::FailedStorageOperation {
+            verb_prep: String::from("remove from"),
+            details: interop::error_details(&error),
+        }
+    })?;
+    Ok(())
+}
+
+thread_local! {
+    /// Entries that are queued to be written, [None] if nothing is queued.
+    static QUEUED_ENTRIES: RefCell<Option<Object>> = const { RefCell::new(None) };
+    /// Entries of large datasets that are queued to be written to
+    /// the large data backend, [None] if nothing is queued.
+    static QUEUED_LARGE_ENTRIES: RefCell<Option<Object>> = const { RefCell::new(None) };
+    /// Number of consecutive failed writes of [QUEUED_ENTRIES].
+    static FAILED_FLUSHES: Cell<u32> = const { Cell::new(0) };
+    /// Number of consecutive failed writes of [QUEUED_LARGE_ENTRIES].
+    static FAILED_LARGE_FLUSHES: Cell<u32> = const { Cell::new(0) };
+    /// Area where queued entries of large datasets are written to,
+    /// see [set_large_data_backend].
+    static LARGE_DATA_BACKEND: Cell<StorageBackend> = const { Cell::new(StorageBackend::Local) };
+}
+
+/// Selects the area where [queued entries of large datasets](queue_large_entry)
+/// are written to, which should follow the preferences.
+/// Entries queued before are written to the new area as well.
+pub fn set_large_data_backend(backend: StorageBackend) {
+    LARGE_DATA_BACKEND.with(|large_data_backend| large_data_backend.set(backend));
 }
 
 /// Gets all stored entries as an object,
 /// fails if the browser indicates so.
 pub async fn get_all() -> Result<Object, CustomError> {
-    JsFuture::from(storage_get(&JsValue::NULL))
+    get_all_in::<LocalArea>().await
+}
+
+/// Gets all entries in an area as an object,
+/// fails in the same way as [get_all].
+async fn get_all_in<A>() -> Result<Object, CustomError>
+where
+    A: StorageArea,
+{
+    flush_before_read().await;
+    let all_stored = JsFuture::from(A::get(&JsValue::NULL))
         .await
-        .or(Err(CustomError::FailedStorageOperation {
+        .map_err(|error| CustomError::FailedStorageOperation {
             verb_prep: String::from("load from"),
-        }))
+            details: interop::error_details(&error),
+        })
+        .map(Object::from)?;
+    decompress_entries(&all_stored);
+    Ok(all_stored)
+}
+
+/// Gets all entries provisioned by administrators as an object,
+/// fails if nothing is provisioned or the browser indicates so.
+pub async fn get_all_managed() -> Result<Object, CustomError> {
+    let error = |error| CustomError::FailedStorageOperation {
+        verb_prep: String::from("load from managed"),
+        details: interop::error_details(&error),
+    };
+    JsFuture::from(storage_managed_get(&JsValue::NULL).map_err(error)?)
+        .await
+        .map_err(error)
         .map(Object::from)
 }
 
+/// Gets the number of bytes used by all entries.
+/// Uses `getBytesInUse` where available, estimates by serializing otherwise.
+/// Fails if the browser indicates so.
+pub async fn usage() -> Result<u64, CustomError> {
+    flush_before_read().await;
+    if let Ok(promise) = storage_get_bytes_in_use(&JsValue::NULL) {
+        if let Some(bytes) = JsFuture::from(promise)
+            .await
+            .ok()
+            .and_then(|bytes| bytes.as_f64())
+        {
+            return Ok(bytes as u64);
+        }
+    }
+    let all_stored = get_all().await?;
+    let serialized = JSON::stringify(&all_stored).or(Err(CustomError::StandardMismatch {
+        message: String::from("stored entries should be serializable"),
+    }))?;
+    Ok(String::from(serialized).len() as u64)
+}
+
 /// Removes all entries with the given collection of keys,
 /// fails if the browser indicates so.
 pub async fn remove_entries<S, K>(keys: &S) -> Result<(), CustomError>
@@ -38,11 +278,24 @@ where
     S: IntoIterator<Item = K> + Serialize,
     K: Serialize,
 {
-    JsFuture::from(storage_remove(&interop::to_jsvalue(keys)))
+    remove_entries_in::<LocalArea, S, K>(keys).await
+}
+
+/// Removes all entries with the given collection of keys from an area,
+/// fails if the browser indicates so.
+async fn remove_entries_in<A, S, K>(keys: &S) -> Result<(), CustomError>
+where
+    A: StorageArea,
+    S: IntoIterator<Item = K> + Serialize,
+    K: Serialize,
+{
+    flush().await?;
+    JsFuture::from(A::remove(&interop::to_jsvalue(keys)))
         .await
-        .or(Err(CustomError::FailedStorageOperation {
+        .map_err(|error| CustomError::FailedStorageOperation {
             verb_prep: String::from("remove from"),
-        }))?;
+            details: interop::error_details(&error),
+        })?;
     Ok(())
 }
 
@@ -53,11 +306,24 @@ pub async fn get_with_keys<T>(keys: &mut T) -> Result<(), CustomError>
 where
     T: for<'de> Deserialize<'de> + Serialize,
 {
-    let got = JsFuture::from(storage_get(&interop::to_jsvalue(keys)))
+    get_with_keys_in::<LocalArea, T>(keys).await
+}
+
+/// Populates a structure with values from an area,
+/// fails in the same way as [get_with_keys].
+async fn get_with_keys_in<A, T>(keys: &mut T) -> Result<(), CustomError>
+where
+    A: StorageArea,
+    T: for<'de> Deserialize<'de> + Serialize,
+{
+    flush_before_read().await;
+    let got = JsFuture::from(A::get(&interop::to_jsvalue(keys)))
         .await
-        .or(Err(CustomError::FailedStorageOperation {
+        .map_err(|error| CustomError::FailedStorageOperation {
             verb_prep: String::from("load from"),
-        }))?;
+            details: interop::error_details(&error),
+        })?;
+    decompress_entries(&Object::from(got.clone()));
     *keys = interop::cast_or_standard_mismatch(got)?;
     Ok(())
 }
@@ -74,16 +340,58 @@ where
 /// Sets values with a [JsValue] in a structural representation,
 /// fails if the browser indicates so.
 pub async fn set_with_value_keys(keys: &JsValue) -> Result<(), CustomError> {
-    JsFuture::from(storage_set(keys))
+    flush().await?;
+    set_without_flush::<LocalArea>(keys).await
+}
+
+/// Sets values in an area without writing queued entries first,
+/// fails if the browser indicates so.
+async fn set_without_flush<A>(keys: &JsValue) -> Result<(), CustomError>
+where
+    A: StorageArea,
+{
+    JsFuture::from(A::set(keys))
         .await
-        .or(Err(CustomError::FailedStorageOperation {
+        .map_err(|error| CustomError::FailedStorageOperation {
             verb_prep: String::from("store to"),
-        }))?;
+            details: interop::error_details(&error),
+        })?;
     Ok(())
 }
 
 /// Sets a single value with a key, fails if the browser indicates so.
+/// The value is compressed if it is larger than [COMPRESSION_THRESHOLD].
 pub async fn store_single_entry<K, V>(key: &K, value: &V) -> Result<(), CustomError>
+where
+    K: Serialize + ?Sized,
+    V: Serialize,
+{
+    store_single_entry_in::<LocalArea, K, V>(key, value).await
+}
+
+/// Sets a single value with a key in an area,
+/// fails in the same way as [store_single_entry].
+async fn store_single_entry_in<A, K, V>(key: &K, value: &V) -> Result<(), CustomError>
+where
+    A: StorageArea,
+    K: Serialize + ?Sized,
+    V: Serialize,
+{
+    let keys = Object::new();
+    Reflect::set(
+        &keys,
+        &interop::to_jsvalue(key),
+        &compress_large(interop::to_jsvalue(value)),
+    )
+    .expect("inline construction");
+    flush().await?;
+    set_without_flush::<A>(&keys).await
+}
+
+/// Sets a single value with a key without waiting for the write,
+/// for when the extension cannot continue, such as after a panic.
+/// Queued entries are not written first, and failure to write is ignored.
+pub fn store_single_entry_detached<K, V>(key: &K, value: &V)
 where
     K: Serialize + ?Sized,
     V: Serialize,
@@ -95,5 +403,211 @@ where
         &interop::to_jsvalue(value),
     )
     .expect("inline construction");
-    set_with_value_keys(&keys).await
+    drop(storage_set(&keys));
+}
+
+/// Queues a single value with a key to be set shortly,
+/// replacing the queued value with the same key.
+/// Failed writes are queued again and logged,
+/// [flush] or [store_single_entry] should be used to handle failures.
+pub fn queue_single_entry<K, V>(key: &K, value: &V)
+where
+    K: Serialize + ?Sized,
+    V: Serialize,
+{
+    queue_entry_in(
+        &QUEUED_ENTRIES,
+        interop::to_jsvalue(key),
+        interop::to_jsvalue(value),
+    );
+}
+
+/// Queues a single value of a large dataset with a key to be set shortly
+/// in the [large data backend](set_large_data_backend),
+/// the value is compressed in the same way as [store_single_entry].
+/// Otherwise the same as [queue_single_entry].
+pub fn queue_large_entry<K, V>(key: &K, value: &V)
+where
+    K: Serialize + ?Sized,
+    V: Serialize,
+{
+    queue_entry_in(
+        &QUEUED_LARGE_ENTRIES,
+        interop::to_jsvalue(key),
+        compress_large(interop::to_jsvalue(value)),
+    );
+}
+
+/// Queues a value with a key in a queue,
+/// scheduling a flush if the queue was empty.
+fn queue_entry_in(queue: &'static LocalKey<RefCell<Option<Object>>>, key: JsValue, value: JsValue) {
+    let is_first_entry = queue.with(|queued_entries| {
+        let mut queued_entries = queued_entries.borrow_mut();
+        let is_first_entry = queued_entries.is_none();
+        Reflect::set(queued_entries.get_or_insert_with(Object::new), &key, &value)
+            .expect("inline construction");
+        is_first_entry
+    });
+    if is_first_entry {
+        schedule_flush(FLUSH_DELAY);
+    }
+}
+
+/// Flushes the queued entries after a delay, logging any failure.
+fn schedule_flush(delay: Duration) {
+    wasm_bindgen_futures::spawn_local(async move {
+        interop::sleep(delay).await;
+        if let Err(error) = flush().await {
+            log::warn!("queued entries are not written, {error}");
+        }
+    });
+}
+
+/// Flushes the queued entries before reading,
+/// so that the entries read are never out of order.
+/// Failures are only logged, as the failed entries are retried
+/// and should not prevent other entries from being read.
+async fn flush_before_read() {
+    if let Err(error) = flush().await {
+        log::warn!("queued entries are not written before reading, {error}");
+    }
+}
+
+/// Gets the new values from a `changes` object of `storage.onChanged`,
+/// removed entries have undefined values.
+/// Queued entries are skipped, as the changes are outdated.
+pub fn new_values(changes: &JsValue) -> Vec<(String, JsValue)> {
+    let Some(changes) = changes.dyn_ref::<Object>() else {
+        return Vec::default();
+    };
+    let is_queued = |key: &str| {
+        [&QUEUED_ENTRIES, &QUEUED_LARGE_ENTRIES]
+            .iter()
+            .any(|queue| {
+                queue.with(|queued_entries| {
+                    queued_entries
+                        .borrow()
+                        .as_ref()
+                        .is_some_and(|queued_entries| {
+                            queued_entries.has_own_property(&JsValue::from_str(key))
+                        })
+                })
+            })
+    };
+    Object::keys(changes)
+        .iter()
+        .filter_map(|key| key.as_string())
+        .filter(|key| !is_queued(key))
+        .map(|key| {
+            let change =
+                Reflect::get(changes, &JsValue::from_str(&key)).expect("key from the same object");
+            let new_value =
+                Reflect::get(&change, &JsValue::from_str("newValue")).unwrap_or(JsValue::UNDEFINED);
+            (key, decompress(new_value))
+        })
+        .collect()
+}
+
+/// Compresses a value into a string if its JSON form is longer than
+/// [COMPRESSION_THRESHOLD], other values are returned as is.
+fn compress_large(value: JsValue) -> JsValue {
+    let Some(json) = JSON::stringify(&value).ok().map(String::from) else {
+        return value;
+    };
+    if json.len() <= COMPRESSION_THRESHOLD {
+        return value;
+    }
+    JsValue::from_str(&compress::compress(&json))
+}
+
+/// Restores a value compressed by [compress_large],
+/// other values, including corrupt compressed values, are returned as is.
+fn decompress(value: JsValue) -> JsValue {
+    value
+        .as_string()
+        .and_then(|text| compress::decompress(&text))
+        .and_then(|json| JSON::parse(&json).ok())
+        .unwrap_or(value)
+}
+
+/// Decompresses all values of an object of entries in place.
+fn decompress_entries(entries: &Object) {
+    for key in Object::keys(entries).iter() {
+        let value = Reflect::get(entries, &key).expect("key from the same object");
+        Reflect::set(entries, &key, &decompress(value)).expect("key from the same object");
+    }
+}
+
+/// Number of entries that are queued to be written,
+/// including those of large datasets.
+pub fn queued_entry_count() -> usize {
+    [&QUEUED_ENTRIES, &QUEUED_LARGE_ENTRIES]
+        .iter()
+        .map(|queue| {
+            queue.with(|queued_entries| {
+                queued_entries
+                    .borrow()
+                    .as_ref()
+                    .map_or(0, |queued_entries| {
+                        Object::keys(queued_entries).length() as usize
+                    })
+            })
+        })
+        .sum()
+}
+
+/// Writes all queued entries immediately, fails if the browser indicates so.
+/// If the write failed, the entries are queued again to be retried
+/// with [FLUSH_BACKOFF], newer values with the same keys queued
+/// in the meantime are kept.
+/// The entries are dropped instead once all attempts failed.
+pub async fn flush() -> Result<(), CustomError> {
+    flush_queue::<LocalArea>(&QUEUED_ENTRIES, &FAILED_FLUSHES).await?;
+    match LARGE_DATA_BACKEND.with(Cell::get) {
+        StorageBackend::Local => {
+            flush_queue::<LocalArea>(&QUEUED_LARGE_ENTRIES, &FAILED_LARGE_FLUSHES).await
+        }
+        StorageBackend::IndexedDb => {
+            flush_queue::<IndexedDbArea>(&QUEUED_LARGE_ENTRIES, &FAILED_LARGE_FLUSHES).await
+        }
+    }
+}
+
+/// Writes the entries of a queue to an area,
+/// counting the consecutive failures, see [flush].
+async fn flush_queue<A>(
+    queue: &'static LocalKey<RefCell<Option<Object>>>,
+    failures: &'static LocalKey<Cell<u32>>,
+) -> Result<(), CustomError>
+where
+    A: StorageArea,
+{
+    let Some(queued_entries) = queue.with(|queued_entries| queued_entries.take()) else {
+        return Ok(());
+    };
+    let result = set_without_flush::<A>(&queued_entries).await;
+    let Err(error) = &result else {
+        failures.with(|failures| failures.set(0));
+        return result;
+    };
+    let failure_count = failures.with(|failures| failures.get() + 1);
+    let Some(delay) = FLUSH_BACKOFF.delays().nth(failure_count as usize - 1) else {
+        failures.with(|failures| failures.set(0));
+        log::warn!("queued entries are dropped after {failure_count} failed writes, {error}");
+        return result;
+    };
+    failures.with(|failures| failures.set(failure_count));
+    let is_requeued = queue.with(|newer_entries| {
+        let mut newer_entries = newer_entries.borrow_mut();
+        let is_requeued = newer_entries.is_none();
+        if let Some(newer_entries) = newer_entries.as_ref() {
+            Object::assign(&queued_entries, newer_entries);
+        }
+        *newer_entries = Some(queued_entries);
+        is_requeued
+    });
+    if is_requeued {
+        schedule_flush(delay);
+    }
+    result
 }
@@ -3,21 +3,35 @@
 //! [FailedTabOperation](CustomError::FailedTabOperation).
 
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use js_sys::{Array, Object, Promise};
 use serde::{Deserialize, Serialize, Serializer};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
+use web_sys::Url;
 
 use super::contextual_identities::CookieStoreId;
+use super::windows::WindowId;
 use crate::domain::EncodedDomain;
 use crate::interop;
 use crate::util::errors::CustomError;
 
+#[cfg(not(test))]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="create")]
     fn tab_create(create_properties: JsValue) -> Promise;
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="discard")]
+    fn tab_discard(tab_ids: JsValue) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="move")]
+    fn tab_move(tab_ids: JsValue, move_properties: JsValue) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="get")]
+    fn tab_get(tab_id: isize) -> Promise;
     #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="query")]
     fn tab_query(query_obj: JsValue) -> Promise;
     #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="remove")]
@@ -26,6 +40,8 @@ extern "C" {
     fn tab_execute_js(tab_id: isize, details: JsValue) -> Promise;
     #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="reload")]
     fn tab_reload(tab_id: isize) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "tabs"], js_name="update")]
+    fn tab_update(tab_id: isize, update_properties: JsValue) -> Promise;
 }
 
 /// Retained properties that affect tab creation,
@@ -41,6 +57,8 @@ pub struct TabProperties {
     )]
     pub cookie_store_id: CookieStoreId,
     discarded: Option<bool>,
+    #[serde(default, skip_serializing)]
+    fav_icon_url: Option<String>,
     #[serde(skip_serializing)]
     id: isize,
     index: usize,
@@ -50,8 +68,13 @@ pub struct TabProperties {
     #[serde(rename(deserialize = "isInReaderMode", serialize = "openInReaderMode"))]
     reader_mode: Option<bool>, // found to be optional
     pinned: bool,
+    #[serde(default, skip_serializing)]
+    status: Option<String>,
+    #[serde(default, skip_serializing)]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     url: Option<String>,
-    window_id: isize,
+    window_id: WindowId,
 }
 
 impl TabProperties {
@@ -64,28 +87,187 @@ impl TabProperties {
         interop::url_to_domain(url).map(Some)
     }
 
+    /// The URL, [None] if the tab does not have a URL.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The URL of the favicon, [None] if the tab does not have one.
+    pub fn fav_icon_url(&self) -> Option<&str> {
+        self.fav_icon_url.as_deref()
+    }
+
+    /// The title, [None] if the tab does not have a title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
     /// See <https://developer.mozilla.org/en-US/docs/Mozilla/Add-ons/WebExtensions/API/tabs/Tab>.
     pub fn opener_tab_id(&self) -> Option<&TabId> {
         self.opener_tab_id.as_ref()
     }
 
-    /// Creates a new tab using this instance,
-    /// the tab index is increased by 1 to place it after the existing tab.
+    /// Repurposes the properties for opening a URL in a new tab
+    /// next to this tab, states that are specific to this tab are reset.
+    pub fn retarget(&mut self, url: String, active: bool) {
+        self.active = active;
+        self.discarded = None;
+        self.muted_info = MutedInfo { muted: false };
+        self.reader_mode = None;
+        self.pinned = false;
+        self.url = Some(url);
+    }
+
+    /// Repurposes the properties for opening the new tab page in a new tab
+    /// next to this tab, as privileged pages cannot be opened by URL.
+    pub fn retarget_new_tab_page(&mut self, active: bool) {
+        self.retarget(String::default(), active);
+        self.url = None;
+    }
+
+    /// Replaces the URL, such as after it is rewritten.
+    pub fn set_url(&mut self, url: String) {
+        self.url = Some(url);
+    }
+
+    /// Checks if the tab appears to be opened by another application,
+    /// which is an active blank tab without an opener that is already loading.
+    /// Blank tabs opened by pages have openers, and those opened
+    /// in the background or restored from a session are left alone.
+    pub fn is_likely_external(&self) -> bool {
+        self.opener_tab_id.is_none()
+            && self.active
+            && self.discarded != Some(true)
+            && self.status.as_deref() == Some("loading")
+            && self.url.as_deref() == Some("about:blank")
+    }
+
+    /// Checks if the tab is showing the new tab page or the home page.
+    pub fn is_new_tab_page(&self) -> bool {
+        matches!(self.url.as_deref(), Some("about:newtab" | "about:home"))
+    }
+
+    /// Repurposes the properties for opening the new tab page in place
+    /// of this tab, as privileged pages cannot be opened by URL.
+    pub fn open_new_tab_page(&mut self) {
+        self.url = None;
+        self.reader_mode = None;
+        self.index = self.index.saturating_sub(1);
+    }
+
+    /// The [TabId] of this tab.
+    pub fn id(&self) -> TabId {
+        TabId::new(self.id)
+    }
+
+    /// The [WindowId] of the window that contains this tab.
+    pub fn window_id(&self) -> &WindowId {
+        &self.window_id
+    }
+
+    /// Creates a new tab using this instance.
+    /// Pinned tabs take the index of the existing tab so that they stay
+    /// in position among pinned tabs, other tabs are placed after it.
+    /// Tabs in reader mode are reopened in reader mode from their original URL,
+    /// as reader pages cannot be opened directly.
     /// Whether the resulting tab completely matches is unchecked.
     /// Fails if the browser indicates so.
     pub async fn new_tab(&mut self) -> Result<TabId, CustomError> {
-        self.index += 1;
-        let new_properties = interop::cast_or_standard_mismatch::<Self>(
-            JsFuture::from(tab_create(interop::to_jsvalue(self)))
-                .await
-                .or(Err(CustomError::FailedTabOperation {
-                    verb: String::from("create"),
-                }))?,
-        )?;
-        Ok(TabId::new(new_properties.id))
+        if !self.pinned {
+            self.index += 1;
+        }
+        if self.reader_mode == Some(true) {
+            let original_url = self.url.as_deref().and_then(reader_original_url);
+            if original_url.is_some() {
+                self.url = original_url;
+            } else {
+                self.reader_mode = None;
+            }
+        }
+        create_tab(interop::to_jsvalue(self)).await
     }
 }
 
+/// Properties of a tab opened in a container,
+/// serializes to a `createProperties` instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenProperties<'a> {
+    active: bool,
+    #[serde(serialize_with = "CookieStoreId::serialize_inner")]
+    cookie_store_id: CookieStoreId,
+    url: &'a str,
+}
+
+/// Opens a URL in a new inactive tab in the container.
+/// Fails if the browser indicates so.
+pub async fn open_in_container(
+    url: &str,
+    cookie_store_id: &CookieStoreId,
+) -> Result<TabId, CustomError> {
+    let open_properties = OpenProperties {
+        active: false,
+        cookie_store_id: cookie_store_id.clone(),
+        url,
+    };
+    create_tab(interop::to_jsvalue(&open_properties)).await
+}
+
+/// Creates a tab with the `createProperties`, returning the ID of the tab.
+/// Fails if the browser indicates so.
+async fn create_tab(create_properties: JsValue) -> Result<TabId, CustomError> {
+    let new_tab = JsFuture::from(tab_create(create_properties))
+        .await
+        .map_err(|error| CustomError::FailedTabOperation {
+            verb: String::from("create"),
+            details: interop::error_details(&error),
+        })?;
+    let new_tab = Object::try_from(&new_tab).ok_or(CustomError::StandardMismatch {
+        message: String::from("expected `tab` to be an object"),
+    })?;
+    interop::cast_or_standard_mismatch(interop::get_or_standard_mismatch(new_tab, "id")?)
+}
+
+/// Extracts the URL of the original page from the URL of a reader page.
+/// Returns [None] if the URL is not of a reader page.
+fn reader_original_url(url: &str) -> Option<String> {
+    let url = Url::new(url).ok()?;
+    (url.protocol() == "about:" && url.pathname() == "reader")
+        .then(|| url.search_params().get("url"))
+        .flatten()
+}
+
+/// Brief description of a tab for listing, deserializes from a `Tab` instance.
+/// Title and URL are empty if they are unavailable.
+#[derive(Deserialize, Serialize)]
+pub struct TabSummary {
+    id: TabId,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+}
+
+impl TabSummary {
+    /// The [TabId] of the described tab.
+    pub fn id(&self) -> &TabId {
+        &self.id
+    }
+
+    /// The URL of the described tab, empty if unavailable.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Query for tabs in a container, serializes to a `queryInfo` instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CookieStoreQuery {
+    #[serde(serialize_with = "CookieStoreId::serialize_inner")]
+    cookie_store_id: CookieStoreId,
+}
+
 /// Unique identifier that allow operations on specific tabs.
 /// All operations may fail if the tab specified by the ID does not exist.
 #[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -102,6 +284,17 @@ impl TabId {
         Self { inner: tab_id }
     }
 
+    /// Gets the current properties of the specified tab,
+    /// fails if the browser indicates so.
+    pub async fn properties(&self) -> Result<TabProperties, CustomError> {
+        interop::cast_or_standard_mismatch(JsFuture::from(tab_get(self.inner)).await.map_err(
+            |error| CustomError::FailedTabOperation {
+                verb: String::from("get"),
+                details: interop::error_details(&error),
+            },
+        )?)
+    }
+
     /// Move the specified tab backward one page into history,
     /// close it if there is no history before it.
     /// Fails if the browser indicates so.
@@ -112,9 +305,10 @@ impl TabId {
         )]));
         JsFuture::from(tab_execute_js(self.inner, details))
             .await
-            .or(Err(CustomError::FailedTabOperation {
+            .map_err(|error| CustomError::FailedTabOperation {
                 verb: String::from("revert"),
-            }))?;
+                details: interop::error_details(&error),
+            })?;
         Ok(())
     }
 
@@ -126,28 +320,52 @@ impl TabId {
         ]));
         JsFuture::from(tab_execute_js(self.inner, details))
             .await
-            .or(Err(CustomError::FailedTabOperation {
+            .map_err(|error| CustomError::FailedTabOperation {
                 verb: String::from("stop loading"),
-            }))?;
+                details: interop::error_details(&error),
+            })?;
         Ok(())
     }
 
     /// Closes the specified tab, fails if the browser indicates so.
     pub async fn close_tab(&self) -> Result<(), CustomError> {
-        interop::cast_or_standard_mismatch(JsFuture::from(tab_remove(self.inner)).await.or(Err(
-            CustomError::FailedTabOperation {
+        interop::cast_or_standard_mismatch(JsFuture::from(tab_remove(self.inner)).await.map_err(
+            |error| CustomError::FailedTabOperation {
                 verb: String::from("remove"),
+                details: interop::error_details(&error),
             },
-        ))?)
+        )?)
+    }
+
+    /// Activates the specified tab and focuses the window containing it,
+    /// fails if the browser indicates so.
+    pub async fn focus(&self) -> Result<(), CustomError> {
+        let update_properties = interop::to_jsvalue(&HashMap::from([("active", true)]));
+        let tab_properties = interop::cast_or_standard_mismatch::<TabProperties>(
+            JsFuture::from(tab_update(self.inner, update_properties))
+                .await
+                .map_err(|error| CustomError::FailedTabOperation {
+                    verb: String::from("activate"),
+                    details: interop::error_details(&error),
+                })?,
+        )?;
+        tab_properties.window_id.focus().await
     }
 
     /// Reloads the specified tab, fails if the browser indicates so.
     pub async fn reload_tab(&self) -> Result<(), CustomError> {
-        interop::cast_or_standard_mismatch(JsFuture::from(tab_reload(self.inner)).await.or(Err(
-            CustomError::FailedTabOperation {
+        interop::cast_or_standard_mismatch(JsFuture::from(tab_reload(self.inner)).await.map_err(
+            |error| CustomError::FailedTabOperation {
                 verb: String::from("reload"),
+                details: interop::error_details(&error),
             },
-        ))?)
+        )?)
+    }
+}
+
+impl Display for TabId {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.inner)
     }
 }
 
@@ -186,3 +404,245 @@ pub async fn current_tab_cookie_store_id() -> Result<CookieStoreId, CustomError>
         Err(CustomError::FailedFetchActiveTab)
     }
 }
+
+/// Gets the properties of the active tab in the current window.
+/// Fails with [FailedFetchActiveTab](CustomError::FailedFetchActiveTab)
+/// if there is no active tab in the current window.
+pub async fn current_tab() -> Result<TabProperties, CustomError> {
+    let query_obj = HashMap::from([("active", true), ("currentWindow", true)]);
+    let active_tabs = JsFuture::from(tab_query(interop::to_jsvalue(&query_obj)))
+        .await
+        .or(Err(CustomError::FailedFetchActiveTab))?;
+    interop::cast_or_standard_mismatch::<Vec<TabProperties>>(active_tabs)?
+        .pop()
+        .ok_or(CustomError::FailedFetchActiveTab)
+}
+
+/// Gets all tabs in all windows, including discarded tabs
+/// that are restored from the previous session.
+/// Fails if the browser indicates so.
+pub async fn query_all() -> Result<Vec<TabProperties>, CustomError> {
+    interop::cast_or_standard_mismatch(
+        JsFuture::from(tab_query(JsValue::from(Object::new())))
+            .await
+            .map_err(|error| CustomError::FailedTabOperation {
+                verb: String::from("query"),
+                details: interop::error_details(&error),
+            })?,
+    )
+}
+
+/// Properties of moving tabs, serializes to a `moveProperties` instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveProperties<'a> {
+    window_id: &'a WindowId,
+    index: isize,
+}
+
+/// Moves the specified tabs to the end of a window.
+/// Fails if the browser indicates so.
+pub async fn move_to_window(tab_ids: &[TabId], window_id: &WindowId) -> Result<(), CustomError> {
+    let move_properties = MoveProperties {
+        window_id,
+        index: -1,
+    };
+    JsFuture::from(tab_move(
+        interop::to_jsvalue(tab_ids),
+        interop::to_jsvalue(&move_properties),
+    ))
+    .await
+    .map_err(|error| CustomError::FailedTabOperation {
+        verb: String::from("move"),
+        details: interop::error_details(&error),
+    })?;
+    Ok(())
+}
+
+/// Unloads the specified tabs from memory without closing them,
+/// active tabs are skipped by the browser.
+/// Fails if the browser indicates so.
+pub async fn discard(tab_ids: &[TabId]) -> Result<(), CustomError> {
+    JsFuture::from(tab_discard(interop::to_jsvalue(tab_ids)))
+        .await
+        .map_err(|error| CustomError::FailedTabOperation {
+            verb: String::from("discard"),
+            details: interop::error_details(&error),
+        })?;
+    Ok(())
+}
+
+/// Gets all tabs that are in the container specified by the [CookieStoreId].
+/// Fails if the browser indicates so.
+pub async fn query_by_cookie_store_id(
+    cookie_store_id: &CookieStoreId,
+) -> Result<Vec<TabSummary>, CustomError> {
+    let query_obj = CookieStoreQuery {
+        cookie_store_id: cookie_store_id.clone(),
+    };
+    interop::cast_or_standard_mismatch(
+        JsFuture::from(tab_query(interop::to_jsvalue(&query_obj)))
+            .await
+            .map_err(|error| CustomError::FailedTabOperation {
+                verb: String::from("query"),
+                details: interop::error_details(&error),
+            })?,
+    )
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Properties passed to the mocked `tabs.create`.
+    static CREATED_TABS: std::cell::RefCell<Vec<JsValue>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Mocked `tabs.create` that records the properties,
+/// the created tab always has an ID of 1.
+#[cfg(test)]
+fn tab_create(create_properties: JsValue) -> Promise {
+    CREATED_TABS.with(|created_tabs| created_tabs.borrow_mut().push(create_properties));
+    Promise::resolve(&interop::to_jsvalue(&HashMap::from([("id", 1)])))
+}
+
+#[cfg(test)]
+pub mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    /// Properties received by `tabs.create`.
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct CreateProperties {
+        index: usize,
+        muted: bool,
+        open_in_reader_mode: Option<bool>,
+        pinned: bool,
+        url: Option<String>,
+    }
+
+    pub fn test_tab_properties(index: usize, pinned: bool, url: &str) -> TabProperties {
+        TabProperties {
+            active: true,
+            cookie_store_id: CookieStoreId::new(String::from("firefox-default")),
+            discarded: None,
+            fav_icon_url: None,
+            id: 0,
+            index,
+            muted_info: MutedInfo { muted: false },
+            opener_tab_id: None,
+            reader_mode: None,
+            pinned,
+            status: None,
+            title: None,
+            url: Some(String::from(url)),
+            window_id: WindowId::new(0),
+        }
+    }
+
+    pub fn test_opened_tab_properties(url: &str, opener_tab_id: isize) -> TabProperties {
+        TabProperties {
+            opener_tab_id: Some(TabId::new(opener_tab_id)),
+            ..test_tab_properties(0, false, url)
+        }
+    }
+
+    async fn create_properties(
+        mut tab_properties: TabProperties,
+    ) -> Result<CreateProperties, CustomError> {
+        assert!(TabId::new(1) == tab_properties.new_tab().await?);
+        let create_properties = CREATED_TABS
+            .with(|created_tabs| created_tabs.borrow_mut().pop())
+            .expect("tab created");
+        interop::cast_or_standard_mismatch(create_properties)
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_tab_properties_new_tab() -> Result<(), CustomError> {
+        let mut pinned = test_tab_properties(2, true, "https://example.com/");
+        pinned.muted_info = MutedInfo { muted: true };
+        assert_eq!(
+            CreateProperties {
+                index: 2,
+                muted: true,
+                open_in_reader_mode: None,
+                pinned: true,
+                url: Some(String::from("https://example.com/")),
+            },
+            create_properties(pinned).await?
+        );
+
+        let mut reader = test_tab_properties(
+            5,
+            false,
+            "about:reader?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc",
+        );
+        reader.reader_mode = Some(true);
+        assert_eq!(
+            CreateProperties {
+                index: 6,
+                muted: false,
+                open_in_reader_mode: Some(true),
+                pinned: false,
+                url: Some(String::from("https://example.com/a?b=c")),
+            },
+            create_properties(reader).await?
+        );
+
+        let mut retargeted = test_tab_properties(0, true, "https://example.com/");
+        retargeted.muted_info = MutedInfo { muted: true };
+        retargeted.reader_mode = Some(true);
+        retargeted.retarget(String::from("https://example.net/"), false);
+        assert_eq!(
+            CreateProperties {
+                index: 1,
+                muted: false,
+                open_in_reader_mode: None,
+                pinned: false,
+                url: Some(String::from("https://example.net/")),
+            },
+            create_properties(retargeted).await?
+        );
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_tab_properties_is_likely_external() {
+        let external = || TabProperties {
+            status: Some(String::from("loading")),
+            ..test_tab_properties(0, false, "about:blank")
+        };
+        assert!(external().is_likely_external());
+        let table = [
+            TabProperties {
+                opener_tab_id: Some(TabId::new(1)),
+                ..external()
+            },
+            TabProperties {
+                active: false,
+                ..external()
+            },
+            TabProperties {
+                discarded: Some(true),
+                ..external()
+            },
+            TabProperties {
+                status: Some(String::from("complete")),
+                ..external()
+            },
+            TabProperties {
+                status: None,
+                ..external()
+            },
+            TabProperties {
+                url: Some(String::from("about:newtab")),
+                ..external()
+            },
+        ];
+        for tab_properties in table {
+            assert!(!tab_properties.is_likely_external());
+        }
+    }
+}
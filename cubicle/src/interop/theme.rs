@@ -0,0 +1,85 @@
+//! Wrappers around the `browser.theme` API,
+//! for matching the views with the colors of the browser's theme.
+//! Most fails are represented by
+//! [FailedThemeOperation](CustomError::FailedThemeOperation).
+
+use js_sys::Promise;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "theme"], js_name="getCurrent")]
+    fn theme_get_current() -> Promise;
+}
+
+/// Color of a theme, either a CSS color string or RGB components.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeColor {
+    Css(String),
+    Rgb(Vec<f64>),
+}
+
+impl ThemeColor {
+    /// Converts the color to a CSS color,
+    /// [None] if the color contains characters that may escape a style sheet.
+    fn to_css(&self) -> Option<String> {
+        let css = match self {
+            Self::Css(css) => css.clone(),
+            Self::Rgb(components) => match components.as_slice() {
+                [red, green, blue] => format!("rgb({red}, {green}, {blue})"),
+                [red, green, blue, alpha] => format!("rgba({red}, {green}, {blue}, {alpha})"),
+                _ => return None,
+            },
+        };
+        css.chars()
+            .all(|char| char.is_ascii_alphanumeric() || "#(),.% ".contains(char))
+            .then_some(css)
+    }
+}
+
+/// Colors of the current theme that are relevant to the views,
+/// all colors are optional as themes may leave them as default.
+#[derive(Default, Deserialize)]
+pub struct ThemeColors {
+    popup: Option<ThemeColor>,
+    popup_text: Option<ThemeColor>,
+}
+
+impl ThemeColors {
+    /// Background color of pop-ups as a CSS color.
+    pub fn background(&self) -> Option<String> {
+        self.popup.as_ref().and_then(ThemeColor::to_css)
+    }
+
+    /// Text color of pop-ups as a CSS color.
+    pub fn text(&self) -> Option<String> {
+        self.popup_text.as_ref().and_then(ThemeColor::to_css)
+    }
+}
+
+/// Theme of the browser, deserializes from a `theme.Theme` instance.
+#[derive(Deserialize)]
+struct Theme {
+    #[serde(default)]
+    colors: Option<ThemeColors>,
+}
+
+/// Gets the colors of the current theme,
+/// which are all [None] for the default theme.
+/// Fails if the browser indicates so.
+pub async fn current_colors() -> Result<ThemeColors, CustomError> {
+    let theme =
+        JsFuture::from(theme_get_current())
+            .await
+            .or(Err(CustomError::FailedThemeOperation {
+                verb: String::from("get the current"),
+            }))?;
+    let theme: Theme = interop::cast_or_standard_mismatch(theme)?;
+    Ok(theme.colors.unwrap_or_default())
+}
@@ -0,0 +1,211 @@
+//! Wrappers around the `browser.webRequest` API for modifying request headers.
+//! The listener is only attached while some containers have headers to set,
+//! so that requests are not blocked otherwise.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::contextual_identities::CookieStoreId;
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(
+        js_namespace=["browser", "webRequest", "onBeforeSendHeaders"],
+        js_name="addListener"
+    )]
+    fn before_send_headers_add_listener(
+        listener: &HeaderListener,
+        filter: JsValue,
+        extra_info_spec: JsValue,
+    );
+    #[wasm_bindgen(
+        js_namespace=["browser", "webRequest", "onBeforeSendHeaders"],
+        js_name="removeListener"
+    )]
+    fn before_send_headers_remove_listener(listener: &HeaderListener);
+}
+
+/// Listener of `onBeforeSendHeaders` that returns a `BlockingResponse`.
+type HeaderListener = Closure<dyn Fn(JsValue) -> JsValue>;
+
+thread_local! {
+    /// Headers to set for requests in each container.
+    static HEADER_RULES: RefCell<HashMap<CookieStoreId, Vec<HttpHeader>>> =
+        RefCell::new(HashMap::new());
+    /// Attached listener, [None] if there are no headers to set.
+    static LISTENER: RefCell<Option<HeaderListener>> =
+        const { RefCell::new(None) };
+}
+
+/// Name and value of an HTTP header, serializes to an entry of `HttpHeaders`.
+/// Binary values are retained as is for headers of intercepted requests.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct HttpHeader {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary_value: Option<Vec<u8>>,
+}
+
+impl TryFrom<&str> for HttpHeader {
+    type Error = CustomError;
+
+    /// Parses a header in the format of `Name: value`.
+    /// Fails with [InvalidRequestHeader](CustomError::InvalidRequestHeader)
+    /// if the name is not a valid token or the value spans multiple lines.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let error = || CustomError::InvalidRequestHeader {
+            header: String::from(value),
+        };
+        let (name, header_value) = value.split_once(':').ok_or_else(error)?;
+        let is_token_char =
+            |char: char| char.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(char);
+        let header_value = header_value.trim();
+        if name.is_empty()
+            || !name.chars().all(is_token_char)
+            || header_value.contains(['\r', '\n'])
+        {
+            return Err(error());
+        }
+        Ok(Self {
+            name: String::from(name),
+            value: Some(String::from(header_value)),
+            binary_value: None,
+        })
+    }
+}
+
+impl Display for HttpHeader {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            formatter,
+            "{}: {}",
+            self.name,
+            self.value.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// Retained properties of the `details` of `onBeforeSendHeaders`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestDetails {
+    #[serde(default)]
+    cookie_store_id: Option<String>,
+    #[serde(default)]
+    request_headers: Vec<HttpHeader>,
+}
+
+/// Response of the listener, serializes to a `BlockingResponse`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockingResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_headers: Option<Vec<HttpHeader>>,
+}
+
+/// Request filter matching all requests, serializes to a `RequestFilter`.
+#[derive(Serialize)]
+struct RequestFilter {
+    urls: [&'static str; 1],
+}
+
+/// Replaces the headers to set for requests in each container,
+/// containers without headers should be omitted.
+/// Attaches the listener if there are headers to set, detaches it otherwise.
+pub fn replace_header_rules(rules: HashMap<CookieStoreId, Vec<HttpHeader>>) {
+    let should_listen = !rules.is_empty();
+    HEADER_RULES.with(|header_rules| *header_rules.borrow_mut() = rules);
+    LISTENER.with(|listener| {
+        let mut listener = listener.borrow_mut();
+        match (should_listen, listener.as_ref()) {
+            (true, None) => {
+                let closure = HeaderListener::new(on_before_send_headers);
+                before_send_headers_add_listener(
+                    &closure,
+                    interop::to_jsvalue(&RequestFilter {
+                        urls: ["<all_urls>"],
+                    }),
+                    interop::to_jsvalue(&["blocking", "requestHeaders"]),
+                );
+                *listener = Some(closure);
+            }
+            (false, Some(closure)) => {
+                before_send_headers_remove_listener(closure);
+                *listener = None;
+            }
+            _ => (),
+        }
+    });
+}
+
+/// Sets the headers configured for the container of the request.
+/// Returns an empty `BlockingResponse` if the request is not in such container.
+fn on_before_send_headers(details: JsValue) -> JsValue {
+    let request_headers = interop::cast_or_standard_mismatch::<RequestDetails>(details)
+        .ok()
+        .and_then(|details| {
+            let cookie_store_id = CookieStoreId::new(details.cookie_store_id?);
+            HEADER_RULES.with(|header_rules| {
+                let header_rules = header_rules.borrow();
+                let headers = header_rules.get(&cookie_store_id)?;
+                Some(merge_headers(details.request_headers, headers))
+            })
+        });
+    interop::to_jsvalue(&BlockingResponse { request_headers })
+}
+
+/// Replaces request headers with the same names as the given headers
+/// case-insensitively, then appends the given headers.
+fn merge_headers(mut request_headers: Vec<HttpHeader>, headers: &[HttpHeader]) -> Vec<HttpHeader> {
+    request_headers.retain(|request_header| {
+        !headers
+            .iter()
+            .any(|header| header.name.eq_ignore_ascii_case(&request_header.name))
+    });
+    request_headers.extend(headers.iter().cloned());
+    request_headers
+}
+
+#[cfg(test)]
+pub mod test {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn test_http_header_parse() -> Result<(), CustomError> {
+        let header = HttpHeader::try_from("X-Work-Env:  production ")?;
+        assert_eq!("X-Work-Env: production", header.to_string());
+        for invalid_header in ["DNT", ": 1", "Bad Name: 1", "DNT: 1\r\nX-Injected: 1"] {
+            assert!(HttpHeader::try_from(invalid_header).is_err());
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    fn test_merge_headers() -> Result<(), CustomError> {
+        let request_headers = vec![
+            HttpHeader::try_from("Accept: */*")?,
+            HttpHeader::try_from("dnt: 0")?,
+        ];
+        let merged = merge_headers(request_headers, &[HttpHeader::try_from("DNT: 1")?]);
+        assert_eq!(
+            vec![
+                HttpHeader::try_from("Accept: */*")?,
+                HttpHeader::try_from("DNT: 1")?
+            ],
+            merged
+        );
+        Ok(())
+    }
+}
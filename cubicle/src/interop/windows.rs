@@ -0,0 +1,140 @@
+//! Wrappers around the `browser.windows` API.
+//! Most fails are represented by
+//! [FailedWindowOperation](CustomError::FailedWindowOperation).
+
+use std::collections::HashMap;
+
+use js_sys::{Object, Promise};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use super::tabs::TabId;
+use crate::interop;
+use crate::util::errors::CustomError;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace=["browser", "windows"], js_name="create")]
+    fn window_create(create_data: JsValue) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "windows"], js_name="getLastFocused")]
+    fn window_get_last_focused() -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "windows"], js_name="update")]
+    fn window_update(window_id: isize, update_info: JsValue) -> Promise;
+    #[wasm_bindgen(js_namespace=["browser", "windows"], js_name="remove")]
+    fn window_remove(window_id: isize) -> Promise;
+}
+
+/// Unique identifier that allow operations on specific windows.
+/// All operations may fail if the window specified by the ID does not exist.
+#[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct WindowId {
+    inner: isize,
+}
+
+impl WindowId {
+    /// Creates a new ID by trusting the given value.
+    pub fn new(window_id: isize) -> Self {
+        Self { inner: window_id }
+    }
+
+    /// Brings the specified window to the front,
+    /// fails if the browser indicates so.
+    pub async fn focus(&self) -> Result<(), CustomError> {
+        let update_info = interop::to_jsvalue(&HashMap::from([("focused", true)]));
+        JsFuture::from(window_update(self.inner, update_info))
+            .await
+            .map_err(|error| CustomError::FailedWindowOperation {
+                verb: String::from("focus"),
+                details: interop::error_details(&error),
+            })?;
+        Ok(())
+    }
+
+    /// Closes the specified window along with its tabs,
+    /// fails if the browser indicates so.
+    pub async fn close(&self) -> Result<(), CustomError> {
+        JsFuture::from(window_remove(self.inner))
+            .await
+            .map_err(|error| CustomError::FailedWindowOperation {
+                verb: String::from("close"),
+                details: interop::error_details(&error),
+            })?;
+        Ok(())
+    }
+}
+
+/// Properties of a new window, serializes to a `createData` instance.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateData<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tab_id: Option<&'a TabId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    window_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title_preface: Option<String>,
+}
+
+/// Creates a window by moving the specified tab into it,
+/// the title of the window is prefixed if a preface is given.
+/// Fails if the browser indicates so.
+pub async fn create_with_tab(
+    tab_id: &TabId,
+    title_preface: Option<String>,
+) -> Result<WindowId, CustomError> {
+    create(CreateData {
+        tab_id: Some(tab_id),
+        url: None,
+        window_type: None,
+        title_preface,
+    })
+    .await
+}
+
+/// Creates a pop-up window without browser controls that shows the URL,
+/// relative URLs are resolved against the extension's root.
+/// Fails if the browser indicates so.
+pub async fn create_popup(url: &str) -> Result<WindowId, CustomError> {
+    create(CreateData {
+        tab_id: None,
+        url: Some(url),
+        window_type: Some("popup"),
+        title_preface: None,
+    })
+    .await
+}
+
+/// Creates a window with the properties,
+/// fails if the browser indicates so.
+async fn create(create_data: CreateData<'_>) -> Result<WindowId, CustomError> {
+    let window = JsFuture::from(window_create(interop::to_jsvalue(&create_data)))
+        .await
+        .map_err(|error| CustomError::FailedWindowOperation {
+            verb: String::from("create"),
+            details: interop::error_details(&error),
+        })?;
+    let window = Object::try_from(&window).ok_or(CustomError::StandardMismatch {
+        message: String::from("expected `window` to be an object"),
+    })?;
+    interop::cast_or_standard_mismatch(interop::get_or_standard_mismatch(window, "id")?)
+}
+
+/// Gets the [WindowId] of the window that was most recently focused,
+/// which is the window of the pop-up if it is open.
+/// Fails if the browser indicates so.
+pub async fn last_focused_window_id() -> Result<WindowId, CustomError> {
+    let window = JsFuture::from(window_get_last_focused())
+        .await
+        .map_err(|error| CustomError::FailedWindowOperation {
+            verb: String::from("get the last focused"),
+            details: interop::error_details(&error),
+        })?;
+    let window = Object::try_from(&window).ok_or(CustomError::StandardMismatch {
+        message: String::from("expected `window` to be an object"),
+    })?;
+    interop::cast_or_standard_mismatch(interop::get_or_standard_mismatch(window, "id")?)
+}
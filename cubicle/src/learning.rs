@@ -0,0 +1,140 @@
+//! Opt-in learning of suffixes from manual reopening of tabs,
+//! which proposes adding a suffix to a container once tabs of the suffix
+//! are repeatedly reopened in that container.
+//! Proposals are rate-limited per suffix and container.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::suffix::Suffix;
+use crate::domain::IdnaPolicy;
+use crate::interop::contextual_identities::CookieStoreId;
+
+/// Key of the reopening statistics in the storage.
+pub const SUFFIX_LEARNING_KEY: &str = "suffix_learning";
+
+/// Number of reopening before a suffix is proposed.
+const PROPOSAL_THRESHOLD: u32 = 3;
+
+/// Minimum interval between proposals of the same suffix and container.
+const PROPOSAL_INTERVAL: Duration = Duration::days(1);
+
+/// Prefix of the IDs of proposal notifications.
+const NOTIFICATION_PREFIX: &str = "suffix-learning:";
+
+/// Statistics of reopening for a suffix in a container.
+#[derive(Default, Deserialize, Serialize)]
+struct ReopenCount {
+    count: u32,
+    last_proposed: Option<DateTime<Utc>>,
+}
+
+/// Statistics of tabs manually reopened in containers by their suffixes.
+#[derive(Default, Deserialize, Serialize)]
+pub struct SuffixLearning {
+    reopens: BTreeMap<Suffix, HashMap<CookieStoreId, ReopenCount>>,
+}
+
+impl SuffixLearning {
+    /// Records that a tab of the suffix was reopened in the container.
+    /// Returns whether the suffix should be proposed now,
+    /// in which case the count restarts.
+    pub fn record(
+        &mut self,
+        suffix: Suffix,
+        cookie_store_id: CookieStoreId,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let reopen_count = self
+            .reopens
+            .entry(suffix)
+            .or_default()
+            .entry(cookie_store_id)
+            .or_default();
+        reopen_count.count += 1;
+        if reopen_count.count < PROPOSAL_THRESHOLD {
+            return false;
+        }
+        let is_limited = match reopen_count.last_proposed {
+            Some(last_proposed) => now - last_proposed < PROPOSAL_INTERVAL,
+            None => false,
+        };
+        if is_limited {
+            return false;
+        }
+        reopen_count.count = 0;
+        reopen_count.last_proposed = Some(now);
+        true
+    }
+
+    /// Forgets the statistics of the suffix, such as once it is added.
+    pub fn forget(&mut self, suffix: &Suffix) {
+        self.reopens.remove(suffix);
+    }
+}
+
+/// ID of the notification proposing the suffix for the container.
+pub fn notification_id(suffix: &Suffix, cookie_store_id: &CookieStoreId) -> String {
+    format!(
+        "{NOTIFICATION_PREFIX}{}:{}",
+        cookie_store_id.encoded(),
+        suffix.encoded()
+    )
+}
+
+/// Parses the ID of a proposal notification,
+/// [None] if the notification is not a proposal.
+pub fn parse_notification_id(notification_id: &str) -> Option<(Suffix, CookieStoreId)> {
+    let (encoded_id, suffix) = notification_id
+        .strip_prefix(NOTIFICATION_PREFIX)?
+        .split_once(':')?;
+    let cookie_store_id = CookieStoreId::from_encoded(encoded_id)?;
+    let suffix = Suffix::parse(suffix, &IdnaPolicy::Lenient).ok()?;
+    Some((suffix, cookie_store_id))
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::domain::suffix::SuffixType;
+    use crate::domain::EncodedDomain;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_suffix_learning_record() {
+        let suffix = Suffix::new(
+            SuffixType::Normal,
+            EncodedDomain::try_from("example.com").unwrap(),
+        );
+        let cookie_store_id = CookieStoreId::new(String::from("firefox-container-1"));
+        let now = Utc::now();
+        let mut learning = SuffixLearning::default();
+
+        let mut record = |now| learning.record(suffix.clone(), cookie_store_id.clone(), now);
+        assert!(!record(now));
+        assert!(!record(now));
+        assert!(record(now));
+        for _ in 0..PROPOSAL_THRESHOLD {
+            assert!(!record(now + Duration::hours(1)));
+        }
+        assert!(record(now + PROPOSAL_INTERVAL));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_notification_id() {
+        let suffix = Suffix::new(
+            SuffixType::Glob,
+            EncodedDomain::try_from("example.com").unwrap(),
+        );
+        let cookie_store_id = CookieStoreId::new(String::from("firefox-container-1"));
+        let id = notification_id(&suffix, &cookie_store_id);
+        assert!(parse_notification_id(&id) == Some((suffix, cookie_store_id)));
+        assert!(parse_notification_id("1").is_none());
+    }
+}
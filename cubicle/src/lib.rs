@@ -7,47 +7,172 @@ wasm_bindgen_test_configure!(run_in_worker);
 
 pub mod container;
 pub mod context;
+pub mod crash;
 pub mod domain;
+pub mod focus;
+pub mod guest;
 pub mod interop;
+pub mod learning;
 pub mod message;
 pub mod migrate;
+pub mod policy;
 pub mod preferences;
+pub mod rewrite;
+pub mod rules;
+pub mod session;
+pub mod statistics;
 pub mod tab;
+pub mod transaction;
 pub mod util;
 
-use std::panic;
+use std::ops::DerefMut;
+use std::time::Duration;
+use std::{iter, mem, panic};
 
-use async_std::sync::Mutex;
-use js_sys::JsString;
+use async_std::future;
+use async_std::sync::{Mutex, MutexGuard};
+use async_std::task;
+use chrono::Utc;
+use js_sys::{JsString, JSON};
 use once_cell::sync::Lazy;
 use wasm_bindgen::prelude::*;
 
-use crate::container::{ContainerHandle, ContainerVariant};
-use crate::context::GlobalContext;
+use crate::container::{Container, ContainerHandle, ContainerKey, ContainerVariant};
+use crate::context::{GlobalContext, ParsedPsl};
+use crate::crash::CrashReport;
+use crate::domain::suffix::{Suffix, SuffixType};
+use crate::domain::EncodedDomain;
+use crate::interop::commands::Command;
+use crate::interop::contextual_identities::{CookieStoreId, IdentityDetailsProvider};
+use crate::interop::devtools::{DeterminantInspection, TabInspection};
+use crate::interop::menus::MenuItem;
+use crate::interop::omnibox::{self, OnInputEnteredDisposition, SuggestResult};
+use crate::interop::runtime::MessageSender;
 use crate::interop::tabs::{TabId, TabProperties};
-use crate::message::Message;
-use crate::tab::{ManagedTabs, RelocationDetail, TabDeterminant};
+use crate::interop::windows::{self, WindowId};
+use crate::interop::{i18n, notifications, storage, tabs};
+use crate::message::{ExternalMessage, Message};
+use crate::rewrite::RewriteRule;
+use crate::rules::{Decision, DecisionTrace, Designation};
+use crate::tab::{ManagedTabs, PromptChoice, RelocationDetail, TabDeterminant};
 use crate::util::errors::CustomError;
+use crate::util::lock::{InstrumentedMutex, LockStats};
+use crate::util::log;
+use crate::util::retry::{self, Backoff};
+
+/// Backoff for relocating a tab again if its container was deleted
+/// while the tab was being created in it.
+const RELOCATION_BACKOFF: Backoff = Backoff {
+    attempts: 2,
+    initial_delay: Duration::ZERO,
+    factor: 1,
+};
+
+/// Duration to wait for the user's choice when the relocation is prompted,
+/// after which a new temporary container is used.
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Entry point for loading this extension.
 /// Mainly to load or populate a [GlobalContext].
 #[wasm_bindgen(start)]
 async fn start() -> Result<(), JsError> {
-    panic::set_hook(Box::new(console_error_panic_hook::hook));
-    let mut global_context = GLOBAL_CONTEXT.lock().await;
-    *global_context = GlobalContext::from_storage()
+    panic::set_hook(Box::new(|panic_info| {
+        console_error_panic_hook::hook(panic_info);
+        let global_context = GLOBAL_CONTEXT.try_lock();
+        let report = CrashReport::new(panic_info.to_string(), global_context.as_deref());
+        crash::record(&report);
+    }));
+    load_global_context(&mut *GLOBAL_CONTEXT.lock().await)
         .await
-        .map_err(|error: CustomError| JsError::new(&error.to_string()))?;
-    Ok(())
+        .map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Replaces the context with one loaded from the storage,
+/// the context is marked as degraded instead if loading fails.
+pub(crate) async fn load_global_context(
+    global_context: &mut GlobalContext,
+) -> Result<(), CustomError> {
+    let mut managed_tabs = MANAGED_TABS.lock().await;
+    match GlobalContext::from_storage(&mut managed_tabs).await {
+        Ok(loaded_context) => {
+            *global_context = loaded_context;
+            wasm_bindgen_futures::spawn_local(hydrate_psl());
+            Ok(())
+        }
+        Err(error) => {
+            global_context.is_degraded = true;
+            log::warn!("failed to load containers, {error}");
+            Err(error)
+        }
+    }
 }
 
 /// Persisting data for determining which container to switch to.
-static GLOBAL_CONTEXT: Lazy<Mutex<GlobalContext>> =
-    Lazy::new(|| Mutex::new(GlobalContext::default()));
+/// Contention is counted for health checks.
+static GLOBAL_CONTEXT: Lazy<InstrumentedMutex<GlobalContext>> =
+    Lazy::new(|| InstrumentedMutex::new(GlobalContext::default()));
 
 /// Managed tabs lookup for quick interception.
 static MANAGED_TABS: Lazy<Mutex<ManagedTabs>> = Lazy::new(|| Mutex::new(ManagedTabs::default()));
 
+/// Loads the public suffix list left pending by [load_global_context],
+/// after yielding to tab events that arrived while the context was loading.
+/// The list is parsed without holding the lock.
+async fn hydrate_psl() {
+    task::yield_now().await;
+    let pending_source = GLOBAL_CONTEXT.lock().await.pending_psl_source().await;
+    let psl = match pending_source {
+        Ok(Some(source)) => source.parse().await,
+        Ok(None) => return,
+        Err(error) => Err(error),
+    };
+    match psl {
+        Ok(psl) => GLOBAL_CONTEXT.lock().await.hydrate_psl(psl),
+        Err(error) => log::warn!("failed to load public suffix list, {error}"),
+    }
+}
+
+/// Updates the public suffix list from the URL, or the bundled list if [None].
+/// Only the changes from the current list are parsed where possible.
+/// The list is parsed without holding the lock,
+/// so that other messages and tab events are handled meanwhile.
+/// Returns the date of the new list.
+async fn update_psl(url: Option<String>) -> Result<String, CustomError> {
+    let base = GLOBAL_CONTEXT.lock().await.current_psl_source().await?;
+    let (parsed, source) = message::fetch_psl(url, base).await?;
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+    if !global_context.update_psl(parsed, &source).await? {
+        drop(global_context);
+        log::info!("public suffix list changed during the update, parsing in full");
+        let psl = source.parse().await?;
+        global_context = GLOBAL_CONTEXT.lock().await;
+        global_context
+            .update_psl(ParsedPsl::Full(psl), &source)
+            .await?;
+    }
+    Ok(global_context.psl.last_updated().to_string())
+}
+
+/// Suggests suffixes for a container from the browser history.
+/// The history is queried without holding the lock, as it can be slow,
+/// the lock is only held for excluding the claimed suffixes.
+/// Returns the suffixes as a JSON array.
+async fn suggest_suffixes(cookie_store_id: &CookieStoreId) -> Result<String, CustomError> {
+    let windows = message::history_windows(cookie_store_id).await?;
+    let suffixes = GLOBAL_CONTEXT
+        .lock()
+        .await
+        .rank_suggested_suffixes(&windows);
+    Ok(JSON::stringify(&interop::to_jsvalue(&suffixes))
+        .map(String::from)
+        .unwrap_or_default())
+}
+
+/// Counts of locking the [GlobalContext] since the extension was loaded.
+pub(crate) fn context_lock_stats() -> LockStats {
+    GLOBAL_CONTEXT.stats()
+}
+
 /// Message passing function for user actions other than tab changes.
 /// See [Message] for all possible message types.
 /// Returns and failures are specific to the message types.
@@ -55,18 +180,63 @@ static MANAGED_TABS: Lazy<Mutex<ManagedTabs>> = Lazy::new(|| Mutex::new(ManagedT
 pub async fn on_message(message: JsValue) -> Result<JsString, JsError> {
     let message =
         serde_wasm_bindgen::from_value::<Message>(message).expect("unexpected message format");
-    message
-        .act(&mut GLOBAL_CONTEXT.lock().await)
-        .await
-        .map(JsString::from)
-        .map_err(|error| JsError::new(&error.to_string()))
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+    match message {
+        Message::RetryLoad if global_context.is_degraded => {
+            load_global_context(&mut global_context)
+                .await
+                .map(|_| String::default())
+        }
+        Message::PslUpdate { url }
+            if !global_context.is_degraded && global_context.guest_mode.is_none() =>
+        {
+            drop(global_context);
+            update_psl(url).await
+        }
+        Message::SuggestSuffixes { cookie_store_id } => {
+            drop(global_context);
+            suggest_suffixes(&cookie_store_id).await
+        }
+        message => message.act(&mut global_context).await,
+    }
+    .map(JsString::from)
+    .map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Message passing function for requests from other extensions.
+/// See [ExternalMessage] for all possible message types.
+/// Fails with [UntrustedSender](CustomError::UntrustedSender) if the sender
+/// is not allowed by the preferences, or if the message is malformed.
+#[wasm_bindgen(js_name = "onMessageExternal")]
+pub async fn on_message_external(message: JsValue, sender: JsValue) -> Result<JsString, JsError> {
+    async {
+        let sender = interop::cast_or_standard_mismatch::<MessageSender>(sender)?;
+        let mut global_context = GLOBAL_CONTEXT.lock().await;
+        if !global_context.preferences.trusts_extension(sender.id()) {
+            return Err(CustomError::UntrustedSender {
+                sender: String::from(sender.id().unwrap_or_default()),
+            });
+        }
+        let message = interop::cast_or_standard_mismatch::<ExternalMessage>(message)?;
+        message.act(&mut global_context).await
+    }
+    .await
+    .map(JsString::from)
+    .map_err(|error: CustomError| JsError::new(&error.to_string()))
 }
 
 /// Intercepts the tabs for container operations.
 /// First stop the tab loading, and recreate the tab if a container switch
 /// is required, reload the tab otherwise.
+/// The relocation is retried if the matched container was deleted meanwhile.
+/// Tabs that are not intercepted mark their containers as used
+/// if their URLs have changed.
 #[wasm_bindgen(js_name = "onTabUpdated")]
-pub async fn on_tab_updated(tab_id: isize, tab_properties: JsValue) -> Result<(), JsError> {
+pub async fn on_tab_updated(
+    tab_id: isize,
+    tab_properties: JsValue,
+    is_url_changed: bool,
+) -> Result<(), JsError> {
     {
         let tab_id = TabId::new(tab_id);
         let tab_properties = interop::cast_or_standard_mismatch::<TabProperties>(tab_properties)?;
@@ -76,50 +246,340 @@ pub async fn on_tab_updated(tab_id: isize, tab_properties: JsValue) -> Result<()
             .await
             .check_relocation(tab_id.clone(), &tab_properties)
         else {
+            if is_url_changed {
+                mark_navigated(&tab_properties.cookie_store_id).await;
+            }
             return Ok(());
         };
-        drop(tab_id.stop_loading().await);
-
-        let mut global_context = GLOBAL_CONTEXT.lock().await;
+        log::debug!(
+            "intercepted tab {tab_id} navigating to `{}`",
+            relocation_detail.new_domain.raw()
+        );
+        if let Some(trace) = simulate_if_dry_run(&tab_properties, &relocation_detail).await {
+            log::info!("simulated relocation of tab {tab_id}, {trace}");
+            return Ok(());
+        }
+        if let Err(error) = tab_id.stop_loading().await {
+            log::warn!("tab {tab_id} kept loading while intercepted, {error}");
+        }
 
-        let Some(relocation_detail) = ContainerVariant::on_pre_relocation(
-            &mut global_context.containers,
-            &tab_id,
-            relocation_detail,
+        let mut tab_properties = Some(tab_properties);
+        retry::retry(
+            &RELOCATION_BACKOFF,
+            || {
+                let tab_id = &tab_id;
+                let tab_properties = tab_properties.take();
+                let relocation_detail = relocation_detail.clone();
+                async move {
+                    let tab_properties = match tab_properties {
+                        Some(tab_properties) => tab_properties,
+                        None => tab_id.properties().await?,
+                    };
+                    relocate_tab(tab_id, tab_properties, relocation_detail).await
+                }
+            },
+            |error| matches!(error, CustomError::DeletedContainer { .. }),
+            interop::sleep,
         )
-        .await?
-        else {
-            return Ok(());
-        };
+        .await
+    }
+    .map_err(|error: CustomError| JsError::new(&error.to_string()))
+}
 
-        let eject_strategy = global_context.preferences.eject_strategy.clone();
-        let assign_strategy = global_context.preferences.assign_strategy.clone();
-        let should_revert_old_tab = global_context.preferences.should_revert_old_tab;
+/// Records that a tab in the container has navigated,
+/// the time is persisted at most once a minute per container.
+/// The container is only borrowed mutably if the time is recorded,
+/// as that reindexes its suffixes.
+async fn mark_navigated(cookie_store_id: &CookieStoreId) {
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+    let should_record = global_context
+        .containers
+        .get(cookie_store_id)
+        .is_some_and(|container| !container.was_used_recently());
+    if !should_record {
+        return;
+    }
+    let mut container = global_context
+        .containers
+        .get_mut(cookie_store_id.clone())
+        .expect("container checked above");
+    container.mark_used();
+    storage::queue_single_entry(&ContainerKey::from(cookie_store_id), &*container);
+}
 
-        let container_handle = if relocation_detail.opener_is_managed {
-            eject_strategy
-                .match_container(
-                    &mut global_context,
+/// Decides where an intercepted tab would be relocated without relocating it,
+/// if relocations are only simulated according to the preferences.
+/// [None] if the tab should be relocated.
+async fn simulate_if_dry_run(
+    tab_properties: &TabProperties,
+    relocation_detail: &RelocationDetail,
+) -> Option<DecisionTrace> {
+    let global_context = GLOBAL_CONTEXT.lock().await;
+    if !global_context.preferences.should_simulate_relocation {
+        return None;
+    }
+    let current_container = relocation_detail
+        .opener_is_managed
+        .then_some(&relocation_detail.current_cookie_store_id);
+    Some(
+        global_context
+            .simulate_relocation(
+                tab_properties.url()?,
+                current_container,
+                relocation_detail.opener_cookie_store_id.clone(),
+            )
+            .await,
+    )
+}
+
+/// Relocates an intercepted tab to the container matched for its new domain,
+/// and notifies the user if enabled.
+/// Fails with [DeletedContainer](CustomError::DeletedContainer)
+/// if the container was deleted while the tab was being created in it,
+/// or if any tab operation failed.
+async fn relocate_tab(
+    tab_id: &TabId,
+    tab_properties: TabProperties,
+    relocation_detail: RelocationDetail,
+) -> Result<(), CustomError> {
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+
+    let Some(relocation_detail) = ContainerVariant::on_pre_relocation(
+        &mut global_context.containers,
+        tab_id,
+        relocation_detail,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let eject_strategy = global_context.preferences.eject_strategy.clone();
+    let assign_chain = global_context.preferences.assign_chain.clone();
+    let should_revert_old_tab = global_context.preferences.should_revert_old_tab;
+    let should_notify_relocation = global_context.preferences.should_notify_relocation;
+
+    let mut designated_container = relocation_detail
+        .is_new_tab
+        .then(|| global_context.window_container(tab_properties.window_id()))
+        .flatten()
+        .map(|handle| (handle, Designation::WindowDefault));
+    if let (None, Some(url)) = (&designated_container, tab_properties.url()) {
+        designated_container = global_context
+            .bookmark_container(url)
+            .await
+            .map(|handle| (handle, Designation::BookmarkBinding));
+    }
+    if let (None, true) = (&designated_container, relocation_detail.is_external) {
+        let target = global_context
+            .preferences
+            .external_link_target(relocation_detail.new_domain.clone())
+            .clone();
+        designated_container = target
+            .match_container(&mut global_context)
+            .await?
+            .map(|handle| (handle, Designation::ExternalLink));
+    }
+    let (container_handle, trace) =
+        match (designated_container, relocation_detail.opener_is_managed) {
+            (Some((handle, designation)), true) => {
+                let trace = global_context.rules_engine().trace_designated(
                     relocation_detail.new_domain.clone(),
-                    &relocation_detail.current_cookie_store_id,
-                    assign_strategy,
+                    handle.cookie_store_id(),
+                    designation,
+                    Some(&relocation_detail.current_cookie_store_id),
+                );
+                (handle, trace)
+            }
+            (None, true) => {
+                let current_container = &relocation_detail.current_cookie_store_id;
+                let mut trace = global_context
+                    .rules_engine()
+                    .opened_from(relocation_detail.opener_cookie_store_id.clone())
+                    .trace_eject(
+                        &eject_strategy,
+                        &assign_chain,
+                        relocation_detail.new_domain.clone(),
+                        current_container,
+                    );
+                if trace.decision == Decision::Prompt {
+                    drop(global_context);
+                    let Some(decision) = prompt_relocation(tab_id, &relocation_detail).await else {
+                        return Ok(());
+                    };
+                    global_context = GLOBAL_CONTEXT.lock().await;
+                    trace = global_context.rules_engine().trace_prompted(
+                        trace,
+                        decision,
+                        Some(current_container),
+                    );
+                }
+                let handle = preferences::realize_decision(
+                    &mut global_context,
+                    trace.decision.clone(),
+                    Some(current_container),
                 )
-                .await?
-        } else {
-            assign_strategy
-                .match_container(&mut global_context, relocation_detail.new_domain.clone())
-                .await?
+                .await?;
+                (handle, trace)
+            }
+            (designated_container, false) => {
+                let mut rules_engine = global_context.rules_engine();
+                if let Some((handle, designation)) = &designated_container {
+                    rules_engine = rules_engine
+                        .designate(Some(handle.cookie_store_id().clone()), *designation);
+                }
+                let mut trace =
+                    rules_engine.trace_assign(&assign_chain, relocation_detail.new_domain.clone());
+                if trace.decision == Decision::Prompt {
+                    drop(global_context);
+                    let decision = prompt_relocation(tab_id, &relocation_detail).await;
+                    global_context = GLOBAL_CONTEXT.lock().await;
+                    let Some(decision) = decision else {
+                        release_designated(&mut global_context, designated_container, tab_id).await;
+                        return Ok(());
+                    };
+                    trace = global_context
+                        .rules_engine()
+                        .trace_prompted(trace, decision, None);
+                }
+                let handle = preferences::realize_decision(
+                    &mut global_context,
+                    trace.decision.clone(),
+                    None,
+                )
+                .await;
+                release_designated(&mut global_context, designated_container, tab_id).await;
+                (handle?, trace)
+            }
+        };
+    let mut container_name = String::default();
+    let mut rewrite_rules = Vec::default();
+    let mut is_temporary = false;
+    if let Some(mut container) = global_context
+        .containers
+        .get_mut(container_handle.cookie_store_id().clone())
+    {
+        container.mark_used();
+        container_name = container.identity_details().name;
+        rewrite_rules = container.rewrite_rules.clone();
+        is_temporary = container.variant == ContainerVariant::Temporary;
+        let key = ContainerKey::from(container_handle.cookie_store_id());
+        storage::queue_single_entry(&key, &*container);
+    }
+    if is_temporary {
+        record_temporary_relocation(&mut global_context, relocation_detail.new_domain.clone());
+    }
+    drop(global_context);
+
+    let new_domain = relocation_detail.new_domain.clone();
+    let new_tab_id = assign_tab(
+        tab_id.clone(),
+        tab_properties,
+        container_handle,
+        Some(relocation_detail.new_domain),
+        relocation_detail.old_domain,
+        should_revert_old_tab,
+        &rewrite_rules,
+    )
+    .await?;
+    if let Some(new_tab_id) = &new_tab_id {
+        log::info!(
+            "relocated tab {tab_id} to tab {new_tab_id} in container `{container_name}` for `{}`, {trace}",
+            new_domain.raw()
+        );
+    }
+    let traced_tab_id = new_tab_id.clone().unwrap_or_else(|| tab_id.clone());
+    MANAGED_TABS
+        .lock()
+        .await
+        .record_decision_trace(traced_tab_id, trace);
+    if let (Some(new_tab_id), true) = (new_tab_id, should_notify_relocation) {
+        let message = i18n::get_message(
+            "notification_relocation_message",
+            &[String::from(new_domain.raw()), container_name],
+        );
+        if let Err(error) = notifications::create_basic(
+            &new_tab_id.to_string(),
+            i18n::get_message("notification_relocation_title", &[]),
+            message,
+        )
+        .await
+        {
+            log::warn!("relocation of tab {new_tab_id} was not notified, {error}");
+        }
+    }
+    Ok(())
+}
+
+/// Counts a relocation into a temporary container by the registrable domain,
+/// for reporting domains that may deserve a permanent container.
+fn record_temporary_relocation(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    domain: EncodedDomain,
+) {
+    if domain.encoded().is_empty() {
+        return;
+    }
+    let registrable_domain = global_context
+        .psl
+        .match_suffix(domain.clone())
+        .unwrap_or(domain);
+    global_context.temporary_statistics.record(
+        Suffix::new(SuffixType::Normal, registrable_domain),
+        Utc::now().date_naive(),
+    );
+    storage::queue_large_entry(
+        statistics::TEMPORARY_STATISTICS_KEY,
+        &global_context.temporary_statistics,
+    );
+}
+
+/// Moves a tab opened to the new tab page into the preferred container,
+/// by recreating it before it navigates anywhere.
+/// Tabs [likely opened from other applications](TabProperties::is_likely_external)
+/// are marked so that they are routed once they navigate.
+/// Tabs opened from other tabs or already in a container are left alone.
+/// Fails if any tab operation failed.
+#[wasm_bindgen(js_name = "onTabCreated")]
+pub async fn on_tab_created(tab_properties: JsValue) -> Result<(), JsError> {
+    {
+        let mut tab_properties =
+            interop::cast_or_standard_mismatch::<TabProperties>(tab_properties)?;
+        if tab_properties.opener_tab_id().is_some()
+            || tab_properties.cookie_store_id != CookieStoreId::default()
+        {
+            return Ok(());
+        }
+        if tab_properties.is_likely_external() {
+            MANAGED_TABS.lock().await.mark_external(tab_properties.id());
+            return Ok(());
+        }
+        if !tab_properties.is_new_tab_page() {
+            return Ok(());
+        }
+
+        let mut global_context = GLOBAL_CONTEXT.lock().await;
+        let new_tab_container = global_context.preferences.new_tab_container.clone();
+        let Some(container_handle) = new_tab_container
+            .match_container(&mut global_context)
+            .await?
+        else {
+            return Ok(());
         };
         drop(global_context);
 
+        tab_properties.open_new_tab_page();
         assign_tab(
-            tab_id,
+            tab_properties.id(),
             tab_properties,
             container_handle,
-            relocation_detail,
-            should_revert_old_tab,
+            None,
+            None,
+            false,
+            &[],
         )
-        .await
+        .await?;
+        Ok(())
     }
     .map_err(|error: CustomError| JsError::new(&error.to_string()))
 }
@@ -130,51 +590,696 @@ pub async fn on_tab_updated(tab_id: isize, tab_properties: JsValue) -> Result<()
 #[wasm_bindgen(js_name = "onTabRemoved")]
 pub async fn on_tab_removed(tab_id: isize) {
     let tab_id = TabId::new(tab_id);
-    let Some(tab_det) = MANAGED_TABS.lock().await.unregister(&tab_id) else {
+    let mut managed_tabs = MANAGED_TABS.lock().await;
+    let Some(tab_det) = managed_tabs.unregister(&tab_id) else {
         return;
     };
-    let cookie_store_id = tab_det.container_handle.cookie_store_id().clone();
-    tab_det.container_handle.finish();
-    drop(tab_det);
+    let origin_det = managed_tabs.take_relocation_origin(&tab_id);
+    drop(managed_tabs);
     let mut global_context = GLOBAL_CONTEXT.lock().await;
-    drop(ContainerVariant::on_handle_drop(&mut global_context.containers, cookie_store_id).await);
+    for tab_det in iter::once(tab_det).chain(origin_det) {
+        let cookie_store_id = tab_det.container_handle.cookie_store_id().clone();
+        tab_det.container_handle.finish();
+        drop(tab_det);
+        if let Err(error) =
+            ContainerVariant::on_handle_drop(&mut global_context.containers, cookie_store_id).await
+        {
+            log::warn!("container of closed tab {tab_id} was not cleaned up, {error}");
+        }
+    }
 }
 
-/// Switchs the tab to a [Container](crate::container::Container).
+/// Synchronizes the [GlobalContext] with changes to the local storage,
+/// such as those written directly by extension pages.
+/// Fails if some changed values are not compatible.
+#[wasm_bindgen(js_name = "onStorageChanged")]
+pub async fn on_storage_changed(changes: JsValue, area_name: String) -> Result<(), JsError> {
+    if area_name != "local" {
+        return Ok(());
+    }
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+    global_context
+        .apply_storage_changes(storage::new_values(&changes))
+        .map_err(|error| JsError::new(&error.to_string()))?;
+    global_context.sync_header_rules();
+    sync_managed_tabs(&global_context).await;
+    Ok(())
+}
+
+/// Copies the configuration of the context to the [ManagedTabs]
+/// and the log level, see [GlobalContext::configure_managed_tabs].
+pub(crate) async fn sync_managed_tabs(global_context: &GlobalContext) {
+    global_context.configure_managed_tabs(&mut *MANAGED_TABS.lock().await);
+    log::set_level(global_context.preferences.log_level);
+}
+
+/// Detects conflicting extensions again when any extension is enabled,
+/// disabled, installed or uninstalled.
+#[wasm_bindgen(js_name = "onExtensionsChanged")]
+pub async fn on_extensions_changed() {
+    let mut global_context = GLOBAL_CONTEXT.lock().await;
+    let mut managed_tabs = MANAGED_TABS.lock().await;
+    global_context.detect_conflicts(&mut managed_tabs).await;
+}
+
+/// Writes queued storage entries before the background page is unloaded.
+#[wasm_bindgen(js_name = "onSuspend")]
+pub async fn on_suspend() -> Result<(), JsError> {
+    storage::flush()
+        .await
+        .map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Undoes the relocation of the tab that the clicked notification is for,
+/// or adds the suffix that the notification proposes.
+/// Does nothing if the notification is for neither.
+#[wasm_bindgen(js_name = "onNotificationClicked")]
+pub async fn on_notification_clicked(notification_id: String) -> Result<(), JsError> {
+    if let Some((suffix, cookie_store_id)) = learning::parse_notification_id(&notification_id) {
+        return adopt_learned_suffix(&mut GLOBAL_CONTEXT.lock().await, suffix, cookie_store_id)
+            .map_err(|error| JsError::new(&error.to_string()));
+    }
+    let Ok(tab_id) = notification_id.parse::<isize>() else {
+        return Ok(());
+    };
+    undo_relocation(&mut GLOBAL_CONTEXT.lock().await, TabId::new(tab_id), false)
+        .await
+        .map_err(|error: CustomError| JsError::new(&error.to_string()))
+}
+
+/// Ends the focus container that the alarm is for,
+/// see [focus](crate::focus).
+/// Does nothing if the alarm is for something else.
+#[wasm_bindgen(js_name = "onAlarm")]
+pub async fn on_alarm(alarm_name: String) -> Result<(), JsError> {
+    let Some(cookie_store_id) = focus::parse_alarm_name(&alarm_name) else {
+        return Ok(());
+    };
+    focus::end(&mut GLOBAL_CONTEXT.lock().await, &cookie_store_id)
+        .await
+        .map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Suggests containers with names containing the typed name
+/// for the omnibox keyword.
+/// Returns an array of suggestions for the `suggest` callback.
+#[wasm_bindgen(js_name = "onOmniboxInputChanged")]
+pub async fn on_omnibox_input_changed(text: String) -> JsValue {
+    let (name, url) = omnibox::parse_input(&text);
+    let url = url.unwrap_or_default();
+    let global_context = GLOBAL_CONTEXT.lock().await;
+    let suggestions = global_context
+        .containers
+        .search(name)
+        .filter(|container| container.variant != ContainerVariant::Temporary)
+        .map(|container| {
+            let container_name = container.identity_details().name;
+            SuggestResult {
+                content: format!("{} {}", container_name, url),
+                description: i18n::get_message(
+                    "omnibox_suggestion",
+                    &[url.clone(), container_name],
+                ),
+            }
+        })
+        .collect::<Vec<SuggestResult>>();
+    interop::to_jsvalue(&suggestions)
+}
+
+/// Opens the URL in the container named in the omnibox input,
+/// the container with the shortest name containing the typed name is chosen.
+/// Does nothing if there is no URL or no such container.
 /// Fails if any tab operation failed.
+#[wasm_bindgen(js_name = "onOmniboxInputEntered")]
+pub async fn on_omnibox_input_entered(text: String, disposition: JsValue) -> Result<(), JsError> {
+    {
+        let disposition =
+            interop::cast_or_standard_mismatch::<OnInputEnteredDisposition>(disposition)?;
+        let (name, url) = omnibox::parse_input(&text);
+        let Some(url) = url else {
+            return Ok(());
+        };
+        let global_context = GLOBAL_CONTEXT.lock().await;
+        let Some(container) = global_context
+            .containers
+            .search(name)
+            .filter(|container| container.variant != ContainerVariant::Temporary)
+            .min_by_key(|container| container.identity_details().name.len())
+        else {
+            return Ok(());
+        };
+
+        let mut tab_properties = tabs::current_tab().await?;
+        let current_tab_id = tab_properties.id();
+        let domain = interop::url_to_domain(&url).ok();
+        tab_properties.retarget(
+            url,
+            disposition != OnInputEnteredDisposition::NewBackgroundTab,
+        );
+        tab_properties.cookie_store_id = container.handle().cookie_store_id().clone();
+        let new_tab_id = tab_properties.new_tab().await?;
+        register_new_tab(new_tab_id, container, domain).await;
+        drop(global_context);
+
+        if disposition == OnInputEnteredDisposition::CurrentTab {
+            current_tab_id.close_tab().await?;
+        }
+        Ok(())
+    }
+    .map_err(|error: CustomError| JsError::new(&error.to_string()))
+}
+
+/// Handles a keyboard shortcut of the commands in the manifest,
+/// unknown commands are ignored.
+/// Fails if the browser indicates so.
+#[wasm_bindgen(js_name = "onCommand")]
+pub async fn on_command(command: String) -> Result<(), JsError> {
+    let Ok(command) = command.parse::<Command>() else {
+        return Ok(());
+    };
+    match command {
+        Command::OpenQuickSwitcher => {
+            let tab_id = tabs::current_tab().await?.id();
+            windows::create_popup(&format!("switcher.html?tab_id={tab_id}")).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the homepage of the container in a new tab next to a tab,
+/// or the new tab page if the container has no homepage.
+/// Does nothing if the container is not owned.
+/// Fails with [DeletedContainer](CustomError::DeletedContainer)
+/// if the container was deleted, or if any tab operation failed.
+pub(crate) async fn open_homepage(
+    global_context: &GlobalContext,
+    mut tab_properties: TabProperties,
+    cookie_store_id: &CookieStoreId,
+) -> Result<(), CustomError> {
+    global_context.containers.ensure_alive(cookie_store_id)?;
+    let Some(container) = global_context.containers.get(cookie_store_id) else {
+        return Ok(());
+    };
+    let domain = match &container.homepage {
+        Some(homepage) => {
+            tab_properties.retarget(homepage.clone(), true);
+            interop::url_to_domain(homepage).ok()
+        }
+        None => {
+            tab_properties.retarget_new_tab_page(true);
+            None
+        }
+    };
+    tab_properties.cookie_store_id = cookie_store_id.clone();
+    let new_tab_id = tab_properties.new_tab().await?;
+    register_new_tab(new_tab_id, container, domain).await;
+    Ok(())
+}
+
+/// Handles a click on the menu items created by the background page,
+/// unknown menu items are ignored.
+/// Fails if any tab operation failed.
+#[wasm_bindgen(js_name = "onMenuClicked")]
+pub async fn on_menu_clicked(menu_item_id: String, tab_properties: JsValue) -> Result<(), JsError> {
+    let Ok(menu_item) = menu_item_id.parse::<MenuItem>() else {
+        return Ok(());
+    };
+    let tab_properties = interop::cast_or_standard_mismatch::<TabProperties>(tab_properties)?;
+    match menu_item {
+        MenuItem::OpenHomepage => {
+            let cookie_store_id = tab_properties.cookie_store_id.clone();
+            open_homepage(
+                &*GLOBAL_CONTEXT.lock().await,
+                tab_properties,
+                &cookie_store_id,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Reopens a tab in the container and closes the original tab,
+/// the current domain is registered for the new tab.
+/// Does nothing if the tab is already in the container,
+/// or if the container is not owned.
+/// Fails with [DeletedContainer](CustomError::DeletedContainer)
+/// if the container was deleted, or if any tab operation failed.
+pub(crate) async fn reopen_tab(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    tab_id: TabId,
+    cookie_store_id: CookieStoreId,
+) -> Result<(), CustomError> {
+    global_context.containers.ensure_alive(&cookie_store_id)?;
+    let Some(container) = global_context.containers.get(&cookie_store_id) else {
+        return Ok(());
+    };
+    let mut tab_properties = tab_id.properties().await?;
+    if tab_properties.cookie_store_id == cookie_store_id {
+        return Ok(());
+    }
+    let domain = tab_properties.domain().ok().flatten();
+    tab_properties.cookie_store_id = cookie_store_id.clone();
+    let new_tab_id = tab_properties.new_tab().await?;
+    register_new_tab(new_tab_id, container, domain.clone()).await;
+    tab_id.close_tab().await?;
+    if let Some(domain) = domain {
+        learn_suffix(global_context, domain, cookie_store_id).await;
+    }
+    Ok(())
+}
+
+/// Records that a tab of the domain was manually reopened in the container,
+/// and proposes adding the suffix of its registrable domain in a notification
+/// once it is reopened repeatedly, see [SuffixLearning](learning::SuffixLearning).
+/// Does nothing if learning is off or if the container already matches.
+async fn learn_suffix(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    domain: EncodedDomain,
+    cookie_store_id: CookieStoreId,
+) {
+    if !global_context.preferences.should_learn_suffixes || global_context.guest_mode.is_some() {
+        return;
+    }
+    let Some(container) = global_context.containers.get(&cookie_store_id) else {
+        return;
+    };
+    if !container.variant.allows_modification()
+        || container.variant == ContainerVariant::Temporary
+        || container
+            .suffixes
+            .iter()
+            .any(|suffix| suffix.match_ordering(&domain).is_eq())
+    {
+        return;
+    }
+    let container_name = container.identity_details().name;
+    let registrable_domain = global_context
+        .psl
+        .match_suffix(domain.clone())
+        .unwrap_or(domain);
+    let suffix = Suffix::new(SuffixType::Normal, registrable_domain);
+    let should_propose =
+        global_context
+            .suffix_learning
+            .record(suffix.clone(), cookie_store_id.clone(), Utc::now());
+    storage::queue_single_entry(
+        learning::SUFFIX_LEARNING_KEY,
+        &global_context.suffix_learning,
+    );
+    if !should_propose {
+        return;
+    }
+    let message = i18n::get_message(
+        "notification_learning_message",
+        &[suffix.unicode(), container_name],
+    );
+    if let Err(error) = notifications::create_basic(
+        &learning::notification_id(&suffix, &cookie_store_id),
+        i18n::get_message("notification_learning_title", &[]),
+        message,
+    )
+    .await
+    {
+        log::warn!("suffix `{}` was not proposed, {error}", suffix.raw());
+    }
+}
+
+/// Adds a suffix proposed by [learn_suffix] to the container.
+/// Does nothing if the container no longer exists.
+/// Fails if the container cannot be modified, including in guest mode.
+fn adopt_learned_suffix(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    suffix: Suffix,
+    cookie_store_id: CookieStoreId,
+) -> Result<(), CustomError> {
+    if global_context.is_degraded {
+        return Err(CustomError::DegradedContext);
+    }
+    if global_context.guest_mode.is_some() {
+        return Err(CustomError::GuestMode);
+    }
+    let Some(container) = global_context.containers.get(&cookie_store_id) else {
+        return Ok(());
+    };
+    container.ensure_modifiable()?;
+    global_context.containers.ensure_no_conflict(
+        Some(&cookie_store_id),
+        container.priority,
+        [&suffix],
+    )?;
+    let mut container = global_context
+        .containers
+        .get_mut(cookie_store_id.clone())
+        .expect("container checked above");
+    container.suffixes.insert(suffix.clone());
+    storage::queue_single_entry(&ContainerKey::from(&cookie_store_id), &*container);
+    drop(container);
+    global_context.suffix_learning.forget(&suffix);
+    storage::queue_single_entry(
+        learning::SUFFIX_LEARNING_KEY,
+        &global_context.suffix_learning,
+    );
+    Ok(())
+}
+
+/// Forgets the default container of a window when it is closed,
+/// and dismisses the prompt whose chooser was in the window.
+#[wasm_bindgen(js_name = "onWindowRemoved")]
+pub async fn on_window_removed(window_id: isize) {
+    let window_id = WindowId::new(window_id);
+    if let Some(tab_id) = MANAGED_TABS.lock().await.dismiss_chooser(&window_id) {
+        log::info!("prompt of tab {tab_id} was dismissed");
+    }
+    GLOBAL_CONTEXT
+        .lock()
+        .await
+        .window_containers
+        .remove(&window_id);
+}
+
+/// Asks the user where a tab should be opened in a chooser window,
+/// then waits for the choice until the prompt expires.
+/// Returns the chosen decision, or a new temporary container
+/// if the chooser cannot be opened, is closed,
+/// or no choice is made in time.
+/// [None] if the prompt was withdrawn by another navigation of the tab,
+/// or by closing the tab.
+async fn prompt_relocation(
+    tab_id: &TabId,
+    relocation_detail: &RelocationDetail,
+) -> Option<Decision> {
+    let receiver = MANAGED_TABS.lock().await.prompt(
+        tab_id.clone(),
+        relocation_detail.new_domain.clone(),
+        relocation_detail.current_cookie_store_id.clone(),
+    );
+    let fallback = Decision::NewTemporary { domain: None };
+    let window_id = match windows::create_popup(&format!("chooser.html?tab_id={tab_id}")).await {
+        Ok(window_id) => window_id,
+        Err(error) => {
+            log::warn!("chooser of tab {tab_id} was not opened, {error}");
+            MANAGED_TABS.lock().await.resolve_prompt(tab_id, None);
+            return Some(fallback);
+        }
+    };
+    MANAGED_TABS
+        .lock()
+        .await
+        .attach_chooser(tab_id, window_id.clone());
+    let decision = match future::timeout(PROMPT_TIMEOUT, receiver.recv()).await {
+        Ok(Ok(choice)) => return Some(Decision::from(choice)),
+        Ok(Err(_withdrawn)) => None,
+        Err(_timeout) => {
+            log::info!("prompt of tab {tab_id} expired");
+            MANAGED_TABS.lock().await.resolve_prompt(tab_id, None);
+            Some(fallback)
+        }
+    };
+    if let Err(error) = window_id.close().await {
+        log::warn!("chooser of tab {tab_id} was not closed, {error}");
+    }
+    decision
+}
+
+/// Describes what is known about a tab for the devtools panel,
+/// including why it was relocated and what would be decided now.
+/// Fails if the browser indicates so.
+pub(crate) async fn inspect_tab(
+    global_context: &GlobalContext,
+    tab_id: &TabId,
+) -> Result<TabInspection, CustomError> {
+    let tab_properties = tab_id.properties().await?;
+    let container_name = |cookie_store_id: &CookieStoreId| {
+        global_context
+            .containers
+            .get(cookie_store_id)
+            .map(|container| container.identity_details().name)
+    };
+    let inspect_determinant = |tab_det: &TabDeterminant| DeterminantInspection {
+        cookie_store_id: tab_det.container_handle.cookie_store_id().clone(),
+        container_name: container_name(tab_det.container_handle.cookie_store_id()),
+        domain: tab_det.domain.clone(),
+    };
+    let cookie_store_id = tab_properties.cookie_store_id.clone();
+    let current_container =
+        (cookie_store_id != CookieStoreId::default()).then_some(&cookie_store_id);
+    let current_trace = tab_properties
+        .url()
+        .map(|url| global_context.rules_engine().trace(url, current_container));
+    let managed_tabs = MANAGED_TABS.lock().await;
+    Ok(TabInspection {
+        tab_id: tab_id.clone(),
+        url: tab_properties.url().map(String::from),
+        container_name: container_name(&cookie_store_id),
+        cookie_store_id,
+        determinant: managed_tabs.get(tab_id).map(inspect_determinant),
+        relocated_from: managed_tabs
+            .relocation_origin(tab_id)
+            .map(inspect_determinant),
+        last_trace: managed_tabs.decision_trace(tab_id).cloned(),
+        current_trace,
+    })
+}
+
+/// Holds the managed tabs so that no tab is checked for relocation
+/// until the guard is dropped, for swapping containers without
+/// tabs being matched against the intermediate state.
+/// The [GlobalContext] must be locked before this, if it is locked at all.
+pub(crate) async fn freeze_matching() -> MutexGuard<'static, ManagedTabs> {
+    MANAGED_TABS.lock().await
+}
+
+/// The domain and the current container of a tab
+/// that is waiting for the user's choice, [None] if there is none.
+pub(crate) async fn pending_prompt(tab_id: &TabId) -> Option<(EncodedDomain, CookieStoreId)> {
+    MANAGED_TABS
+        .lock()
+        .await
+        .pending_prompt(tab_id)
+        .map(|pending_prompt| {
+            (
+                pending_prompt.domain.clone(),
+                pending_prompt.current_cookie_store_id.clone(),
+            )
+        })
+}
+
+/// Resumes the relocation of a tab with the user's choice.
+/// Returns whether the tab was waiting for the choice.
+pub(crate) async fn resolve_prompt(tab_id: &TabId, choice: PromptChoice) -> bool {
+    MANAGED_TABS
+        .lock()
+        .await
+        .resolve_prompt(tab_id, Some(choice))
+}
+
+/// The trace of why a tab was last relocated to its container,
+/// [None] if the tab was not relocated.
+pub(crate) async fn decision_trace(tab_id: &TabId) -> Option<DecisionTrace> {
+    MANAGED_TABS.lock().await.decision_trace(tab_id).cloned()
+}
+
+/// Registers a tab that is newly created in a [Container],
+/// so that it is not relocated again once it starts loading.
+pub(crate) async fn register_new_tab(
+    tab_id: TabId,
+    container: &Container,
+    domain: Option<EncodedDomain>,
+) {
+    let tab_det = TabDeterminant {
+        container_handle: container.handle().clone(),
+        domain,
+    };
+    if let Some(reused_det) = MANAGED_TABS.lock().await.register(tab_id, tab_det) {
+        reused_det.container_handle.finish();
+    }
+}
+
+/// Releases the handle of a container designated for an uncontained tab,
+/// which is held until the decision is realized so that a temporary container
+/// created for the designation is reused instead of being deleted early.
+/// The container is cleaned up if the tab was not moved there.
+async fn release_designated(
+    global_context: &mut GlobalContext,
+    designated_container: Option<(ContainerHandle, Designation)>,
+    tab_id: &TabId,
+) {
+    let Some((handle, _designation)) = designated_container else {
+        return;
+    };
+    let cookie_store_id = handle.cookie_store_id().clone();
+    handle.finish();
+    drop(handle);
+    if let Err(error) =
+        ContainerVariant::on_handle_drop(&mut global_context.containers, cookie_store_id).await
+    {
+        log::warn!("designated container of tab {tab_id} was not cleaned up, {error}");
+    }
+}
+
+/// Reopens a relocated tab in the container it was relocated from,
+/// and closes the relocated tab.
+/// The domain may be added to the suffixes of that container,
+/// so that it is assigned there in the future,
+/// see [GlobalContext::suffix_builder].
+/// Does nothing if the tab was not relocated.
+/// Fails if any tab operation failed, the relocation can be undone again.
+/// Also fails if the suffix conflicts with another container,
+/// see [ContainerOwner::ensure_no_conflict](crate::container::ContainerOwner::ensure_no_conflict),
+/// in which case the relocation is still undone without adding the suffix.
+pub(crate) async fn undo_relocation(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    tab_id: TabId,
+    should_add_suffix: bool,
+) -> Result<(), CustomError> {
+    let mut tab_properties = tab_id.properties().await?;
+    let Some(origin_det) = MANAGED_TABS.lock().await.take_relocation_origin(&tab_id) else {
+        return Ok(());
+    };
+    let domain = tab_properties.domain().ok().flatten();
+    let origin = origin_det.container_handle.cookie_store_id().clone();
+    tab_properties.cookie_store_id = origin.clone();
+    let new_tab_id = match tab_properties.new_tab().await {
+        Ok(new_tab_id) => new_tab_id,
+        Err(error) => {
+            MANAGED_TABS
+                .lock()
+                .await
+                .record_relocation(tab_id, origin_det);
+            return Err(error);
+        }
+    };
+
+    let tab_det = TabDeterminant {
+        container_handle: origin_det.container_handle,
+        domain: domain.clone(),
+    };
+    if let Some(reused_det) = MANAGED_TABS.lock().await.register(new_tab_id, tab_det) {
+        reused_det.container_handle.finish();
+    }
+
+    let mut result = Ok(());
+    if let (true, Some(domain)) = (should_add_suffix, domain) {
+        let suffix = global_context
+            .suffix_builder()
+            .parse(domain.encoded(), &global_context.preferences.idna_policy);
+        let priority = global_context
+            .containers
+            .get(&origin)
+            .filter(|container| {
+                container.variant != ContainerVariant::Temporary
+                    && container.variant.allows_modification()
+            })
+            .map(|container| container.priority);
+        if let Some(priority) = priority {
+            result = suffix.and_then(|suffix| {
+                global_context
+                    .containers
+                    .ensure_no_conflict(Some(&origin), priority, [&suffix])?;
+                let mut container = global_context
+                    .containers
+                    .get_mut(origin.clone())
+                    .expect("container checked above");
+                container.suffixes.insert(suffix);
+                storage::queue_single_entry(&ContainerKey::from(&origin), &*container);
+                Ok(())
+            });
+        }
+    }
+    tab_id.close_tab().await.and(result)
+}
+
+/// Switchs the tab to a [Container](crate::container::Container).
+/// The new domain is registered for the tab in the container,
+/// and the old domain is restored if the old tab is reverted.
+/// Returns the ID of the new tab if the tab is recreated in another container,
+/// [None] if the tab is reloaded in place.
+/// The URL of a recreated tab is rewritten with the rules of the container,
+/// in which case the domain of the rewritten URL is registered instead.
+/// If the tab cannot be created, the handle is released and the container
+/// is cleaned up immediately, instead of waiting for another tab to close.
+/// Fails with [DeletedContainer](CustomError::DeletedContainer)
+/// if the container was deleted before the tab was created,
+/// or if any tab operation failed.
 async fn assign_tab(
     tab_id: TabId,
     mut tab_properties: TabProperties,
     container_handle: ContainerHandle,
-    relocation_detail: RelocationDetail,
+    new_domain: Option<EncodedDomain>,
+    old_domain: Option<EncodedDomain>,
     should_revert_old_tab: bool,
-) -> Result<(), CustomError> {
-    let tab_det = TabDeterminant {
+    rewrite_rules: &[RewriteRule],
+) -> Result<Option<TabId>, CustomError> {
+    let mut tab_det = TabDeterminant {
         container_handle,
-        domain: Some(relocation_detail.new_domain),
+        domain: new_domain,
     };
     if *tab_det.container_handle.cookie_store_id() == tab_properties.cookie_store_id {
         if let Some(old_det) = MANAGED_TABS.lock().await.register(tab_id.clone(), tab_det) {
             old_det.container_handle.finish();
         }
-        tab_id.reload_tab().await
+        tab_id.reload_tab().await?;
+        Ok(None)
     } else {
-        tab_properties.cookie_store_id = tab_det.container_handle.cookie_store_id().clone();
-        let new_tab_id = tab_properties.new_tab().await?;
+        let origin = mem::replace(
+            &mut tab_properties.cookie_store_id,
+            tab_det.container_handle.cookie_store_id().clone(),
+        );
+        if let Some(url) = tab_properties.url() {
+            let rewritten_url = rewrite::apply_all(rewrite_rules, url);
+            if rewritten_url != url {
+                tab_det.domain = interop::url_to_domain(&rewritten_url).ok();
+            }
+            tab_properties.set_url(rewritten_url);
+        }
+        let new_tab_id = match tab_properties.new_tab().await {
+            Ok(new_tab_id) => new_tab_id,
+            Err(error) => {
+                let cookie_store_id = tab_det.container_handle.cookie_store_id().clone();
+                tab_det.container_handle.finish();
+                drop(tab_det);
+                let mut global_context = GLOBAL_CONTEXT.lock().await;
+                global_context.containers.ensure_alive(&cookie_store_id)?;
+                if let Err(cleanup_error) = ContainerVariant::on_handle_drop(
+                    &mut global_context.containers,
+                    cookie_store_id,
+                )
+                .await
+                {
+                    log::warn!("container of tab {tab_id} was not cleaned up, {cleanup_error}");
+                }
+                return Err(error);
+            }
+        };
 
-        if let Some(reused_det) = MANAGED_TABS.lock().await.register(new_tab_id, tab_det) {
+        let mut managed_tabs = MANAGED_TABS.lock().await;
+        if let Some(reused_det) = managed_tabs.register(new_tab_id.clone(), tab_det) {
             reused_det.container_handle.finish();
         }
+        let origin_det = TabDeterminant {
+            container_handle: managed_tabs.get_mut(&tab_id).map_or_else(
+                || {
+                    let handle = ContainerHandle::from(origin);
+                    handle.finish();
+                    handle
+                },
+                |old_det| old_det.container_handle.clone(),
+            ),
+            domain: old_domain.clone(),
+        };
+        managed_tabs.record_relocation(new_tab_id.clone(), origin_det);
+        drop(managed_tabs);
 
         if should_revert_old_tab {
             if let Some(old_det) = MANAGED_TABS.lock().await.get_mut(&tab_id) {
-                old_det.domain = relocation_detail.old_domain;
+                old_det.domain = old_domain;
             }
             tab_id.back_or_close().await?;
         } else {
             tab_id.close_tab().await?;
         }
 
-        Ok(())
+        Ok(Some(new_tab_id))
     }
 }
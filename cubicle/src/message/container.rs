@@ -2,16 +2,23 @@
 
 use std::collections::BTreeSet;
 use std::ops::DerefMut;
+use std::time::Duration;
 
 use serde::Deserialize;
 
-use crate::container::{Container, ContainerVariant};
+use crate::container::{ActiveHours, Container, ContainerKey, ContainerVariant, OwnerHandle};
 use crate::context::GlobalContext;
 use crate::domain::suffix::Suffix;
+use crate::domain::IdnaPolicy;
 use crate::interop::contextual_identities::{
     CookieStoreId, IdentityDetails, IdentityDetailsProvider,
 };
+use crate::interop::web_request::HttpHeader;
+use crate::interop::{self, cookies, i18n, storage, tabs, windows};
+use crate::rewrite::RewriteRule;
+use crate::transaction::Transaction;
 use crate::util::errors::CustomError;
+use crate::util::log;
 
 /// Message type for container operations that are not tab related.
 #[derive(Deserialize)]
@@ -27,22 +34,97 @@ pub enum ContainerAction {
         old_suffix: String,
         new_suffix: String,
     },
+    ReplaceSuffixes {
+        cookie_store_id: CookieStoreId,
+        suffixes: Vec<String>,
+    },
     DeleteContainer {
         cookie_store_id: CookieStoreId,
     },
+    CloneContainer {
+        cookie_store_id: CookieStoreId,
+        #[serde(default)]
+        exclude_suffixes: bool,
+        #[serde(default)]
+        as_family_member: bool,
+    },
+    LeaveFamily {
+        cookie_store_id: CookieStoreId,
+    },
     ConfirmRecording {
         cookie_store_id: CookieStoreId,
     },
+    SetWindowDefault {
+        cookie_store_id: CookieStoreId,
+        is_default: bool,
+    },
+    ClearCookies {
+        cookie_store_id: CookieStoreId,
+        domains: Option<Vec<String>>,
+    },
+    ReplaceRequestHeaders {
+        cookie_store_id: CookieStoreId,
+        headers: Vec<String>,
+    },
+    ReplaceRewriteRules {
+        cookie_store_id: CookieStoreId,
+        rules: Vec<String>,
+    },
+    SetHomepage {
+        cookie_store_id: CookieStoreId,
+        homepage: Option<String>,
+    },
+    SetLocked {
+        cookie_store_id: CookieStoreId,
+        is_locked: bool,
+    },
+    SetActiveHours {
+        cookie_store_id: CookieStoreId,
+        active_hours: Option<ActiveHours>,
+    },
+    SetPriority {
+        cookie_store_id: CookieStoreId,
+        priority: i32,
+    },
+    DiscardContainerTabs {
+        cookie_store_id: CookieStoreId,
+    },
+    GatherTabs {
+        cookie_store_id: CookieStoreId,
+        #[serde(default)]
+        should_name_window: bool,
+    },
+    PurgeTemporary {
+        older_than: Option<Duration>,
+    },
+    CreateForSuffix {
+        suffix: String,
+    },
+}
+
+/// Result of a container operation.
+/// - [Focused](ActionOutcome::Focused) means that the container should be
+///   focused in the listing, which may no longer exist if it was deleted.
+/// - [Purged](ActionOutcome::Purged) means that a number of containers
+///   were deleted, and no container in particular should be focused.
+pub enum ActionOutcome {
+    Focused(CookieStoreId),
+    Purged(usize),
 }
 
 impl ContainerAction {
-    /// Performs the container operation,
-    /// returns the [CookieStoreId] of the newly focused container.
-    /// Fails if the browser indicates so.
+    /// Performs the container operation, see [ActionOutcome].
+    /// Changes that are only made to the context are persisted before
+    /// they are committed, see [Transaction],
+    /// and created containers are persisted before they are inserted.
+    /// Fails if the browser indicates so,
+    /// or if the suffixes or details of a locked container are modified,
+    /// or if an added suffix conflicts with another container,
+    /// see [ContainerOwner::ensure_no_conflict](crate::container::ContainerOwner::ensure_no_conflict).
     pub async fn act(
         self,
         global_context: &mut impl DerefMut<Target = GlobalContext>,
-    ) -> Result<CookieStoreId, CustomError> {
+    ) -> Result<ActionOutcome, CustomError> {
         use ContainerAction::*;
         match self {
             SubmitIdentityDetails {
@@ -52,12 +134,11 @@ impl ContainerAction {
             } => {
                 let cookie_store_id = match cookie_store_id {
                     Some(cookie_store_id) => {
-                        let mut container = global_context
-                            .containers
-                            .get_mut(cookie_store_id.clone())
-                            .expect("valid ID passed from message");
+                        let mut container = transact_container(global_context, &cookie_store_id);
+                        container.ensure_modifiable()?;
                         container.update(details).await?;
-                        container.handle().cookie_store_id().clone()
+                        container.commit().await?;
+                        cookie_store_id
                     }
                     None => {
                         let variant = if should_record {
@@ -67,12 +148,10 @@ impl ContainerAction {
                         };
                         let container =
                             Container::create(details, variant, BTreeSet::default()).await?;
-                        let cookie_store_id = container.handle().cookie_store_id().clone();
-                        global_context.containers.insert(container);
-                        cookie_store_id
+                        insert_created(global_context, container).await?
                     }
                 };
-                Ok(cookie_store_id)
+                Ok(ActionOutcome::Focused(cookie_store_id))
             }
 
             UpdateSuffix {
@@ -81,24 +160,49 @@ impl ContainerAction {
                 new_suffix,
             } => {
                 let old_suffix = (!old_suffix.is_empty()).then(|| {
-                    Suffix::try_from(&*old_suffix).expect("valid suffix passed from message")
+                    Suffix::parse(&old_suffix, &IdnaPolicy::Lenient)
+                        .expect("valid suffix passed from message")
                 });
                 let new_suffix = if new_suffix.is_empty() {
                     None
                 } else {
-                    Some(Suffix::try_from(&*new_suffix)?)
+                    Some(
+                        global_context
+                            .suffix_builder()
+                            .parse(&new_suffix, &global_context.preferences.idna_policy)?,
+                    )
                 };
-                let mut container = global_context
-                    .containers
-                    .get_mut(cookie_store_id.clone())
-                    .expect("valid ID passed from message");
+                ensure_no_conflict(global_context, &cookie_store_id, &new_suffix)?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
                 if let Some(suffix) = old_suffix {
                     container.suffixes.remove(&suffix);
                 }
                 if let Some(suffix) = new_suffix {
                     container.suffixes.insert(suffix);
                 }
-                Ok(cookie_store_id)
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            ReplaceSuffixes {
+                cookie_store_id,
+                suffixes,
+            } => {
+                let idna_policy = &global_context.preferences.idna_policy;
+                let builder = global_context.suffix_builder();
+                let suffixes = suffixes
+                    .iter()
+                    .map(|suffix| suffix.trim())
+                    .filter(|suffix| !suffix.is_empty())
+                    .map(|suffix| builder.parse(suffix, idna_policy))
+                    .collect::<Result<BTreeSet<Suffix>, CustomError>>()?;
+                ensure_no_conflict(global_context, &cookie_store_id, &suffixes)?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.suffixes = suffixes;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
             }
 
             DeleteContainer { cookie_store_id } => {
@@ -107,8 +211,68 @@ impl ContainerAction {
                     .get(&cookie_store_id)
                     .expect("valid ID passed from message");
                 container.delete().await?;
-                global_context.containers.remove(&cookie_store_id);
-                Ok(cookie_store_id)
+                global_context.containers.bury(&cookie_store_id);
+                storage::remove_entries(&[ContainerKey::from(&cookie_store_id)]).await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            CloneContainer {
+                cookie_store_id,
+                exclude_suffixes,
+                as_family_member,
+            } => {
+                let container = global_context
+                    .containers
+                    .get(&cookie_store_id)
+                    .expect("valid ID passed from message");
+                let mut details = container.identity_details();
+                details.name = i18n::get_message("cloned_container_name", &[details.name]);
+                let suffixes = if exclude_suffixes || as_family_member {
+                    BTreeSet::default()
+                } else {
+                    container.suffixes.clone()
+                };
+                // A copy with the suffixes is preferred less than the original,
+                // so that it does not take over the matching of the original.
+                let priority = if suffixes.is_empty() {
+                    container.priority
+                } else {
+                    container.priority.saturating_sub(1)
+                };
+                global_context
+                    .containers
+                    .ensure_no_conflict(None, priority, &suffixes)?;
+                let mut new_container =
+                    Container::create(details, container.variant.clone(), suffixes).await?;
+                new_container.request_headers = container.request_headers.clone();
+                new_container.rewrite_rules = container.rewrite_rules.clone();
+                new_container.homepage = container.homepage.clone();
+                new_container.active_hours = container.active_hours.clone();
+                new_container.priority = priority;
+                if as_family_member {
+                    new_container.family =
+                        Some(global_context.containers.canonical_id(&cookie_store_id));
+                }
+
+                let new_cookie_store_id = insert_created(global_context, new_container).await?;
+                Ok(ActionOutcome::Focused(new_cookie_store_id))
+            }
+
+            LeaveFamily { cookie_store_id } => {
+                let canonical_id = global_context.containers.canonical_id(&cookie_store_id);
+                let suffixes = global_context
+                    .containers
+                    .get(&canonical_id)
+                    .filter(|_canonical| canonical_id != cookie_store_id)
+                    .map(|canonical| canonical.suffixes.clone())
+                    .unwrap_or_default();
+                ensure_no_conflict(global_context, &cookie_store_id, &suffixes)?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.family = None;
+                container.suffixes.extend(suffixes);
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
             }
 
             ConfirmRecording { cookie_store_id } => {
@@ -116,20 +280,505 @@ impl ContainerAction {
                     .containers
                     .get(&cookie_store_id)
                     .expect("valid ID passed from message");
-                let new_container = Container::create(
+                global_context.containers.ensure_no_conflict(
+                    Some(&cookie_store_id),
+                    container.priority,
+                    &container.suffixes,
+                )?;
+                let _frozen_matching = crate::freeze_matching().await;
+                let mut new_container = Container::create(
                     container.identity_details(),
                     ContainerVariant::Permanent,
                     container.suffixes.clone(),
                 )
                 .await?;
+                new_container.request_headers = container.request_headers.clone();
+                new_container.rewrite_rules = container.rewrite_rules.clone();
+                new_container.homepage = container.homepage.clone();
+                new_container.active_hours = container.active_hours.clone();
+                new_container.priority = container.priority;
+                new_container.family = container.family.clone();
 
-                container.delete().await?;
-                global_context.containers.remove(&cookie_store_id);
-
+                if let Err(error) = container.delete().await {
+                    if let Err(rollback_error) = new_container.delete().await {
+                        log::warn!("confirmed container was not rolled back, {rollback_error}");
+                        new_container.handle().finish();
+                    }
+                    return Err(error);
+                }
                 let new_cookie_store_id = new_container.handle().cookie_store_id().clone();
-                global_context.containers.insert(new_container);
-                Ok(new_cookie_store_id)
+                global_context
+                    .containers
+                    .replace(&cookie_store_id, new_container);
+                storage::queue_single_entry(
+                    &ContainerKey::from(&cookie_store_id),
+                    &None::<&Container>,
+                );
+                storage::queue_single_entry(
+                    &ContainerKey::from(&new_cookie_store_id),
+                    &global_context.containers.get(&new_cookie_store_id),
+                );
+                for member_id in global_context
+                    .containers
+                    .family_members(&new_cookie_store_id)
+                {
+                    storage::queue_single_entry(
+                        &ContainerKey::from(&member_id),
+                        &global_context.containers.get(&member_id),
+                    );
+                }
+                for window_cookie_store_id in global_context.window_containers.values_mut() {
+                    if *window_cookie_store_id == cookie_store_id {
+                        *window_cookie_store_id = new_cookie_store_id.clone();
+                    }
+                }
+                Ok(ActionOutcome::Focused(new_cookie_store_id))
+            }
+
+            SetWindowDefault {
+                cookie_store_id,
+                is_default,
+            } => {
+                let window_id = windows::last_focused_window_id().await?;
+                if is_default {
+                    global_context
+                        .window_containers
+                        .insert(window_id, cookie_store_id.clone());
+                } else if global_context.window_containers.get(&window_id) == Some(&cookie_store_id)
+                {
+                    global_context.window_containers.remove(&window_id);
+                }
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            ClearCookies {
+                cookie_store_id,
+                domains,
+            } => {
+                let mut cookies = cookies::get_all(&cookie_store_id).await?;
+                if let Some(domains) = domains {
+                    cookies.retain(|cookie| domains.iter().any(|domain| domain == cookie.domain()));
+                }
+                for cookie in cookies {
+                    cookie.remove(&cookie_store_id).await?;
+                }
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            ReplaceRequestHeaders {
+                cookie_store_id,
+                headers,
+            } => {
+                let headers = headers
+                    .iter()
+                    .map(|header| header.trim())
+                    .filter(|header| !header.is_empty())
+                    .map(HttpHeader::try_from)
+                    .collect::<Result<Vec<HttpHeader>, CustomError>>()?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.request_headers = headers;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            ReplaceRewriteRules {
+                cookie_store_id,
+                rules,
+            } => {
+                let rules = rules
+                    .iter()
+                    .map(|rule| rule.trim())
+                    .filter(|rule| !rule.is_empty())
+                    .map(RewriteRule::try_from)
+                    .collect::<Result<Vec<RewriteRule>, CustomError>>()?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.rewrite_rules = rules;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            SetHomepage {
+                cookie_store_id,
+                homepage,
+            } => {
+                let homepage = homepage
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|homepage| !homepage.is_empty())
+                    .map(parse_homepage)
+                    .transpose()?;
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.homepage = homepage;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            SetLocked {
+                cookie_store_id,
+                is_locked,
+            } => {
+                if global_context.managed_containers.contains(&cookie_store_id) {
+                    let container = global_context
+                        .containers
+                        .get(&cookie_store_id)
+                        .expect("valid ID passed from message");
+                    return Err(CustomError::ManagedContainer {
+                        name: container.identity_details().name,
+                    });
+                }
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.variant = match (&container.variant, is_locked) {
+                    (ContainerVariant::Permanent, true) => ContainerVariant::Locked,
+                    (ContainerVariant::Locked, false) => ContainerVariant::Permanent,
+                    (variant, _) => variant.clone(),
+                };
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            SetActiveHours {
+                cookie_store_id,
+                active_hours,
+            } => {
+                if let Some(active_hours) = active_hours.as_ref().filter(|hours| !hours.is_valid())
+                {
+                    return Err(CustomError::InvalidActiveHours {
+                        start_hour: active_hours.start_hour,
+                        end_hour: active_hours.end_hour,
+                    });
+                }
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.active_hours = active_hours;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            SetPriority {
+                cookie_store_id,
+                priority,
+            } => {
+                let mut container = transact_container(global_context, &cookie_store_id);
+                container.ensure_modifiable()?;
+                container.priority = priority;
+                container.commit().await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            DiscardContainerTabs { cookie_store_id } => {
+                let tab_ids = tabs::query_by_cookie_store_id(&cookie_store_id)
+                    .await?
+                    .iter()
+                    .map(|tab| tab.id().clone())
+                    .collect::<Vec<_>>();
+                tabs::discard(&tab_ids).await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            GatherTabs {
+                cookie_store_id,
+                should_name_window,
+            } => {
+                let container = global_context
+                    .containers
+                    .get(&cookie_store_id)
+                    .expect("valid ID passed from message");
+                let title_preface =
+                    should_name_window.then(|| format!("{} - ", container.identity_details().name));
+                let tab_ids = tabs::query_by_cookie_store_id(&cookie_store_id)
+                    .await?
+                    .iter()
+                    .map(|tab| tab.id().clone())
+                    .collect::<Vec<_>>();
+                if let Some((first_tab_id, other_tab_ids)) = tab_ids.split_first() {
+                    let window_id = windows::create_with_tab(first_tab_id, title_preface).await?;
+                    tabs::move_to_window(other_tab_ids, &window_id).await?;
+                }
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+
+            PurgeTemporary { older_than } => {
+                let idle_ids = global_context
+                    .containers
+                    .iter()
+                    .filter(|container| container.variant == ContainerVariant::Temporary)
+                    .filter(|container| match older_than {
+                        Some(older_than) => container.is_idle_for(older_than),
+                        None => true,
+                    })
+                    .map(|container| container.handle().cookie_store_id().clone())
+                    .collect::<Vec<CookieStoreId>>();
+                let mut purged_keys = Vec::new();
+                let mut result = Ok(());
+                for cookie_store_id in idle_ids {
+                    let container = global_context
+                        .containers
+                        .get(&cookie_store_id)
+                        .expect("collected from the owner");
+                    result = container.delete().await;
+                    if result.is_err() {
+                        break;
+                    }
+                    global_context.containers.bury(&cookie_store_id);
+                    purged_keys.push(ContainerKey::from(&cookie_store_id));
+                }
+                storage::remove_entries(&purged_keys).await?;
+                result.and(Ok(ActionOutcome::Purged(purged_keys.len())))
             }
+
+            CreateForSuffix { suffix } => {
+                let suffix = global_context
+                    .suffix_builder()
+                    .parse(&suffix, &global_context.preferences.idna_policy)?;
+                global_context
+                    .containers
+                    .ensure_no_conflict(None, 0, [&suffix])?;
+                let details = IdentityDetails {
+                    name: suffix.unicode(),
+                    ..Default::default()
+                };
+                let container = Container::create(
+                    details,
+                    ContainerVariant::Permanent,
+                    BTreeSet::from([suffix]),
+                )
+                .await?;
+                let cookie_store_id = insert_created(global_context, container).await?;
+                Ok(ActionOutcome::Focused(cookie_store_id))
+            }
+        }
+    }
+}
+
+/// Checks that a homepage is a web address that tabs can be opened to,
+/// as privileged pages cannot be opened by URL.
+/// Fails with [InvalidHomepage](CustomError::InvalidHomepage) otherwise.
+fn parse_homepage(homepage: &str) -> Result<String, CustomError> {
+    let is_web_address = (homepage.starts_with("https://") || homepage.starts_with("http://"))
+        && interop::url_to_domain(homepage).is_ok_and(|domain| !domain.encoded().is_empty());
+    if !is_web_address {
+        return Err(CustomError::InvalidHomepage {
+            url: String::from(homepage),
+        });
+    }
+    Ok(String::from(homepage))
+}
+
+/// Checks that the suffixes can be added to an owned container,
+/// see [ContainerOwner::ensure_no_conflict](crate::container::ContainerOwner::ensure_no_conflict).
+fn ensure_no_conflict<'a>(
+    global_context: &GlobalContext,
+    cookie_store_id: &CookieStoreId,
+    suffixes: impl IntoIterator<Item = &'a Suffix>,
+) -> Result<(), CustomError> {
+    let container = global_context
+        .containers
+        .get(cookie_store_id)
+        .expect("valid ID passed from message");
+    global_context.containers.ensure_no_conflict(
+        Some(cookie_store_id),
+        container.priority,
+        suffixes,
+    )
+}
+
+/// Persists a newly created container, then inserts it into the owner.
+/// Returns the ID of the container.
+/// Fails if the browser indicates so, in which case the container is deleted
+/// so that it is neither in memory nor on disk.
+async fn insert_created(
+    global_context: &mut GlobalContext,
+    container: Container,
+) -> Result<CookieStoreId, CustomError> {
+    let cookie_store_id = container.handle().cookie_store_id().clone();
+    let key = ContainerKey::from(&cookie_store_id);
+    if let Err(error) = storage::store_single_entry(&key, &container).await {
+        if let Err(rollback_error) = container.delete().await {
+            log::warn!("created container was not rolled back, {rollback_error}");
+            container.handle().finish();
         }
+        return Err(error);
+    }
+    global_context.containers.insert(container);
+    Ok(cookie_store_id)
+}
+
+/// Starts a [Transaction] on a container, stored under its [ContainerKey].
+fn transact_container<'a>(
+    global_context: &'a mut GlobalContext,
+    cookie_store_id: &CookieStoreId,
+) -> Transaction<ContainerKey, OwnerHandle<'a>> {
+    let container = global_context
+        .containers
+        .get_mut(cookie_store_id.clone())
+        .expect("valid ID passed from message");
+    Transaction::new(ContainerKey::from(cookie_store_id), container)
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::container::test::{test_container_with_id, CONTEXTUAL_IDENTITY_MUTEX};
+    use crate::interop::contextual_identities::{IdentityDetails, MockContextualIdentity};
+
+    fn failed_deletion() -> Result<(), CustomError> {
+        Err(CustomError::FailedContainerOperation {
+            verb: String::from("delete"),
+            details: String::from("mocked failure"),
+        })
+    }
+
+    async fn test_confirm_recording_rollback(
+        rollback_result: Result<(), CustomError>,
+    ) -> Result<(), CustomError> {
+        let mut global_context = GlobalContext::default();
+        let mut recording = test_container_with_id(
+            "mock_recording",
+            IdentityDetails::default(),
+            BTreeSet::from([Suffix::try_from("example.com")?]),
+            |mock_identity| {
+                mock_identity
+                    .expect_identity_details()
+                    .returning(IdentityDetails::default);
+                mock_identity.expect_delete().return_once(failed_deletion);
+            },
+        )
+        .await;
+        recording.variant = ContainerVariant::Recording { active: false };
+        global_context.containers.insert(recording);
+
+        let mut confirmed_identity = MockContextualIdentity::new();
+        confirmed_identity
+            .expect_cookie_store_id()
+            .return_const(CookieStoreId::new(String::from("mock_confirmed")));
+        confirmed_identity
+            .expect_delete()
+            .times(1)
+            .return_once(|| rollback_result);
+        let ctx_mock_identity = MockContextualIdentity::create_context();
+        ctx_mock_identity
+            .expect()
+            .return_once(|_details| Ok(confirmed_identity));
+
+        let recording_id = CookieStoreId::new(String::from("mock_recording"));
+        let action = ContainerAction::ConfirmRecording {
+            cookie_store_id: recording_id.clone(),
+        };
+        assert!(action.act(&mut &mut global_context).await.is_err());
+        let recording = global_context
+            .containers
+            .remove(&recording_id)
+            .expect("recording container kept");
+        assert!(recording.variant == ContainerVariant::Recording { active: false });
+        assert!(global_context.containers.iter().next().is_none());
+        recording.handle().finish();
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_confirm_recording_failed_deletion() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        test_confirm_recording_rollback(Ok(())).await?;
+        test_confirm_recording_rollback(failed_deletion()).await
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_confirm_recording_conflict() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut global_context = GlobalContext::default();
+        for (cookie_store_id, variant) in [
+            (
+                "mock_recording",
+                ContainerVariant::Recording { active: false },
+            ),
+            ("mock_permanent", ContainerVariant::Permanent),
+        ] {
+            let mut container = test_container_with_id(
+                cookie_store_id,
+                IdentityDetails::default(),
+                BTreeSet::from([Suffix::try_from("example.com")?]),
+                |mock_identity| {
+                    mock_identity
+                        .expect_identity_details()
+                        .returning(IdentityDetails::default);
+                },
+            )
+            .await;
+            container.variant = variant;
+            global_context.containers.insert(container);
+        }
+
+        let recording_id = CookieStoreId::new(String::from("mock_recording"));
+        let action = ContainerAction::ConfirmRecording {
+            cookie_store_id: recording_id.clone(),
+        };
+        assert!(matches!(
+            action.act(&mut &mut global_context).await,
+            Err(CustomError::SuffixConflict { .. })
+        ));
+        for cookie_store_id in [
+            recording_id,
+            CookieStoreId::new(String::from("mock_permanent")),
+        ] {
+            global_context
+                .containers
+                .remove(&cookie_store_id)
+                .expect("container kept")
+                .handle()
+                .finish();
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_replace_request_headers_locked() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut global_context = GlobalContext::default();
+        let mut locked = test_container_with_id(
+            "mock_locked",
+            IdentityDetails::default(),
+            BTreeSet::default(),
+            |mock_identity| {
+                mock_identity
+                    .expect_identity_details()
+                    .returning(IdentityDetails::default);
+                mock_identity.expect_clone().returning(|| {
+                    let mut staged_identity = MockContextualIdentity::new();
+                    staged_identity
+                        .expect_identity_details()
+                        .returning(IdentityDetails::default);
+                    staged_identity
+                });
+            },
+        )
+        .await;
+        locked.variant = ContainerVariant::Locked;
+        global_context.containers.insert(locked);
+
+        let locked_id = CookieStoreId::new(String::from("mock_locked"));
+        let action = ContainerAction::ReplaceRequestHeaders {
+            cookie_store_id: locked_id.clone(),
+            headers: vec![String::from("X-Test: value")],
+        };
+        assert!(matches!(
+            action.act(&mut &mut global_context).await,
+            Err(CustomError::LockedContainer { .. })
+        ));
+        let locked = global_context
+            .containers
+            .remove(&locked_id)
+            .expect("locked container kept");
+        assert!(locked.request_headers.is_empty());
+        locked.handle().finish();
+        Ok(())
     }
 }
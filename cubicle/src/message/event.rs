@@ -0,0 +1,21 @@
+//! Event type for notifying content and pop-up scripts of background progress.
+
+use serde::Serialize;
+
+use crate::interop::{self, fetch::FetchProgress};
+
+/// Event type for notifying content and pop-up scripts.
+/// Listening scripts may receive any of these types.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "event_type")]
+pub enum Event {
+    PslUpdateProgress(FetchProgress),
+}
+
+impl Event {
+    /// Emits this event to all listening extension pages,
+    /// best effort as there may be no listeners.
+    pub fn emit(&self) {
+        interop::broadcast_runtime_message(self);
+    }
+}
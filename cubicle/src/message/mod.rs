@@ -1,24 +1,45 @@
 //! Message type for communicating with content and pop-up scripts.
 
 mod container;
+mod event;
 mod view;
 
+use std::collections::BTreeSet;
 use std::ops::DerefMut;
+use std::time::Duration;
 
 use async_std::io::BufReader;
-use chrono::Utc;
-use serde::Deserialize;
+use chrono::{NaiveDate, Utc};
+use js_sys::JSON;
+use serde::{Deserialize, Serialize};
 
-use self::container::ContainerAction;
+use self::container::{ActionOutcome, ContainerAction};
+use self::event::Event;
 use self::view::View;
-use crate::context::GlobalContext;
-use crate::domain::psl::Psl;
-use crate::interop::tabs;
-use crate::interop::{self, fetch::Fetch, storage};
-use crate::migrate;
+use crate::container::ContainerVariant;
+use crate::context::{Adoption, GlobalContext, ParsedPsl, PslSource};
+use crate::domain::alias::AliasGroups;
+use crate::domain::psl::{Psl, PslDiff};
+use crate::domain::suffix::SuffixType;
+use crate::focus;
+use crate::guest::{GuestMode, GUEST_MODE_KEY};
+use crate::interop::bookmarks::BookmarkFolderId;
+use crate::interop::contextual_identities::{
+    CookieStoreId, IdentityColor, IdentityDetails, IdentityDetailsProvider, IdentityIcon,
+};
+use crate::interop::tabs::{self, TabId, TabProperties};
+use crate::interop::{self, favicon, fetch, history, storage};
 use crate::migrate::import::MigrateType;
-use crate::preferences::Preferences;
+use crate::preferences::{AssignChain, ContainerEjectStrategy, DomainOverride, Preferences};
+use crate::session::{self, Session, Sessions};
+use crate::tab::PromptChoice;
+use crate::transaction::Transaction;
 use crate::util::errors::CustomError;
+use crate::util::lock::LockStats;
+use crate::util::log;
+
+/// Maximum duration of downloading a public suffix list.
+const PSL_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Message type for communicating with content and pop-up scripts.
 /// All passed structures must conform to this type definition.
@@ -41,24 +62,156 @@ pub enum Message {
     ApplyPreferences {
         preferences: Preferences,
     },
+    FocusTab {
+        tab_id: TabId,
+    },
+    BindBookmarkFolder {
+        folder_id: BookmarkFolderId,
+        cookie_store_id: CookieStoreId,
+    },
+    UnbindBookmarkFolder {
+        folder_id: BookmarkFolderId,
+    },
+    ReplaceAliasGroups {
+        groups: Vec<String>,
+    },
+    UndoRelocation {
+        tab_id: TabId,
+        #[serde(default)]
+        should_add_suffix: bool,
+    },
+    CompactStorage,
+    StartFocus {
+        duration: Duration,
+    },
+    EnterGuestMode {
+        #[serde(default)]
+        pin: Option<String>,
+    },
+    ExitGuestMode {
+        #[serde(default)]
+        pin: Option<String>,
+    },
+    Reconcile {
+        #[serde(default)]
+        adopt: Vec<Adoption>,
+        #[serde(default)]
+        clean: Vec<CookieStoreId>,
+    },
+    SuggestIdentityDetails,
+    SuggestSuffixes {
+        cookie_store_id: CookieStoreId,
+    },
+    HandleDiagnostics,
+    HealthCheck,
+    RetryLoad,
+    ValidateSuffix {
+        value: String,
+        #[serde(default)]
+        cookie_store_id: Option<CookieStoreId>,
+    },
+    SaveSession {
+        name: String,
+    },
+    SimulateRelocation {
+        url: String,
+        #[serde(default)]
+        current_container: Option<CookieStoreId>,
+    },
+    SearchContainers {
+        query: String,
+    },
+    InspectTab {
+        tab_id: TabId,
+    },
+    ReopenTab {
+        tab_id: TabId,
+        cookie_store_id: CookieStoreId,
+    },
+    OpenHomepage {
+        cookie_store_id: CookieStoreId,
+    },
+    PreviewStrategies {
+        urls: Vec<String>,
+        #[serde(default)]
+        current_container: Option<CookieStoreId>,
+        assign_chain: AssignChain,
+        eject_strategy: ContainerEjectStrategy,
+    },
+    RestoreSession {
+        name: String,
+    },
+    RestoreLastSession,
+    ChooseRelocation {
+        tab_id: TabId,
+        choice: PromptChoice,
+        #[serde(default)]
+        should_remember: bool,
+    },
 }
 
 impl Message {
+    /// Checks if the message writes over stored parts of the context,
+    /// which is not allowed while the context is degraded.
+    fn is_destructive(&self) -> bool {
+        use Message::*;
+        matches!(
+            self,
+            ContainerAction { .. }
+                | MigrateContainer { .. }
+                | PslUpdate { .. }
+                | ApplyPreferences { .. }
+                | BindBookmarkFolder { .. }
+                | UnbindBookmarkFolder { .. }
+                | ReplaceAliasGroups { .. }
+                | StartFocus { .. }
+                | UndoRelocation {
+                    should_add_suffix: true,
+                    ..
+                }
+                | CompactStorage
+                | EnterGuestMode { .. }
+                | SaveSession { .. }
+                | RestoreSession { .. }
+                | RestoreLastSession
+                | ChooseRelocation {
+                    should_remember: true,
+                    ..
+                }
+        ) || matches!(self, Reconcile { adopt, clean } if !adopt.is_empty() || !clean.is_empty())
+    }
+
     /// Perform action requested by the message,
     /// this may be separated in the future to avoid excessive locking.
+    /// Fails with [DegradedContext](CustomError::DegradedContext)
+    /// if the message is destructive while the context is degraded,
+    /// or with [GuestMode](CustomError::GuestMode) while in guest mode.
     pub async fn act(
         self,
         global_context: &mut impl DerefMut<Target = GlobalContext>,
     ) -> Result<String, CustomError> {
+        if global_context.is_degraded && self.is_destructive() {
+            return Err(CustomError::DegradedContext);
+        }
+        if global_context.guest_mode.is_some() && self.is_destructive() {
+            return Err(CustomError::GuestMode);
+        }
         use Message::*;
         match self {
             RequestPage { view } => view.render(global_context).await,
             ContainerAction { action } => {
-                let cookie_store_id = action.act(global_context).await?;
+                let outcome = action.act(global_context).await?;
+                global_context.sync_header_rules();
+                let cookie_store_id = match outcome {
+                    ActionOutcome::Focused(cookie_store_id) => cookie_store_id,
+                    ActionOutcome::Purged(count) => return Ok(count.to_string()),
+                };
                 let existing_container = global_context.containers.get(&cookie_store_id);
-                storage::store_single_entry(&cookie_store_id, &existing_container).await?;
                 View::FetchAllContainers {
                     selected: existing_container.and(Some(cookie_store_id)),
+                    filter: None,
+                    offset: 0,
+                    limit: None,
                 }
                 .render(global_context)
                 .await
@@ -71,31 +224,509 @@ impl Message {
                     .containers
                     .merge(migrate_type.act(detect_temp).await?);
                 storage::set_with_serde_keys(&global_context.containers).await?;
+                global_context.sync_header_rules();
                 View::FetchAllContainers {
                     selected: Some(tabs::current_tab_cookie_store_id().await?),
+                    filter: None,
+                    offset: 0,
+                    limit: None,
                 }
                 .render(global_context)
                 .await
             }
             PslUpdate { url } => {
-                let local_path = interop::prepend_extension_base_url("public_suffix_list.dat");
-                let use_external = url.is_some();
-                let mut reader =
-                    BufReader::new(Fetch::get_stream(&url.unwrap_or(local_path)).await?);
-                let new_date = if use_external {
-                    Utc::now().date_naive()
-                } else {
-                    *migrate::BUILTIN_PSL_VERSION
-                };
-                global_context.psl = Psl::from_stream(&mut reader, new_date).await.unwrap();
-                storage::store_single_entry("psl", &global_context.psl).await?;
-                Ok(new_date.to_string())
+                let (parsed, source) = fetch_psl(url, None).await?;
+                global_context.update_psl(parsed, &source).await?;
+                Ok(global_context.psl.last_updated().to_string())
             }
             ApplyPreferences { preferences } => {
-                global_context.preferences = preferences;
-                storage::store_single_entry("preferences", &global_context.preferences).await?;
+                if global_context.are_preferences_managed {
+                    return Err(CustomError::ManagedPreferences);
+                }
+                let previous_backend = global_context.preferences.large_data_backend;
+                let mut transaction =
+                    Transaction::new("preferences", &mut global_context.preferences);
+                *transaction = preferences;
+                transaction.commit().await?;
+                global_context.move_large_data(previous_backend).await?;
+                crate::sync_managed_tabs(global_context).await;
+                Ok(String::default())
+            }
+            FocusTab { tab_id } => {
+                tab_id.focus().await?;
+                Ok(String::default())
+            }
+            BindBookmarkFolder {
+                folder_id,
+                cookie_store_id,
+            } => {
+                global_context
+                    .bookmark_bindings
+                    .insert(folder_id, cookie_store_id);
+                storage::store_single_entry("bookmark_bindings", &global_context.bookmark_bindings)
+                    .await?;
+                Ok(String::default())
+            }
+            UnbindBookmarkFolder { folder_id } => {
+                global_context.bookmark_bindings.remove(&folder_id);
+                storage::store_single_entry("bookmark_bindings", &global_context.bookmark_bindings)
+                    .await?;
+                Ok(String::default())
+            }
+            ReplaceAliasGroups { groups } => {
+                global_context.alias_groups = AliasGroups::parse_lines(&groups)?;
+                storage::store_single_entry("alias_groups", &global_context.alias_groups).await?;
+                crate::sync_managed_tabs(global_context).await;
+                Ok(String::default())
+            }
+            UndoRelocation {
+                tab_id,
+                should_add_suffix,
+            } => {
+                crate::undo_relocation(global_context, tab_id, should_add_suffix).await?;
+                Ok(String::default())
+            }
+            CompactStorage => Ok(global_context.compact_storage().await?.to_string()),
+            StartFocus { duration } => {
+                focus::start(global_context, duration).await?;
+                Ok(String::default())
+            }
+            EnterGuestMode { pin } => {
+                let guest_mode = GuestMode::new(pin.as_deref()).await?;
+                storage::store_single_entry(GUEST_MODE_KEY, &guest_mode).await?;
+                global_context.guest_mode = Some(guest_mode);
+                Ok(String::default())
+            }
+            ExitGuestMode { pin } => {
+                if let Some(guest_mode) = &global_context.guest_mode {
+                    guest_mode.verify(pin.as_deref()).await?;
+                    storage::remove_entries(&[GUEST_MODE_KEY]).await?;
+                    global_context.guest_mode = None;
+                }
+                Ok(String::default())
+            }
+            Reconcile { adopt, clean } => {
+                let reconciliation = global_context.reconcile(&adopt, &clean).await?;
+                Ok(JSON::stringify(&interop::to_jsvalue(&reconciliation))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            SuggestSuffixes { cookie_store_id } => {
+                let windows = history_windows(&cookie_store_id).await?;
+                let suffixes = global_context.rank_suggested_suffixes(&windows);
+                Ok(JSON::stringify(&interop::to_jsvalue(&suffixes))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            SuggestIdentityDetails => {
+                let tab_properties = tabs::current_tab().await?;
+                let details = suggest_identity_details(global_context, &tab_properties).await;
+                Ok(JSON::stringify(&interop::to_jsvalue(&details))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            HandleDiagnostics => {
+                let diagnostics = global_context
+                    .containers
+                    .iter()
+                    .map(|container| HandleDiagnostic {
+                        name: container.identity_details().name,
+                        is_temporary: container.variant == ContainerVariant::Temporary,
+                        outstanding_handles: container.outstanding_handles(),
+                    })
+                    .collect::<Vec<_>>();
+                Ok(JSON::stringify(&interop::to_jsvalue(&diagnostics))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            HealthCheck => {
+                let status = HealthStatus {
+                    is_context_loaded: global_context.is_loaded,
+                    is_degraded: global_context.is_degraded,
+                    is_psl_pending: global_context.is_psl_pending,
+                    psl_len: global_context.psl.len(),
+                    psl_last_updated: global_context.psl.last_updated(),
+                    container_count: global_context.containers.iter().count(),
+                    context_lock: crate::context_lock_stats(),
+                    queued_storage_entries: storage::queued_entry_count(),
+                };
+                Ok(JSON::stringify(&interop::to_jsvalue(&status))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            // Loading acts on messages, so this is handled before acting.
+            RetryLoad => Ok(String::default()),
+            ValidateSuffix {
+                value,
+                cookie_store_id,
+            } => {
+                let validation =
+                    SuffixValidation::new(global_context, value.trim(), cookie_store_id.as_ref());
+                Ok(JSON::stringify(&interop::to_jsvalue(&validation))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            SimulateRelocation {
+                url,
+                current_container,
+            } => {
+                // Tabs opened from another tab start in the opener's container.
+                let trace = global_context
+                    .simulate_relocation(
+                        url.trim(),
+                        current_container.as_ref(),
+                        current_container.clone(),
+                    )
+                    .await;
+                Ok(JSON::stringify(&interop::to_jsvalue(&trace))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            InspectTab { tab_id } => {
+                let inspection = crate::inspect_tab(global_context, &tab_id).await?;
+                Ok(JSON::stringify(&interop::to_jsvalue(&inspection))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            SearchContainers { query } => {
+                let matches = global_context
+                    .containers
+                    .fuzzy_search(query.trim())
+                    .into_iter()
+                    .map(|container| container.handle().cookie_store_id())
+                    .collect::<Vec<_>>();
+                Ok(JSON::stringify(&interop::to_jsvalue(&matches))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            ReopenTab {
+                tab_id,
+                cookie_store_id,
+            } => {
+                crate::reopen_tab(global_context, tab_id, cookie_store_id).await?;
+                Ok(String::default())
+            }
+            OpenHomepage { cookie_store_id } => {
+                let tab_properties = tabs::current_tab().await?;
+                crate::open_homepage(global_context, tab_properties, &cookie_store_id).await?;
+                Ok(String::default())
+            }
+            PreviewStrategies {
+                urls,
+                current_container,
+                assign_chain,
+                eject_strategy,
+            } => {
+                // Overrides are left out so that only the strategies are shown.
+                let rules_engine = global_context
+                    .rules_engine()
+                    .opened_from(current_container.clone());
+                let traces = urls
+                    .iter()
+                    .map(|url| {
+                        rules_engine.trace_with_strategies(
+                            url.trim(),
+                            current_container.as_ref(),
+                            &assign_chain,
+                            &eject_strategy,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                Ok(JSON::stringify(&interop::to_jsvalue(&traces))
+                    .map(String::from)
+                    .unwrap_or_default())
+            }
+            SaveSession { name } => {
+                let backend = global_context.preferences.large_data_backend;
+                let session = Session::snapshot(global_context, &tabs::query_all().await?);
+                let tab_count = session.tab_count();
+                let mut sessions = Sessions::load(backend).await?;
+                sessions.insert(name, session);
+                sessions.store(backend).await?;
+                Ok(tab_count.to_string())
+            }
+            RestoreSession { name } => {
+                let sessions =
+                    Sessions::load(global_context.preferences.large_data_backend).await?;
+                let Some(session) = sessions.get(&name) else {
+                    return Err(CustomError::SessionNotFound { name });
+                };
+                Ok(session.restore(global_context).await?.to_string())
+            }
+            RestoreLastSession => {
+                let sessions =
+                    Sessions::load(global_context.preferences.large_data_backend).await?;
+                let Some(session) = sessions.last_session() else {
+                    return Err(CustomError::SessionNotFound {
+                        name: String::from(session::LAST_SESSION_KEY),
+                    });
+                };
+                Ok(session.restore(global_context).await?.to_string())
+            }
+            ChooseRelocation {
+                tab_id,
+                choice,
+                should_remember,
+            } => {
+                let pending_prompt = crate::pending_prompt(&tab_id).await;
+                if !crate::resolve_prompt(&tab_id, choice.clone()).await {
+                    return Err(CustomError::ExpiredPrompt {
+                        tab_id: tab_id.to_string(),
+                    });
+                }
+                let (true, Some((domain, current_cookie_store_id))) =
+                    (should_remember, pending_prompt)
+                else {
+                    return Ok(String::default());
+                };
+                if global_context.are_preferences_managed {
+                    return Err(CustomError::ManagedPreferences);
+                }
+                let suffix = DomainOverride::suffix_for(&global_context.psl, domain);
+                let target = choice.target(&current_cookie_store_id);
+                let mut transaction =
+                    Transaction::new("preferences", &mut global_context.preferences);
+                transaction.remember_domain_override(suffix, current_cookie_store_id, target);
+                transaction.commit().await?;
                 Ok(String::default())
             }
         }
     }
 }
+
+/// Handles held outside of a container, for debugging leaked containers.
+#[derive(Serialize)]
+struct HandleDiagnostic {
+    name: String,
+    is_temporary: bool,
+    outstanding_handles: usize,
+}
+
+/// Status of the background, so that a context that failed to load
+/// can be told apart from one that has nothing to list.
+#[derive(Serialize)]
+struct HealthStatus {
+    is_context_loaded: bool,
+    is_degraded: bool,
+    is_psl_pending: bool,
+    psl_len: usize,
+    psl_last_updated: NaiveDate,
+    container_count: usize,
+    context_lock: LockStats,
+    queued_storage_entries: usize,
+}
+
+/// Result of validating a suffix before it is submitted,
+/// so that problems can be shown as the user types.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "validity")]
+enum SuffixValidation {
+    Invalid {
+        reason: String,
+    },
+    Valid {
+        suffix_type: SuffixType,
+        encoded: String,
+        conflicts: Vec<String>,
+        is_public_suffix: bool,
+    },
+}
+
+impl SuffixValidation {
+    /// Parses and checks a suffix against the stored containers and the
+    /// public suffix list. The container being edited, if any,
+    /// is not considered to be in conflict with itself.
+    fn new(
+        global_context: &GlobalContext,
+        value: &str,
+        cookie_store_id: Option<&CookieStoreId>,
+    ) -> Self {
+        let suffix = match global_context
+            .suffix_builder()
+            .parse(value, &global_context.preferences.idna_policy)
+        {
+            Ok(suffix) => suffix,
+            Err(error) => {
+                return Self::Invalid {
+                    reason: error.to_string(),
+                }
+            }
+        };
+        let conflicts = global_context
+            .containers
+            .iter()
+            .filter(|container| Some(container.handle().cookie_store_id()) != cookie_store_id)
+            .filter(|container| container.suffixes.contains(&suffix))
+            .map(|container| container.identity_details().name)
+            .collect();
+        Self::Valid {
+            suffix_type: suffix.suffix_type().clone(),
+            encoded: suffix.encoded(),
+            conflicts,
+            is_public_suffix: global_context.psl.is_public_suffix(suffix.domain()),
+        }
+    }
+}
+
+/// Message type for communicating with trusted extensions,
+/// which are checked against the preferences before acting.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", tag = "message_type")]
+pub enum ExternalMessage {
+    OpenInContainer { url: String, container_name: String },
+}
+
+impl ExternalMessage {
+    /// Perform action requested by the message.
+    /// Fails with [ContainerNotFound](CustomError::ContainerNotFound)
+    /// if no permanent container has the name,
+    /// or if the URL does not have a domain.
+    pub async fn act(
+        self,
+        global_context: &mut impl DerefMut<Target = GlobalContext>,
+    ) -> Result<String, CustomError> {
+        use ExternalMessage::*;
+        match self {
+            OpenInContainer {
+                url,
+                container_name,
+            } => {
+                let container = global_context
+                    .containers
+                    .find_by_name(&container_name)
+                    .ok_or(CustomError::ContainerNotFound {
+                        name: container_name,
+                    })?;
+                let domain = interop::url_to_domain(&url)?;
+                let mut tab_properties = tabs::current_tab().await?;
+                tab_properties.retarget(url, true);
+                tab_properties.cookie_store_id = container.handle().cookie_store_id().clone();
+                let new_tab_id = tab_properties.new_tab().await?;
+                crate::register_new_tab(new_tab_id, container, Some(domain)).await;
+                Ok(String::default())
+            }
+        }
+    }
+}
+
+/// Collects the URLs visited around the times that the container's tabs were
+/// visited from the browser history, one window of URLs per visit,
+/// see [GlobalContext::rank_suggested_suffixes].
+/// The context is not needed, so that this can be done without its lock.
+/// Fails if the browser indicates so.
+pub(crate) async fn history_windows(
+    cookie_store_id: &CookieStoreId,
+) -> Result<Vec<Vec<String>>, CustomError> {
+    const SEED_LIMIT: usize = 20;
+    const VISITS_PER_SEED: usize = 10;
+    const WINDOW_MILLIS: f64 = 15.0 * 60.0 * 1000.0;
+    const RESULTS_PER_WINDOW: usize = 100;
+
+    let seed_urls = tabs::query_by_cookie_store_id(cookie_store_id)
+        .await?
+        .iter()
+        .map(|tab| tab.url().to_owned())
+        .filter(|url| url.starts_with("http"))
+        .collect::<BTreeSet<String>>();
+    let mut windows = Vec::new();
+    for url in seed_urls.iter().take(SEED_LIMIT) {
+        for visit_time in history::visit_times(url)
+            .await?
+            .into_iter()
+            .take(VISITS_PER_SEED)
+        {
+            let items = history::search(
+                visit_time - WINDOW_MILLIS,
+                visit_time + WINDOW_MILLIS,
+                RESULTS_PER_WINDOW,
+            )
+            .await?;
+            windows.push(items.into_iter().map(|item| item.url).collect());
+        }
+    }
+    Ok(windows)
+}
+
+/// Downloads the public suffix list from the URL, or opens the bundled list
+/// if [None], then parses it while emitting
+/// [PslUpdateProgress](Event::PslUpdateProgress).
+/// Only the changes are parsed if the list is compared against a base source,
+/// which is read before downloading as the download may replace it.
+/// The whole list is parsed if the base cannot be read.
+/// The context is not needed, so that this can be done without its lock.
+/// Fails if the list cannot be downloaded or parsed.
+pub(crate) async fn fetch_psl(
+    url: Option<String>,
+    base: Option<PslSource>,
+) -> Result<(ParsedPsl, PslSource), CustomError> {
+    let base_rules = match base {
+        Some(base) => match base.read_rules().await {
+            Ok(rules) => Some((base, rules)),
+            Err(error) => {
+                log::warn!("failed to read current public suffix list, {error}");
+                None
+            }
+        },
+        None => None,
+    };
+    let source = match url {
+        Some(url) => {
+            fetch::download_to_cache(&url, Some(PSL_FETCH_TIMEOUT)).await?;
+            PslSource {
+                url: Some(url),
+                last_updated: Utc::now().date_naive(),
+            }
+        }
+        None => PslSource::default(),
+    };
+    let (mut fetch, last_updated) = source.open().await?;
+    fetch.on_progress(|progress| Event::PslUpdateProgress(progress).emit());
+    let mut reader = BufReader::new(fetch);
+    let parsed = match base_rules {
+        Some((base, rules)) => ParsedPsl::Diff {
+            diff: PslDiff::from_stream(&rules, &mut reader, last_updated).await?,
+            base,
+        },
+        None => ParsedPsl::Full(Psl::from_stream(&mut reader, last_updated).await?),
+    };
+    Ok((parsed, source))
+}
+
+/// Suggests details of a new container for the site of a tab,
+/// named after the public suffix of the site.
+/// The icon is chosen by keywords in the name and the title,
+/// and the color is the closest to the favicon.
+/// Best effort as parts that cannot be suggested are set to default.
+async fn suggest_identity_details(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    tab_properties: &TabProperties,
+) -> IdentityDetails {
+    let name = tab_properties
+        .domain()
+        .ok()
+        .flatten()
+        .map(|domain| {
+            let domain = global_context
+                .psl
+                .match_suffix(domain.clone())
+                .unwrap_or(domain);
+            String::from(domain.raw())
+        })
+        .unwrap_or_default();
+    let keyword_text = format!(
+        "{} {}",
+        name,
+        tab_properties.title().unwrap_or_default().to_lowercase()
+    );
+    let icon = IdentityIcon::from_keywords(&keyword_text);
+    let color = match tab_properties.fav_icon_url() {
+        Some(url) => favicon::average_color(url).await.ok().flatten(),
+        None => None,
+    };
+    let default_details = IdentityDetails::default();
+    IdentityDetails {
+        color: color.map_or(default_details.color, IdentityColor::closest_to),
+        icon: icon.unwrap_or(default_details.icon),
+        name,
+    }
+}
@@ -1,20 +1,44 @@
 //! Message for content that can be rendered to a string.
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::{iter, ops::DerefMut};
 
+use async_std::sync::Mutex;
 use chrono::offset::Utc;
-use chrono::Duration;
-use serde::Deserialize;
+use chrono::{Duration, Weekday};
+use js_sys::JSON;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::Display;
 use tera::{Context, Tera};
+use wasm_bindgen::JsValue;
 
 use crate::container::{Container, ContainerVariant};
-use crate::context::GlobalContext;
+use crate::context::{GlobalContext, PSL_DEFAULT_URL};
+use crate::crash;
+use crate::domain::suffix::{self, Suffix};
+use crate::domain::EncodedDomain;
+use crate::guest::GuestMode;
+use crate::interop::bookmarks::{self, BookmarkFolder};
 use crate::interop::contextual_identities::{
-    CookieStoreId, IdentityColor, IdentityDetails, IdentityDetailsProvider, IdentityIcon,
+    ContextualIdentity, CookieStoreId, IdentityColor, IdentityDetails, IdentityDetailsProvider,
+    IdentityIcon,
 };
-use crate::interop::{self, tabs};
+use crate::interop::cookies::{self, Cookie};
+use crate::interop::devtools::TabInspection;
+use crate::interop::privacy::{self, IsolationState};
+use crate::interop::tabs::{self, TabId, TabSummary};
+use crate::interop::{self, i18n, storage, theme, windows};
+use crate::preferences::{
+    AssignStep, ContainerEjectStrategy, ContainerSort, ContainerTarget, DomainOverride,
+    Preferences, ThemeMode, WebScheme,
+};
+use crate::session;
+use crate::statistics;
+use crate::util::clock::SystemClock;
 use crate::util::errors::CustomError;
+use crate::util::log;
 
 /// Message for content that can be rendered to a string,
 /// kebab-case name of the view should be the start
@@ -24,12 +48,47 @@ use crate::util::errors::CustomError;
 #[strum(serialize_all = "kebab-case")]
 pub enum View {
     NewContainer,
-    Welcome,
+    Welcome {
+        #[serde(default)]
+        step: Option<WelcomeStep>,
+    },
     Import,
-    FetchAllContainers { selected: Option<CookieStoreId> },
-    DeletePrompt { cookie_store_id: CookieStoreId },
-    UpdateContainer { cookie_store_id: CookieStoreId },
-    ContainerDetail { cookie_store_id: CookieStoreId },
+    FetchAllContainers {
+        selected: Option<CookieStoreId>,
+        #[serde(default)]
+        filter: Option<String>,
+        #[serde(default)]
+        offset: usize,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    DeletePrompt {
+        cookie_store_id: CookieStoreId,
+    },
+    UpdateContainer {
+        cookie_store_id: CookieStoreId,
+    },
+    ContainerDetail {
+        cookie_store_id: CookieStoreId,
+    },
+    ContainerCookies {
+        cookie_store_id: CookieStoreId,
+    },
+    Sessions,
+    TemporaryReport {
+        #[serde(default)]
+        days: Option<u64>,
+    },
+    RuleTester,
+    RelocationChooser {
+        tab_id: TabId,
+    },
+    QuickSwitcher {
+        tab_id: TabId,
+    },
+    DevtoolsPanel {
+        tab_id: TabId,
+    },
 
     OptionsBody,
 }
@@ -43,46 +102,173 @@ impl View {
         &self,
         global_context: &mut impl DerefMut<Target = GlobalContext>,
     ) -> Result<String, CustomError> {
+        let theme = view_theme(global_context.preferences.theme_mode).await;
         use View::*;
         match self {
-            NewContainer => Ok(render_with(new_container(None), self).await),
-            Welcome | Import => Ok(render_with(Context::default(), self).await),
-            FetchAllContainers { selected } => {
+            NewContainer => {
+                Ok(render_with(new_container(global_context, None), self, &theme).await)
+            }
+            Welcome { step } => {
+                let is_first_run = global_context.containers.iter().next().is_none();
+                let step = step.unwrap_or(if is_first_run {
+                    WelcomeStep::Identities
+                } else {
+                    WelcomeStep::Overview
+                });
+                let mut context = welcome(global_context, step);
+                if step == WelcomeStep::Identities {
+                    let unowned_names = ContextualIdentity::fetch_all()
+                        .await?
+                        .into_iter()
+                        .filter(|identity| {
+                            global_context
+                                .containers
+                                .get(identity.cookie_store_id())
+                                .is_none()
+                        })
+                        .map(|identity| identity.identity_details().name)
+                        .collect::<Vec<_>>();
+                    context.insert("unowned_names", &unowned_names);
+                }
+                if step == WelcomeStep::Psl {
+                    let psl_url = global_context
+                        .current_psl_source()
+                        .await?
+                        .and_then(|source| source.url)
+                        .unwrap_or_else(|| String::from(PSL_DEFAULT_URL));
+                    context.insert("psl_url", &psl_url);
+                }
+                Ok(render_with(context, self, &theme).await)
+            }
+            Import => Ok(render_with(Context::default(), self, &theme).await),
+            FetchAllContainers {
+                selected,
+                filter,
+                offset,
+                limit,
+            } => {
                 let selected = selected
                     .clone()
                     .unwrap_or(tabs::current_tab_cookie_store_id().await?);
-                fetch_all_containers(global_context, &selected).await
+                let limit = limit.unwrap_or(CONTAINER_PAGE_SIZE);
+                fetch_all_containers(global_context, &selected, filter.as_deref(), *offset, limit)
+                    .await
             }
             DeletePrompt { cookie_store_id } => {
                 let container = global_context
                     .containers
                     .get(cookie_store_id)
                     .expect("valid ID passed from message");
-                Ok(render_with(delete_prompt(container), self).await)
+                Ok(render_with(delete_prompt(container), self, &theme).await)
             }
             UpdateContainer { cookie_store_id } => {
                 let container = global_context
                     .containers
                     .get(cookie_store_id)
                     .expect("valid ID passed from message");
-                Ok(render_with(new_container(Some(container)), &NewContainer).await)
+                Ok(render_with(
+                    new_container(global_context, Some(container)),
+                    &NewContainer,
+                    &theme,
+                )
+                .await)
             }
             ContainerDetail { cookie_store_id } => {
                 let container = global_context
                     .containers
                     .get(cookie_store_id)
                     .expect("valid ID passed from message");
-                Ok(render_with(container_detail(container), self).await)
+                let tabs = tabs::query_by_cookie_store_id(cookie_store_id).await?;
+                let window_id = windows::last_focused_window_id().await?;
+                let is_window_default =
+                    global_context.window_containers.get(&window_id) == Some(cookie_store_id);
+                let is_managed = global_context.managed_containers.contains(cookie_store_id);
+                let mut decision_traces = BTreeMap::new();
+                for tab in &tabs {
+                    if let Some(trace) = crate::decision_trace(tab.id()).await {
+                        decision_traces.insert(tab.id().to_string(), trace.to_string());
+                    }
+                }
+                let mut context = container_detail(container, &tabs);
+                context.insert("decision_traces", &decision_traces);
+                context.insert("is_window_default", &is_window_default);
+                context.insert("is_managed", &is_managed);
+                insert_family(&mut context, global_context, cookie_store_id);
+                insert_domain_display(&mut context, global_context);
+                Ok(render_with(context, self, &theme).await)
+            }
+            ContainerCookies { cookie_store_id } => {
+                let cookies = cookies::get_all(cookie_store_id).await?;
+                let mut context = container_cookies(&cookies);
+                insert_domain_display(&mut context, global_context);
+                Ok(render_with(context, self, &theme).await)
+            }
+            RuleTester => Ok(render_with(rule_tester(global_context), self, &theme).await),
+            RelocationChooser { tab_id } => {
+                let pending_prompt = crate::pending_prompt(tab_id).await;
+                let mut context = relocation_chooser(global_context, pending_prompt);
+                context.insert("tab_id", tab_id);
+                Ok(render_with(context, self, &theme).await)
+            }
+            QuickSwitcher { tab_id } => {
+                let mut context = quick_switcher(global_context);
+                context.insert("tab_id", tab_id);
+                Ok(render_with(context, self, &theme).await)
+            }
+            DevtoolsPanel { tab_id } => {
+                let inspection = crate::inspect_tab(global_context, tab_id).await?;
+                Ok(render_with(devtools_panel(&inspection), self, &theme).await)
+            }
+            Sessions => {
+                let sessions =
+                    session::Sessions::load(global_context.preferences.large_data_backend).await?;
+                Ok(render_with(sessions_list(&sessions), self, &theme).await)
+            }
+            TemporaryReport { days } => {
+                let days = days
+                    .unwrap_or(TEMPORARY_REPORT_DAYS)
+                    .clamp(1, statistics::RETENTION_DAYS);
+                let mut context = temporary_report(global_context, days);
+                insert_domain_display(&mut context, global_context);
+                Ok(render_with(context, self, &theme).await)
+            }
+            OptionsBody => {
+                let folders = bookmarks::fetch_all_folders().await?;
+                let mut context = options_body(global_context, &folders);
+                let storage_usage = storage::usage().await?;
+                context.insert("storage_usage_kib", &storage_usage.div_ceil(1024));
+                context.insert(
+                    "storage_near_quota",
+                    &(storage_usage >= storage::QUOTA_BYTES / 10 * 9),
+                );
+                let last_crash = crash::last_crash().await?;
+                context.insert("last_crash", &last_crash.map(|report| report.to_string()));
+                let isolation = privacy::isolation_state().await.unwrap_or_else(|error| {
+                    log::warn!("isolation state was not detected, {error}");
+                    Some(IsolationState::default())
+                });
+                context.insert("is_isolation_detectable", &isolation.is_none());
+                let isolation = isolation.unwrap_or_default();
+                context.insert("isolation", &isolation);
+                context.insert(
+                    "should_suggest_fewer_temporary",
+                    &(isolation.isolates_sites()
+                        && relies_on_temporary(&global_context.preferences)),
+                );
+                Ok(render_with(context, self, &theme).await)
             }
-            OptionsBody => Ok(render_with(options_body(global_context), self).await),
         }
     }
 }
 
 /// View for the customization of container styles when creating a new
 /// container or updating an existing container.
+/// Names of other containers are listed for warning about duplicates.
 /// This may be renamed later to be less misleading.
-fn new_container(existing_container: Option<&Container>) -> Context {
+fn new_container(
+    global_context: &GlobalContext,
+    existing_container: Option<&Container>,
+) -> Context {
     let mut context = Context::new();
 
     context.insert(
@@ -97,6 +283,21 @@ fn new_container(existing_container: Option<&Container>) -> Context {
     );
 
     context.insert("update_existing", &existing_container.is_some());
+    let existing_id = existing_container.map(|container| container.handle().cookie_store_id());
+    context.insert(
+        "taken_names",
+        &global_context
+            .containers
+            .names_except(existing_id)
+            .collect::<Vec<&str>>(),
+    );
+    context.insert(
+        "duplicate_names",
+        &global_context
+            .containers
+            .duplicate_names()
+            .collect::<Vec<&str>>(),
+    );
     context.insert(
         "details",
         &match existing_container {
@@ -111,51 +312,106 @@ fn new_container(existing_container: Option<&Container>) -> Context {
     context
 }
 
+/// Number of containers listed per page if no limit is given.
+const CONTAINER_PAGE_SIZE: usize = 50;
+
 /// View for existing container list with additional action entries.
+/// If a filter is given, only containers with a name or a suffix containing
+/// it are listed.
+/// Only the page of containers starting from the offset is listed,
+/// the action entries are only on the first page, along with
+/// the selected container if it would be on a later page.
+/// An entry for the next page is listed with the total count
+/// if there are more containers.
 /// Returns a string of HTML fragment, which is an `option` element.
 /// Fails if the browser indicates so.
 async fn fetch_all_containers(
     global_context: &mut impl DerefMut<Target = GlobalContext>,
     selected: &CookieStoreId,
+    filter: Option<&str>,
+    offset: usize,
+    limit: usize,
 ) -> Result<String, CustomError> {
     let mut context = Context::new();
 
+    let mut containers = match filter {
+        Some(filter) if !filter.is_empty() => {
+            global_context.containers.search(filter).collect::<Vec<_>>()
+        }
+        _ => global_context.containers.iter().collect::<Vec<_>>(),
+    };
+    containers.retain(|container| container.variant != ContainerVariant::Temporary);
+    let container_sort = &global_context.preferences.container_sort;
+    containers.sort_by(|left, right| container_sort.compare(left, right));
+    if let Some(index) = containers
+        .iter()
+        .position(|container| container.handle().cookie_store_id() == selected)
+        .filter(|index| *index >= limit)
+    {
+        let selected_container = containers.remove(index);
+        containers.insert(0, selected_container);
+    }
+    let total = containers.len();
+    let next_offset = offset.saturating_add(limit).min(total);
     context.insert(
         "containers",
-        &global_context
-            .containers
-            .iter()
-            .filter_map(|container| {
-                use ContainerVariant::*;
-                match container.variant {
-                    Permanent | Recording { .. } => Some((
-                        container.handle().cookie_store_id().clone(),
-                        container.identity_details(),
-                    )),
-                    Temporary => None,
-                }
+        &containers
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|container| {
+                (
+                    container.handle().cookie_store_id().clone(),
+                    container.identity_details(),
+                )
             })
             .collect::<Vec<(CookieStoreId, IdentityDetails)>>(),
     );
 
     context.insert("selected", selected);
-    Ok(Tera::default()
-        .render_str(
-            r#"
-        <option value="none">No Container</option>
-        {% for container in containers %}
-            <option value="{{container.0}}"
-                {% if container.0 == selected %}selected=""{% endif %}>
-                {{container.1.name}}
-            </option>
-        {% endfor %}
-        <option value="new">+ Create New</option>
-    "#,
-            &context,
-        )
+    context.insert("filter", filter.unwrap_or_default());
+    context.insert("offset", &offset);
+    context.insert("next_offset", &next_offset);
+    context.insert("total", &total);
+    context.insert("remaining", &(total - next_offset).to_string());
+    Ok(TEMPLATE_ENGINE
+        .lock()
+        .await
+        .render(CONTAINER_LISTING_TEMPLATE_NAME, &context)
         .expect("controlled enum template rendering"))
 }
 
+/// Inserts the family of a container, which is the name of the canonical
+/// container if the container is a member, and the names of the members.
+fn insert_family(
+    context: &mut Context,
+    global_context: &GlobalContext,
+    cookie_store_id: &CookieStoreId,
+) {
+    let containers = &global_context.containers;
+    let canonical_id = containers.canonical_id(cookie_store_id);
+    let family_name = |cookie_store_id: &CookieStoreId| {
+        containers
+            .get(cookie_store_id)
+            .map(|container| container.identity_details().name)
+    };
+    let family_head = if &canonical_id == cookie_store_id {
+        None
+    } else {
+        family_name(&canonical_id)
+    };
+    context.insert("family_head", &family_head);
+    context.insert(
+        "family_members",
+        &containers
+            .family_members(&canonical_id)
+            .iter()
+            .filter(|member_id| *member_id != cookie_store_id)
+            .filter_map(family_name)
+            .collect::<Vec<String>>(),
+    );
+}
+
 /// View for the deletion confirmation prompt.
 fn delete_prompt(container: &Container) -> Context {
     let mut context = Context::new();
@@ -163,8 +419,9 @@ fn delete_prompt(container: &Container) -> Context {
     context
 }
 
-/// View for the body of the pop-up if a container is selected.
-fn container_detail(container: &Container) -> Context {
+/// View for the body of the pop-up if a container is selected,
+/// open tabs in the container are listed for switching to.
+fn container_detail(container: &Container, tabs: &[TabSummary]) -> Context {
     let mut context = Context::new();
     context.insert("icon_link", &container.identity_details().icon.url());
     context.insert("icon_color", &container.identity_details().color);
@@ -172,50 +429,700 @@ fn container_detail(container: &Container) -> Context {
         "is_recording",
         &matches!(container.variant, ContainerVariant::Recording { .. }),
     );
+    context.insert("is_locked", &!container.variant.allows_modification());
     context.insert(
         "suffixes",
         &container
             .suffixes
             .iter()
-            .map(|suffix| (suffix.raw(), suffix.encoded()))
-            .chain(iter::once((String::new(), String::new())))
-            .collect::<Vec<(String, String)>>(),
+            .map(|suffix| {
+                let toggled = suffix.toggle_exclusion().map(|toggled| toggled.raw());
+                (
+                    suffix.raw(),
+                    suffix.encoded(),
+                    toggled,
+                    suffix.is_exclusion(),
+                )
+            })
+            .chain(iter::once((String::new(), String::new(), None, false)))
+            .collect::<Vec<(String, String, Option<String>, bool)>>(),
+    );
+    context.insert(
+        "suffix_text",
+        &container
+            .suffixes
+            .iter()
+            .map(|suffix| suffix.raw())
+            .collect::<Vec<String>>()
+            .join("\n"),
+    );
+    context.insert(
+        "header_text",
+        &container
+            .request_headers
+            .iter()
+            .map(|header| header.to_string())
+            .collect::<Vec<String>>()
+            .join("\n"),
+    );
+    context.insert(
+        "rewrite_text",
+        &container
+            .rewrite_rules
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect::<Vec<String>>()
+            .join("\n"),
+    );
+    context.insert("homepage", &container.homepage);
+    context.insert(
+        "last_used",
+        &container
+            .last_used()
+            .map(|last_used| last_used.format("%Y-%m-%d %H:%M UTC").to_string()),
+    );
+    context.insert("priority", &container.priority);
+    context.insert("has_active_hours", &container.active_hours.is_some());
+    let active_hours = container.active_hours.clone().unwrap_or_default();
+    context.insert(
+        "weekdays",
+        &iter::successors(Some(Weekday::Mon), |weekday| Some(weekday.succ()))
+            .take(7)
+            .map(|weekday| {
+                (
+                    weekday.to_string().to_lowercase(),
+                    active_hours.weekdays.contains(&weekday),
+                )
+            })
+            .collect::<Vec<(String, bool)>>(),
+    );
+    context.insert("active_start_hour", &active_hours.start_hour);
+    context.insert("active_end_hour", &active_hours.end_hour);
+    context.insert("tabs", tabs);
+    context
+}
+
+/// Inserts the preferred form of domains as `domain_display`,
+/// and the other form as `domain_alternate` for showing on hover.
+/// Both are passed to the `domain_form` filter.
+fn insert_domain_display(context: &mut Context, global_context: &GlobalContext) {
+    let domain_display = &global_context.preferences.domain_display;
+    context.insert("domain_display", domain_display);
+    context.insert("domain_alternate", &domain_display.alternate());
+}
+
+/// View for the cookies in a container, grouped by domains.
+/// Lists domains and cookie counts for selective clearing.
+fn container_cookies(cookies: &[Cookie]) -> Context {
+    let mut context = Context::new();
+    let mut domain_counts = BTreeMap::<&str, usize>::new();
+    for cookie in cookies {
+        *domain_counts.entry(cookie.domain()).or_default() += 1;
+    }
+    context.insert("domain_counts", &domain_counts);
+    context
+}
+
+/// View for testing which container a URL would be opened in,
+/// from no container or from one of the listed containers.
+fn rule_tester(global_context: &GlobalContext) -> Context {
+    let mut context = Context::new();
+    context.insert("containers", &named_containers(global_context));
+    context
+}
+
+/// View for reopening a tab in another container,
+/// listing containers other than temporary containers in the preferred order.
+fn quick_switcher(global_context: &GlobalContext) -> Context {
+    let mut context = Context::new();
+    let mut containers = global_context
+        .containers
+        .iter()
+        .filter(|container| container.variant != ContainerVariant::Temporary)
+        .collect::<Vec<_>>();
+    let container_sort = &global_context.preferences.container_sort;
+    containers.sort_by(|left, right| container_sort.compare(left, right));
+    context.insert(
+        "containers",
+        &containers
+            .into_iter()
+            .map(|container| {
+                (
+                    container.handle().cookie_store_id().clone(),
+                    container.identity_details(),
+                )
+            })
+            .collect::<Vec<(CookieStoreId, IdentityDetails)>>(),
+    );
+    context
+}
+
+/// IDs and names of all containers sorted by name,
+/// for choosing the current container of a simulated tab.
+fn named_containers(global_context: &GlobalContext) -> Vec<(CookieStoreId, String)> {
+    let mut containers = global_context.containers.iter().collect::<Vec<_>>();
+    containers.sort_by(|left, right| ContainerSort::Name.compare(left, right));
+    containers
+        .into_iter()
+        .map(|container| {
+            (
+                container.handle().cookie_store_id().clone(),
+                container.identity_details().name,
+            )
+        })
+        .collect()
+}
+
+/// Sample URLs for previewing the strategies,
+/// one accepted by a permanent container and one accepted by
+/// a temporary container if there are any, then one for an unknown site.
+fn preview_urls(global_context: &GlobalContext) -> Vec<String> {
+    let sample_domain = |variant: ContainerVariant| {
+        global_context
+            .containers
+            .iter()
+            .filter(|container| container.variant == variant)
+            .flat_map(|container| container.suffixes.iter())
+            .find(|suffix| !suffix.is_exclusion())
+            .map(|suffix| suffix.domain().encoded().to_owned())
+    };
+    [
+        sample_domain(ContainerVariant::Permanent),
+        sample_domain(ContainerVariant::Temporary),
+        Some(String::from("example.com")),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|domain| format!("https://{domain}/"))
+    .collect()
+}
+
+/// View for choosing where a tab should be opened when prompted,
+/// listing the current container and the permanent containers
+/// that accept the domain, the choice can be remembered for the site.
+/// The prompt has expired if there is no pending prompt.
+fn relocation_chooser(
+    global_context: &GlobalContext,
+    pending_prompt: Option<(EncodedDomain, CookieStoreId)>,
+) -> Context {
+    let mut context = Context::new();
+    let Some((domain, current_cookie_store_id)) = pending_prompt else {
+        context.insert("is_expired", &true);
+        return context;
+    };
+    context.insert("is_expired", &false);
+    context.insert("domain", domain.raw());
+    let suffix = DomainOverride::suffix_for(&global_context.psl, domain.clone());
+    context.insert("remembered_suffix", &suffix.raw());
+    let current_name = global_context
+        .containers
+        .get(&current_cookie_store_id)
+        .map(|container| container.identity_details().name);
+    context.insert("current_name", &current_name);
+    let containers = global_context
+        .containers
+        .find_matches(domain, &global_context.psl, &SystemClock)
+        .into_iter()
+        .filter(|cookie_store_id| cookie_store_id != &current_cookie_store_id)
+        .filter_map(|cookie_store_id| {
+            let container = global_context.containers.get(&cookie_store_id)?;
+            (container.variant != ContainerVariant::Temporary)
+                .then(|| (cookie_store_id, container.identity_details().name))
+        })
+        .collect::<Vec<(CookieStoreId, String)>>();
+    context.insert("containers", &containers);
+    context
+}
+
+/// Default number of days covered by the temporary container report.
+const TEMPORARY_REPORT_DAYS: u64 = 30;
+
+/// Maximum number of domains listed in the temporary container report.
+const TEMPORARY_REPORT_LIMIT: usize = 20;
+
+/// View for the domains most frequently relocated to temporary containers
+/// over the last number of days, with the most frequent domain first.
+/// Domains already claimed by a container are skipped.
+fn temporary_report(global_context: &GlobalContext, days: u64) -> Context {
+    let mut context = Context::new();
+    let entries = global_context
+        .temporary_statistics
+        .most_frequent(Utc::now().date_naive(), days)
+        .into_iter()
+        .filter(|(suffix, _count)| {
+            !global_context
+                .containers
+                .iter()
+                .any(|container| container.suffixes.contains(suffix))
+        })
+        .take(TEMPORARY_REPORT_LIMIT)
+        .collect::<Vec<(&Suffix, u32)>>();
+    context.insert("entries", &entries);
+    context.insert("days", &days);
+    context.insert("max_days", &statistics::RETENTION_DAYS);
+    context
+}
+
+/// Summary of a saved session for listing.
+#[derive(Serialize)]
+struct SessionSummary<'a> {
+    name: &'a str,
+    saved_at: String,
+    tab_count: usize,
+}
+
+/// Summary of the session saved before the last purge.
+#[derive(Serialize)]
+struct LastSessionSummary {
+    saved_at: String,
+    container_count: usize,
+}
+
+/// View for the saved sessions, most recently saved first,
+/// and the last session saved before a purge, if any.
+fn sessions_list(sessions: &session::Sessions) -> Context {
+    let mut context = Context::new();
+    let mut summaries = sessions
+        .iter()
+        .map(|(name, session)| (session.saved_at, name, session.tab_count()))
+        .collect::<Vec<_>>();
+    summaries.sort_by_key(|(saved_at, _name, _tab_count)| Reverse(*saved_at));
+    let summaries = summaries
+        .into_iter()
+        .map(|(saved_at, name, tab_count)| SessionSummary {
+            name,
+            saved_at: saved_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            tab_count,
+        })
+        .collect::<Vec<_>>();
+    context.insert("sessions", &summaries);
+    if let Some(last_session) = sessions.last_session() {
+        let summary = LastSessionSummary {
+            saved_at: last_session
+                .saved_at
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+            container_count: last_session.container_count(),
+        };
+        context.insert("last_session", &summary);
+    }
+    context
+}
+
+/// Step of the welcome wizard, which guides first-time users through
+/// the setup before they are shown the overview.
+/// - [Overview](WelcomeStep::Overview) links to the tools,
+///   shown once there are containers.
+/// - [Identities](WelcomeStep::Identities) lists the existing identities
+///   that are not owned by the extension.
+/// - [Import](WelcomeStep::Import) offers importing existing containers.
+/// - [Psl](WelcomeStep::Psl) proposes downloading the latest
+///   public suffix list.
+/// - [Recording](WelcomeStep::Recording) creates a first recording container.
+#[derive(Clone, Copy, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WelcomeStep {
+    Overview,
+    Identities,
+    Import,
+    Psl,
+    Recording,
+}
+
+/// View for the body of the pop-up if no container is selected,
+/// conflicting extensions are listed as they pause relocation.
+/// The date of the public suffix list is included for its step.
+fn welcome(global_context: &GlobalContext, step: WelcomeStep) -> Context {
+    let mut context = Context::new();
+    context.insert("step", &step);
+    context.insert(
+        "conflicting_extensions",
+        &global_context.conflicting_extensions,
+    );
+    context.insert("psl_last_updated", &global_context.psl.last_updated());
+    context
+}
+
+/// Remembered choice of a site for listing,
+/// names are empty if the containers no longer exist.
+#[derive(Serialize)]
+struct DomainOverrideRow<'a> {
+    suffix: String,
+    from_container: &'a CookieStoreId,
+    from_name: String,
+    target: &'a ContainerTarget,
+    target_name: String,
+}
+
+impl<'a> DomainOverrideRow<'a> {
+    /// Creates a row of a remembered choice,
+    /// with the names of the containers looked up from the context.
+    fn new(
+        global_context: &GlobalContext,
+        suffix: &Suffix,
+        domain_override: &'a DomainOverride,
+    ) -> Self {
+        let name_of = |cookie_store_id| {
+            global_context
+                .containers
+                .get(cookie_store_id)
+                .map(|container| container.identity_details().name)
+                .unwrap_or_default()
+        };
+        let target_name = match &domain_override.target {
+            ContainerTarget::Designated { cookie_store_id } => name_of(cookie_store_id),
+            ContainerTarget::NoContainer | ContainerTarget::Temporary => String::new(),
+        };
+        Self {
+            suffix: suffix.raw(),
+            from_container: &domain_override.from_container,
+            from_name: name_of(&domain_override.from_container),
+            target: &domain_override.target,
+            target_name,
+        }
+    }
+}
+
+/// Checks if the preferences create temporary containers for untracked sites,
+/// which is less necessary if the browser already isolates sites.
+fn relies_on_temporary(preferences: &Preferences) -> bool {
+    matches!(
+        preferences.eject_strategy,
+        ContainerEjectStrategy::IsolatedTemporary
+    ) || matches!(preferences.new_tab_container, ContainerTarget::Temporary)
+        || matches!(
+            preferences.external_link_container,
+            ContainerTarget::Temporary
+        )
+}
+
+/// View for the devtools panel, describing the inspected tab.
+/// Traces are given both as text and as the JSON given to the panel.
+fn devtools_panel(inspection: &TabInspection) -> Context {
+    let mut context = Context::new();
+    context.insert("inspection", inspection);
+    context.insert(
+        "last_trace",
+        &inspection
+            .last_trace
+            .as_ref()
+            .map(|trace| trace.to_string()),
+    );
+    context.insert(
+        "current_trace",
+        &inspection
+            .current_trace
+            .as_ref()
+            .map(|trace| trace.to_string()),
+    );
+    context.insert(
+        "inspection_json",
+        &JSON::stringify_with_replacer_and_space(
+            &interop::to_jsvalue(inspection),
+            &JsValue::NULL,
+            &JsValue::from(2),
+        )
+        .map(String::from)
+        .unwrap_or_default(),
     );
     context
 }
 
 /// View for the body of the preferences page.
 /// May be rename to `preference_body` as the name has changed for that page.
-fn options_body(global_context: &mut impl DerefMut<Target = GlobalContext>) -> Context {
+/// Bindings to folders that no longer exist are listed without titles.
+fn options_body(
+    global_context: &mut impl DerefMut<Target = GlobalContext>,
+    folders: &[BookmarkFolder],
+) -> Context {
     let mut context = Context::new();
     let last_updated = global_context.psl.last_updated();
     context.insert("psl_last_updated", &last_updated);
+    context.insert("psl_default_url", PSL_DEFAULT_URL);
     let duration_since_update = Utc::now().date_naive().signed_duration_since(last_updated);
     context.insert(
         "psl_no_update",
         &(duration_since_update < Duration::weeks(1)),
     );
-    context.insert(
-        "assign_strategy",
-        &global_context.preferences.assign_strategy,
-    );
+    let assign_chain = global_context.preferences.assign_chain.steps();
+    let assign_steps: Vec<(AssignStep, bool)> = assign_chain
+        .iter()
+        .map(|step| (*step, true))
+        .chain(
+            AssignStep::iter()
+                .filter(|step| !assign_chain.contains(step))
+                .map(|step| (step, false)),
+        )
+        .collect();
+    context.insert("assign_steps", &assign_steps);
     context.insert("eject_strategy", &global_context.preferences.eject_strategy);
     context.insert(
         "should_revert_old_tab",
         &global_context.preferences.should_revert_old_tab,
     );
+    context.insert("container_sort", &global_context.preferences.container_sort);
+    context.insert("domain_display", &global_context.preferences.domain_display);
+    context.insert("theme_mode", &global_context.preferences.theme_mode);
+    context.insert("preview_urls", &preview_urls(global_context));
+    context.insert("preview_containers", &named_containers(global_context));
+    context.insert("idna_policy", &global_context.preferences.idna_policy);
+    context.insert("log_level", &global_context.preferences.log_level);
+    context.insert(
+        "large_data_backend",
+        &global_context.preferences.large_data_backend,
+    );
+    context.insert(
+        "temporary_container_color",
+        &global_context.preferences.temporary_container_color,
+    );
+    context.insert(
+        "should_notify_relocation",
+        &global_context.preferences.should_notify_relocation,
+    );
+    context.insert(
+        "should_simulate_relocation",
+        &global_context.preferences.should_simulate_relocation,
+    );
+    context.insert(
+        "should_learn_suffixes",
+        &global_context.preferences.should_learn_suffixes,
+    );
+    context.insert(
+        "should_reduce_suffixes",
+        &global_context.preferences.should_reduce_suffixes,
+    );
+    context.insert(
+        "should_inherit_opener_container",
+        &global_context.preferences.should_inherit_opener_container,
+    );
+    context.insert("opener_chain", &global_context.preferences.opener_chain);
+    context.insert(
+        "new_tab_container",
+        &global_context.preferences.new_tab_container,
+    );
+    context.insert(
+        "external_link_container",
+        &global_context.preferences.external_link_container,
+    );
+    context.insert(
+        "external_link_rules",
+        &global_context
+            .preferences
+            .external_link_rules
+            .iter()
+            .map(|(suffix, target)| (suffix.raw(), target))
+            .collect::<Vec<(String, &ContainerTarget)>>(),
+    );
+    context.insert(
+        "domain_overrides",
+        &global_context
+            .preferences
+            .domain_overrides
+            .iter()
+            .flat_map(|(suffix, domain_overrides)| {
+                domain_overrides.iter().map(|domain_override| {
+                    DomainOverrideRow::new(global_context, suffix, domain_override)
+                })
+            })
+            .collect::<Vec<DomainOverrideRow>>(),
+    );
+    context.insert(
+        "conflicting_extensions",
+        &global_context.conflicting_extensions,
+    );
+    context.insert(
+        "are_preferences_managed",
+        &global_context.are_preferences_managed,
+    );
+    context.insert("is_guest_mode", &global_context.guest_mode.is_some());
+    context.insert(
+        "guest_has_pin",
+        &global_context
+            .guest_mode
+            .as_ref()
+            .is_some_and(GuestMode::has_pin),
+    );
+    context.insert(
+        "trusted_extension_ids",
+        &global_context
+            .preferences
+            .trusted_extension_ids
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join("\n"),
+    );
+    context.insert(
+        "web_schemes",
+        &WebScheme::iter()
+            .map(|web_scheme| {
+                let is_intercepted = global_context
+                    .preferences
+                    .intercepted_schemes
+                    .contains(&web_scheme);
+                (web_scheme.to_string(), is_intercepted)
+            })
+            .collect::<Vec<(String, bool)>>(),
+    );
+    context.insert(
+        "additional_schemes",
+        &global_context
+            .preferences
+            .additional_schemes
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(" "),
+    );
+
+    let mut containers = global_context
+        .containers
+        .iter()
+        .filter(|container| container.variant != ContainerVariant::Temporary)
+        .collect::<Vec<_>>();
+    containers.sort_by(|left, right| ContainerSort::Name.compare(left, right));
+    context.insert(
+        "containers",
+        &containers
+            .into_iter()
+            .map(|container| {
+                (
+                    container.handle().cookie_store_id().clone(),
+                    container.identity_details().name,
+                )
+            })
+            .collect::<Vec<(CookieStoreId, String)>>(),
+    );
+    context.insert("bookmark_folders", folders);
+    context.insert(
+        "bookmark_bindings",
+        &global_context
+            .bookmark_bindings
+            .iter()
+            .filter_map(|(folder_id, cookie_store_id)| {
+                let container = global_context.containers.get(cookie_store_id)?;
+                let folder_title = folders
+                    .iter()
+                    .find(|folder| &folder.id == folder_id)
+                    .map(|folder| folder.title.clone());
+                Some((folder_id, folder_title, container.identity_details().name))
+            })
+            .collect::<Vec<_>>(),
+    );
+    context.insert(
+        "alias_group_text",
+        &global_context.alias_groups.to_lines().join("\n"),
+    );
     context
 }
 
+/// Colors of the views, available to all templates as `theme`.
+#[derive(Default, Serialize)]
+struct ViewTheme {
+    scheme: &'static str,
+    background: Option<String>,
+    text: Option<String>,
+}
+
+/// Resolves the colors of the views from the preferred [ThemeMode].
+/// The colors of the browser's theme are used if there are any,
+/// otherwise the scheme follows the system.
+async fn view_theme(theme_mode: ThemeMode) -> ViewTheme {
+    match theme_mode {
+        ThemeMode::Light => ViewTheme {
+            scheme: "light",
+            ..Default::default()
+        },
+        ThemeMode::Dark => ViewTheme {
+            scheme: "dark",
+            ..Default::default()
+        },
+        ThemeMode::Browser => {
+            let colors = theme::current_colors().await.unwrap_or_else(|error| {
+                log::warn!("browser theme is not applied, {error}");
+                Default::default()
+            });
+            ViewTheme {
+                scheme: "light dark",
+                background: colors.background(),
+                text: colors.text(),
+            }
+        }
+    }
+}
+
 /// Helper for rendering, since the templates are stored in the same directory,
 /// and the fetching methods are the same.
-/// Returns the rendered template as a string.
-async fn render_with(context: Context, view: &View) -> String {
-    Tera::default()
-        .render_str(
-            &interop::fetch_extension_file(&format!("components/{filename}.html", filename = view))
-                .await,
-            &context,
-        )
-        .expect("controlled enum template rendering")
+/// Returns the rendered template as a string, preceded by the theme styles.
+/// Templates are fetched and parsed on first render, then cached.
+async fn render_with(mut context: Context, view: &View, theme: &ViewTheme) -> String {
+    let template_name = view.to_string();
+    let mut tera = TEMPLATE_ENGINE.lock().await;
+    if !tera.get_template_names().any(|name| name == template_name) {
+        let template =
+            interop::fetch_extension_file(&format!("components/{template_name}.html")).await;
+        tera.add_raw_template(&template_name, &template)
+            .expect("controlled enum template parsing");
+    }
+    context.insert("theme", theme);
+    let theme_styles = tera
+        .render(THEME_TEMPLATE_NAME, &context)
+        .expect("controlled template rendering");
+    theme_styles
+        + &tera
+            .render(&template_name, &context)
+            .expect("controlled enum template rendering")
 }
+
+/// Name of the template for [fetch_all_containers].
+const CONTAINER_LISTING_TEMPLATE_NAME: &str = "container-listing";
+
+/// Name of the template for the styles of [ViewTheme].
+const THEME_TEMPLATE_NAME: &str = "theme";
+
+/// Template engine with functions that are available to all views,
+/// currently `i18n` for localized messages,
+/// and the `domain_form` filter for switching forms of domains.
+/// Templates are named by the views without extensions,
+/// so that they are not autoescaped.
+static TEMPLATE_ENGINE: Lazy<Mutex<Tera>> = Lazy::new(|| {
+    let mut tera = Tera::default();
+    tera.register_function("i18n", i18n::tera_function);
+    tera.register_filter("domain_form", suffix::tera_filter);
+    tera.add_raw_template(
+        CONTAINER_LISTING_TEMPLATE_NAME,
+        r#"
+        {% if offset == 0 %}
+            <option value="none">{{ i18n(key="container_list_none") }}</option>
+        {% endif %}
+        {% for container in containers %}
+            <option value="{{container.0}}"
+                {% if container.0 == selected %}selected=""{% endif %}>
+                {{container.1.name}}
+            </option>
+        {% endfor %}
+        {% if next_offset < total %}
+            <option value="more" data-next-offset="{{next_offset}}" data-total="{{total}}"
+                data-selected="{{selected}}" data-filter="{{filter | escape}}">
+                {{ i18n(key="container_list_more", substitutions=[remaining]) }}
+            </option>
+        {% endif %}
+        {% if offset == 0 %}
+            <option value="new">{{ i18n(key="container_list_new") }}</option>
+        {% endif %}
+    "#,
+    )
+    .expect("controlled template parsing");
+    tera.add_raw_template(
+        THEME_TEMPLATE_NAME,
+        r#"
+        <style>
+            :root { color-scheme: {{theme.scheme}}; }
+            {% if theme.background %}body { background-color: {{theme.background}}; }{% endif %}
+            {% if theme.text %}body { color: {{theme.text}}; }{% endif %}
+        </style>
+    "#,
+    )
+    .expect("controlled template parsing");
+    Mutex::new(tera)
+});
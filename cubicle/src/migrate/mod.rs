@@ -4,14 +4,24 @@
 pub mod import;
 
 use chrono::NaiveDate;
+use js_sys::{JsString, Object, Reflect};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::container::ContainerKey;
+use crate::interop::contextual_identities::CookieStoreId;
+use crate::interop::storage;
+use crate::util::errors::CustomError;
+
+/// Key of the version before storage keys were namespaced.
+const LEGACY_VERSION_KEY: &str = "version";
+
 /// Versioning of [GlobalContext](crate::context::GlobalContext)
 /// for migrating and detecteing older version.
 /// The versioning scheme is to be decided in the next release.
 #[derive(Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Version {
+    #[serde(rename = "meta/version")]
     pub version: (i16, i16, i16),
 }
 pub const CURRENT_VERSION: Version = Version { version: (0, 1, 0) };
@@ -19,6 +29,47 @@ pub static BUILTIN_PSL_VERSION: Lazy<NaiveDate> = Lazy::new(|| {
     NaiveDate::from_ymd_opt(2023, 5, 8).expect("date checked to be valid at compile time")
 });
 
+/// Moves entries stored before storage keys were namespaced,
+/// containers keyed by their encoded [CookieStoreId] are moved to
+/// their [ContainerKey], and the version is moved to `meta/version`.
+/// Does nothing if the legacy version does not exist.
+/// Entries are removed only after they are moved,
+/// so an interrupted migration is completed on the next attempt.
+/// Fails if the browser indicates so.
+pub async fn namespace_storage_keys() -> Result<(), CustomError> {
+    let all_stored = storage::get_all().await?;
+    let get_stored = |key: &str| {
+        Reflect::get(&all_stored, &JsString::from(key))
+            .expect("constructed object from get all function")
+    };
+    let legacy_version = get_stored(LEGACY_VERSION_KEY);
+    if legacy_version.is_undefined() {
+        return Ok(());
+    }
+    let moved_entries = Object::new();
+    Reflect::set(
+        &moved_entries,
+        &JsString::from("meta/version"),
+        &legacy_version,
+    )
+    .expect("inline construction");
+    let mut legacy_keys = vec![String::from(LEGACY_VERSION_KEY)];
+    for key in Object::keys(&all_stored)
+        .iter()
+        .filter_map(|key| key.as_string())
+    {
+        let Some(cookie_store_id) = CookieStoreId::from_encoded(&key) else {
+            continue;
+        };
+        let new_key = ContainerKey::from(&cookie_store_id).to_string();
+        Reflect::set(&moved_entries, &JsString::from(new_key), &get_stored(&key))
+            .expect("inline construction");
+        legacy_keys.push(key);
+    }
+    storage::set_with_value_keys(&moved_entries).await?;
+    storage::remove_entries(&legacy_keys).await
+}
+
 #[cfg(test)]
 mod test {
     use wasm_bindgen_test::wasm_bindgen_test;
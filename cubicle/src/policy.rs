@@ -0,0 +1,58 @@
+//! Container policy and preferences managed by administrators,
+//! read from the managed storage or pulled from a native host at startup.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::domain::suffix::Suffix;
+use crate::interop::{self, native_messaging, storage};
+use crate::preferences::Preferences;
+use crate::util::errors::CustomError;
+
+/// Name of the native host that provides the managed policy.
+pub const POLICY_HOST: &str = "cubicle_policy";
+
+/// Request sent to the native host, serializes to a tagged object.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "message_type")]
+enum PolicyRequest {
+    GetPolicy,
+}
+
+/// Containers and preferences that are enforced by administrators,
+/// deserializes from the managed storage or the response of the native host.
+#[derive(Default, Deserialize)]
+pub struct ManagedPolicy {
+    #[serde(default)]
+    pub containers: Vec<ManagedContainer>,
+    #[serde(default)]
+    pub preferences: Option<Preferences>,
+}
+
+/// Container identified by its name, and the suffixes that should
+/// always open in it.
+#[derive(Deserialize)]
+pub struct ManagedContainer {
+    pub name: String,
+    #[serde(default)]
+    pub suffixes: BTreeSet<Suffix>,
+}
+
+impl ManagedPolicy {
+    /// Pulls the policy from the native host.
+    /// Fails if the host is not installed or the policy is malformed,
+    /// which is expected for browsers that are not managed.
+    pub async fn fetch() -> Result<Self, CustomError> {
+        native_messaging::send(POLICY_HOST, &PolicyRequest::GetPolicy).await
+    }
+
+    /// Reads the policy from the managed storage.
+    /// Fails if nothing is provisioned or the policy is malformed,
+    /// which is expected for browsers that are not managed.
+    pub async fn from_managed_storage() -> Result<Self, CustomError> {
+        let managed = storage::get_all_managed().await?;
+        interop::cast_or_standard_mismatch(JsValue::from(managed))
+    }
+}
@@ -1,59 +1,326 @@
 //! All preferences that are not container or storage item specific.
 
-use std::collections::BTreeSet;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet};
 
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
 
-use crate::container::{Container, ContainerHandle, ContainerVariant};
+use crate::container::{Container, ContainerHandle, ContainerKey, ContainerVariant};
 use crate::context::GlobalContext;
-use crate::domain::suffix::{Suffix, SuffixType};
-use crate::domain::EncodedDomain;
-use crate::interop::contextual_identities::{CookieStoreId, IdentityDetails};
-use crate::interop::storage;
+use crate::domain::psl::Psl;
+use crate::domain::suffix::{self, MatchMode, Suffix, SuffixType};
+use crate::domain::{EncodedDomain, IdnaPolicy};
+use crate::interop::contextual_identities::{
+    CookieStoreId, IdentityColor, IdentityDetails, IdentityDetailsProvider,
+};
+use crate::interop::storage::{self, StorageBackend};
+use crate::rules::Decision;
+use crate::transaction::Staged;
 use crate::util::errors::CustomError;
+use crate::util::log::LogLevel;
 
 /// All preferences that are not container or storage item specific.
-#[derive(Derivative, Deserialize, Serialize)]
+#[derive(Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Default)]
 pub struct Preferences {
-    pub assign_strategy: ContainerAssignStrategy,
+    #[serde(default, alias = "assign_strategy")]
+    pub assign_chain: AssignChain,
+    #[serde(default)]
     pub eject_strategy: ContainerEjectStrategy,
     #[derivative(Default(value = "true"))]
     pub should_revert_old_tab: bool,
+    #[serde(default)]
+    pub container_sort: ContainerSort,
+    #[serde(default)]
+    pub domain_display: DomainDisplay,
+    #[serde(default)]
+    pub theme_mode: ThemeMode,
+    #[serde(default)]
+    pub idna_policy: IdnaPolicy,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub should_notify_relocation: bool,
+    #[serde(default)]
+    pub should_simulate_relocation: bool,
+    #[serde(default)]
+    pub should_learn_suffixes: bool,
+    #[serde(default)]
+    pub should_reduce_suffixes: bool,
+    #[serde(default)]
+    pub should_inherit_opener_container: bool,
+    #[serde(default)]
+    pub opener_chain: OpenerChain,
+    #[serde(default)]
+    pub new_tab_container: ContainerTarget,
+    #[serde(default)]
+    pub external_link_container: ContainerTarget,
+    #[serde(default)]
+    pub external_link_rules: BTreeMap<Suffix, ContainerTarget>,
+    #[serde(default)]
+    pub domain_overrides: BTreeMap<Suffix, Vec<DomainOverride>>,
+    #[serde(default)]
+    pub temporary_container_color: IdentityColor,
+    #[serde(default)]
+    pub trusted_extension_ids: BTreeSet<String>,
+    #[serde(default)]
+    pub additional_schemes: BTreeSet<String>,
+    #[derivative(Default(value = "WebScheme::default_intercepted()"))]
+    #[serde(default = "WebScheme::default_intercepted")]
+    pub intercepted_schemes: BTreeSet<WebScheme>,
+    #[serde(default)]
+    pub large_data_backend: StorageBackend,
 }
 
-/// Assigning strategy for tabs that are previously not contained,
-/// mainly addresses what happens if no permanent container accepts the tab.
-/// - [SuffixedTemporary](ContainerAssignStrategy::SuffixedTemporary) means
-///   that the tab will be assigned to a new or existing temporary container
-///   that matches the public suffix of the domain.
-/// - [IsolatedTemporary](ContainerAssignStrategy::IsolatedTemporary) means
-///   that a new temporary container will always be created for the tab.
-#[derive(Clone, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+impl Preferences {
+    /// Checks if the extension with the ID is allowed to send messages,
+    /// senders without an ID are never trusted.
+    pub fn trusts_extension(&self, extension_id: Option<&str>) -> bool {
+        extension_id.is_some_and(|extension_id| self.trusted_extension_ids.contains(extension_id))
+    }
+
+    /// Target of a link opened from another application,
+    /// the rule with the most specific suffix matching the domain wins.
+    pub fn external_link_target(&self, domain: EncodedDomain) -> &ContainerTarget {
+        suffix::match_suffix(&self.external_link_rules, domain, MatchMode::Full)
+            .next()
+            .and_then(|(_domain, suffix)| self.external_link_rules.get(&suffix))
+            .unwrap_or(&self.external_link_container)
+    }
+
+    /// Target remembered for navigations from the container to the domain,
+    /// the override with the most specific suffix matching the domain wins.
+    /// [None] if the user has not made a choice to remember.
+    pub fn domain_override(
+        &self,
+        domain: EncodedDomain,
+        from_container: &CookieStoreId,
+    ) -> Option<&ContainerTarget> {
+        suffix::match_suffix(&self.domain_overrides, domain, MatchMode::Full).find_map(
+            |(_domain, suffix)| {
+                self.domain_overrides[&suffix]
+                    .iter()
+                    .find(|domain_override| &domain_override.from_container == from_container)
+                    .map(|domain_override| &domain_override.target)
+            },
+        )
+    }
+
+    /// Remembers the target for navigations from the container
+    /// to domains matching the suffix, replacing the previous target.
+    pub fn remember_domain_override(
+        &mut self,
+        suffix: Suffix,
+        from_container: CookieStoreId,
+        target: ContainerTarget,
+    ) {
+        let domain_overrides = self.domain_overrides.entry(suffix).or_default();
+        domain_overrides.retain(|domain_override| domain_override.from_container != from_container);
+        domain_overrides.push(DomainOverride {
+            from_container,
+            target,
+        });
+    }
+}
+
+impl Staged for Preferences {
+    fn stage(&self) -> Self {
+        self.clone()
+    }
+
+    fn commit(&mut self, staged: Self) {
+        *self = staged;
+    }
+}
+
+/// Limits of following openers of openers when deciding if a tab should
+/// stay in the container of an opener, so that interstitial pages between
+/// them do not break the decision.
+/// A depth of 1 only follows the immediate opener, which never expires.
+/// Links between further openers expire after the specified seconds.
+#[derive(Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Default)]
-#[serde(rename_all = "snake_case")]
-pub enum ContainerAssignStrategy {
+pub struct OpenerChain {
+    #[derivative(Default(value = "1"))]
+    pub depth: u8,
+    #[derivative(Default(value = "30"))]
+    pub expiry_secs: u32,
+}
+
+/// Container to open certain kinds of new tabs in,
+/// such as those showing the new tab page.
+/// - [NoContainer](ContainerTarget::NoContainer) means that such tabs are
+///   left alone.
+/// - [Designated](ContainerTarget::Designated) means that such tabs are
+///   moved to the specified container, or left alone if it does not exist.
+/// - [Temporary](ContainerTarget::Temporary) means that such tabs are
+///   moved to a new temporary container.
+#[derive(Clone, Derivative, Deserialize, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case", tag = "target")]
+pub enum ContainerTarget {
     #[derivative(Default)]
-    SuffixedTemporary,
-    IsolatedTemporary,
+    NoContainer,
+    Designated {
+        cookie_store_id: CookieStoreId,
+    },
+    Temporary,
 }
 
-impl ContainerAssignStrategy {
-    /// Matches a tab's domain to an accepting container, regardless of type.
-    /// Returns a container handle that must be properly released.
+impl ContainerTarget {
+    /// Finds or creates the container for a tab.
+    /// Returns a container handle that must be properly released,
+    /// [None] if the tab should not be moved.
     /// Fails if the browser indicates so.
-    #[must_use = "clean up must be done before releasing the handle"]
     pub async fn match_container(
         &self,
         global_context: &mut GlobalContext,
-        domain: EncodedDomain,
-    ) -> Result<ContainerHandle, CustomError> {
-        if let Some(container_match) = global_context.containers.match_container(domain.clone()) {
-            return Ok(container_match.container.handle().clone());
+    ) -> Result<Option<ContainerHandle>, CustomError> {
+        match self {
+            Self::NoContainer => Ok(None),
+            Self::Designated { cookie_store_id } => Ok(global_context
+                .containers
+                .get(cookie_store_id)
+                .map(|container| container.handle().clone())),
+            Self::Temporary => new_temporary_container(global_context, None)
+                .await
+                .map(Some),
+        }
+    }
+}
+
+/// Choice remembered for navigations from a container to a site,
+/// so that the user is not prompted again for the same pair.
+/// Sites are remembered by the suffix of their registrable domains,
+/// see [DomainOverride::suffix_for].
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DomainOverride {
+    pub from_container: CookieStoreId,
+    pub target: ContainerTarget,
+}
+
+impl DomainOverride {
+    /// The suffix that a choice for the domain is remembered by,
+    /// which matches the registrable domain and its subdomains.
+    pub fn suffix_for(psl: &Psl, domain: EncodedDomain) -> Suffix {
+        let registrable_domain = psl.match_suffix(domain.clone()).unwrap_or(domain);
+        Suffix::new(SuffixType::Normal, registrable_domain)
+    }
+}
+
+/// Schemes of web navigations that may trigger relocation,
+/// other schemes can only be opted in as additional schemes.
+#[derive(
+    Clone,
+    Copy,
+    Deserialize,
+    Display,
+    EnumIter,
+    EnumString,
+    Eq,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum WebScheme {
+    Http,
+    Https,
+    Ws,
+    Wss,
+}
+
+impl WebScheme {
+    /// Schemes intercepted unless configured otherwise.
+    fn default_intercepted() -> BTreeSet<Self> {
+        BTreeSet::from([Self::Http, Self::Https])
+    }
+}
+
+/// Step of assigning tabs that are previously not contained.
+/// - [Overrides](AssignStep::Overrides) means that the tab will be assigned
+///   to the container designated by a window default, a bookmark binding
+///   or an external link rule, if there is one.
+/// - [PermanentSuffixes](AssignStep::PermanentSuffixes) means that the tab
+///   will be assigned to the container matched by suffixes,
+///   if it is not a temporary container.
+/// - [SuffixedTemporary](AssignStep::SuffixedTemporary) means
+///   that the tab will be assigned to a new or existing temporary container
+///   that matches the public suffix of the domain.
+/// - [IsolatedTemporary](AssignStep::IsolatedTemporary) means
+///   that a new temporary container will always be created for the tab.
+#[derive(Clone, Copy, Deserialize, EnumIter, Eq, PartialEq, Serialize)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(rename_all = "snake_case")]
+pub enum AssignStep {
+    Overrides,
+    PermanentSuffixes,
+    SuffixedTemporary,
+    IsolatedTemporary,
+}
+
+/// Steps of assigning tabs that are previously not contained,
+/// evaluated in order until one of them yields a container.
+/// Temporary steps always yield, a new isolated temporary container
+/// is created if no step yields.
+/// A single step is read as a chain that has the overrides and the
+/// permanent suffixes before it, which is how strategies were stored.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "StoredAssignChain", into = "Vec<AssignStep>")]
+pub struct AssignChain {
+    steps: Vec<AssignStep>,
+}
+
+impl AssignChain {
+    /// Creates a chain that evaluates the steps in order.
+    pub fn new(steps: Vec<AssignStep>) -> Self {
+        Self { steps }
+    }
+
+    /// Creates a chain with the overrides and the permanent suffixes
+    /// before the given step.
+    fn with_strategy(step: AssignStep) -> Self {
+        Self {
+            steps: vec![AssignStep::Overrides, AssignStep::PermanentSuffixes, step],
+        }
+    }
+
+    /// Steps in the order of evaluation.
+    pub fn steps(&self) -> &[AssignStep] {
+        &self.steps
+    }
+}
+
+impl Default for AssignChain {
+    fn default() -> Self {
+        Self::with_strategy(AssignStep::SuffixedTemporary)
+    }
+}
+
+impl From<AssignChain> for Vec<AssignStep> {
+    fn from(assign_chain: AssignChain) -> Self {
+        assign_chain.steps
+    }
+}
+
+/// Assigning chain as stored, see [AssignChain].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAssignChain {
+    Steps(Vec<AssignStep>),
+    Strategy(AssignStep),
+}
+
+impl From<StoredAssignChain> for AssignChain {
+    fn from(stored: StoredAssignChain) -> Self {
+        match stored {
+            StoredAssignChain::Steps(steps) => Self::new(steps),
+            StoredAssignChain::Strategy(step) => Self::with_strategy(step),
         }
-        let domain = (*self == ContainerAssignStrategy::SuffixedTemporary).then_some(domain);
-        new_temporary_container(global_context, domain).await
     }
 }
 
@@ -66,7 +333,14 @@ impl ContainerAssignStrategy {
 ///   referral links.
 /// - [Reassignment](ContainerEjectStrategy::Reassignment) means that the tab
 ///   will be relocated as if it is a new uncontained tab, using a
-///   [ContainerAssignStrategy].
+///   [AssignChain].
+/// - [StayWithOpener](ContainerEjectStrategy::StayWithOpener) means that
+///   the tab will be kept in the container of the tab that opened it,
+///   useful for documentation links opened from a web application.
+///   A new temporary container is created if there is no such container.
+/// - [Prompt](ContainerEjectStrategy::Prompt) means that the relocation
+///   will be paused until the user chooses where the tab should be opened,
+///   a new temporary container is created if no choice is made in time.
 #[derive(Clone, Derivative, Deserialize, Serialize)]
 #[derivative(Default)]
 #[serde(rename_all = "snake_case")]
@@ -75,58 +349,123 @@ pub enum ContainerEjectStrategy {
     IsolatedTemporary,
     RemainInPlace,
     Reassignment,
+    StayWithOpener,
+    Prompt,
 }
 
-impl ContainerEjectStrategy {
-    /// Matches a rejected tab's domain to a new container, regardless of type.
-    /// Returns a container handle that must be properly released.
-    /// Fails if the browser indicates so.
-    #[must_use = "clean up must be done before releasing the handle"]
-    pub async fn match_container(
-        &self,
-        global_context: &mut GlobalContext,
-        domain: EncodedDomain,
-        cookie_store_id: &CookieStoreId,
-        assign_strategy: ContainerAssignStrategy,
-    ) -> Result<ContainerHandle, CustomError> {
-        if let Some(container_match) = global_context.containers.match_container(domain.clone()) {
-            if container_match.container.handle().cookie_store_id() == cookie_store_id {
-                return Ok(container_match.container.handle().clone());
-            }
+/// Obtains a handle of the container chosen by a [Decision].
+/// A new isolated temporary container is created
+/// if the container disappears before the handle is obtained,
+/// or if the decision is to [Prompt](Decision::Prompt),
+/// which should have been resolved by the caller.
+/// Returns a container handle that must be properly released.
+/// Fails if the browser indicates so.
+#[must_use = "clean up must be done before releasing the handle"]
+pub async fn realize_decision(
+    global_context: &mut GlobalContext,
+    decision: Decision,
+    current_container: Option<&CookieStoreId>,
+) -> Result<ContainerHandle, CustomError> {
+    let cookie_store_id = match decision {
+        Decision::Move { cookie_store_id } => Some(cookie_store_id),
+        Decision::Stay | Decision::Ignore => current_container.cloned(),
+        Decision::NewTemporary { domain } => {
+            return new_temporary_container(global_context, domain).await;
         }
+        Decision::Prompt => None,
+    };
+    match cookie_store_id
+        .and_then(|cookie_store_id| global_context.containers.get(&cookie_store_id))
+    {
+        Some(container) => Ok(container.handle().clone()),
+        None => new_temporary_container(global_context, None).await,
+    }
+}
 
-        use ContainerEjectStrategy::*;
+/// Sorting order of containers when they are listed.
+/// - [Name](ContainerSort::Name) means that containers are sorted
+///   alphabetically by name, ignoring cases.
+/// - [CreationTime](ContainerSort::CreationTime) means that the oldest
+///   containers are listed first.
+/// - [LastUsed](ContainerSort::LastUsed) means that the most recently used
+///   containers are listed first.
+/// - [SuffixCount](ContainerSort::SuffixCount) means that containers with
+///   the most suffixes are listed first.
+#[derive(Clone, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerSort {
+    #[derivative(Default)]
+    Name,
+    CreationTime,
+    LastUsed,
+    SuffixCount,
+}
+
+impl ContainerSort {
+    /// Compares two containers with this order,
+    /// ties are broken by name so that the listing is stable.
+    pub fn compare(&self, left: &Container, right: &Container) -> Ordering {
+        let name_ordering = || {
+            let left_name = left.identity_details().name.to_lowercase();
+            left_name.cmp(&right.identity_details().name.to_lowercase())
+        };
+        use ContainerSort::*;
         match *self {
-            IsolatedTemporary => new_temporary_container(global_context, None).await,
-            RemainInPlace => Self::eject_remain_in_place(global_context, cookie_store_id).await,
-            Reassignment => {
-                assign_strategy
-                    .match_container(global_context, domain)
-                    .await
-            }
+            Name => Ordering::Equal,
+            CreationTime => left.created().cmp(&right.created()),
+            LastUsed => Reverse(left.last_used()).cmp(&Reverse(right.last_used())),
+            SuffixCount => right.suffixes.len().cmp(&left.suffixes.len()),
         }
+        .then_with(name_ordering)
     }
+}
 
-    /// Remain in place eject strategy implementation.
-    /// A new isolated temporary container is created
-    /// if the container disappears before the handle is obtained.
-    /// Fails if the browser indicates so.
-    async fn eject_remain_in_place(
-        global_context: &mut GlobalContext,
-        cookie_store_id: &CookieStoreId,
-    ) -> Result<ContainerHandle, CustomError> {
-        if let Some(container) = global_context.containers.get(cookie_store_id) {
-            Ok(container.handle().clone())
-        } else {
-            new_temporary_container(global_context, None).await
+/// Form of domains shown in views, the other form is shown on hover.
+/// - [Unicode](DomainDisplay::Unicode) means that international domain
+///   names are shown as typed, such as `測試.net`.
+/// - [Encoded](DomainDisplay::Encoded) means that international domain
+///   names are shown in Punycode, such as `xn--g6w251d.net`.
+#[derive(Clone, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainDisplay {
+    #[derivative(Default)]
+    Unicode,
+    Encoded,
+}
+
+impl DomainDisplay {
+    /// The other form, for showing on hover.
+    pub fn alternate(&self) -> Self {
+        match self {
+            Self::Unicode => Self::Encoded,
+            Self::Encoded => Self::Unicode,
         }
     }
 }
 
+/// Colors of the views.
+/// - [Browser](ThemeMode::Browser) means that the colors of the browser's
+///   theme are used, falling back to the system's light or dark scheme.
+/// - [Light](ThemeMode::Light) and [Dark](ThemeMode::Dark) force the
+///   respective scheme regardless of the browser's theme.
+#[derive(Clone, Copy, Derivative, Deserialize, Eq, PartialEq, Serialize)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    #[derivative(Default)]
+    Browser,
+    Light,
+    Dark,
+}
+
 /// Creates a new temporary container,
 /// does not check for an existing temporary container.
 /// If a domain is supplied, its suffix will be appended.
 /// the naming scheme may be changed in the future.
+/// The color is derived from the suffix if the preference is
+/// [FromDomain](IdentityColor::FromDomain) and a domain is supplied.
 /// Fails if the browser indicates so.
 async fn new_temporary_container(
     global_context: &mut GlobalContext,
@@ -134,6 +473,7 @@ async fn new_temporary_container(
 ) -> Result<ContainerHandle, CustomError> {
     let mut details = IdentityDetails {
         name: String::from("Temporary Container "),
+        color: global_context.preferences.temporary_container_color.clone(),
         ..Default::default()
     };
     let mut suffixes = BTreeSet::default();
@@ -143,12 +483,16 @@ async fn new_temporary_container(
             .match_suffix(domain.clone())
             .unwrap_or(domain);
         details.name.push_str(domain.raw());
+        if details.color == IdentityColor::FromDomain {
+            details.color = IdentityColor::from_text(domain.encoded());
+        }
         suffixes.insert(Suffix::new(SuffixType::Normal, domain));
     }
 
     let container = Container::create(details, ContainerVariant::Temporary, suffixes).await?;
     let container_handle = container.handle().clone();
-    storage::store_single_entry(container_handle.cookie_store_id(), &container).await?;
+    let key = ContainerKey::from(container_handle.cookie_store_id());
+    storage::store_single_entry(&key, &container).await?;
     global_context.containers.insert(container);
     Ok(container_handle)
 }
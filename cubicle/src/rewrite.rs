@@ -0,0 +1,194 @@
+//! Per-container rules for rewriting the URL of a tab
+//! before it is opened in the container,
+//! such as forcing an alternative host or stripping tracking parameters.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::url::ParsedUrl;
+use crate::domain::{EncodedDomain, IdnaPolicy};
+use crate::util::errors::CustomError;
+
+/// Rule for rewriting URLs, stored in its textual format.
+/// - [Host](RewriteRule::Host) means that the host `from` is replaced by
+///   the host `to`, in the format of `host from to`.
+/// - [StripParameter](RewriteRule::StripParameter) means that query
+///   parameters with the name are removed, in the format of `strip name`.
+///   A name ending with `*` removes all parameters with the prefix.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(try_from = "String", into = "String")]
+pub enum RewriteRule {
+    Host {
+        from: EncodedDomain,
+        to: EncodedDomain,
+    },
+    StripParameter {
+        name: String,
+    },
+}
+
+impl RewriteRule {
+    /// Rewrites the URL according to this rule,
+    /// URLs that the rule does not apply to are returned as is.
+    pub fn apply(&self, url: &str) -> String {
+        let (Some(parsed_url), Some((scheme, rest))) =
+            (ParsedUrl::parse(url), url.split_once("://"))
+        else {
+            return String::from(url);
+        };
+        let authority_end = rest.find(['/', '\\', '?', '#']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(authority_end);
+        match self {
+            Self::Host { from, to } => {
+                if parsed_url.host != from.encoded() {
+                    return String::from(url);
+                }
+                let userinfo = authority
+                    .rsplit_once('@')
+                    .map(|(userinfo, _host_port)| format!("{userinfo}@"));
+                let port = parsed_url.port.map(|port| format!(":{port}"));
+                format!(
+                    "{scheme}://{}{}{}{rest}",
+                    userinfo.unwrap_or_default(),
+                    to.encoded(),
+                    port.unwrap_or_default()
+                )
+            }
+            Self::StripParameter { name } => {
+                let (before_fragment, fragment) = match rest.split_once('#') {
+                    Some((before_fragment, fragment)) => (before_fragment, Some(fragment)),
+                    None => (rest, None),
+                };
+                let Some((path, query)) = before_fragment.split_once('?') else {
+                    return String::from(url);
+                };
+                let is_stripped = |parameter: &&str| {
+                    let parameter_name = parameter.split('=').next().unwrap_or_default();
+                    match name.strip_suffix('*') {
+                        Some(prefix) => parameter_name.starts_with(prefix),
+                        None => parameter_name == name,
+                    }
+                };
+                let query = query
+                    .split('&')
+                    .filter(|parameter| !is_stripped(parameter))
+                    .collect::<Vec<&str>>()
+                    .join("&");
+                let query = (!query.is_empty()).then(|| format!("?{query}"));
+                let fragment = fragment.map(|fragment| format!("#{fragment}"));
+                format!(
+                    "{scheme}://{authority}{path}{}{}",
+                    query.unwrap_or_default(),
+                    fragment.unwrap_or_default()
+                )
+            }
+        }
+    }
+}
+
+/// Rewrites the URL with all rules in order.
+pub fn apply_all(rules: &[RewriteRule], url: &str) -> String {
+    rules
+        .iter()
+        .fold(String::from(url), |url, rule| rule.apply(&url))
+}
+
+impl TryFrom<&str> for RewriteRule {
+    type Error = CustomError;
+
+    /// Parses a rule in its textual format, see [RewriteRule].
+    /// Fails with [InvalidRewriteRule](CustomError::InvalidRewriteRule)
+    /// if the format is unknown or the hosts are invalid.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let error = || CustomError::InvalidRewriteRule {
+            rule: String::from(value),
+        };
+        let parse_domain =
+            |domain: &str| EncodedDomain::parse(domain, &IdnaPolicy::Lenient).or(Err(error()));
+        match value.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["host", from, to] => Ok(Self::Host {
+                from: parse_domain(from)?,
+                to: parse_domain(to)?,
+            }),
+            ["strip", name] if !name.contains(['=', '&', '#']) => Ok(Self::StripParameter {
+                name: String::from(*name),
+            }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl TryFrom<String> for RewriteRule {
+    type Error = CustomError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+impl From<RewriteRule> for String {
+    fn from(rule: RewriteRule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl Display for RewriteRule {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Host { from, to } => write!(formatter, "host {} {}", from.raw(), to.raw()),
+            Self::StripParameter { name } => write!(formatter, "strip {name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_rewrite_rule_apply() -> Result<(), CustomError> {
+        let rules = [
+            RewriteRule::try_from("host www.reddit.com old.reddit.com")?,
+            RewriteRule::try_from("strip utm_*")?,
+            RewriteRule::try_from("strip fbclid")?,
+        ];
+        assert_eq!(
+            apply_all(
+                &rules,
+                "https://www.reddit.com/r/rust?utm_source=a&sort=new&fbclid=b#top"
+            ),
+            "https://old.reddit.com/r/rust?sort=new#top"
+        );
+        assert_eq!(
+            apply_all(&rules, "https://user@WWW.reddit.com:8443?utm_medium=c"),
+            "https://user@old.reddit.com:8443"
+        );
+        assert_eq!(
+            apply_all(&rules, "https://example.com/?fbclid_other=1"),
+            "https://example.com/?fbclid_other=1"
+        );
+        assert_eq!(
+            apply_all(&rules, "http://[::1]:8080/path?utm_term=d"),
+            "http://[::1]:8080/path"
+        );
+        assert_eq!(apply_all(&rules, "about:blank"), "about:blank");
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_rewrite_rule_parse() -> Result<(), CustomError> {
+        let rule = RewriteRule::try_from("  host   a.example.com b.example.com ")?;
+        assert_eq!(rule.to_string(), "host a.example.com b.example.com");
+        assert!(RewriteRule::try_from("host a.example.com").is_err());
+        assert!(RewriteRule::try_from("strip a=b").is_err());
+        assert!(RewriteRule::try_from("replace a b").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+//! Saved sessions of tabs along with their containers,
+//! so that the tabs can be reopened later in the same containers.
+//! Temporary containers are recreated with the same details and suffixes,
+//! as they are deleted once their tabs are closed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::container::{Container, ContainerKey, ContainerVariant};
+use crate::context::GlobalContext;
+use crate::domain::suffix::Suffix;
+use crate::interop::contextual_identities::{
+    CookieStoreId, IdentityDetails, IdentityDetailsProvider,
+};
+use crate::interop::storage::{self, StorageBackend};
+use crate::interop::tabs::{self, TabProperties};
+use crate::util::errors::CustomError;
+use crate::util::log;
+
+/// Key of the saved sessions, which are kept in the large data backend.
+pub const SESSIONS_KEY: &str = "sessions";
+
+/// Key of the session saved before temporary containers are purged,
+/// kept apart from the named sessions so that it never replaces one.
+pub const LAST_SESSION_KEY: &str = "last_session";
+
+/// Tabs of a saved session that were in the same container.
+#[derive(Deserialize, Serialize)]
+struct SessionGroup {
+    cookie_store_id: CookieStoreId,
+    is_temporary: bool,
+    details: IdentityDetails,
+    suffixes: BTreeSet<Suffix>,
+    urls: Vec<String>,
+}
+
+impl SessionGroup {
+    /// Creates a group for tabs in a container, without any tab.
+    fn new(container: &Container) -> Self {
+        Self {
+            cookie_store_id: container.handle().cookie_store_id().clone(),
+            is_temporary: container.variant == ContainerVariant::Temporary,
+            details: container.identity_details(),
+            suffixes: container.suffixes.clone(),
+            urls: Vec::new(),
+        }
+    }
+
+    /// The container that the tabs are reopened in if it can be reused,
+    /// [None] if it should be recreated, as it was temporary
+    /// or no longer exists.
+    fn reusable_container(&self, global_context: &GlobalContext) -> Option<CookieStoreId> {
+        let exists = global_context
+            .containers
+            .get(&self.cookie_store_id)
+            .is_some();
+        (!self.is_temporary && exists).then(|| self.cookie_store_id.clone())
+    }
+
+    /// Checks if the group had tabs but none of them were reopened,
+    /// in which case a recreated container would be left empty.
+    /// Groups without tabs are never abandoned,
+    /// as their containers are restored for their suffixes.
+    fn is_abandoned(&self, restored_count: usize) -> bool {
+        restored_count == 0 && !self.urls.is_empty()
+    }
+}
+
+/// Snapshot of the tabs in containers owned by the extension.
+#[derive(Deserialize, Serialize)]
+pub struct Session {
+    pub saved_at: DateTime<Utc>,
+    groups: Vec<SessionGroup>,
+}
+
+impl Session {
+    /// Takes a snapshot of the tabs that are in containers of the context,
+    /// grouped by their containers in the order of the tabs.
+    /// Tabs without a web URL are skipped, as they cannot be reopened.
+    pub fn snapshot(global_context: &GlobalContext, tabs: &[TabProperties]) -> Self {
+        let mut groups = Vec::<SessionGroup>::new();
+        for tab in tabs {
+            let (Some(url), Ok(Some(_domain))) = (tab.url(), tab.domain()) else {
+                continue;
+            };
+            let Some(container) = global_context.containers.get(&tab.cookie_store_id) else {
+                continue;
+            };
+            let group = match groups
+                .iter_mut()
+                .position(|group| group.cookie_store_id == tab.cookie_store_id)
+            {
+                Some(index) => &mut groups[index],
+                None => {
+                    groups.push(SessionGroup::new(container));
+                    groups.last_mut().expect("group pushed above")
+                }
+            };
+            group.urls.push(String::from(url));
+        }
+        Self {
+            saved_at: Utc::now(),
+            groups,
+        }
+    }
+
+    /// Takes a snapshot of the containers that are about to be purged,
+    /// so that their suffixes can be recovered after they are deleted.
+    /// The groups have no tab, as only containers without tabs are purged.
+    pub fn snapshot_before_purge<'a>(containers: impl IntoIterator<Item = &'a Container>) -> Self {
+        Self {
+            saved_at: Utc::now(),
+            groups: containers.into_iter().map(SessionGroup::new).collect(),
+        }
+    }
+
+    /// The number of containers in this session.
+    pub fn container_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// The number of tabs in this session.
+    pub fn tab_count(&self) -> usize {
+        self.groups.iter().map(|group| group.urls.len()).sum()
+    }
+
+    /// Reopens the tabs in their containers in the background.
+    /// Temporary containers, and other containers that no longer exist,
+    /// are recreated as temporary containers, even if they had no tab.
+    /// Tabs that cannot be opened are skipped, and recreated containers
+    /// whose tabs all cannot be opened are deleted again.
+    /// Returns the number of tabs reopened.
+    /// Fails if a container cannot be recreated.
+    pub async fn restore(&self, global_context: &mut GlobalContext) -> Result<usize, CustomError> {
+        let mut restored_count = 0;
+        for group in &self.groups {
+            let reusable_id = group.reusable_container(global_context);
+            let cookie_store_id = match &reusable_id {
+                Some(cookie_store_id) => cookie_store_id.clone(),
+                None => recreate_container(global_context, group).await?,
+            };
+            let container = global_context
+                .containers
+                .get(&cookie_store_id)
+                .expect("container checked or inserted above");
+            let mut group_restored_count = 0;
+            for url in &group.urls {
+                match tabs::open_in_container(url, &cookie_store_id).await {
+                    Ok(tab_id) => {
+                        let domain = crate::interop::url_to_domain(url).ok();
+                        crate::register_new_tab(tab_id, container, domain).await;
+                        group_restored_count += 1;
+                    }
+                    Err(error) => log::warn!("tab of `{url}` was not restored, {error}"),
+                }
+            }
+            restored_count += group_restored_count;
+            if reusable_id.is_none() && group.is_abandoned(group_restored_count) {
+                discard_container(global_context, &cookie_store_id).await;
+            }
+        }
+        Ok(restored_count)
+    }
+}
+
+/// Deletes a recreated container whose tabs all cannot be reopened,
+/// failures are logged as the container is only left empty.
+async fn discard_container(global_context: &mut GlobalContext, cookie_store_id: &CookieStoreId) {
+    let container = global_context
+        .containers
+        .get(cookie_store_id)
+        .expect("recreated container");
+    let name = container.identity_details().name;
+    if let Err(error) = container.delete().await {
+        log::warn!("empty container `{name}` was not deleted, {error}");
+        return;
+    }
+    global_context.containers.bury(cookie_store_id);
+    if let Err(error) = storage::remove_entries(&[ContainerKey::from(cookie_store_id)]).await {
+        log::warn!("entry of empty container `{name}` was not removed, {error}");
+    }
+}
+
+/// Creates a temporary container with the details and suffixes of a group,
+/// returns the [CookieStoreId] of the new container.
+/// Fails if the browser indicates so,
+/// or if a suffix conflicts with another container,
+/// see [ContainerOwner::ensure_no_conflict](crate::container::ContainerOwner::ensure_no_conflict).
+async fn recreate_container(
+    global_context: &mut GlobalContext,
+    group: &SessionGroup,
+) -> Result<CookieStoreId, CustomError> {
+    global_context
+        .containers
+        .ensure_no_conflict(None, 0, &group.suffixes)?;
+    let details = IdentityDetails {
+        color: group.details.color.clone(),
+        icon: group.details.icon.clone(),
+        name: group.details.name.clone(),
+    };
+    let container =
+        Container::create(details, ContainerVariant::Temporary, group.suffixes.clone()).await?;
+    let cookie_store_id = container.handle().cookie_store_id().clone();
+    storage::store_single_entry(&ContainerKey::from(&cookie_store_id), &container).await?;
+    global_context.containers.insert(container);
+    Ok(cookie_store_id)
+}
+
+/// All saved sessions by their names, stored as a single entry,
+/// along with the last session saved before a purge.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Sessions {
+    sessions: BTreeMap<String, Session>,
+    #[serde(default)]
+    last_session: Option<Session>,
+}
+
+impl Sessions {
+    /// Loads the saved sessions from the backend,
+    /// fails if the browser indicates so.
+    pub async fn load(backend: StorageBackend) -> Result<Self, CustomError> {
+        let mut sessions = Self::default();
+        backend.get_with_keys(&mut sessions).await?;
+        Ok(sessions)
+    }
+
+    /// Stores the saved sessions to the backend,
+    /// fails if the browser indicates so.
+    pub async fn store(&self, backend: StorageBackend) -> Result<(), CustomError> {
+        backend
+            .store_single_entry(SESSIONS_KEY, &self.sessions)
+            .await
+    }
+
+    /// Replaces the last session and stores only it to the backend,
+    /// fails if the browser indicates so.
+    pub async fn store_last_session(
+        &mut self,
+        session: Session,
+        backend: StorageBackend,
+    ) -> Result<(), CustomError> {
+        let session = self.last_session.insert(session);
+        backend.store_single_entry(LAST_SESSION_KEY, session).await
+    }
+
+    /// The session saved before the last purge, [None] if there is none.
+    pub fn last_session(&self) -> Option<&Session> {
+        self.last_session.as_ref()
+    }
+
+    /// Saves a session under the name, replacing any session with that name.
+    pub fn insert(&mut self, name: String, session: Session) {
+        self.sessions.insert(name, session);
+    }
+
+    /// The session with the name, [None] if there is none.
+    pub fn get(&self, name: &str) -> Option<&Session> {
+        self.sessions.get(name)
+    }
+
+    /// Iterates over the sessions with their names in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Session)> {
+        self.sessions.iter()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::container::test::{remove_container, test_owner, CONTEXTUAL_IDENTITY_MUTEX};
+    use crate::interop::tabs::test::test_tab_properties;
+
+    fn tab_in(cookie_store_id: &str, url: &str) -> TabProperties {
+        let mut tab_properties = test_tab_properties(0, false, url);
+        tab_properties.cookie_store_id = CookieStoreId::new(String::from(cookie_store_id));
+        tab_properties
+    }
+
+    async fn test_context() -> Result<GlobalContext, CustomError> {
+        Ok(GlobalContext {
+            containers: test_owner(&[("mock_a", &["example.com"]), ("mock_b", &[])]).await?,
+            ..Default::default()
+        })
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_session_snapshot() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut global_context = test_context().await?;
+        let tabs = [
+            tab_in("mock_a", "https://example.com/1"),
+            tab_in("mock_b", "https://example.net/"),
+            tab_in("firefox-default", "https://example.org/"),
+            tab_in("mock_a", "about:blank"),
+            tab_in("mock_a", "https://example.com/2"),
+        ];
+        let session = Session::snapshot(&global_context, &tabs);
+        let groups = session
+            .groups
+            .iter()
+            .map(|group| (group.cookie_store_id.clone(), group.urls.clone()))
+            .collect::<Vec<_>>();
+        assert!(
+            groups
+                == vec![
+                    (
+                        CookieStoreId::new(String::from("mock_a")),
+                        vec![
+                            String::from("https://example.com/1"),
+                            String::from("https://example.com/2"),
+                        ],
+                    ),
+                    (
+                        CookieStoreId::new(String::from("mock_b")),
+                        vec![String::from("https://example.net/")],
+                    ),
+                ]
+        );
+        assert_eq!(session.container_count(), 2);
+        assert_eq!(session.tab_count(), 3);
+        assert!(session.groups[0].is_temporary);
+        assert!(session.groups[0].suffixes.len() == 1);
+        for cookie_store_id in ["mock_a", "mock_b"] {
+            remove_container(&mut global_context.containers, cookie_store_id);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_session_restore_plan() -> Result<(), CustomError> {
+        let _guard = CONTEXTUAL_IDENTITY_MUTEX.lock().await;
+        let mut global_context = test_context().await?;
+        let tabs = [
+            tab_in("mock_a", "https://example.com/"),
+            tab_in("mock_b", "https://example.net/"),
+        ];
+        let mut session = Session::snapshot(&global_context, &tabs);
+        let mock_a = CookieStoreId::new(String::from("mock_a"));
+        assert!(session.groups[0]
+            .reusable_container(&global_context)
+            .is_none());
+        session.groups[0].is_temporary = false;
+        assert!(session.groups[0].reusable_container(&global_context) == Some(mock_a));
+        session.groups[1].is_temporary = false;
+        remove_container(&mut global_context.containers, "mock_b");
+        assert!(session.groups[1]
+            .reusable_container(&global_context)
+            .is_none());
+
+        assert!(session.groups[0].is_abandoned(0));
+        assert!(!session.groups[0].is_abandoned(1));
+        let purged = global_context
+            .containers
+            .get(&CookieStoreId::new(String::from("mock_a")))
+            .expect("inserted container");
+        let last_session = Session::snapshot_before_purge([purged]);
+        assert!(!last_session.groups[0].is_abandoned(0));
+        remove_container(&mut global_context.containers, "mock_a");
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! Statistics of domains that ended up in temporary containers,
+//! for reporting which domains may deserve a permanent container.
+//! Counts are kept per day, and days beyond the retention are dropped.
+
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::suffix::Suffix;
+
+/// Key of the temporary container statistics in the storage.
+pub const TEMPORARY_STATISTICS_KEY: &str = "temporary_statistics";
+
+/// Number of days that the statistics are kept for,
+/// which is also the longest period that can be reported.
+pub const RETENTION_DAYS: u64 = 90;
+
+/// Number of relocations into temporary containers per day and suffix.
+#[derive(Default, Deserialize, Serialize)]
+pub struct TemporaryStatistics {
+    daily: BTreeMap<NaiveDate, BTreeMap<Suffix, u32>>,
+}
+
+impl TemporaryStatistics {
+    /// Records that a tab of the suffix was relocated to a temporary container,
+    /// and drops the days beyond the retention.
+    pub fn record(&mut self, suffix: Suffix, today: NaiveDate) {
+        *self
+            .daily
+            .entry(today)
+            .or_default()
+            .entry(suffix)
+            .or_default() += 1;
+        if let Some(oldest) = today.checked_sub_days(Days::new(RETENTION_DAYS)) {
+            self.daily = self.daily.split_off(&oldest);
+        }
+    }
+
+    /// Suffixes with their total counts over the last number of days,
+    /// including today, with the most frequent suffix first.
+    pub fn most_frequent(&self, today: NaiveDate, days: u64) -> Vec<(&Suffix, u32)> {
+        let since = today
+            .checked_sub_days(Days::new(days.saturating_sub(1)))
+            .unwrap_or(NaiveDate::MIN);
+        let mut totals = BTreeMap::<&Suffix, u32>::new();
+        for counts in self
+            .daily
+            .range(since..=today)
+            .map(|(_date, counts)| counts)
+        {
+            for (suffix, count) in counts {
+                *totals.entry(suffix).or_default() += count;
+            }
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|(suffix, count), (other_suffix, other_count)| {
+            other_count
+                .cmp(count)
+                .then_with(|| suffix.cmp(other_suffix))
+        });
+        totals
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::domain::suffix::SuffixType;
+    use crate::domain::EncodedDomain;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_temporary_statistics_most_frequent() {
+        let suffix =
+            |domain| Suffix::new(SuffixType::Normal, EncodedDomain::try_from(domain).unwrap());
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let days_ago = |days| today.checked_sub_days(Days::new(days)).unwrap();
+        let mut statistics = TemporaryStatistics::default();
+        statistics.record(suffix("example.com"), days_ago(RETENTION_DAYS + 1));
+        statistics.record(suffix("example.net"), days_ago(20));
+        statistics.record(suffix("example.net"), days_ago(10));
+        statistics.record(suffix("example.com"), days_ago(1));
+        statistics.record(suffix("example.org"), today);
+
+        assert!(
+            statistics.most_frequent(today, 7)
+                == vec![(&suffix("example.com"), 1), (&suffix("example.org"), 1)]
+        );
+        assert!(
+            statistics.most_frequent(today, RETENTION_DAYS)
+                == vec![
+                    (&suffix("example.net"), 2),
+                    (&suffix("example.com"), 1),
+                    (&suffix("example.org"), 1),
+                ]
+        );
+    }
+}
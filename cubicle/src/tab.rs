@@ -1,12 +1,20 @@
 //! Structures that allow checking if a tab may need to be relocated.
 
-use std::collections::HashMap;
-use std::mem;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::iter;
+
+use async_std::channel::{self, Receiver, Sender};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
 
 use crate::container::ContainerHandle;
+use crate::domain::alias::AliasGroups;
 use crate::domain::EncodedDomain;
 use crate::interop::contextual_identities::CookieStoreId;
 use crate::interop::tabs::{TabId, TabProperties};
+use crate::interop::windows::WindowId;
+use crate::preferences::{ContainerTarget, OpenerChain, WebScheme};
+use crate::rules::{self, Decision, DecisionTrace};
 
 /// Determinant that stores the current handle for bypassing context lock.
 /// Contains all detail that are used to determine if the tab does not require
@@ -16,22 +24,102 @@ pub struct TabDeterminant {
     pub domain: Option<EncodedDomain>,
 }
 
+/// Link from a tab to the tab that opened it, timed for expiry.
+struct OpenerLink {
+    opener_tab_id: TabId,
+    linked_at: DateTime<Utc>,
+}
+
 /// Detail required for determining where the tab should be relocated to.
 /// When wrapped in [Option], it indicates whether relocation should occur.
+#[derive(Clone)]
 pub struct RelocationDetail {
     pub old_domain: Option<EncodedDomain>,
     pub new_domain: EncodedDomain,
     pub current_cookie_store_id: CookieStoreId,
+    pub opener_cookie_store_id: Option<CookieStoreId>,
     pub opener_is_managed: bool,
+    pub is_new_tab: bool,
+    pub is_external: bool,
+}
+
+/// Where a tab should be opened, as chosen by the user when prompted.
+/// - [Stay](PromptChoice::Stay) means that the tab should remain
+///   in its current container.
+/// - [Temporary](PromptChoice::Temporary) means that a new temporary container
+///   should be created for the tab specifically.
+/// - [Container](PromptChoice::Container) means that the tab should be moved
+///   to an existing container.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "choice")]
+pub enum PromptChoice {
+    Stay,
+    Temporary,
+    Container { cookie_store_id: CookieStoreId },
+}
+
+impl PromptChoice {
+    /// Target of the choice for remembering it,
+    /// given the container that the tab was in when prompted.
+    pub fn target(&self, current_cookie_store_id: &CookieStoreId) -> ContainerTarget {
+        let cookie_store_id = match self {
+            Self::Stay => current_cookie_store_id.clone(),
+            Self::Temporary => return ContainerTarget::Temporary,
+            Self::Container { cookie_store_id } => cookie_store_id.clone(),
+        };
+        ContainerTarget::Designated { cookie_store_id }
+    }
+}
+
+impl From<PromptChoice> for Decision {
+    fn from(choice: PromptChoice) -> Self {
+        match choice {
+            PromptChoice::Stay => Self::Stay,
+            PromptChoice::Temporary => Self::NewTemporary { domain: None },
+            PromptChoice::Container { cookie_store_id } => Self::Move { cookie_store_id },
+        }
+    }
+}
+
+/// Relocation that is paused until the user chooses where the tab goes.
+pub struct PendingPrompt {
+    pub domain: EncodedDomain,
+    pub current_cookie_store_id: CookieStoreId,
+    chooser_window_id: Option<WindowId>,
+    sender: Sender<PromptChoice>,
 }
 
 /// Structure that allows checking if a tab may need to be relocated.
 /// This does not lock up the context.
 /// Should be synchronous as this is used before tab interception.
-/// Currently this just checks for a domain change.
+/// Currently this just checks for a domain change,
+/// navigation between domains of the same alias group is not a change.
+/// Openers of openers are followed up to a limit,
+/// so a tab stays in the container of an ancestor with the same domain.
+/// New tabs opened from managed tabs may inherit the opener's container
+/// regardless of their domains.
+/// New tabs opened from other applications are marked before they navigate.
+/// Only URLs with the chosen web schemes or opted in schemes are checked.
+/// Relocation can be paused, in which case tabs are only tracked.
+/// Tabs created by relocation are mapped to the determinants of the tabs
+/// they replaced, so that the relocation can be undone,
+/// and to the traces of why they were relocated.
+/// Relocations waiting for the user's choice are kept per tab,
+/// a newer prompt of the same tab withdraws the older one.
 #[derive(Default)]
 pub struct ManagedTabs {
     determinant_map: HashMap<TabId, TabDeterminant>,
+    relocation_origins: HashMap<TabId, TabDeterminant>,
+    decision_traces: HashMap<TabId, DecisionTrace>,
+    pending_prompts: HashMap<TabId, PendingPrompt>,
+    opener_links: HashMap<TabId, OpenerLink>,
+    external_tabs: HashSet<TabId>,
+    opener_chain: OpenerChain,
+    alias_groups: AliasGroups,
+    intercepted_schemes: BTreeSet<WebScheme>,
+    additional_schemes: BTreeSet<String>,
+    should_inherit_opener: bool,
+    is_relocation_paused: bool,
 }
 
 impl ManagedTabs {
@@ -43,37 +131,59 @@ impl ManagedTabs {
         tab_id: TabId,
         tab_properties: &TabProperties,
     ) -> Option<RelocationDetail> {
+        let (scheme, _) = tab_properties.url()?.split_once(':')?;
+        if !self.is_intercepted(scheme) {
+            return None;
+        }
         let new_domain = tab_properties.domain().ok()??;
         let mut old_domain = None;
         let mut same_domain = false;
+        let mut is_new_tab = false;
 
         let opener_det = tab_properties
             .opener_tab_id()
             .and_then(|tab_id| self.determinant_map.get(tab_id));
         let opener_domain = opener_det.and_then(|tab_det| tab_det.domain.clone());
+        let opener_cookie_store_id =
+            opener_det.map(|tab_det| tab_det.container_handle.cookie_store_id().clone());
 
-        let same_domain_as_opener = opener_domain.as_ref() == Some(&new_domain);
-        let mut opener_handle = opener_det
-            .filter(|_| same_domain_as_opener)
-            .map(|tab_det| tab_det.container_handle.clone());
+        let alias_groups = &self.alias_groups;
+        let should_inherit_opener =
+            self.should_inherit_opener && !self.determinant_map.contains_key(&tab_id);
+        let mut opener_handle = self
+            .opener_dets(tab_properties.opener_tab_id())
+            .enumerate()
+            .find(|(depth, tab_det)| {
+                tab_det.domain.as_ref().is_some_and(|domain| {
+                    (*depth == 0 && should_inherit_opener)
+                        || alias_groups.are_aliases(domain, &new_domain)
+                })
+            })
+            .map(|(_, tab_det)| tab_det.container_handle.clone());
+        let inherits_opener = opener_handle.is_some();
 
         let current_cookie_store_id = self
             .determinant_map
-            .entry(tab_id)
+            .entry(tab_id.clone())
             .and_modify(|old_det| {
-                let new_domain = Some(new_domain.clone());
-                same_domain = old_det.domain == new_domain;
+                let replaced_domain = old_det.domain.replace(new_domain.clone());
+                same_domain = replaced_domain.as_ref().is_some_and(|replaced_domain| {
+                    alias_groups.are_aliases(replaced_domain, &new_domain)
+                });
                 if !same_domain {
-                    old_domain = mem::replace(&mut old_det.domain, new_domain);
+                    old_domain = replaced_domain;
                 }
             })
-            .or_insert_with(|| TabDeterminant {
-                container_handle: opener_handle.take().unwrap_or_else(|| {
-                    let handle = ContainerHandle::from(tab_properties.cookie_store_id.clone());
-                    handle.finish();
-                    handle
-                }),
-                domain: Some(new_domain.clone()),
+            .or_insert_with(|| {
+                is_new_tab = true;
+                TabDeterminant {
+                    container_handle: opener_handle.take().unwrap_or_else(|| {
+                        let handle = ContainerHandle::from(tab_properties.cookie_store_id.clone());
+                        handle.finish();
+                        handle
+                    }),
+                    domain: Some(new_domain.clone()),
+                }
             })
             .container_handle
             .cookie_store_id()
@@ -81,20 +191,101 @@ impl ManagedTabs {
         if let Some(opener_handle) = opener_handle {
             opener_handle.finish();
         }
+        let is_external = is_new_tab && self.external_tabs.remove(&tab_id);
+        if let (true, Some(opener_tab_id)) = (is_new_tab, tab_properties.opener_tab_id()) {
+            let opener_link = OpenerLink {
+                opener_tab_id: opener_tab_id.clone(),
+                linked_at: Utc::now(),
+            };
+            self.opener_links.insert(tab_id, opener_link);
+        }
 
-        (!same_domain && !same_domain_as_opener).then_some(RelocationDetail {
+        let should_relocate = !self.is_relocation_paused && !same_domain && !inherits_opener;
+        should_relocate.then_some(RelocationDetail {
             old_domain,
             new_domain,
             current_cookie_store_id,
+            opener_cookie_store_id,
             opener_is_managed: opener_domain.is_some(),
+            is_new_tab,
+            is_external,
         })
     }
 
+    /// Replaces the alias groups, which should be copied from the context.
+    pub fn replace_alias_groups(&mut self, alias_groups: AliasGroups) {
+        self.alias_groups = alias_groups;
+    }
+
+    /// Replaces the chosen web schemes and the schemes opted in
+    /// for interception, which should be copied from the preferences.
+    pub fn replace_schemes(
+        &mut self,
+        intercepted_schemes: BTreeSet<WebScheme>,
+        additional_schemes: BTreeSet<String>,
+    ) {
+        self.intercepted_schemes = intercepted_schemes;
+        self.additional_schemes = additional_schemes;
+    }
+
+    /// Checks if URLs with the scheme should be intercepted,
+    /// see [rules::is_intercepted].
+    fn is_intercepted(&self, scheme: &str) -> bool {
+        rules::is_intercepted(scheme, &self.intercepted_schemes, &self.additional_schemes)
+    }
+
+    /// Walks up the chain of openers from the immediate opener of a tab,
+    /// until the depth limit, an expired link or an unmanaged opener.
+    fn opener_dets<'a>(
+        &'a self,
+        opener_tab_id: Option<&'a TabId>,
+    ) -> impl Iterator<Item = &'a TabDeterminant> + 'a {
+        let now = Utc::now();
+        let expiry = Duration::seconds(i64::from(self.opener_chain.expiry_secs));
+        iter::successors(opener_tab_id, move |tab_id| {
+            self.opener_links
+                .get(tab_id)
+                .filter(|opener_link| now - opener_link.linked_at <= expiry)
+                .map(|opener_link| &opener_link.opener_tab_id)
+        })
+        .take(usize::from(self.opener_chain.depth))
+        .map_while(|tab_id| self.determinant_map.get(tab_id))
+    }
+
+    /// Replaces the limits of following openers,
+    /// which should be copied from the preferences.
+    pub fn replace_opener_chain(&mut self, opener_chain: OpenerChain) {
+        self.opener_chain = opener_chain;
+    }
+
+    /// Sets whether new tabs opened from managed tabs always stay
+    /// in the opener's container, which should be copied from the preferences.
+    pub fn inherit_opener(&mut self, should_inherit_opener: bool) {
+        self.should_inherit_opener = should_inherit_opener;
+    }
+
+    /// Pauses or resumes relocation, for avoiding conflicts with other
+    /// extensions that relocate tabs.
+    pub fn pause_relocation(&mut self, is_paused: bool) {
+        self.is_relocation_paused = is_paused;
+    }
+
+    /// Marks a new tab as opened from another application,
+    /// so that it is routed accordingly once it navigates.
+    pub fn mark_external(&mut self, tab_id: TabId) {
+        self.external_tabs.insert(tab_id);
+    }
+
     /// Registers a tab for quick relocation lookup later.
     pub fn register(&mut self, tab_id: TabId, tab_det: TabDeterminant) -> Option<TabDeterminant> {
         self.determinant_map.insert(tab_id, tab_det)
     }
 
+    /// Gets the [TabDeterminant] of a managed tab, [None] if it does not exist.
+    pub fn get(&self, tab_id: &TabId) -> Option<&TabDeterminant> {
+        self.determinant_map.get(tab_id)
+    }
+
     /// Gets a mutable reference to [TabDeterminant] for modifying, [None] if it does not exist.
     pub fn get_mut(&mut self, tab_id: &TabId) -> Option<&mut TabDeterminant> {
         self.determinant_map.get_mut(tab_id)
@@ -102,7 +293,226 @@ impl ManagedTabs {
 
     /// Unregisters a tab to avoid possible collision.
     /// Returns a [TabDeterminant] if the tab was managed, [None] otherwise.
+    /// The record of relocation is kept, see [ManagedTabs::take_relocation_origin].
     pub fn unregister(&mut self, tab_id: &TabId) -> Option<TabDeterminant> {
+        self.opener_links.remove(tab_id);
+        self.external_tabs.remove(tab_id);
+        self.decision_traces.remove(tab_id);
+        self.pending_prompts.remove(tab_id);
         self.determinant_map.remove(tab_id)
     }
+
+    /// Records the determinant of the tab that a tab was relocated from.
+    pub fn record_relocation(&mut self, tab_id: TabId, origin_det: TabDeterminant) {
+        if let Some(old_origin_det) = self.relocation_origins.insert(tab_id, origin_det) {
+            old_origin_det.container_handle.finish();
+        }
+    }
+
+    /// The [TabDeterminant] of a tab that a relocation can be undone to,
+    /// see [ManagedTabs::take_relocation_origin].
+    pub fn relocation_origin(&self, tab_id: &TabId) -> Option<&TabDeterminant> {
+        self.relocation_origins.get(tab_id)
+    }
+
+    /// Records why a tab was relocated, replacing the previous trace.
+    pub fn record_decision_trace(&mut self, tab_id: TabId, trace: DecisionTrace) {
+        self.decision_traces.insert(tab_id, trace);
+    }
+
+    /// The trace of why a tab was last relocated,
+    /// [None] if the tab was not relocated.
+    pub fn decision_trace(&self, tab_id: &TabId) -> Option<&DecisionTrace> {
+        self.decision_traces.get(tab_id)
+    }
+
+    /// Pauses the relocation of a tab until the user's choice arrives
+    /// through the returned receiver, withdrawing any pending prompt
+    /// of the tab, whose receiver is then closed.
+    pub fn prompt(
+        &mut self,
+        tab_id: TabId,
+        domain: EncodedDomain,
+        current_cookie_store_id: CookieStoreId,
+    ) -> Receiver<PromptChoice> {
+        let (sender, receiver) = channel::bounded(1);
+        let pending_prompt = PendingPrompt {
+            domain,
+            current_cookie_store_id,
+            chooser_window_id: None,
+            sender,
+        };
+        self.pending_prompts.insert(tab_id, pending_prompt);
+        receiver
+    }
+
+    /// The relocation of a tab that is waiting for the user's choice,
+    /// [None] if there is none.
+    pub fn pending_prompt(&self, tab_id: &TabId) -> Option<&PendingPrompt> {
+        self.pending_prompts.get(tab_id)
+    }
+
+    /// Resolves the pending prompt of a tab with the user's choice,
+    /// or withdraws it if there is no choice.
+    /// Returns whether a prompt was pending.
+    pub fn resolve_prompt(&mut self, tab_id: &TabId, choice: Option<PromptChoice>) -> bool {
+        let Some(pending_prompt) = self.pending_prompts.remove(tab_id) else {
+            return false;
+        };
+        if let Some(choice) = choice {
+            drop(pending_prompt.sender.try_send(choice));
+        }
+        true
+    }
+
+    /// Records the window where the user is asked about the pending prompt
+    /// of a tab, so that the prompt is dismissed once the window is closed.
+    pub fn attach_chooser(&mut self, tab_id: &TabId, window_id: WindowId) {
+        if let Some(pending_prompt) = self.pending_prompts.get_mut(tab_id) {
+            pending_prompt.chooser_window_id = Some(window_id);
+        }
+    }
+
+    /// Dismisses the pending prompt whose chooser is in a closed window,
+    /// which is resolved with [Temporary](PromptChoice::Temporary)
+    /// like an expired prompt.
+    /// Returns the tab of the dismissed prompt, [None] if there is none.
+    pub fn dismiss_chooser(&mut self, window_id: &WindowId) -> Option<TabId> {
+        let tab_id = self
+            .pending_prompts
+            .iter()
+            .find(|(_, pending_prompt)| {
+                pending_prompt.chooser_window_id.as_ref() == Some(window_id)
+            })
+            .map(|(tab_id, _)| tab_id.clone())?;
+        self.resolve_prompt(&tab_id, Some(PromptChoice::Temporary));
+        Some(tab_id)
+    }
+
+    /// Takes the determinant of the tab that a tab was relocated from,
+    /// [None] if the tab was not relocated or the record was taken.
+    pub fn take_relocation_origin(&mut self, tab_id: &TabId) -> Option<TabDeterminant> {
+        self.relocation_origins.remove(tab_id)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::interop::tabs::test::{test_opened_tab_properties, test_tab_properties};
+    use crate::preferences::Preferences;
+    use crate::util::test::TestFrom;
+
+    fn test_managed_tabs() -> ManagedTabs {
+        let preferences = Preferences::default();
+        let mut managed_tabs = ManagedTabs::default();
+        managed_tabs.replace_schemes(
+            preferences.intercepted_schemes,
+            preferences.additional_schemes,
+        );
+        managed_tabs
+    }
+
+    fn check(managed_tabs: &mut ManagedTabs, tab_id: isize, url: &str) -> Option<RelocationDetail> {
+        managed_tabs.check_relocation(TabId::new(tab_id), &test_tab_properties(0, false, url))
+    }
+
+    /// Unregisters the tabs and finishes their handles,
+    /// since inherited handles are left unfinished for the caller.
+    fn unregister_all(managed_tabs: &mut ManagedTabs, tab_ids: impl IntoIterator<Item = isize>) {
+        for tab_id in tab_ids {
+            if let Some(tab_det) = managed_tabs.unregister(&TabId::new(tab_id)) {
+                tab_det.container_handle.finish();
+            }
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_managed_tabs_check_relocation() {
+        let mut managed_tabs = test_managed_tabs();
+        assert!(check(&mut managed_tabs, 0, "about:blank").is_none());
+
+        let detail = check(&mut managed_tabs, 0, "https://example.com/").expect("new tab");
+        assert!(detail.is_new_tab);
+        assert!(detail.old_domain.is_none());
+        assert!(detail.new_domain == EncodedDomain::tfrom("example.com"));
+
+        assert!(check(&mut managed_tabs, 0, "https://example.com/path").is_none());
+
+        let detail = check(&mut managed_tabs, 0, "https://example.net/").expect("domain change");
+        assert!(!detail.is_new_tab);
+        assert!(detail.old_domain == Some(EncodedDomain::tfrom("example.com")));
+
+        managed_tabs.pause_relocation(true);
+        assert!(check(&mut managed_tabs, 0, "https://example.org/").is_none());
+        managed_tabs.pause_relocation(false);
+        let detail = check(&mut managed_tabs, 0, "https://example.com/").expect("resumed");
+        assert!(detail.old_domain == Some(EncodedDomain::tfrom("example.org")));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_managed_tabs_check_relocation_opener() {
+        let mut managed_tabs = test_managed_tabs();
+        check(&mut managed_tabs, 0, "https://example.com/").expect("opener");
+
+        let same_domain = test_opened_tab_properties("https://example.com/path", 0);
+        assert!(managed_tabs
+            .check_relocation(TabId::new(1), &same_domain)
+            .is_none());
+
+        let other_domain = test_opened_tab_properties("https://example.net/", 0);
+        let detail = managed_tabs
+            .check_relocation(TabId::new(2), &other_domain)
+            .expect("different domain");
+        assert!(detail.opener_is_managed);
+
+        managed_tabs.inherit_opener(true);
+        assert!(managed_tabs
+            .check_relocation(TabId::new(3), &other_domain)
+            .is_none());
+        unregister_all(&mut managed_tabs, 0..=3);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_managed_tabs_mark_external() {
+        let mut managed_tabs = test_managed_tabs();
+        managed_tabs.mark_external(TabId::new(0));
+        let detail = check(&mut managed_tabs, 0, "https://example.com/").expect("new tab");
+        assert!(detail.is_external);
+        let detail = check(&mut managed_tabs, 0, "https://example.net/").expect("domain change");
+        assert!(!detail.is_external);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_managed_tabs_prompt() {
+        let mut managed_tabs = test_managed_tabs();
+        let tab_id = TabId::new(0);
+        let domain = EncodedDomain::tfrom("example.com");
+        let current = CookieStoreId::new(String::from("mock_current"));
+        assert!(!managed_tabs.resolve_prompt(&tab_id, Some(PromptChoice::Stay)));
+
+        let withdrawn = managed_tabs.prompt(tab_id.clone(), domain.clone(), current.clone());
+        let receiver = managed_tabs.prompt(tab_id.clone(), domain, current.clone());
+        assert!(withdrawn.is_closed());
+        assert!(managed_tabs.pending_prompt(&tab_id).is_some());
+        assert!(managed_tabs.resolve_prompt(&tab_id, Some(PromptChoice::Temporary)));
+        assert!(managed_tabs.pending_prompt(&tab_id).is_none());
+        assert!(matches!(receiver.try_recv(), Ok(PromptChoice::Temporary)));
+
+        let window_id = WindowId::new(1);
+        let receiver =
+            managed_tabs.prompt(tab_id.clone(), EncodedDomain::tfrom("example.net"), current);
+        assert!(managed_tabs.dismiss_chooser(&window_id).is_none());
+        managed_tabs.attach_chooser(&tab_id, window_id.clone());
+        assert!(managed_tabs.dismiss_chooser(&window_id) == Some(tab_id.clone()));
+        assert!(managed_tabs.pending_prompt(&tab_id).is_none());
+        assert!(matches!(receiver.try_recv(), Ok(PromptChoice::Temporary)));
+    }
 }
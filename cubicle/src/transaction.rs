@@ -0,0 +1,88 @@
+//! Transactions that apply changes to a staged copy of a part of the context,
+//! which only replaces the part in memory once the copy is persisted.
+//! This keeps the memory consistent with the storage if writing fails.
+
+use std::ops::{Deref, DerefMut};
+
+use serde::Serialize;
+
+use crate::interop::storage;
+use crate::util::errors::CustomError;
+
+/// Part of the context that can be changed in a [Transaction].
+pub trait Staged: Serialize + Sized {
+    /// Creates a copy to apply changes to.
+    fn stage(&self) -> Self;
+
+    /// Replaces this with a staged copy that has been persisted.
+    fn commit(&mut self, staged: Self);
+}
+
+/// Changes to a part of the context that is stored under a key.
+/// Dereferences into the staged copy,
+/// which is discarded if the transaction is dropped without committing.
+pub struct Transaction<K, C>
+where
+    K: Serialize,
+    C: DerefMut,
+    C::Target: Staged,
+{
+    key: K,
+    current: C,
+    staged: C::Target,
+}
+
+impl<K, C> Transaction<K, C>
+where
+    K: Serialize,
+    C: DerefMut,
+    C::Target: Staged,
+{
+    /// Starts a transaction on the current value stored under the key.
+    pub fn new(key: K, current: C) -> Self {
+        let staged = current.stage();
+        Self {
+            key,
+            current,
+            staged,
+        }
+    }
+
+    /// Persists the staged copy, then replaces the current value with it.
+    /// Fails if the browser indicates so, in which case
+    /// the current value is left unchanged.
+    pub async fn commit(self) -> Result<(), CustomError> {
+        let Self {
+            key,
+            mut current,
+            staged,
+        } = self;
+        storage::store_single_entry(&key, &staged).await?;
+        current.commit(staged);
+        Ok(())
+    }
+}
+
+impl<K, C> Deref for Transaction<K, C>
+where
+    K: Serialize,
+    C: DerefMut,
+    C::Target: Staged,
+{
+    type Target = C::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.staged
+    }
+}
+
+impl<K, C> DerefMut for Transaction<K, C>
+where
+    K: Serialize,
+    C: DerefMut,
+    C::Target: Staged,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.staged
+    }
+}
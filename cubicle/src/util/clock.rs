@@ -0,0 +1,30 @@
+//! Time providers for behaviours that depend on the local time,
+//! so that the time can be fixed in tests.
+
+use chrono::{Local, NaiveDateTime};
+
+/// Provider of the current local date and time.
+pub trait Clock {
+    /// Current date and time in the local time zone.
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// Clock that follows the system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// Clock that always reports the same time.
+#[cfg(test)]
+pub struct FixedClock(pub NaiveDateTime);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
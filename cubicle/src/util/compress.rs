@@ -0,0 +1,54 @@
+//! DEFLATE compression of large text, encoded back into text with a marker
+//! so that compressed text can be told apart from uncompressed text.
+
+use base64::prelude::*;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+/// Prefix of compressed text, followed by the base-64 encoded DEFLATE stream.
+pub const COMPRESSED_MARKER_PREFIX: &str = "deflate_b64_";
+
+/// Compression level between 0 and 10, higher is smaller but slower.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Maximum size of decompressed text, which guards against corrupt streams.
+const DECOMPRESSED_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Compresses text into marked text.
+pub fn compress(text: &str) -> String {
+    let compressed = compress_to_vec(text.as_bytes(), COMPRESSION_LEVEL);
+    String::from(COMPRESSED_MARKER_PREFIX) + &BASE64_STANDARD_NO_PAD.encode(compressed)
+}
+
+/// Decompresses marked text, [None] if the text is not marked,
+/// or if the compressed stream is corrupt.
+pub fn decompress(text: &str) -> Option<String> {
+    let encoded = text.strip_prefix(COMPRESSED_MARKER_PREFIX)?;
+    let compressed = BASE64_STANDARD_NO_PAD.decode(encoded).ok()?;
+    let decompressed = decompress_to_vec_with_limit(&compressed, DECOMPRESSED_LIMIT).ok()?;
+    String::from_utf8(decompressed).ok()
+}
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_compress_round_trip() {
+        let text = "com\nnet\norg\n".repeat(1000);
+        let compressed = compress(&text);
+        assert!(compressed.starts_with(COMPRESSED_MARKER_PREFIX));
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(Some(text), decompress(&compressed));
+
+        assert_eq!(None, decompress("com\nnet\norg\n"));
+        assert_eq!(
+            None,
+            decompress(&(String::from(COMPRESSED_MARKER_PREFIX) + "AAAA"))
+        );
+    }
+}
@@ -13,27 +13,80 @@ pub enum CustomError {
     IoError(ErrorKind),
     #[error("browser's return value doesn't match the standard, {message}")]
     StandardMismatch { message: String },
-    #[error("failed to {verb} container")]
-    FailedContainerOperation { verb: String },
-    #[error("failed to {verb_prep} storage")]
-    FailedStorageOperation { verb_prep: String },
+    #[error("failed to {verb} alarm")]
+    FailedAlarmOperation { verb: String },
+    #[error("failed to {verb} bookmarks")]
+    FailedBookmarkOperation { verb: String },
+    #[error("failed to {verb} cookies")]
+    FailedCookieOperation { verb: String },
+    #[error("failed to {verb} container, {details}")]
+    FailedContainerOperation { verb: String, details: String },
+    #[error("failed to {verb} notification")]
+    FailedNotificationOperation { verb: String },
+    #[error("failed to {verb_prep} storage, {details}")]
+    FailedStorageOperation { verb_prep: String, details: String },
+    #[error("failed to {verb_prep} cache, {details}")]
+    FailedCacheOperation { verb_prep: String, details: String },
     #[error("failed to fetch the active tab")]
     FailedFetchActiveTab,
     #[error("failed to fetch, {message}")]
     FailedFetchRequest { message: String },
-    #[error("failed to {verb} tab")]
-    FailedTabOperation { verb: String },
+    #[error("failed to {verb} tab, {details}")]
+    FailedTabOperation { verb: String, details: String },
+    #[error("failed to {verb} history")]
+    FailedHistoryOperation { verb: String },
+    #[error("failed to {verb} window, {details}")]
+    FailedWindowOperation { verb: String, details: String },
+    #[error("failed to {verb} theme")]
+    FailedThemeOperation { verb: String },
+    #[error("failed to {verb} privacy setting")]
+    FailedPrivacyOperation { verb: String },
+    #[error("failed to {verb} with web crypto")]
+    FailedCryptoOperation { verb: String },
+    #[error("failed to {verb} extensions")]
+    FailedExtensionOperation { verb: String },
+    #[error("failed to message native application `{application}`")]
+    FailedNativeMessaging { application: String },
 
     // predictable errors that are uncommon
     #[error("unsupported version")]
     UnsupportedVersion,
+    #[error("sender `{sender}` is not trusted")]
+    UntrustedSender { sender: String },
 
     // predictable errors that are common
-    #[error(transparent)]
-    InvalidDomain {
-        #[from]
-        source: idna::Errors,
-    },
+    #[error("invalid domain `{domain}`, {details}")]
+    InvalidDomain { domain: String, details: String },
     #[error("invalid suffix format `{suffix}`")]
     InvalidSuffix { suffix: String },
+    #[error("invalid request header `{header}`")]
+    InvalidRequestHeader { header: String },
+    #[error("suffix `{suffix}` is already used by container `{name}` of the same priority")]
+    SuffixConflict { suffix: String, name: String },
+    #[error("invalid homepage `{url}`, expected a web address")]
+    InvalidHomepage { url: String },
+    #[error("invalid rewrite rule `{rule}`")]
+    InvalidRewriteRule { rule: String },
+    #[error("invalid active hours from {start_hour} to {end_hour}")]
+    InvalidActiveHours { start_hour: u32, end_hour: u32 },
+    #[error("container `{name}` is locked")]
+    LockedContainer { name: String },
+    #[error("container `{name}` was deleted while in use, try again")]
+    DeletedContainer { name: String },
+    #[error("container `{name}` is not found")]
+    ContainerNotFound { name: String },
+    #[error("container `{name}` is managed by policy")]
+    ManagedContainer { name: String },
+    #[error("session `{name}` is not found")]
+    SessionNotFound { name: String },
+    #[error("prompt of tab {tab_id} has expired")]
+    ExpiredPrompt { tab_id: String },
+    #[error("preferences are managed by policy")]
+    ManagedPreferences,
+    #[error("containers failed to load, retry loading before making changes")]
+    DegradedContext,
+    #[error("changes are not allowed in guest mode")]
+    GuestMode,
+    #[error("incorrect PIN for leaving guest mode")]
+    IncorrectPin,
 }
@@ -0,0 +1,92 @@
+//! Mutex that counts how often it was locked and had to be waited for,
+//! for diagnosing stalls caused by holding a lock for too long.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_std::sync::{Mutex, MutexGuard};
+use serde::Serialize;
+
+/// Number of times a lock was acquired,
+/// and how many of those had to wait for another holder.
+#[derive(Clone, Copy, Default, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct LockStats {
+    pub acquisitions: usize,
+    pub contentions: usize,
+}
+
+/// [Mutex] that keeps [LockStats] of its asynchronous locking.
+#[derive(Default)]
+pub struct InstrumentedMutex<T> {
+    mutex: Mutex<T>,
+    acquisitions: AtomicUsize,
+    contentions: AtomicUsize,
+}
+
+impl<T> InstrumentedMutex<T> {
+    /// Creates a mutex holding the value.
+    pub fn new(value: T) -> Self {
+        Self {
+            mutex: Mutex::new(value),
+            acquisitions: AtomicUsize::new(0),
+            contentions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Acquires the lock, counted as contended if it is currently held.
+    pub async fn lock(&self) -> MutexGuard<'_, T> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        if let Some(guard) = self.mutex.try_lock() {
+            return guard;
+        }
+        self.contentions.fetch_add(1, Ordering::Relaxed);
+        self.mutex.lock().await
+    }
+
+    /// Attempts to acquire the lock without waiting, [None] if it is held.
+    /// This is not counted, as it is meant for inspecting the value
+    /// where waiting is not possible.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.mutex.try_lock()
+    }
+
+    /// Counts of locking so far.
+    pub fn stats(&self) -> LockStats {
+        LockStats {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contentions: self.contentions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::time::Duration;
+
+    use async_std::future;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_instrumented_mutex_stats() {
+        let mutex = InstrumentedMutex::new(0);
+        *mutex.lock().await += 1;
+        let guard = mutex.lock().await;
+        assert!(future::timeout(Duration::from_millis(1), mutex.lock())
+            .await
+            .is_err());
+        assert!(mutex.try_lock().is_none());
+        drop(guard);
+        assert_eq!(*mutex.lock().await, 1);
+        assert_eq!(
+            mutex.stats(),
+            LockStats {
+                acquisitions: 4,
+                contentions: 1,
+            }
+        );
+    }
+}
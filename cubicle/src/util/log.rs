@@ -0,0 +1,196 @@
+//! Logging facade that writes to the console and a persistent event log,
+//! messages below the level chosen in the preferences are discarded.
+//! Use the [debug], [info] and [warn] macros instead of [record] directly.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::interop::browser::{Browser, CurrentBrowser};
+
+/// Key of the event log in the storage.
+pub const EVENT_LOG_KEY: &str = "event_log";
+
+thread_local! {
+    /// Minimum level of messages that are recorded.
+    static LEVEL: Cell<LogLevel> = const { Cell::new(LogLevel::Warn) };
+    /// Recent messages, persisted shortly after each message.
+    static EVENT_LOG: RefCell<EventLog> = RefCell::new(EventLog::default());
+}
+
+/// Severity of a message, from the least to the most severe.
+/// - [Debug](LogLevel::Debug) is for tracing decisions,
+///   such as why a tab was intercepted.
+/// - [Info](LogLevel::Info) is for notable actions, such as relocations.
+/// - [Warn](LogLevel::Warn) is for failures that are otherwise ignored.
+#[derive(
+    Clone, Copy, Derivative, Deserialize, Display, Eq, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    #[derivative(Default)]
+    Warn,
+}
+
+/// Message recorded in the event log.
+#[derive(Deserialize, Serialize)]
+pub struct LogEntry {
+    pub time: DateTime<Utc>,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// Ring buffer of the most recent messages,
+/// the oldest message is dropped once the capacity is reached.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl EventLog {
+    /// Maximum number of messages kept.
+    pub const CAPACITY: usize = 200;
+
+    /// Appends a message, dropping the oldest if the log is full.
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() >= Self::CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Messages from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+}
+
+/// Sets the minimum level of messages that are recorded,
+/// which should be copied from the preferences.
+pub fn set_level(level: LogLevel) {
+    LEVEL.with(|current_level| current_level.set(level));
+}
+
+/// Checks if messages of the level are recorded.
+pub fn is_enabled(level: LogLevel) -> bool {
+    LEVEL.with(|current_level| level >= current_level.get())
+}
+
+/// Restores the event log from the storage,
+/// messages recorded before this are kept as the most recent.
+pub fn restore(stored: EventLog) {
+    EVENT_LOG.with(|event_log| {
+        let mut event_log = event_log.borrow_mut();
+        let recent = std::mem::replace(&mut *event_log, stored);
+        for entry in recent.entries {
+            event_log.push(entry);
+        }
+    });
+}
+
+/// Writes a message to the console and the event log if its level is enabled.
+pub fn record(level: LogLevel, message: String) {
+    if !is_enabled(level) {
+        return;
+    }
+    CurrentBrowser::log(level, &message);
+    EVENT_LOG.with(|event_log| {
+        let mut event_log = event_log.borrow_mut();
+        event_log.push(LogEntry {
+            time: Utc::now(),
+            level,
+            message,
+        });
+        CurrentBrowser::persist_event_log(&event_log);
+    });
+}
+
+/// Records a message for tracing decisions, see [record].
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::util::log::record($crate::util::log::LogLevel::Debug, format!($($arg)*))
+    };
+}
+
+/// Records a message for notable actions, see [record].
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::util::log::record($crate::util::log::LogLevel::Info, format!($($arg)*))
+    };
+}
+
+/// Records a message for failures that are otherwise ignored, see [record].
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::util::log::record($crate::util::log::LogLevel::Warn, format!($($arg)*))
+    };
+}
+
+// Renamed on export, as `warn` is ambiguous with the builtin attribute.
+pub(crate) use {log_debug as debug, log_info as info, log_warn as warn};
+
+#[cfg(test)]
+pub mod test {
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn messages() -> Vec<String> {
+        EVENT_LOG.with(|event_log| {
+            event_log
+                .borrow()
+                .iter()
+                .map(|entry| entry.message.clone())
+                .collect()
+        })
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_event_log_capacity() {
+        let mut event_log = EventLog::default();
+        for index in 0..=EventLog::CAPACITY {
+            event_log.push(LogEntry {
+                time: Utc::now(),
+                level: LogLevel::Warn,
+                message: index.to_string(),
+            });
+        }
+        assert_eq!(event_log.iter().count(), EventLog::CAPACITY);
+        assert_eq!(
+            event_log.iter().next().map(|entry| &*entry.message),
+            Some("1")
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_record_level() {
+        restore(EventLog::default());
+        set_level(LogLevel::Info);
+        super::debug!("hidden");
+        super::info!("shown {}", 1);
+        super::warn!("shown {}", 2);
+        assert_eq!(messages(), ["shown 1", "shown 2"]);
+
+        let mut stored = EventLog::default();
+        stored.push(LogEntry {
+            time: Utc::now(),
+            level: LogLevel::Info,
+            message: String::from("stored"),
+        });
+        restore(stored);
+        assert_eq!(messages(), ["stored", "shown 1", "shown 2"]);
+        set_level(LogLevel::default());
+    }
+}
@@ -1,7 +1,12 @@
 //! Generic reusable functions that do not rely on WebAssembly or project
 //! specific resources.
 
+pub mod clock;
+pub mod compress;
 pub mod errors;
+pub mod lock;
+pub mod log;
+pub mod retry;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Formatter, Result as FmtResult};
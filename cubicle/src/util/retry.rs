@@ -0,0 +1,117 @@
+//! Retrying of fallible asynchronous operations with exponential backoff.
+
+use std::future::Future;
+use std::iter;
+use std::time::Duration;
+
+/// Policy of how many times and how long apart an operation is attempted.
+/// The delay is multiplied by the factor after every failed attempt.
+pub struct Backoff {
+    pub attempts: u32,
+    pub initial_delay: Duration,
+    pub factor: u32,
+}
+
+impl Backoff {
+    /// Delays between consecutive attempts, one fewer than the attempts.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> + '_ {
+        iter::successors(Some(self.initial_delay), |delay| {
+            delay.checked_mul(self.factor)
+        })
+        .take(self.attempts.saturating_sub(1) as usize)
+    }
+}
+
+/// Attempts an operation until it succeeds, fails with a non-transient error,
+/// or runs out of attempts, waiting with the sleep function in between.
+/// The sleep function is passed in as this does not rely on WebAssembly.
+/// Returns the result of the last attempt.
+pub async fn retry<T, E, O, OF, S, SF>(
+    backoff: &Backoff,
+    mut operation: O,
+    is_transient: impl Fn(&E) -> bool,
+    mut sleep: S,
+) -> Result<T, E>
+where
+    O: FnMut() -> OF,
+    OF: Future<Output = Result<T, E>>,
+    S: FnMut(Duration) -> SF,
+    SF: Future<Output = ()>,
+{
+    let mut delays = backoff.delays();
+    loop {
+        match operation().await {
+            Err(error) if is_transient(&error) => match delays.next() {
+                Some(delay) => sleep(delay).await,
+                None => return Err(error),
+            },
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::cell::Cell;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    const TEST_BACKOFF: Backoff = Backoff {
+        attempts: 4,
+        initial_delay: Duration::from_millis(100),
+        factor: 2,
+    };
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_backoff_delays() {
+        assert_eq!(
+            vec![100, 200, 400],
+            TEST_BACKOFF
+                .delays()
+                .map(|delay| delay.as_millis())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), async_std::test)]
+    async fn test_retry() {
+        let attempts = Cell::new(0);
+        let slept = Cell::new(Duration::ZERO);
+        let operation = || {
+            attempts.set(attempts.get() + 1);
+            async { Err::<(), _>(attempts.get()) }
+        };
+        let sleep = |delay| {
+            slept.set(slept.get() + delay);
+            async {}
+        };
+
+        assert_eq!(
+            Err(4),
+            retry(&TEST_BACKOFF, operation, |_| true, sleep).await
+        );
+        assert_eq!(Duration::from_millis(700), slept.get());
+
+        attempts.set(0);
+        assert_eq!(
+            Err(1),
+            retry(&TEST_BACKOFF, operation, |_| false, sleep).await
+        );
+
+        attempts.set(0);
+        let succeed_later = || {
+            attempts.set(attempts.get() + 1);
+            async { (attempts.get() == 2).then_some(()).ok_or(()) }
+        };
+        assert_eq!(
+            Ok(()),
+            retry(&TEST_BACKOFF, succeed_later, |_| true, |_| async {}).await
+        );
+        assert_eq!(2, attempts.get());
+    }
+}